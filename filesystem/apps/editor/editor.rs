@@ -7,6 +7,7 @@ pub struct Editor {
     offset: usize,
     scroll_x: isize,
     scroll_y: isize,
+    ctrl: bool,
 }
 
 impl Editor {
@@ -19,6 +20,7 @@ impl Editor {
             offset: 0,
             scroll_x: 0,
             scroll_y: 0,
+            ctrl: false,
         }
     }
 
@@ -38,6 +40,32 @@ impl Editor {
         }
     }
 
+    /// Copy the line of the cursor
+    fn copy(&self) {
+        let start = match self.string[0 .. self.offset].rfind('\n') {
+            Some(i) => i + 1,
+            None => 0,
+        };
+        let end = match self.string[self.offset ..].find('\n') {
+            Some(i) => self.offset + i + 1,
+            None => self.string.len(),
+        };
+        clipboard::set_text(&self.string[start .. end]);
+    }
+
+    /// Insert the text on the clipboard at the cursor
+    ///
+    /// The cursor moves a byte at a time, like typed keys, so only ASCII is pasted
+    fn paste(&mut self) {
+        if let Some(text) = clipboard::get_text() {
+            let text: String = text.chars().filter(|&c| (c as u32) < 0x80).collect();
+            self.string = self.string[0 .. self.offset].to_string() +
+                          &text +
+                          &self.string[self.offset .. self.string.len()];
+            self.offset += text.len();
+        }
+    }
+
     fn save(&mut self, window: &Window) {
         if self.file.is_none() {
             let mut save_window = {
@@ -161,6 +189,9 @@ impl Editor {
 
         while let Some(event) = window.poll() {
             if let EventOption::Key(key_event) = event.to_option() {
+                if key_event.scancode == K_CTRL {
+                    self.ctrl = key_event.pressed;
+                }
                 if key_event.pressed {
                     match key_event.scancode {
                         K_ESC => break,
@@ -213,6 +244,8 @@ impl Editor {
                         }
                         _ => match key_event.character {
                             '\0' => (),
+                            'c' if self.ctrl => self.copy(),
+                            'v' if self.ctrl => self.paste(),
                             _ => {
                                 self.string = self.string[0 .. self.offset].to_string() +
                                               &key_event.character.to_string() +
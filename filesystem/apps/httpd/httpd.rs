@@ -0,0 +1,323 @@
+use redox::*;
+use redox::http::{Connection, Request, Response};
+use redox::net::TcpListener;
+
+/// Well known MIME types, by extension
+static MIME_TYPES: [(&'static str, &'static str); 16] = [("html", "text/html"),
+                                                         ("htm", "text/html"),
+                                                         ("css", "text/css"),
+                                                         ("js", "application/javascript"),
+                                                         ("json", "application/json"),
+                                                         ("txt", "text/plain"),
+                                                         ("md", "text/markdown"),
+                                                         ("bmp", "image/bmp"),
+                                                         ("png", "image/png"),
+                                                         ("jpg", "image/jpeg"),
+                                                         ("jpeg", "image/jpeg"),
+                                                         ("gif", "image/gif"),
+                                                         ("wav", "audio/wav"),
+                                                         ("pdf", "application/pdf"),
+                                                         ("zip", "application/zip"),
+                                                         ("bin", "application/octet-stream")];
+
+/// Map the extensions accepted by packages to MIME types
+///
+/// Extensions without a well known type are text if the package accepting them also accepts
+/// `*.txt`, as it is an editor.
+fn package_mime_types() -> Vec<(String, String)> {
+    let mut types = Vec::new();
+
+    if let Some(apps) = fs::read_dir("file:///apps/") {
+        for entry in apps {
+            if !entry.path().ends_with('/') {
+                continue;
+            }
+
+            let mut info = String::new();
+            if let Some(mut file) = File::open(&("file:///apps/".to_string() + entry.path() + "_REDOX")) {
+                file.read_to_string(&mut info);
+            }
+
+            let mut extensions = Vec::new();
+            for line in info.lines() {
+                if line.starts_with("accept=*.") {
+                    extensions.push(line[9 ..].to_lowercase());
+                }
+            }
+
+            let text = extensions.iter().any(|extension| extension == "txt");
+            for extension in extensions.iter() {
+                let mime = match MIME_TYPES.iter().find(|&&(known, _)| known == *extension) {
+                    Some(&(_, mime)) => mime,
+                    None if text => "text/plain",
+                    None => continue,
+                };
+
+                if !types.iter().any(|&(ref known, _): &(String, String)| known == extension) {
+                    types.push((extension.clone(), mime.to_string()));
+                }
+            }
+        }
+    }
+
+    types
+}
+
+/// The MIME type of a file, from the extensions packages accept first and then from `MIME_TYPES`
+fn mime_type(types: &Vec<(String, String)>, path: &str) -> String {
+    if let Some(extension) = path.rsplit('.').next() {
+        let extension = extension.to_lowercase();
+        for &(ref known, ref mime) in types.iter() {
+            if *known == extension {
+                return mime.clone();
+            }
+        }
+        for &(known, mime) in MIME_TYPES.iter() {
+            if known == extension {
+                return mime.to_string();
+            }
+        }
+    }
+
+    "application/octet-stream".to_string()
+}
+
+fn hex_digit(byte: u8) -> Option<u8> {
+    match byte {
+        b'0' ... b'9' => Some(byte - b'0'),
+        b'a' ... b'f' => Some(byte - b'a' + 10),
+        b'A' ... b'F' => Some(byte - b'A' + 10),
+        _ => None,
+    }
+}
+
+/// Decode `%XX` escapes, returns None if the result is not UTF-8
+fn percent_decode(string: &str) -> Option<String> {
+    let bytes = string.as_bytes();
+    let mut decoded = Vec::new();
+
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            if let (Some(high), Some(low)) = (hex_digit(bytes[i + 1]), hex_digit(bytes[i + 2])) {
+                decoded.push(high << 4 | low);
+                i += 3;
+                continue;
+            }
+        }
+
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+
+    String::from_utf8(decoded).ok()
+}
+
+fn html_escape(string: &str) -> String {
+    string.replace("&", "&amp;").replace("<", "&lt;").replace(">", "&gt;").replace("\"", "&quot;")
+}
+
+/// Parse a single `bytes=` range against a body of `len` bytes, as an inclusive start and end
+///
+/// Returns Some(None) for a range that can not be satisfied, and None to serve the whole body.
+fn parse_range(range: &str, len: usize) -> Option<Option<(usize, usize)>> {
+    if !range.starts_with("bytes=") || range.contains(',') {
+        return None;
+    }
+
+    let mut parts = range[6 ..].trim().splitn(2, '-');
+    let (start, end) = match (parts.next(), parts.next()) {
+        (Some(start), Some(end)) => (start.trim(), end.trim()),
+        _ => return None,
+    };
+
+    if start.is_empty() {
+        // The last bytes
+        let suffix = end.to_string().to_num();
+        if suffix == 0 || len == 0 {
+            return Some(None);
+        }
+        return Some(Some((len - cmp::min(suffix, len), len - 1)));
+    }
+
+    let start = start.to_string().to_num();
+    let end = if end.is_empty() {
+        len.saturating_sub(1)
+    } else {
+        cmp::min(end.to_string().to_num(), len.saturating_sub(1))
+    };
+
+    if start >= len || start > end {
+        Some(None)
+    } else {
+        Some(Some((start, end)))
+    }
+}
+
+fn error(status: u16, reason: &str) -> Response {
+    let mut response = Response::new(status, reason);
+    response.headers.set("Content-Type", "text/html");
+    response.body = format!("<html><body><h1>{} {}</h1></body></html>\n", status, reason).into_bytes();
+    response
+}
+
+fn directory_listing(url_path: &str, path: &str) -> Option<Response> {
+    match fs::read_dir(path) {
+        Some(entries) => {
+            let mut html = format!("<html><head><title>Index of {0}</title></head><body><h1>Index of {0}</h1><ul>\n",
+                                   html_escape(url_path));
+            if url_path != "/" {
+                html = html + "<li><a href=\"../\">../</a></li>\n";
+            }
+            for entry in entries {
+                let name = html_escape(entry.path());
+                html = html + &format!("<li><a href=\"{0}\">{0}</a></li>\n", name);
+            }
+            html = html + "</ul></body></html>\n";
+
+            let mut response = Response::new(200, "OK");
+            response.headers.set("Content-Type", "text/html");
+            response.body = html.into_bytes();
+            Some(response)
+        }
+        None => None,
+    }
+}
+
+/// Answer a GET or HEAD for a path under `root`
+fn serve(root: &str, types: &Vec<(String, String)>, request: &Request) -> Response {
+    let url_path = match percent_decode(request.path.split('?').next().unwrap_or("/")) {
+        Some(url_path) => url_path,
+        None => return error(400, "Bad Request"),
+    };
+
+    if !url_path.starts_with('/') {
+        return error(400, "Bad Request");
+    }
+
+    if url_path.split('/').any(|part| part == "..") {
+        return error(403, "Forbidden");
+    }
+
+    let path = if root.ends_with('/') {
+        root.to_string() + &url_path[1 ..]
+    } else {
+        root.to_string() + &url_path
+    };
+
+    if url_path.ends_with('/') {
+        return match directory_listing(&url_path, &path) {
+            Some(response) => response,
+            None => error(404, "Not Found"),
+        };
+    }
+
+    let mut data = Vec::new();
+    match File::open(&path) {
+        Some(mut file) => {
+            file.read_to_end(&mut data);
+        }
+        None => {
+            // A directory without the slash
+            if fs::read_dir(&(path.clone() + "/")).is_some() {
+                let mut response = error(301, "Moved Permanently");
+                response.headers.set("Location", &(url_path.clone() + "/"));
+                return response;
+            }
+
+            return error(404, "Not Found");
+        }
+    }
+
+    let len = data.len();
+
+    let mut response = Response::new(200, "OK");
+    response.headers.set("Content-Type", &mime_type(types, &url_path));
+    response.headers.set("Accept-Ranges", "bytes");
+
+    if let Some(range) = request.headers.get("Range") {
+        match parse_range(range, len) {
+            Some(Some((start, end))) => {
+                response.status = 206;
+                response.reason = "Partial Content".to_string();
+                response.headers.set("Content-Range", &format!("bytes {}-{}/{}", start, end, len));
+                data = data[start .. end + 1].to_vec();
+            }
+            Some(None) => {
+                let mut response = error(416, "Range Not Satisfiable");
+                response.headers.set("Content-Range", &format!("bytes */{}", len));
+                return response;
+            }
+            None => (),
+        }
+    }
+
+    response.body = data;
+    response
+}
+
+/// Serve the requests on one connection, until either side closes it
+fn handle(mut connection: Connection<net::TcpStream>, peer: String, root: String, types: Vec<(String, String)>) {
+    while let Some(request) = Request::read(&mut connection) {
+        let mut response = if request.method == "GET" || request.method == "HEAD" {
+            serve(&root, &types, &request)
+        } else {
+            let mut response = error(405, "Method Not Allowed");
+            response.headers.set("Allow", "GET, HEAD");
+            response
+        };
+
+        let keep_alive = request.keep_alive();
+        if !keep_alive {
+            response.headers.set("Connection", "close");
+        }
+
+        println!("{} {} {} {}", peer, request.method, request.path, response.status);
+
+        if request.method == "HEAD" {
+            // The headers of a GET, without the body
+            let len = response.body.len();
+            response.headers.set("Content-Length", &len.to_string());
+            response.body.clear();
+        }
+
+        if !connection.write(&response.to_bytes()) || !keep_alive {
+            break;
+        }
+    }
+}
+
+pub fn main() {
+    console_title("HTTP Server");
+
+    let port = match args().get(1) {
+        Some(arg) => arg.to_string().to_num(),
+        None => 8080,
+    };
+
+    let root = match args().get(2) {
+        Some(arg) => arg.to_string(),
+        None => "file:///".to_string(),
+    };
+
+    let types = package_mime_types();
+
+    let listener = match TcpListener::bind(&format!("0.0.0.0:{}", port)) {
+        Some(listener) => listener,
+        None => {
+            println!("Failed to listen on port {}", port);
+            return;
+        }
+    };
+
+    println!("Serving {} on port {}", root, listener.local_port());
+
+    while let Some((stream, addr)) = listener.accept() {
+        let peer = addr.to_string();
+        let root = root.clone();
+        let types = types.clone();
+        thread::spawn(move || {
+            handle(Connection::new(stream), peer, root, types);
+        });
+    }
+}
@@ -23,6 +23,10 @@ pub struct Editor {
     pub options: Options,
     /// The key state
     pub key_state: KeyState,
+    /// The registers, except for the clipboard
+    pub registers: BTreeMap<char, String>,
+    /// The register chosen for the next yank, put or delete
+    pub register: char,
     /// Redraw
     pub redraw_task: RedrawTask,
 }
@@ -49,6 +53,8 @@ impl Editor {
             prompt: String::new(),
             options: Options::new(),
             key_state: KeyState::new(),
+            registers: BTreeMap::new(),
+            register: DEFAULT_REGISTER,
             redraw_task: RedrawTask::Null,
         };
 
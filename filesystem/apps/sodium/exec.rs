@@ -140,9 +140,21 @@ impl Editor {
                     Char('d') => {
                         let ins = self.get_inst();
                         if let Some(m) = self.to_motion_unbounded(ins) {
+                            self.yank(m);
                             self.remove_rb(m);
                         }
                     },
+                    Char('y') => {
+                        let ins = self.get_inst();
+                        if let Some(m) = self.to_motion_unbounded(ins) {
+                            self.yank(m);
+                        }
+                    },
+                    Char('p') => self.put(n, false),
+                    Char('P') => self.put(n, true),
+                    Char('"') => {
+                        self.register = self.get_char();
+                    },
                     Char('G') => {
                         let last = self.text.len() - 1;
                         self.goto((0, last));
@@ -0,0 +1,121 @@
+use super::*;
+use redox::*;
+use core::iter::FromIterator;
+
+/// The register used when no other is chosen
+pub const DEFAULT_REGISTER: char = '"';
+/// The register holding the clipboard, which is shared with other applications
+pub const CLIPBOARD_REGISTER: char = '+';
+
+impl Editor {
+    /// Get the text of a register
+    pub fn get_register(&self, reg: char) -> Option<String> {
+        if reg == CLIPBOARD_REGISTER {
+            clipboard::get_text()
+        } else {
+            self.registers.get(&reg).map(|text| text.clone())
+        }
+    }
+
+    /// Set the text of a register
+    pub fn set_register(&mut self, reg: char, text: String) {
+        if reg == CLIPBOARD_REGISTER {
+            if !clipboard::set_text(&text) {
+                self.status_bar.msg = format!("Could not copy to the clipboard");
+                self.redraw_task = RedrawTask::StatusBar;
+            }
+        } else {
+            self.registers.insert(reg, text);
+        }
+    }
+
+    /// Get the text of a motion (row based), like `remove_rb`. Whole lines end with a newline,
+    /// which makes them be put as lines.
+    pub fn get_rb(&self, (x, y): (isize, isize)) -> String {
+        if y == self.y() as isize {
+            let (x, y) = self.bound((x as usize, y as usize));
+            // Single line mode
+            let (a, b) = if self.x() > x {
+                (x, self.x())
+            } else {
+                (self.x(), x)
+            };
+            self.text[y].iter().skip(a).take(b - a).map(|c| *c).collect()
+        } else {
+            let (_, y) = self.bound((x as usize, y as usize));
+            // Full line mode
+            let (a, b) = if self.y() < y {
+                (self.y(), y)
+            } else {
+                (y, self.y())
+            };
+            let mut text = String::new();
+            for ln in a..(b + 1) {
+                text.extend(self.text[ln].iter().map(|c| *c));
+                text.push('\n');
+            }
+            text
+        }
+    }
+
+    /// Yank (copy) a given motion (row based) into the chosen register
+    pub fn yank(&mut self, motion: (isize, isize)) {
+        let text = self.get_rb(motion);
+        let reg = self.register;
+        self.set_register(reg, text);
+        self.register = DEFAULT_REGISTER;
+    }
+
+    /// Put (paste) the chosen register n times after the cursor, or before it. Whole lines are
+    /// put below the current line, or above it.
+    pub fn put(&mut self, n: usize, before: bool) {
+        let reg = self.register;
+        self.register = DEFAULT_REGISTER;
+
+        let text = match self.get_register(reg) {
+            Some(text) => text,
+            None => {
+                self.status_bar.msg = format!("Register {} is empty", reg);
+                self.redraw_task = RedrawTask::StatusBar;
+                return;
+            }
+        };
+
+        let (x, y) = self.pos();
+        if text.ends_with('\n') {
+            // Line mode
+            let first = if before { y } else { y + 1 };
+            let mut ln = first;
+            for _ in 0..n {
+                for line in text[..text.len() - 1].split('\n') {
+                    self.text.insert(ln, VecDeque::from_iter(line.chars()));
+                    ln += 1;
+                }
+            }
+            self.redraw_task = RedrawTask::LinesAfter(first);
+            self.goto((0, first));
+        } else {
+            // Character mode
+            let (mut x, mut ln) = if before || self.text[y].is_empty() {
+                (x, y)
+            } else {
+                (x + 1, y)
+            };
+            for _ in 0..n {
+                for c in text.chars() {
+                    if c == '\n' {
+                        let rest = self.text[ln].split_off(x);
+                        self.text.insert(ln + 1, rest);
+                        x = 0;
+                        ln += 1;
+                    } else {
+                        self.text[ln].insert(x, c);
+                        x += 1;
+                    }
+                }
+            }
+            self.redraw_task = RedrawTask::LinesAfter(y);
+            self.goto((x.saturating_sub(1), ln));
+        }
+    }
+}
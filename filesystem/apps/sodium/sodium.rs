@@ -1,4 +1,4 @@
-pub use redox::collections::VecDeque;
+pub use redox::collections::{BTreeMap, VecDeque};
 
 
 mod editor;
@@ -49,6 +49,9 @@ pub use self::insert::*;
 mod delete;
 pub use self::delete::*;
 
+mod register;
+pub use self::register::*;
+
 mod exec;
 pub use self::exec::*;
 
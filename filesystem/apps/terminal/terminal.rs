@@ -1,9 +1,12 @@
 use redox::ops::DerefMut;
+use redox::str;
 use redox::string::*;
 use redox::vec::Vec;
 use redox::boxed::Box;
 use redox::fs::*;
 use redox::io::*;
+use redox::http::Client;
+use redox::orbital::display::{self, DisplayMode};
 use redox::console::*;
 use redox::env::*;
 use redox::time::Duration;
@@ -185,23 +188,78 @@ impl<'a> Command<'a> {
         commands.push(Command {
             name: "wget",
             main: box |args: &Vec<String>| {
-                if let Some(host) = args.get(1) {
-                    if let Some(req) = args.get(2) {
-                        if let Some(mut con) = File::open(&("tcp://".to_string() + host)) {
-                            con.write(("GET ".to_string() + req + " HTTP/1.1").as_bytes());
+                if let Some(url) = args.get(1) {
+                    match Client::new().get(url) {
+                        Some(response) => if let Some(path) = args.get(2) {
+                            if let Some(mut file) = File::create(path) {
+                                file.write(&response.body);
+                                file.sync();
+                            }
+                            println!("{} {}: {} bytes", response.status, response.reason, response.body.len());
+                        } else {
+                            println!("{}", unsafe { str::from_utf8_unchecked(&response.body) });
+                        },
+                        None => println!("Failed to fetch {}", url),
+                    }
+                } else {
+                    println!("No url given");
+                }
+            },
+        });
 
-                            let mut res = Vec::new();
-                            con.read_to_end(&mut res);
+        commands.push(Command {
+            name: "ping",
+            main: box |args: &Vec<String>| {
+                if let Some(host) = args.get(1) {
+                    let count = match args.get(2) {
+                        Some(arg) => arg.to_num(),
+                        None => 4,
+                    };
+
+                    if let Some(mut con) = File::open(&("icmp://".to_string() + host)) {
+                        for i in 0..count {
+                            if i > 0 {
+                                Duration::new(1, 0).sleep();
+                            }
 
-                            if let Some(mut file) = File::open(&req) {
-                                file.write(&res);
+                            let mut buf = [0; 256];
+                            match con.read(&mut buf) {
+                                Some(count) => print!("{}", unsafe { str::from_utf8_unchecked(&buf[.. count]) }),
+                                None => {
+                                    println!("Failed to ping {}", host);
+                                    break;
+                                }
                             }
                         }
                     } else {
-                        println!("No request given");
+                        println!("Failed to open icmp://{}", host);
                     }
                 } else {
-                    println!("No url given");
+                    println!("No host given");
+                }
+            },
+        });
+
+        commands.push(Command {
+            name: "display",
+            main: box |args: &Vec<String>| {
+                match args.get(1) {
+                    Some(arg) => match DisplayMode::from_str(arg) {
+                        Some(mode) => if !display::set_mode(mode) {
+                            println!("Failed to switch to {}", arg);
+                        },
+                        None => println!("Mode is not WIDTHxHEIGHT: {}", arg),
+                    },
+                    None => {
+                        let current = display::mode();
+                        for mode in display::modes().iter() {
+                            if Some(*mode) == current {
+                                println!("{} *", mode.to_string());
+                            } else {
+                                println!("{}", mode.to_string());
+                            }
+                        }
+                    }
                 }
             },
         });
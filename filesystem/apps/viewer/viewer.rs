@@ -13,14 +13,17 @@ pub fn main() {
         file.read_to_end(&mut vec);
     }
 
-    let bmp = BmpFile::from_data(&vec);
+    let image: Box<Image> = match load_image(&vec) {
+        Some(image) => image,
+        None => box BmpFile::default(),
+    };
     let mut window = Window::new((rand() % 400 + 50) as isize,
                                  (rand() % 300 + 50) as isize,
-                                 max(320, bmp.width()),
-                                 bmp.height(),
+                                 max(320, image.width()),
+                                 image.height(),
                                  &("Viewer (".to_string() + &url + ")")).unwrap();
     window.set(Color::BLACK);
-    window.image(0, 0, bmp.width(), bmp.height(), bmp.as_slice());
+    window.image(0, 0, image.width(), image.height(), image.as_slice());
     window.sync();
 
     while let Some(event) = window.poll() {
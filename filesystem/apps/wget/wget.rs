@@ -0,0 +1,62 @@
+use redox::*;
+use redox::http::{Client, HttpUrl};
+
+/// The file a download is saved to when none is given, named after the last part of the path
+fn default_file(url: &HttpUrl) -> String {
+    let path = url.path.split('?').next().unwrap_or("");
+    match path.rsplit('/').next() {
+        Some(name) if !name.is_empty() => "file:///home/".to_string() + name,
+        _ => "file:///home/index.html".to_string(),
+    }
+}
+
+pub fn main() {
+    console_title("Wget");
+
+    let url = match args().get(1) {
+        Some(arg) => arg.to_string(),
+        None => {
+            println!("URL:");
+            match readln!() {
+                Some(line) => line.trim().to_string(),
+                None => return,
+            }
+        }
+    };
+
+    let http_url = match HttpUrl::from_string(&url) {
+        Some(http_url) => http_url,
+        None => {
+            println!("Not an http:// URL: {}", url);
+            return;
+        }
+    };
+
+    let path = match args().get(2) {
+        Some(arg) => arg.to_string(),
+        None => default_file(&http_url),
+    };
+
+    println!("Fetching {}", http_url.to_string());
+
+    let mut client = Client::new();
+    match client.get(&url) {
+        Some(response) => {
+            println!("{} {}", response.status, response.reason);
+
+            if response.status >= 200 && response.status < 300 {
+                match File::create(&path) {
+                    Some(mut file) => {
+                        match file.write(&response.body) {
+                            Some(count) => println!("Saved {} bytes to {}", count, path),
+                            None => println!("Failed to write {}", path),
+                        }
+                        file.sync();
+                    }
+                    None => println!("Failed to create {}", path),
+                }
+            }
+        }
+        None => println!("Failed to fetch {}", url),
+    }
+}
@@ -1,4 +1,5 @@
 use redox::boxed::Box;
+use redox::collections::VecDeque;
 use redox::fs::File;
 use redox::io::{Read, Write, SeekFrom};
 use redox::mem;
@@ -35,6 +36,9 @@ pub const TCP_RST: u16 = 1 << 2;
 pub const TCP_PSH: u16 = 1 << 3;
 pub const TCP_ACK: u16 = 1 << 4;
 
+/// The most connections waiting to be accepted, more SYNs are dropped and sent again by the peers
+pub const TCP_BACKLOG: usize = 16;
+
 impl FromBytes for Tcp {
     fn from_bytes(bytes: Vec<u8>) -> Option<Self> {
         if bytes.len() >= mem::size_of::<TcpHeader>() {
@@ -65,49 +69,172 @@ impl ToBytes for Tcp {
     }
 }
 
-/// A TCP resource
+/// A TCP resource, a connection or a listener
 pub struct Resource {
     ip: File,
-    peer_addr: IPv4Addr,
+    host_addr: IpAddr,
+    peer_addr: IpAddr,
     peer_port: u16,
     host_port: u16,
     sequence: u32,
     acknowledge: u32,
+    /// The peer sent FIN, reads return the end of the stream
+    closed: bool,
+    /// Listening on `host_port`, `ip` is a `ip:///6` listener and each dup accepts a connection
+    listening: bool,
+    /// Connections waiting to be accepted, the peer, its port and the sequence number of its SYN
+    backlog: VecDeque<(IPv4Addr, u16, u32)>,
+    /// The state listed by `netstat:`
+    state: &'static str,
+    netstat: NetstatEntry,
 }
 
 impl Resource {
-    pub fn dup(&self) -> Option<Box<Resource>> {
+    /// Duplicate a connection, or accept the next connection of a listener
+    pub fn dup(&mut self) -> Option<Box<Resource>> {
+        if self.listening {
+            return self.accept();
+        }
+
         match self.ip.dup() {
-            Some(ip) => Some(box Resource {
-                ip: ip,
-                peer_addr: self.peer_addr,
-                peer_port: self.peer_port,
-                host_port: self.host_port,
-                sequence: self.sequence,
-                acknowledge: self.acknowledge,
-            }),
+            Some(ip) => {
+                let mut ret = box Resource {
+                    ip: ip,
+                    host_addr: self.host_addr,
+                    peer_addr: self.peer_addr,
+                    peer_port: self.peer_port,
+                    host_port: self.host_port,
+                    sequence: self.sequence,
+                    acknowledge: self.acknowledge,
+                    closed: self.closed,
+                    listening: false,
+                    backlog: VecDeque::new(),
+                    state: self.state,
+                    netstat: NetstatEntry::new(),
+                };
+                ret.report();
+                Some(ret)
+            }
             None => None
         }
     }
 
+    /// List the connection and its state with `netstat:`
+    fn report(&mut self) {
+        self.netstat.report("tcp", self.host_addr, self.host_port, self.peer_addr, self.peer_port, self.state);
+    }
+
+    fn set_state(&mut self, state: &'static str) {
+        self.state = state;
+        self.report();
+    }
+
+    /// Read the next segment sent to the listener, keeping SYNs for our port in the backlog
+    fn listen(&mut self) -> bool {
+        let mut bytes = [0; 65536];
+        match self.ip.read(&mut bytes) {
+            Some(count) => {
+                if let Some(segment) = Tcp::from_bytes(bytes[.. count].to_vec()) {
+                    if segment.header.dst.get() == self.host_port &&
+                       (segment.header.flags.get() & (TCP_PSH | TCP_SYN | TCP_ACK)) == TCP_SYN {
+                        if let Some(path) = self.ip.path() {
+                            let peer_addr = IPv4Addr::from_string(&Url::from_string(path).host());
+                            let peer_port = segment.header.src.get();
+
+                            // A SYN sent again while waiting is already in the backlog
+                            let waiting = self.backlog.iter().any(|&(addr, port, _)| {
+                                addr.bytes == peer_addr.bytes && port == peer_port
+                            });
+                            if !waiting && self.backlog.len() < TCP_BACKLOG {
+                                self.backlog.push_back((peer_addr, peer_port, segment.header.sequence.get()));
+                            }
+                        }
+                    }
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Establish the next connection of the backlog, waiting for one if it is empty
+    fn accept(&mut self) -> Option<Box<Resource>> {
+        loop {
+            while self.backlog.is_empty() {
+                if !self.listen() {
+                    return None;
+                }
+            }
+
+            if let Some((peer_addr, peer_port, sequence)) = self.backlog.pop_front() {
+                let peer_addr = IpAddr::V4(peer_addr);
+                if let Some(ip) = File::open(&peer_addr.ip_path(6)) {
+                    let host_addr = match IpAddr::local(&ip) {
+                        Some(host_addr) => host_addr,
+                        None => continue,
+                    };
+
+                    let mut ret = box Resource {
+                        ip: ip,
+                        host_addr: host_addr,
+                        peer_addr: peer_addr,
+                        peer_port: peer_port,
+                        host_port: self.host_port,
+                        sequence: rand() as u32,
+                        acknowledge: sequence,
+                        closed: false,
+                        listening: false,
+                        backlog: VecDeque::new(),
+                        state: "SYN_RECEIVED",
+                        netstat: NetstatEntry::new(),
+                    };
+                    ret.report();
+
+                    if ret.server_establish() {
+                        return Some(ret);
+                    }
+                }
+            }
+        }
+    }
+
     pub fn path(&self) -> Option<String> {
-        Some(format!("tcp://{}:{}/{}", self.peer_addr.to_string(), self.peer_port, self.host_port as usize))
+        if self.listening {
+            return Some(format!("tcp:///{}", self.host_port));
+        }
+
+        Some(format!("tcp://{}:{}/{}", self.peer_addr.to_url_host(), self.peer_port, self.host_port as usize))
     }
 
     pub fn read(&mut self, buf: &mut [u8]) -> Option<usize> {
+        if self.listening {
+            return None;
+        }
+
+        if self.closed {
+            return Some(0);
+        }
+
         loop {
-            let mut bytes: Vec<u8> = Vec::new();
-            match self.ip.read_to_end(&mut bytes) {
-                Some(_) => {
-                    if let Some(segment) = Tcp::from_bytes(bytes) {
-                        if (segment.header.flags.get() & (TCP_PSH | TCP_SYN | TCP_ACK)) ==
-                           (TCP_PSH | TCP_ACK) &&
+            let mut bytes = [0; 65536];
+            match self.ip.read(&mut bytes) {
+                Some(count) => {
+                    if let Some(segment) = Tcp::from_bytes(bytes[.. count].to_vec()) {
+                        if ((segment.header.flags.get() & (TCP_PSH | TCP_SYN | TCP_ACK)) ==
+                            (TCP_PSH | TCP_ACK) ||
+                            segment.header.flags.get() & TCP_FIN == TCP_FIN) &&
                            segment.header.dst.get() == self.host_port &&
                            segment.header.src.get() == self.peer_port {
                             //Send ACK
                             self.sequence = segment.header.ack_num.get();
                             self.acknowledge = segment.header.sequence.get() +
                                                segment.data.len() as u32;
+                            if segment.header.flags.get() & TCP_FIN == TCP_FIN {
+                                // The FIN takes up one sequence number
+                                self.acknowledge += 1;
+                                self.closed = true;
+                                self.set_state("CLOSE_WAIT");
+                            }
                             let mut tcp = Tcp {
                                 header: TcpHeader {
                                     src: n16::new(self.host_port),
@@ -129,8 +256,7 @@ impl Resource {
                                 let proto = n16::new(0x06);
                                 let segment_len = n16::new((mem::size_of::<TcpHeader>() + tcp.options.len() + tcp.data.len()) as u16);
                                 tcp.header.checksum.data = Checksum::compile(
-                                    Checksum::sum((&IP_ADDR as *const IPv4Addr) as usize, mem::size_of::<IPv4Addr>()) +
-                                    Checksum::sum((&self.peer_addr as *const IPv4Addr) as usize, mem::size_of::<IPv4Addr>()) +
+                                    self.host_addr.sum() + self.peer_addr.sum() +
                                     Checksum::sum((&proto as *const n16) as usize, mem::size_of::<n16>()) +
                                     Checksum::sum((&segment_len as *const n16) as usize, mem::size_of::<n16>()) +
                                     Checksum::sum((&tcp.header as *const TcpHeader) as usize, mem::size_of::<TcpHeader>()) +
@@ -157,6 +283,10 @@ impl Resource {
     }
 
     pub fn write(&mut self, buf: &[u8]) -> Option<usize> {
+        if self.listening {
+            return None;
+        }
+
         let tcp_data = Vec::from(buf);
 
         let mut tcp = Tcp {
@@ -179,10 +309,7 @@ impl Resource {
             let proto = n16::new(0x06);
             let segment_len = n16::new((mem::size_of::<TcpHeader>() + tcp.data.len()) as u16);
             tcp.header.checksum.data =
-                Checksum::compile(Checksum::sum((&IP_ADDR as *const IPv4Addr) as usize,
-                                                mem::size_of::<IPv4Addr>()) +
-                                  Checksum::sum((&self.peer_addr as *const IPv4Addr) as usize,
-                                                mem::size_of::<IPv4Addr>()) +
+                Checksum::compile(self.host_addr.sum() + self.peer_addr.sum() +
                                   Checksum::sum((&proto as *const n16) as usize,
                                                 mem::size_of::<n16>()) +
                                   Checksum::sum((&segment_len as *const n16) as usize,
@@ -195,10 +322,10 @@ impl Resource {
 
         match self.ip.write(&tcp.to_bytes()) {
             Some(size) => loop { // Wait for ACK
-                let mut bytes: Vec<u8> = Vec::new();
-                match self.ip.read_to_end(&mut bytes) {
-                    Some(_) => {
-                        if let Some(segment) = Tcp::from_bytes(bytes) {
+                let mut bytes = [0; 65536];
+                match self.ip.read(&mut bytes) {
+                    Some(count) => {
+                        if let Some(segment) = Tcp::from_bytes(bytes[.. count].to_vec()) {
                             if segment.header.dst.get() == self.host_port &&
                                segment.header.src.get() == self.peer_port {
                                 if (segment.header.flags.get() & (TCP_PSH | TCP_SYN | TCP_ACK)) ==
@@ -250,10 +377,7 @@ impl Resource {
             let segment_len =
                 n16::new((mem::size_of::<TcpHeader>() + tcp.options.len() + tcp.data.len()) as u16);
             tcp.header.checksum.data =
-                Checksum::compile(Checksum::sum((&IP_ADDR as *const IPv4Addr) as usize,
-                                                mem::size_of::<IPv4Addr>()) +
-                                  Checksum::sum((&self.peer_addr as *const IPv4Addr) as usize,
-                                                mem::size_of::<IPv4Addr>()) +
+                Checksum::compile(self.host_addr.sum() + self.peer_addr.sum() +
                                   Checksum::sum((&proto as *const n16) as usize,
                                                 mem::size_of::<n16>()) +
                                   Checksum::sum((&segment_len as *const n16) as usize,
@@ -266,10 +390,10 @@ impl Resource {
 
         match self.ip.write(&tcp.to_bytes()) {
             Some(_) => loop { // Wait for SYN-ACK
-                let mut bytes: Vec<u8> = Vec::new();
-                match self.ip.read_to_end(&mut bytes) {
-                    Some(_) => {
-                        if let Some(segment) = Tcp::from_bytes(bytes) {
+                let mut bytes = [0; 65536];
+                match self.ip.read(&mut bytes) {
+                    Some(count) => {
+                        if let Some(segment) = Tcp::from_bytes(bytes[.. count].to_vec()) {
                             if segment.header.dst.get() == self.host_port &&
                                segment.header.src.get() == self.peer_port {
                                 if (segment.header.flags.get() & (TCP_PSH | TCP_SYN | TCP_ACK)) ==
@@ -299,8 +423,7 @@ impl Resource {
                                         let proto = n16::new(0x06);
                                         let segment_len = n16::new((mem::size_of::<TcpHeader>() + tcp.options.len() + tcp.data.len()) as u16);
                                         tcp.header.checksum.data = Checksum::compile(
-                                            Checksum::sum((&IP_ADDR as *const IPv4Addr) as usize, mem::size_of::<IPv4Addr>()) +
-                                            Checksum::sum((&self.peer_addr as *const IPv4Addr) as usize, mem::size_of::<IPv4Addr>()) +
+                                            self.host_addr.sum() + self.peer_addr.sum() +
                                             Checksum::sum((&proto as *const n16) as usize, mem::size_of::<n16>()) +
                                             Checksum::sum((&segment_len as *const n16) as usize, mem::size_of::<n16>()) +
                                             Checksum::sum((&tcp.header as *const TcpHeader) as usize, mem::size_of::<TcpHeader>()) +
@@ -310,6 +433,7 @@ impl Resource {
                                     }
 
                                     self.ip.write(&tcp.to_bytes());
+                                    self.set_state("ESTABLISHED");
 
                                     return true;
                                 } else {
@@ -326,7 +450,7 @@ impl Resource {
     }
 
     /// Try to establish a server connection
-    pub fn server_establish(&mut self, syn: Tcp) -> bool {
+    pub fn server_establish(&mut self) -> bool {
         //Send SYN-ACK
         self.acknowledge += 1;
         let mut tcp = Tcp {
@@ -350,10 +474,7 @@ impl Resource {
             let segment_len =
                 n16::new((mem::size_of::<TcpHeader>() + tcp.options.len() + tcp.data.len()) as u16);
             tcp.header.checksum.data =
-                Checksum::compile(Checksum::sum((&IP_ADDR as *const IPv4Addr) as usize,
-                                                mem::size_of::<IPv4Addr>()) +
-                                  Checksum::sum((&self.peer_addr as *const IPv4Addr) as usize,
-                                                mem::size_of::<IPv4Addr>()) +
+                Checksum::compile(self.host_addr.sum() + self.peer_addr.sum() +
                                   Checksum::sum((&proto as *const n16) as usize,
                                                 mem::size_of::<n16>()) +
                                   Checksum::sum((&segment_len as *const n16) as usize,
@@ -366,16 +487,17 @@ impl Resource {
 
         match self.ip.write(&tcp.to_bytes()) {
             Some(_) => loop { // Wait for ACK
-                let mut bytes: Vec<u8> = Vec::new();
-                match self.ip.read_to_end(&mut bytes) {
-                    Some(_) => {
-                        if let Some(segment) = Tcp::from_bytes(bytes) {
+                let mut bytes = [0; 65536];
+                match self.ip.read(&mut bytes) {
+                    Some(count) => {
+                        if let Some(segment) = Tcp::from_bytes(bytes[.. count].to_vec()) {
                             if segment.header.dst.get() == self.host_port &&
                                segment.header.src.get() == self.peer_port {
                                 if (segment.header.flags.get() & (TCP_PSH | TCP_SYN | TCP_ACK)) ==
                                    TCP_ACK {
                                     self.sequence = segment.header.ack_num.get();
                                     self.acknowledge = segment.header.sequence.get();
+                                    self.set_state("ESTABLISHED");
                                     return true;
                                 } else {
                                     return false;
@@ -393,6 +515,10 @@ impl Resource {
 
 impl Drop for Resource {
     fn drop(&mut self) {
+        if self.listening {
+            return;
+        }
+
         //Send FIN-ACK
         let mut tcp = Tcp {
             header: TcpHeader {
@@ -415,10 +541,7 @@ impl Drop for Resource {
             let segment_len =
                 n16::new((mem::size_of::<TcpHeader>() + tcp.options.len() + tcp.data.len()) as u16);
             tcp.header.checksum.data =
-                Checksum::compile(Checksum::sum((&IP_ADDR as *const IPv4Addr) as usize,
-                                                mem::size_of::<IPv4Addr>()) +
-                                  Checksum::sum((&self.peer_addr as *const IPv4Addr) as usize,
-                                                mem::size_of::<IPv4Addr>()) +
+                Checksum::compile(self.host_addr.sum() + self.peer_addr.sum() +
                                   Checksum::sum((&proto as *const n16) as usize,
                                                 mem::size_of::<n16>()) +
                                   Checksum::sum((&segment_len as *const n16) as usize,
@@ -445,19 +568,31 @@ impl Scheme {
         let url = Url::from_str(&url_str);
 
         if !url.host().is_empty() && !url.port().is_empty() {
-            let peer_addr = IPv4Addr::from_string(&url.host());
+            let peer_addr = IpAddr::from_string(&url.host());
             let peer_port = url.port().to_num() as u16;
             let host_port = (rand() % 32768 + 32768) as u16;
 
-            if let Some(ip) = File::open(&("ip://".to_string() + &peer_addr.to_string() + "/6")) {
+            if let Some(ip) = File::open(&peer_addr.ip_path(6)) {
+                let host_addr = match IpAddr::local(&ip) {
+                    Some(host_addr) => host_addr,
+                    None => return None,
+                };
+
                 let mut ret = box Resource {
                     ip: ip,
+                    host_addr: host_addr,
                     peer_addr: peer_addr,
                     peer_port: peer_port,
                     host_port: host_port,
                     sequence: rand() as u32,
                     acknowledge: 0,
+                    closed: false,
+                    listening: false,
+                    backlog: VecDeque::new(),
+                    state: "SYN_SENT",
+                    netstat: NetstatEntry::new(),
                 };
+                ret.report();
 
                 if ret.client_establish() {
                     return Some(ret);
@@ -466,35 +601,26 @@ impl Scheme {
         } else if !url.path().is_empty() {
             let host_port = url.path().to_num() as u16;
 
-            while let Some(mut ip) = File::open("ip:///6") {
-                let mut bytes: Vec<u8> = Vec::new();
-                match ip.read_to_end(&mut bytes) {
-                    Some(_) => {
-                        if let Some(segment) = Tcp::from_bytes(bytes) {
-                            if segment.header.dst.get() == host_port && (segment.header.flags.get() & (TCP_PSH | TCP_SYN | TCP_ACK)) == TCP_SYN {
-                                if let Some(path) = ip.path() {
-                                    let url = Url::from_string(path);
-
-                                    let peer_addr = IPv4Addr::from_string(&url.host());
-
-                                    let mut ret = box Resource {
-                                        ip: ip,
-                                        peer_addr: peer_addr,
-                                        peer_port: segment.header.src.get(),
-                                        host_port: host_port,
-                                        sequence: rand() as u32,
-                                        acknowledge: segment.header.sequence.get(),
-                                    };
+            if let Some(ip) = File::open("ip:///6") {
+                let any = IpAddr::V4(IPv4Addr { bytes: [0, 0, 0, 0] });
 
-                                    if ret.server_establish(segment) {
-                                        return Some(ret);
-                                    }
-                                }
-                            }
-                        }
-                    }
-                    None => break,
-                }
+                let mut ret = box Resource {
+                    ip: ip,
+                    host_addr: any,
+                    peer_addr: any,
+                    peer_port: 0,
+                    host_port: host_port,
+                    sequence: 0,
+                    acknowledge: 0,
+                    closed: false,
+                    listening: true,
+                    backlog: VecDeque::new(),
+                    state: "LISTEN",
+                    netstat: NetstatEntry::new(),
+                };
+                ret.report();
+
+                return Some(ret);
             }
         }
 
@@ -0,0 +1,212 @@
+use redox::*;
+
+static SBOX: [u8; 256] = [0x63, 0x7c, 0x77, 0x7b, 0xf2, 0x6b, 0x6f, 0xc5, 0x30, 0x01, 0x67, 0x2b, 0xfe, 0xd7, 0xab, 0x76,
+                          0xca, 0x82, 0xc9, 0x7d, 0xfa, 0x59, 0x47, 0xf0, 0xad, 0xd4, 0xa2, 0xaf, 0x9c, 0xa4, 0x72, 0xc0,
+                          0xb7, 0xfd, 0x93, 0x26, 0x36, 0x3f, 0xf7, 0xcc, 0x34, 0xa5, 0xe5, 0xf1, 0x71, 0xd8, 0x31, 0x15,
+                          0x04, 0xc7, 0x23, 0xc3, 0x18, 0x96, 0x05, 0x9a, 0x07, 0x12, 0x80, 0xe2, 0xeb, 0x27, 0xb2, 0x75,
+                          0x09, 0x83, 0x2c, 0x1a, 0x1b, 0x6e, 0x5a, 0xa0, 0x52, 0x3b, 0xd6, 0xb3, 0x29, 0xe3, 0x2f, 0x84,
+                          0x53, 0xd1, 0x00, 0xed, 0x20, 0xfc, 0xb1, 0x5b, 0x6a, 0xcb, 0xbe, 0x39, 0x4a, 0x4c, 0x58, 0xcf,
+                          0xd0, 0xef, 0xaa, 0xfb, 0x43, 0x4d, 0x33, 0x85, 0x45, 0xf9, 0x02, 0x7f, 0x50, 0x3c, 0x9f, 0xa8,
+                          0x51, 0xa3, 0x40, 0x8f, 0x92, 0x9d, 0x38, 0xf5, 0xbc, 0xb6, 0xda, 0x21, 0x10, 0xff, 0xf3, 0xd2,
+                          0xcd, 0x0c, 0x13, 0xec, 0x5f, 0x97, 0x44, 0x17, 0xc4, 0xa7, 0x7e, 0x3d, 0x64, 0x5d, 0x19, 0x73,
+                          0x60, 0x81, 0x4f, 0xdc, 0x22, 0x2a, 0x90, 0x88, 0x46, 0xee, 0xb8, 0x14, 0xde, 0x5e, 0x0b, 0xdb,
+                          0xe0, 0x32, 0x3a, 0x0a, 0x49, 0x06, 0x24, 0x5c, 0xc2, 0xd3, 0xac, 0x62, 0x91, 0x95, 0xe4, 0x79,
+                          0xe7, 0xc8, 0x37, 0x6d, 0x8d, 0xd5, 0x4e, 0xa9, 0x6c, 0x56, 0xf4, 0xea, 0x65, 0x7a, 0xae, 0x08,
+                          0xba, 0x78, 0x25, 0x2e, 0x1c, 0xa6, 0xb4, 0xc6, 0xe8, 0xdd, 0x74, 0x1f, 0x4b, 0xbd, 0x8b, 0x8a,
+                          0x70, 0x3e, 0xb5, 0x66, 0x48, 0x03, 0xf6, 0x0e, 0x61, 0x35, 0x57, 0xb9, 0x86, 0xc1, 0x1d, 0x9e,
+                          0xe1, 0xf8, 0x98, 0x11, 0x69, 0xd9, 0x8e, 0x94, 0x9b, 0x1e, 0x87, 0xe9, 0xce, 0x55, 0x28, 0xdf,
+                          0x8c, 0xa1, 0x89, 0x0d, 0xbf, 0xe6, 0x42, 0x68, 0x41, 0x99, 0x2d, 0x0f, 0xb0, 0x54, 0xbb, 0x16];
+
+fn xtime(x: u8) -> u8 {
+    (x << 1) ^ (if x & 0x80 == 0x80 { 0x1b } else { 0 })
+}
+
+/// AES-128, only the forward cipher, which is all that GCM needs
+pub struct Aes128 {
+    round_keys: [[u8; 16]; 11],
+}
+
+impl Aes128 {
+    pub fn new(key: &[u8]) -> Self {
+        let mut words = [[0u8; 4]; 44];
+        for i in 0..4 {
+            for j in 0..4 {
+                words[i][j] = key[i * 4 + j];
+            }
+        }
+
+        let mut rcon = 1u8;
+        for i in 4..44 {
+            let mut word = words[i - 1];
+            if i % 4 == 0 {
+                word = [SBOX[word[1] as usize] ^ rcon,
+                        SBOX[word[2] as usize],
+                        SBOX[word[3] as usize],
+                        SBOX[word[0] as usize]];
+                rcon = xtime(rcon);
+            }
+            for j in 0..4 {
+                words[i][j] = words[i - 4][j] ^ word[j];
+            }
+        }
+
+        let mut round_keys = [[0u8; 16]; 11];
+        for i in 0..44 {
+            for j in 0..4 {
+                round_keys[i / 4][(i % 4) * 4 + j] = words[i][j];
+            }
+        }
+
+        Aes128 { round_keys: round_keys }
+    }
+
+    pub fn encrypt_block(&self, block: &mut [u8; 16]) {
+        for i in 0..16 {
+            block[i] ^= self.round_keys[0][i];
+        }
+
+        for round in 1..11 {
+            // SubBytes and ShiftRows, the state is in columns
+            let mut state = [0u8; 16];
+            for column in 0..4 {
+                for row in 0..4 {
+                    state[column * 4 + row] = SBOX[block[((column + row) % 4) * 4 + row] as usize];
+                }
+            }
+
+            if round != 10 {
+                for column in 0..4 {
+                    let a = [state[column * 4], state[column * 4 + 1], state[column * 4 + 2], state[column * 4 + 3]];
+                    let all = a[0] ^ a[1] ^ a[2] ^ a[3];
+                    for row in 0..4 {
+                        state[column * 4 + row] = a[row] ^ all ^ xtime(a[row] ^ a[(row + 1) % 4]);
+                    }
+                }
+            }
+
+            for i in 0..16 {
+                block[i] = state[i] ^ self.round_keys[round][i];
+            }
+        }
+    }
+}
+
+/// Multiply in GF(2^128) with the bit order of GCM
+fn gf_mul(x: [u8; 16], y: [u8; 16]) -> [u8; 16] {
+    let mut z = [0u8; 16];
+    let mut v = y;
+    for i in 0..128 {
+        if x[i / 8] & (0x80 >> (i % 8)) != 0 {
+            for j in 0..16 {
+                z[j] ^= v[j];
+            }
+        }
+
+        let lsb = v[15] & 1;
+        for j in (1..16).rev() {
+            v[j] = v[j] >> 1 | v[j - 1] << 7;
+        }
+        v[0] >>= 1;
+        if lsb == 1 {
+            v[0] ^= 0xe1;
+        }
+    }
+    z
+}
+
+/// AES-128-GCM with 12 byte nonces and 16 byte tags
+pub struct Gcm {
+    aes: Aes128,
+    h: [u8; 16],
+}
+
+impl Gcm {
+    pub fn new(key: &[u8]) -> Self {
+        let aes = Aes128::new(key);
+        let mut h = [0u8; 16];
+        aes.encrypt_block(&mut h);
+        Gcm { aes: aes, h: h }
+    }
+
+    fn counter_block(nonce: &[u8], counter: u32) -> [u8; 16] {
+        let mut block = [0u8; 16];
+        for i in 0..12 {
+            block[i] = nonce[i];
+        }
+        block[12] = (counter >> 24) as u8;
+        block[13] = (counter >> 16) as u8;
+        block[14] = (counter >> 8) as u8;
+        block[15] = counter as u8;
+        block
+    }
+
+    fn ctr(&self, nonce: &[u8], data: &mut [u8]) {
+        let mut counter = 2;
+        for chunk in data.chunks_mut(16) {
+            let mut stream = Gcm::counter_block(nonce, counter);
+            self.aes.encrypt_block(&mut stream);
+            for i in 0..chunk.len() {
+                chunk[i] ^= stream[i];
+            }
+            counter += 1;
+        }
+    }
+
+    fn tag(&self, nonce: &[u8], aad: &[u8], ciphertext: &[u8]) -> [u8; 16] {
+        let mut s = [0u8; 16];
+        for data in [aad, ciphertext].iter() {
+            for chunk in data.chunks(16) {
+                for i in 0..chunk.len() {
+                    s[i] ^= chunk[i];
+                }
+                s = gf_mul(s, self.h);
+            }
+        }
+
+        let aad_bits = aad.len() as u64 * 8;
+        let ciphertext_bits = ciphertext.len() as u64 * 8;
+        for i in 0..8 {
+            s[i] ^= (aad_bits >> (56 - i * 8)) as u8;
+            s[8 + i] ^= (ciphertext_bits >> (56 - i * 8)) as u8;
+        }
+        s = gf_mul(s, self.h);
+
+        let mut mask = Gcm::counter_block(nonce, 1);
+        self.aes.encrypt_block(&mut mask);
+        for i in 0..16 {
+            s[i] ^= mask[i];
+        }
+        s
+    }
+
+    /// Encrypt `plaintext`, returns the ciphertext followed by the tag
+    pub fn seal(&self, nonce: &[u8], aad: &[u8], plaintext: &[u8]) -> Vec<u8> {
+        let mut data = plaintext.to_vec();
+        self.ctr(nonce, &mut data);
+        let tag = self.tag(nonce, aad, &data);
+        data.push_all(&tag);
+        data
+    }
+
+    /// Check the tag and decrypt, returns None if the data was tampered with
+    pub fn open(&self, nonce: &[u8], aad: &[u8], data: &[u8]) -> Option<Vec<u8>> {
+        if data.len() < 16 {
+            return None;
+        }
+
+        let (ciphertext, tag) = data.split_at(data.len() - 16);
+        let expected = self.tag(nonce, aad, ciphertext);
+
+        let mut diff = 0;
+        for i in 0..16 {
+            diff |= expected[i] ^ tag[i];
+        }
+        if diff != 0 {
+            return None;
+        }
+
+        let mut plaintext = ciphertext.to_vec();
+        self.ctr(nonce, &mut plaintext);
+        Some(plaintext)
+    }
+}
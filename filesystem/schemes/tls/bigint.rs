@@ -0,0 +1,207 @@
+use redox::*;
+use redox::cmp::Ordering;
+
+/// An unsigned big integer, in 32 bit limbs with the least significant first
+///
+/// Only what signature verification needs, nothing here runs in constant time.
+#[derive(Clone, PartialEq)]
+pub struct BigUint {
+    limbs: Vec<u32>,
+}
+
+impl BigUint {
+    pub fn zero() -> Self {
+        BigUint { limbs: Vec::new() }
+    }
+
+    pub fn from_u32(value: u32) -> Self {
+        let mut ret = BigUint { limbs: vec![value] };
+        ret.normalize();
+        ret
+    }
+
+    pub fn from_bytes_be(bytes: &[u8]) -> Self {
+        let mut limbs = vec![0; (bytes.len() + 3) / 4];
+        for (i, &byte) in bytes.iter().rev().enumerate() {
+            limbs[i / 4] |= (byte as u32) << ((i % 4) * 8);
+        }
+        let mut ret = BigUint { limbs: limbs };
+        ret.normalize();
+        ret
+    }
+
+    /// Big endian bytes, left padded with zeros to `len`
+    pub fn to_bytes_be(&self, len: usize) -> Vec<u8> {
+        let mut bytes = vec![0; len];
+        for i in 0..cmp::min(len, self.limbs.len() * 4) {
+            bytes[len - 1 - i] = (self.limbs[i / 4] >> ((i % 4) * 8)) as u8;
+        }
+        bytes
+    }
+
+    fn normalize(&mut self) {
+        while self.limbs.last() == Some(&0) {
+            self.limbs.pop();
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.limbs.is_empty()
+    }
+
+    pub fn bits(&self) -> usize {
+        match self.limbs.last() {
+            Some(&top) => self.limbs.len() * 32 - top.leading_zeros() as usize,
+            None => 0,
+        }
+    }
+
+    pub fn bit(&self, i: usize) -> bool {
+        match self.limbs.get(i / 32) {
+            Some(&limb) => (limb >> (i % 32)) & 1 == 1,
+            None => false,
+        }
+    }
+
+    pub fn cmp(&self, other: &BigUint) -> Ordering {
+        if self.limbs.len() != other.limbs.len() {
+            return self.limbs.len().cmp(&other.limbs.len());
+        }
+        for i in (0..self.limbs.len()).rev() {
+            if self.limbs[i] != other.limbs[i] {
+                return self.limbs[i].cmp(&other.limbs[i]);
+            }
+        }
+        Ordering::Equal
+    }
+
+    pub fn add(&self, other: &BigUint) -> BigUint {
+        let len = cmp::max(self.limbs.len(), other.limbs.len());
+        let mut limbs = Vec::with_capacity(len + 1);
+        let mut carry = 0u64;
+        for i in 0..len {
+            let sum = *self.limbs.get(i).unwrap_or(&0) as u64 + *other.limbs.get(i).unwrap_or(&0) as u64 + carry;
+            limbs.push(sum as u32);
+            carry = sum >> 32;
+        }
+        limbs.push(carry as u32);
+        let mut ret = BigUint { limbs: limbs };
+        ret.normalize();
+        ret
+    }
+
+    /// `self - other`, which must not be negative
+    pub fn sub(&self, other: &BigUint) -> BigUint {
+        let mut limbs = self.limbs.clone();
+        let mut borrow = 0i64;
+        for i in 0..limbs.len() {
+            let diff = limbs[i] as i64 - *other.limbs.get(i).unwrap_or(&0) as i64 - borrow;
+            if diff < 0 {
+                limbs[i] = (diff + (1 << 32)) as u32;
+                borrow = 1;
+            } else {
+                limbs[i] = diff as u32;
+                borrow = 0;
+            }
+        }
+        let mut ret = BigUint { limbs: limbs };
+        ret.normalize();
+        ret
+    }
+
+    pub fn mul(&self, other: &BigUint) -> BigUint {
+        let mut limbs = vec![0u32; self.limbs.len() + other.limbs.len()];
+        for i in 0..self.limbs.len() {
+            let mut carry = 0u64;
+            for j in 0..other.limbs.len() {
+                let product = self.limbs[i] as u64 * other.limbs[j] as u64 + limbs[i + j] as u64 + carry;
+                limbs[i + j] = product as u32;
+                carry = product >> 32;
+            }
+            limbs[i + other.limbs.len()] = carry as u32;
+        }
+        let mut ret = BigUint { limbs: limbs };
+        ret.normalize();
+        ret
+    }
+
+    /// `self mod modulus`, by shifting in one bit at a time
+    pub fn rem(&self, modulus: &BigUint) -> BigUint {
+        if self.cmp(modulus) == Ordering::Less {
+            return self.clone();
+        }
+
+        let len = modulus.limbs.len() + 1;
+        let mut r = vec![0u32; len];
+        for i in (0..self.bits()).rev() {
+            // r = r << 1 | bit
+            let mut carry = if self.bit(i) { 1 } else { 0 };
+            for limb in r.iter_mut() {
+                let next = *limb >> 31;
+                *limb = *limb << 1 | carry;
+                carry = next;
+            }
+
+            // if r >= modulus { r -= modulus }
+            let mut ge = true;
+            for j in (0..len).rev() {
+                let m = *modulus.limbs.get(j).unwrap_or(&0);
+                if r[j] != m {
+                    ge = r[j] > m;
+                    break;
+                }
+            }
+            if ge {
+                let mut borrow = 0i64;
+                for j in 0..len {
+                    let diff = r[j] as i64 - *modulus.limbs.get(j).unwrap_or(&0) as i64 - borrow;
+                    if diff < 0 {
+                        r[j] = (diff + (1 << 32)) as u32;
+                        borrow = 1;
+                    } else {
+                        r[j] = diff as u32;
+                        borrow = 0;
+                    }
+                }
+            }
+        }
+
+        let mut ret = BigUint { limbs: r };
+        ret.normalize();
+        ret
+    }
+
+    pub fn mod_add(&self, other: &BigUint, modulus: &BigUint) -> BigUint {
+        self.add(other).rem(modulus)
+    }
+
+    /// `self - other mod modulus`, both already reduced
+    pub fn mod_sub(&self, other: &BigUint, modulus: &BigUint) -> BigUint {
+        if self.cmp(other) == Ordering::Less {
+            self.add(modulus).sub(other)
+        } else {
+            self.sub(other)
+        }
+    }
+
+    pub fn mod_mul(&self, other: &BigUint, modulus: &BigUint) -> BigUint {
+        self.mul(other).rem(modulus)
+    }
+
+    pub fn mod_pow(&self, exponent: &BigUint, modulus: &BigUint) -> BigUint {
+        let base = self.rem(modulus);
+        let mut result = BigUint::from_u32(1).rem(modulus);
+        for i in (0..exponent.bits()).rev() {
+            result = result.mod_mul(&result, modulus);
+            if exponent.bit(i) {
+                result = result.mod_mul(&base, modulus);
+            }
+        }
+        result
+    }
+
+    /// The inverse modulo a prime, by Fermat's little theorem
+    pub fn mod_inverse(&self, prime: &BigUint) -> BigUint {
+        self.mod_pow(&prime.sub(&BigUint::from_u32(2)), prime)
+    }
+}
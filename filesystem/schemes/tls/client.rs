@@ -0,0 +1,790 @@
+use redox::*;
+use redox::io::{Read, Write};
+
+use super::aes::Gcm;
+use super::random::Random;
+use super::sha2::*;
+use super::x25519;
+use super::x509::{verify_chain, Certificate, SignatureAlgorithm};
+
+const CONTENT_CHANGE_CIPHER_SPEC: u8 = 20;
+const CONTENT_ALERT: u8 = 21;
+const CONTENT_HANDSHAKE: u8 = 22;
+const CONTENT_APPLICATION_DATA: u8 = 23;
+
+const HANDSHAKE_CLIENT_HELLO: u8 = 1;
+const HANDSHAKE_SERVER_HELLO: u8 = 2;
+const HANDSHAKE_ENCRYPTED_EXTENSIONS: u8 = 8;
+const HANDSHAKE_CERTIFICATE: u8 = 11;
+const HANDSHAKE_SERVER_KEY_EXCHANGE: u8 = 12;
+const HANDSHAKE_CERTIFICATE_REQUEST: u8 = 13;
+const HANDSHAKE_SERVER_HELLO_DONE: u8 = 14;
+const HANDSHAKE_CERTIFICATE_VERIFY: u8 = 15;
+const HANDSHAKE_CLIENT_KEY_EXCHANGE: u8 = 16;
+const HANDSHAKE_FINISHED: u8 = 20;
+const HANDSHAKE_KEY_UPDATE: u8 = 24;
+
+const EXTENSION_SERVER_NAME: u16 = 0;
+const EXTENSION_SUPPORTED_GROUPS: u16 = 10;
+const EXTENSION_EC_POINT_FORMATS: u16 = 11;
+const EXTENSION_SIGNATURE_ALGORITHMS: u16 = 13;
+const EXTENSION_SUPPORTED_VERSIONS: u16 = 43;
+const EXTENSION_KEY_SHARE: u16 = 51;
+const EXTENSION_RENEGOTIATION_INFO: u16 = 0xff01;
+
+const TLS_1_2: u16 = 0x0303;
+const TLS_1_3: u16 = 0x0304;
+
+const TLS_AES_128_GCM_SHA256: u16 = 0x1301;
+const TLS_ECDHE_ECDSA_WITH_AES_128_GCM_SHA256: u16 = 0xc02b;
+const TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256: u16 = 0xc02f;
+
+const GROUP_SECP256R1: u16 = 0x0017;
+const GROUP_SECP384R1: u16 = 0x0018;
+const GROUP_X25519: u16 = 0x001d;
+
+/// The signature schemes we can verify, in order of preference
+static SIGNATURE_SCHEMES: [u16; 8] = [0x0403, 0x0503, 0x0804, 0x0805, 0x0806, 0x0401, 0x0501, 0x0601];
+
+/// The random of a HelloRetryRequest, which asks for another key share
+static HELLO_RETRY_REQUEST: [u8; 32] = [0xcf, 0x21, 0xad, 0x74, 0xe5, 0x9a, 0x61, 0x11, 0xbe, 0x1d, 0x8c, 0x02, 0x1e, 0x65, 0xb8, 0x91,
+                                        0xc2, 0xa2, 0x11, 0x16, 0x7a, 0xbb, 0x8c, 0x5e, 0x07, 0x9e, 0x09, 0xe2, 0xc8, 0xa8, 0x33, 0x9c];
+
+/// The end of the server random when a TLS 1.3 server was talked down to TLS 1.2
+static DOWNGRADE: [u8; 8] = [0x44, 0x4f, 0x57, 0x4e, 0x47, 0x52, 0x44, 0x01];
+
+/// Largest plaintext in a record
+const MAX_FRAGMENT: usize = 16384;
+
+fn push_u16(vec: &mut Vec<u8>, value: u16) {
+    vec.push((value >> 8) as u8);
+    vec.push(value as u8);
+}
+
+fn push_u24(vec: &mut Vec<u8>, value: usize) {
+    vec.push((value >> 16) as u8);
+    vec.push((value >> 8) as u8);
+    vec.push(value as u8);
+}
+
+fn push_u64(vec: &mut Vec<u8>, value: u64) {
+    for i in 0..8 {
+        vec.push((value >> (56 - i * 8)) as u8);
+    }
+}
+
+/// Append a vector with its length in front, in `len_bytes` bytes
+fn push_vector(vec: &mut Vec<u8>, len_bytes: usize, data: &[u8]) {
+    for i in (0..len_bytes).rev() {
+        vec.push((data.len() >> (i * 8)) as u8);
+    }
+    vec.push_all(data);
+}
+
+fn handshake_message(kind: u8, body: &[u8]) -> Vec<u8> {
+    let mut message = vec![kind];
+    push_vector(&mut message, 3, body);
+    message
+}
+
+/// A reader of the fields of a handshake message
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Reader {
+            data: data,
+            pos: 0,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.pos >= self.data.len()
+    }
+
+    fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        if self.pos + len <= self.data.len() {
+            let bytes = &self.data[self.pos .. self.pos + len];
+            self.pos += len;
+            Some(bytes)
+        } else {
+            None
+        }
+    }
+
+    fn number(&mut self, len: usize) -> Option<usize> {
+        let bytes = try_option!(self.bytes(len));
+        let mut value = 0;
+        for &byte in bytes.iter() {
+            value = value << 8 | byte as usize;
+        }
+        Some(value)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.number(1).map(|value| value as u8)
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        self.number(2).map(|value| value as u16)
+    }
+
+    /// A vector with its length in front, in `len_bytes` bytes
+    fn vector(&mut self, len_bytes: usize) -> Option<&'a [u8]> {
+        let len = try_option!(self.number(len_bytes));
+        self.bytes(len)
+    }
+}
+
+/// The keys of one direction
+struct Keys {
+    gcm: Gcm,
+    /// The whole nonce in TLS 1.3, its implicit first four bytes in TLS 1.2
+    iv: Vec<u8>,
+    seq: u64,
+}
+
+impl Keys {
+    fn new(key: &[u8], iv: &[u8]) -> Self {
+        Keys {
+            gcm: Gcm::new(key),
+            iv: iv.to_vec(),
+            seq: 0,
+        }
+    }
+
+    fn from_secret(secret: &[u8]) -> Self {
+        Keys::new(&hkdf_expand_label(secret, "key", &[], 16), &hkdf_expand_label(secret, "iv", &[], 12))
+    }
+
+    /// The per-record nonce of TLS 1.3, the IV xored with the sequence number
+    fn nonce(&self) -> Vec<u8> {
+        let mut nonce = self.iv.clone();
+        for i in 0..8 {
+            nonce[4 + i] ^= (self.seq >> (56 - i * 8)) as u8;
+        }
+        nonce
+    }
+}
+
+/// A TLS 1.2 or 1.3 client connection over `stream`
+pub struct TlsStream<S: Read + Write> {
+    stream: S,
+    version: u16,
+    read_keys: Option<Keys>,
+    write_keys: Option<Keys>,
+    /// Keys of the server to switch to on its ChangeCipherSpec, in TLS 1.2
+    pending_read_keys: Option<Keys>,
+    /// Traffic secrets, for key updates in TLS 1.3
+    read_secret: Vec<u8>,
+    write_secret: Vec<u8>,
+    /// Bytes received and not yet parsed into records
+    buf: Vec<u8>,
+    /// Handshake records not yet parsed into messages
+    handshake: Vec<u8>,
+    /// The handshake messages so far
+    transcript: Vec<u8>,
+    /// Application data not yet read
+    plaintext: Vec<u8>,
+    /// The server said close_notify, or the stream ended
+    closed: bool,
+}
+
+impl<S: Read + Write> TlsStream<S> {
+    /// Run the handshake over `stream`, checking that the server has a certificate for `name`
+    /// that leads to one of `roots` at `time`, in seconds since the epoch
+    pub fn connect(stream: S, name: &str, roots: &[Certificate], time: i64, random: &mut Random) -> Result<Self, &'static str> {
+        let mut tls = TlsStream {
+            stream: stream,
+            version: TLS_1_2,
+            read_keys: None,
+            write_keys: None,
+            pending_read_keys: None,
+            read_secret: Vec::new(),
+            write_secret: Vec::new(),
+            buf: Vec::new(),
+            handshake: Vec::new(),
+            transcript: Vec::new(),
+            plaintext: Vec::new(),
+            closed: false,
+        };
+
+        let mut client_random = [0; 32];
+        let mut session_id = [0; 32];
+        let mut secret = [0; 32];
+        random.fill(&mut client_random);
+        random.fill(&mut session_id);
+        random.fill(&mut secret);
+        let public = x25519::scalarmult(&secret, &x25519::BASE_POINT);
+
+        let client_hello = TlsStream::<S>::client_hello(name, &client_random, &session_id, &public);
+        try!(tls.send_handshake(&client_hello));
+
+        let server_hello = try!(tls.read_handshake());
+        if server_hello[0] != HANDSHAKE_SERVER_HELLO {
+            return Err("expected ServerHello");
+        }
+
+        let mut reader = Reader::new(&server_hello[4 ..]);
+        let mut version = try!(reader.u16().ok_or("bad ServerHello"));
+        let server_random = try!(reader.bytes(32).ok_or("bad ServerHello")).to_vec();
+        try!(reader.vector(1).ok_or("bad ServerHello"));
+        let cipher_suite = try!(reader.u16().ok_or("bad ServerHello"));
+        try!(reader.u8().ok_or("bad ServerHello"));
+
+        if server_random == &HELLO_RETRY_REQUEST[..] {
+            return Err("server wants a key exchange other than X25519");
+        }
+
+        let mut server_share = None;
+        if !reader.is_empty() {
+            let mut extensions = Reader::new(try!(reader.vector(2).ok_or("bad ServerHello")));
+            while !extensions.is_empty() {
+                let kind = try!(extensions.u16().ok_or("bad extension"));
+                let mut data = Reader::new(try!(extensions.vector(2).ok_or("bad extension")));
+                match kind {
+                    EXTENSION_SUPPORTED_VERSIONS => version = try!(data.u16().ok_or("bad supported_versions")),
+                    EXTENSION_KEY_SHARE => {
+                        if try!(data.u16().ok_or("bad key_share")) != GROUP_X25519 {
+                            return Err("server chose a key exchange other than X25519");
+                        }
+                        server_share = Some(try!(data.vector(2).ok_or("bad key_share")).to_vec());
+                    }
+                    _ => (),
+                }
+            }
+        }
+
+        tls.version = version;
+        match (version, cipher_suite) {
+            (TLS_1_3, TLS_AES_128_GCM_SHA256) => {
+                let server_share = try!(server_share.ok_or("server sent no key share"));
+                try!(tls.handshake_13(&secret, &server_share, name, roots, time));
+            }
+            (TLS_1_2, TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256) | (TLS_1_2, TLS_ECDHE_ECDSA_WITH_AES_128_GCM_SHA256) => {
+                if server_random[24 ..] == DOWNGRADE[..] {
+                    return Err("connection was downgraded to TLS 1.2");
+                }
+                try!(tls.handshake_12(&secret, &public, &client_random, &server_random, name, roots, time));
+            }
+            _ => return Err("server chose an unsupported version or cipher suite"),
+        }
+
+        Ok(tls)
+    }
+
+    fn client_hello(name: &str, client_random: &[u8], session_id: &[u8], public: &[u8]) -> Vec<u8> {
+        let mut body = Vec::new();
+        push_u16(&mut body, TLS_1_2);
+        body.push_all(client_random);
+        // A session ID for the middlebox compatibility of TLS 1.3
+        push_vector(&mut body, 1, session_id);
+
+        let mut suites = Vec::new();
+        push_u16(&mut suites, TLS_AES_128_GCM_SHA256);
+        push_u16(&mut suites, TLS_ECDHE_ECDSA_WITH_AES_128_GCM_SHA256);
+        push_u16(&mut suites, TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256);
+        push_vector(&mut body, 2, &suites);
+
+        // Only the null compression
+        push_vector(&mut body, 1, &[0]);
+
+        let mut extensions = Vec::new();
+
+        // Servers are named by DNS names, addresses are not sent
+        if !name.bytes().all(|byte| byte == b'.' || (byte >= b'0' && byte <= b'9')) {
+            let mut server_name = vec![0];
+            push_vector(&mut server_name, 2, name.as_bytes());
+            let mut list = Vec::new();
+            push_vector(&mut list, 2, &server_name);
+            push_u16(&mut extensions, EXTENSION_SERVER_NAME);
+            push_vector(&mut extensions, 2, &list);
+        }
+
+        // Key exchange is only X25519, the curves are listed so that TLS 1.2 servers may use ECDSA certificates
+        let mut groups = Vec::new();
+        push_u16(&mut groups, GROUP_X25519);
+        push_u16(&mut groups, GROUP_SECP256R1);
+        push_u16(&mut groups, GROUP_SECP384R1);
+        let mut list = Vec::new();
+        push_vector(&mut list, 2, &groups);
+        push_u16(&mut extensions, EXTENSION_SUPPORTED_GROUPS);
+        push_vector(&mut extensions, 2, &list);
+
+        push_u16(&mut extensions, EXTENSION_EC_POINT_FORMATS);
+        push_vector(&mut extensions, 2, &[1, 0]);
+
+        let mut schemes = Vec::new();
+        for scheme in SIGNATURE_SCHEMES.iter() {
+            push_u16(&mut schemes, *scheme);
+        }
+        let mut list = Vec::new();
+        push_vector(&mut list, 2, &schemes);
+        push_u16(&mut extensions, EXTENSION_SIGNATURE_ALGORITHMS);
+        push_vector(&mut extensions, 2, &list);
+
+        let mut versions = Vec::new();
+        push_u16(&mut versions, TLS_1_3);
+        push_u16(&mut versions, TLS_1_2);
+        let mut list = Vec::new();
+        push_vector(&mut list, 1, &versions);
+        push_u16(&mut extensions, EXTENSION_SUPPORTED_VERSIONS);
+        push_vector(&mut extensions, 2, &list);
+
+        let mut share = Vec::new();
+        push_u16(&mut share, GROUP_X25519);
+        push_vector(&mut share, 2, public);
+        let mut list = Vec::new();
+        push_vector(&mut list, 2, &share);
+        push_u16(&mut extensions, EXTENSION_KEY_SHARE);
+        push_vector(&mut extensions, 2, &list);
+
+        push_u16(&mut extensions, EXTENSION_RENEGOTIATION_INFO);
+        push_vector(&mut extensions, 2, &[0]);
+
+        push_vector(&mut body, 2, &extensions);
+
+        handshake_message(HANDSHAKE_CLIENT_HELLO, &body)
+    }
+
+    /// Parse a Certificate message and verify the chain, `tls13` entries have extensions
+    fn certificate_chain(message: &[u8], tls13: bool, name: &str, roots: &[Certificate], time: i64) -> Result<Vec<Certificate>, &'static str> {
+        let mut reader = Reader::new(&message[4 ..]);
+        if tls13 {
+            try!(reader.vector(1).ok_or("bad Certificate"));
+        }
+
+        let mut list = Reader::new(try!(reader.vector(3).ok_or("bad Certificate")));
+        let mut chain = Vec::new();
+        while !list.is_empty() {
+            let der = try!(list.vector(3).ok_or("bad Certificate"));
+            if tls13 {
+                try!(list.vector(2).ok_or("bad Certificate"));
+            }
+            chain.push(try!(Certificate::from_der(der).ok_or("unreadable certificate")));
+        }
+
+        try!(verify_chain(&chain, roots, name, time));
+        Ok(chain)
+    }
+
+    /// Verify a signature made by the key of the server certificate
+    fn verify_signature(chain: &[Certificate], scheme: u16, message: &[u8], signature: &[u8]) -> Result<(), &'static str> {
+        let algorithm = try!(SignatureAlgorithm::from_tls(scheme).ok_or("unsupported signature scheme"));
+        let key = match chain.first() {
+            Some(&Certificate { public_key: Some(ref key), .. }) => key,
+            _ => return Err("unsupported public key"),
+        };
+
+        if key.verify(algorithm, message, signature) {
+            Ok(())
+        } else {
+            Err("bad signature")
+        }
+    }
+
+    fn handshake_13(&mut self, secret: &[u8; 32], server_share: &[u8], name: &str, roots: &[Certificate], time: i64) -> Result<(), &'static str> {
+        if server_share.len() != 32 {
+            return Err("bad key share");
+        }
+        let mut server_public = [0; 32];
+        for i in 0..32 {
+            server_public[i] = server_share[i];
+        }
+        let shared = x25519::scalarmult(secret, &server_public);
+        if shared.iter().all(|byte| *byte == 0) {
+            return Err("bad key share");
+        }
+
+        let zeros = [0; 32];
+        let empty_hash = sha256(&[]);
+        let early_secret = hkdf_extract(&zeros, &zeros);
+        let derived = hkdf_expand_label(&early_secret, "derived", &empty_hash, 32);
+        let handshake_secret = hkdf_extract(&derived, &shared);
+
+        let hash = sha256(&self.transcript);
+        let client_secret = hkdf_expand_label(&handshake_secret, "c hs traffic", &hash, 32);
+        let server_secret = hkdf_expand_label(&handshake_secret, "s hs traffic", &hash, 32);
+        self.read_keys = Some(Keys::from_secret(&server_secret));
+
+        let mut chain = Vec::new();
+        let mut verified = false;
+        let mut certificate_request = None;
+        loop {
+            // The hash before the message, which CertificateVerify and Finished cover
+            let hash = sha256(&self.transcript);
+            let message = try!(self.read_handshake());
+            match message[0] {
+                HANDSHAKE_ENCRYPTED_EXTENSIONS => (),
+                HANDSHAKE_CERTIFICATE_REQUEST => {
+                    let mut reader = Reader::new(&message[4 ..]);
+                    certificate_request = Some(try!(reader.vector(1).ok_or("bad CertificateRequest")).to_vec());
+                }
+                HANDSHAKE_CERTIFICATE => {
+                    chain = try!(TlsStream::<S>::certificate_chain(&message, true, name, roots, time));
+                }
+                HANDSHAKE_CERTIFICATE_VERIFY => {
+                    let mut reader = Reader::new(&message[4 ..]);
+                    let scheme = try!(reader.u16().ok_or("bad CertificateVerify"));
+                    let signature = try!(reader.vector(2).ok_or("bad CertificateVerify"));
+
+                    let mut signed = vec![0x20; 64];
+                    signed.push_all(b"TLS 1.3, server CertificateVerify");
+                    signed.push(0);
+                    signed.push_all(&hash);
+                    try!(TlsStream::<S>::verify_signature(&chain, scheme, &signed, signature));
+                    verified = true;
+                }
+                HANDSHAKE_FINISHED => {
+                    if !verified {
+                        return Err("server did not prove its identity");
+                    }
+
+                    let finished_key = hkdf_expand_label(&server_secret, "finished", &[], 32);
+                    if message[4 ..] != hmac_sha256(&finished_key, &hash)[..] {
+                        return Err("bad server Finished");
+                    }
+                    break;
+                }
+                _ => return Err("unexpected handshake message"),
+            }
+        }
+
+        let hash = sha256(&self.transcript);
+        let derived = hkdf_expand_label(&handshake_secret, "derived", &empty_hash, 32);
+        let master_secret = hkdf_extract(&derived, &zeros);
+        self.write_secret = hkdf_expand_label(&master_secret, "c ap traffic", &hash, 32);
+        self.read_secret = hkdf_expand_label(&master_secret, "s ap traffic", &hash, 32);
+
+        try!(self.write_record(CONTENT_CHANGE_CIPHER_SPEC, &[1]));
+        self.write_keys = Some(Keys::from_secret(&client_secret));
+
+        if let Some(context) = certificate_request {
+            // We have no certificate, let the server decide if that is enough
+            let mut body = Vec::new();
+            push_vector(&mut body, 1, &context);
+            push_vector(&mut body, 3, &[]);
+            try!(self.send_handshake(&handshake_message(HANDSHAKE_CERTIFICATE, &body)));
+        }
+
+        let finished_key = hkdf_expand_label(&client_secret, "finished", &[], 32);
+        let verify_data = hmac_sha256(&finished_key, &sha256(&self.transcript));
+        try!(self.send_handshake(&handshake_message(HANDSHAKE_FINISHED, &verify_data)));
+
+        self.write_keys = Some(Keys::from_secret(&self.write_secret));
+        self.read_keys = Some(Keys::from_secret(&self.read_secret));
+        Ok(())
+    }
+
+    fn handshake_12(&mut self, secret: &[u8; 32], public: &[u8], client_random: &[u8], server_random: &[u8],
+                    name: &str, roots: &[Certificate], time: i64) -> Result<(), &'static str> {
+        let message = try!(self.read_handshake());
+        if message[0] != HANDSHAKE_CERTIFICATE {
+            return Err("expected Certificate");
+        }
+        let chain = try!(TlsStream::<S>::certificate_chain(&message, false, name, roots, time));
+
+        let message = try!(self.read_handshake());
+        if message[0] != HANDSHAKE_SERVER_KEY_EXCHANGE {
+            return Err("expected ServerKeyExchange");
+        }
+        let mut reader = Reader::new(&message[4 ..]);
+        if try!(reader.u8().ok_or("bad ServerKeyExchange")) != 3 ||
+           try!(reader.u16().ok_or("bad ServerKeyExchange")) != GROUP_X25519 {
+            return Err("server chose a key exchange other than X25519");
+        }
+        let server_share = try!(reader.vector(1).ok_or("bad ServerKeyExchange"));
+        let params = &message[4 .. 4 + 4 + server_share.len()];
+        let scheme = try!(reader.u16().ok_or("bad ServerKeyExchange"));
+        let signature = try!(reader.vector(2).ok_or("bad ServerKeyExchange"));
+
+        let mut signed = client_random.to_vec();
+        signed.push_all(server_random);
+        signed.push_all(params);
+        try!(TlsStream::<S>::verify_signature(&chain, scheme, &signed, signature));
+
+        if server_share.len() != 32 {
+            return Err("bad key share");
+        }
+        let mut server_public = [0; 32];
+        for i in 0..32 {
+            server_public[i] = server_share[i];
+        }
+        let shared = x25519::scalarmult(secret, &server_public);
+        if shared.iter().all(|byte| *byte == 0) {
+            return Err("bad key share");
+        }
+
+        let mut certificate_requested = false;
+        loop {
+            let message = try!(self.read_handshake());
+            match message[0] {
+                HANDSHAKE_CERTIFICATE_REQUEST => certificate_requested = true,
+                HANDSHAKE_SERVER_HELLO_DONE => break,
+                _ => return Err("unexpected handshake message"),
+            }
+        }
+
+        if certificate_requested {
+            try!(self.send_handshake(&handshake_message(HANDSHAKE_CERTIFICATE, &[0, 0, 0])));
+        }
+
+        let mut body = Vec::new();
+        push_vector(&mut body, 1, public);
+        try!(self.send_handshake(&handshake_message(HANDSHAKE_CLIENT_KEY_EXCHANGE, &body)));
+
+        let mut randoms = client_random.to_vec();
+        randoms.push_all(server_random);
+        let master_secret = prf(&shared, "master secret", &randoms, 48);
+
+        let mut randoms = server_random.to_vec();
+        randoms.push_all(client_random);
+        let key_block = prf(&master_secret, "key expansion", &randoms, 40);
+
+        try!(self.write_record(CONTENT_CHANGE_CIPHER_SPEC, &[1]));
+        self.write_keys = Some(Keys::new(&key_block[0 .. 16], &key_block[32 .. 36]));
+        self.pending_read_keys = Some(Keys::new(&key_block[16 .. 32], &key_block[36 .. 40]));
+
+        let verify_data = prf(&master_secret, "client finished", &sha256(&self.transcript), 12);
+        try!(self.send_handshake(&handshake_message(HANDSHAKE_FINISHED, &verify_data)));
+
+        let expected = prf(&master_secret, "server finished", &sha256(&self.transcript), 12);
+        let message = try!(self.read_handshake());
+        if self.read_keys.is_none() {
+            return Err("server Finished was not encrypted");
+        }
+        if message[0] != HANDSHAKE_FINISHED || message[4 ..] != expected[..] {
+            return Err("bad server Finished");
+        }
+
+        Ok(())
+    }
+
+    /// Read more from the stream, returns false at its end
+    fn fill(&mut self) -> bool {
+        let mut bytes = [0; 65536];
+        match self.stream.read(&mut bytes) {
+            Some(0) | None => false,
+            Some(count) => {
+                self.buf.push_all(&bytes[.. count]);
+                true
+            }
+        }
+    }
+
+    /// Read and decrypt one record, returns its type and plaintext
+    fn read_record(&mut self) -> Result<(u8, Vec<u8>), &'static str> {
+        while self.buf.len() < 5 || self.buf.len() < 5 + ((self.buf[3] as usize) << 8 | self.buf[4] as usize) {
+            if !self.fill() {
+                return Err("connection closed");
+            }
+        }
+
+        let len = (self.buf[3] as usize) << 8 | self.buf[4] as usize;
+        if len > MAX_FRAGMENT + 256 {
+            return Err("record too long");
+        }
+
+        let header = self.buf[.. 5].to_vec();
+        let payload = self.buf[5 .. 5 + len].to_vec();
+        self.buf = self.buf[5 + len ..].to_vec();
+
+        let mut kind = header[0];
+        if kind == CONTENT_CHANGE_CIPHER_SPEC {
+            // Switches keys in TLS 1.2, means nothing in TLS 1.3
+            if let Some(keys) = self.pending_read_keys.take() {
+                self.read_keys = Some(keys);
+            }
+            return Ok((kind, payload));
+        }
+
+        let plaintext = match self.read_keys {
+            Some(ref mut keys) => {
+                let plaintext = if self.version == TLS_1_3 {
+                    let nonce = keys.nonce();
+                    try!(keys.gcm.open(&nonce, &header, &payload).ok_or("bad record"))
+                } else {
+                    if payload.len() < 8 + 16 {
+                        return Err("bad record");
+                    }
+                    let mut nonce = keys.iv.clone();
+                    nonce.push_all(&payload[.. 8]);
+                    let mut aad = Vec::new();
+                    push_u64(&mut aad, keys.seq);
+                    aad.push(kind);
+                    push_u16(&mut aad, TLS_1_2);
+                    push_u16(&mut aad, (payload.len() - 8 - 16) as u16);
+                    try!(keys.gcm.open(&nonce, &aad, &payload[8 ..]).ok_or("bad record"))
+                };
+                keys.seq += 1;
+                plaintext
+            }
+            None => payload,
+        };
+
+        if self.version == TLS_1_3 && self.read_keys.is_some() {
+            // The real type is the last byte that is not padding
+            let mut plaintext = plaintext;
+            while plaintext.last() == Some(&0) {
+                plaintext.pop();
+            }
+            kind = try!(plaintext.pop().ok_or("bad record"));
+            Ok((kind, plaintext))
+        } else {
+            Ok((kind, plaintext))
+        }
+    }
+
+    /// Encrypt and send one record of at most MAX_FRAGMENT bytes
+    fn write_record(&mut self, kind: u8, data: &[u8]) -> Result<(), &'static str> {
+        let mut record = Vec::new();
+
+        match self.write_keys {
+            Some(ref mut keys) => {
+                if self.version == TLS_1_3 {
+                    let mut inner = data.to_vec();
+                    inner.push(kind);
+                    record.push(CONTENT_APPLICATION_DATA);
+                    push_u16(&mut record, TLS_1_2);
+                    push_u16(&mut record, (inner.len() + 16) as u16);
+                    let sealed = keys.gcm.seal(&keys.nonce(), &record, &inner);
+                    record.push_all(&sealed);
+                } else {
+                    let mut explicit = Vec::new();
+                    push_u64(&mut explicit, keys.seq);
+                    let mut nonce = keys.iv.clone();
+                    nonce.push_all(&explicit);
+                    let mut aad = explicit.clone();
+                    aad.push(kind);
+                    push_u16(&mut aad, TLS_1_2);
+                    push_u16(&mut aad, data.len() as u16);
+
+                    record.push(kind);
+                    push_u16(&mut record, TLS_1_2);
+                    push_u16(&mut record, (8 + data.len() + 16) as u16);
+                    record.push_all(&explicit);
+                    record.push_all(&keys.gcm.seal(&nonce, &aad, data));
+                }
+                keys.seq += 1;
+            }
+            None => {
+                record.push(kind);
+                // Some servers insist that the ClientHello says TLS 1.0 here
+                let hello = kind == CONTENT_HANDSHAKE && data.first() == Some(&HANDSHAKE_CLIENT_HELLO);
+                push_u16(&mut record, if hello { 0x0301 } else { TLS_1_2 });
+                push_vector(&mut record, 2, data);
+            }
+        }
+
+        match self.stream.write(&record) {
+            Some(_) => Ok(()),
+            None => Err("write failed"),
+        }
+    }
+
+    fn send_handshake(&mut self, message: &[u8]) -> Result<(), &'static str> {
+        self.transcript.push_all(message);
+        self.write_record(CONTENT_HANDSHAKE, message)
+    }
+
+    /// Read one handshake message, with its header, adding it to the transcript
+    fn read_handshake(&mut self) -> Result<Vec<u8>, &'static str> {
+        loop {
+            if self.handshake.len() >= 4 {
+                let len = (self.handshake[1] as usize) << 16 | (self.handshake[2] as usize) << 8 | self.handshake[3] as usize;
+                if self.handshake.len() >= 4 + len {
+                    let message = self.handshake[.. 4 + len].to_vec();
+                    self.handshake = self.handshake[4 + len ..].to_vec();
+                    self.transcript.push_all(&message);
+                    return Ok(message);
+                }
+            }
+
+            let (kind, data) = try!(self.read_record());
+            match kind {
+                CONTENT_HANDSHAKE => self.handshake.push_all(&data),
+                CONTENT_CHANGE_CIPHER_SPEC => (),
+                CONTENT_ALERT => return Err("server sent an alert"),
+                _ => return Err("unexpected record"),
+            }
+        }
+    }
+
+    /// Handle a handshake message after the handshake, in TLS 1.3
+    fn post_handshake(&mut self, message: &[u8]) -> Result<(), &'static str> {
+        if self.version == TLS_1_3 && message[0] == HANDSHAKE_KEY_UPDATE {
+            self.read_secret = hkdf_expand_label(&self.read_secret, "traffic upd", &[], 32);
+            self.read_keys = Some(Keys::from_secret(&self.read_secret));
+
+            if message.get(4) == Some(&1) {
+                try!(self.write_record(CONTENT_HANDSHAKE, &handshake_message(HANDSHAKE_KEY_UPDATE, &[0])));
+                self.write_secret = hkdf_expand_label(&self.write_secret, "traffic upd", &[], 32);
+                self.write_keys = Some(Keys::from_secret(&self.write_secret));
+            }
+        }
+        // Session tickets are not used, and TLS 1.2 renegotiation is refused by ignoring it
+
+        Ok(())
+    }
+
+    pub fn read(&mut self, buf: &mut [u8]) -> Option<usize> {
+        while self.plaintext.is_empty() && !self.closed {
+            match self.read_record() {
+                Ok((CONTENT_APPLICATION_DATA, data)) => self.plaintext.push_all(&data),
+                Ok((CONTENT_HANDSHAKE, data)) => {
+                    self.handshake.push_all(&data);
+                    while self.handshake.len() >= 4 {
+                        let len = (self.handshake[1] as usize) << 16 | (self.handshake[2] as usize) << 8 | self.handshake[3] as usize;
+                        if self.handshake.len() < 4 + len {
+                            break;
+                        }
+                        let message = self.handshake[.. 4 + len].to_vec();
+                        self.handshake = self.handshake[4 + len ..].to_vec();
+                        if self.post_handshake(&message).is_err() {
+                            return None;
+                        }
+                    }
+                }
+                Ok((CONTENT_ALERT, data)) => {
+                    if data.get(1) == Some(&0) {
+                        self.closed = true;
+                    } else {
+                        return None;
+                    }
+                }
+                Ok(_) => (),
+                // A stream that ends without close_notify is taken as the end too
+                Err(_) if self.buf.is_empty() => self.closed = true,
+                Err(_) => return None,
+            }
+        }
+
+        let count = cmp::min(buf.len(), self.plaintext.len());
+        for i in 0..count {
+            buf[i] = self.plaintext[i];
+        }
+        self.plaintext = self.plaintext[count ..].to_vec();
+        Some(count)
+    }
+
+    pub fn write(&mut self, buf: &[u8]) -> Option<usize> {
+        for chunk in buf.chunks(MAX_FRAGMENT) {
+            if self.write_record(CONTENT_APPLICATION_DATA, chunk).is_err() {
+                return None;
+            }
+        }
+        Some(buf.len())
+    }
+
+    /// Tell the server that we are done writing
+    pub fn close(&mut self) {
+        let _ = self.write_record(CONTENT_ALERT, &[1, 0]);
+    }
+
+    pub fn stream(&mut self) -> &mut S {
+        &mut self.stream
+    }
+}
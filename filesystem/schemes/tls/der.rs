@@ -0,0 +1,101 @@
+pub const TAG_BOOLEAN: u8 = 0x01;
+pub const TAG_INTEGER: u8 = 0x02;
+pub const TAG_BIT_STRING: u8 = 0x03;
+pub const TAG_OCTET_STRING: u8 = 0x04;
+pub const TAG_NULL: u8 = 0x05;
+pub const TAG_OID: u8 = 0x06;
+pub const TAG_UTC_TIME: u8 = 0x17;
+pub const TAG_GENERALIZED_TIME: u8 = 0x18;
+pub const TAG_SEQUENCE: u8 = 0x30;
+pub const TAG_SET: u8 = 0x31;
+
+/// A reader of DER encoded values, one tag, length and contents at a time
+pub struct Der<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Der<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Der {
+            data: data,
+            pos: 0,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pos >= self.data.len()
+    }
+
+    pub fn peek_tag(&self) -> Option<u8> {
+        self.data.get(self.pos).map(|tag| *tag)
+    }
+
+    /// Read the next value, returns its tag, its contents, and the whole encoding
+    pub fn read_raw(&mut self) -> Option<(u8, &'a [u8], &'a [u8])> {
+        let start = self.pos;
+        let tag = match self.data.get(self.pos) {
+            Some(&tag) => tag,
+            None => return None,
+        };
+
+        let first = match self.data.get(self.pos + 1) {
+            Some(&first) => first as usize,
+            None => return None,
+        };
+        self.pos += 2;
+
+        let len = if first & 0x80 == 0 {
+            first
+        } else {
+            let count = first & 0x7f;
+            if count == 0 || count > 4 || self.pos + count > self.data.len() {
+                return None;
+            }
+            let mut len = 0;
+            for i in 0..count {
+                len = len << 8 | self.data[self.pos + i] as usize;
+            }
+            self.pos += count;
+            len
+        };
+
+        if self.pos + len > self.data.len() {
+            return None;
+        }
+
+        let contents = &self.data[self.pos .. self.pos + len];
+        self.pos += len;
+        Some((tag, contents, &self.data[start .. self.pos]))
+    }
+
+    /// Read the next value, returns its tag and contents
+    pub fn read(&mut self) -> Option<(u8, &'a [u8])> {
+        self.read_raw().map(|(tag, contents, _)| (tag, contents))
+    }
+
+    /// Read the next value, which must have the tag `tag`
+    pub fn expect(&mut self, tag: u8) -> Option<&'a [u8]> {
+        match self.read() {
+            Some((found, contents)) if found == tag => Some(contents),
+            _ => None,
+        }
+    }
+
+    /// Read the next value only if it has the tag `tag`
+    pub fn optional(&mut self, tag: u8) -> Option<&'a [u8]> {
+        if self.peek_tag() == Some(tag) {
+            self.expect(tag)
+        } else {
+            None
+        }
+    }
+
+    /// Read a BIT STRING without unused bits
+    pub fn bit_string(&mut self) -> Option<&'a [u8]> {
+        match self.expect(TAG_BIT_STRING) {
+            Some(contents) if contents.len() >= 1 && contents[0] == 0 => Some(&contents[1 ..]),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,198 @@
+use redox::*;
+use redox::cmp::Ordering;
+
+use super::bigint::BigUint;
+
+/// A NIST prime curve, y^2 = x^3 - 3x + b
+pub struct Curve {
+    p: BigUint,
+    n: BigUint,
+    b: BigUint,
+    gx: BigUint,
+    gy: BigUint,
+    /// Bytes in a coordinate
+    pub len: usize,
+}
+
+impl Curve {
+    pub fn p256() -> Self {
+        Curve {
+            p: BigUint::from_bytes_be(&[0xff, 0xff, 0xff, 0xff, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                                        0x00, 0x00, 0x00, 0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]),
+            n: BigUint::from_bytes_be(&[0xff, 0xff, 0xff, 0xff, 0x00, 0x00, 0x00, 0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+                                        0xbc, 0xe6, 0xfa, 0xad, 0xa7, 0x17, 0x9e, 0x84, 0xf3, 0xb9, 0xca, 0xc2, 0xfc, 0x63, 0x25, 0x51]),
+            b: BigUint::from_bytes_be(&[0x5a, 0xc6, 0x35, 0xd8, 0xaa, 0x3a, 0x93, 0xe7, 0xb3, 0xeb, 0xbd, 0x55, 0x76, 0x98, 0x86, 0xbc,
+                                        0x65, 0x1d, 0x06, 0xb0, 0xcc, 0x53, 0xb0, 0xf6, 0x3b, 0xce, 0x3c, 0x3e, 0x27, 0xd2, 0x60, 0x4b]),
+            gx: BigUint::from_bytes_be(&[0x6b, 0x17, 0xd1, 0xf2, 0xe1, 0x2c, 0x42, 0x47, 0xf8, 0xbc, 0xe6, 0xe5, 0x63, 0xa4, 0x40, 0xf2,
+                                         0x77, 0x03, 0x7d, 0x81, 0x2d, 0xeb, 0x33, 0xa0, 0xf4, 0xa1, 0x39, 0x45, 0xd8, 0x98, 0xc2, 0x96]),
+            gy: BigUint::from_bytes_be(&[0x4f, 0xe3, 0x42, 0xe2, 0xfe, 0x1a, 0x7f, 0x9b, 0x8e, 0xe7, 0xeb, 0x4a, 0x7c, 0x0f, 0x9e, 0x16,
+                                         0x2b, 0xce, 0x33, 0x57, 0x6b, 0x31, 0x5e, 0xce, 0xcb, 0xb6, 0x40, 0x68, 0x37, 0xbf, 0x51, 0xf5]),
+            len: 32,
+        }
+    }
+
+    pub fn p384() -> Self {
+        Curve {
+            p: BigUint::from_bytes_be(&[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+                                        0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xfe,
+                                        0xff, 0xff, 0xff, 0xff, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xff, 0xff, 0xff, 0xff]),
+            n: BigUint::from_bytes_be(&[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+                                        0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xc7, 0x63, 0x4d, 0x81, 0xf4, 0x37, 0x2d, 0xdf,
+                                        0x58, 0x1a, 0x0d, 0xb2, 0x48, 0xb0, 0xa7, 0x7a, 0xec, 0xec, 0x19, 0x6a, 0xcc, 0xc5, 0x29, 0x73]),
+            b: BigUint::from_bytes_be(&[0xb3, 0x31, 0x2f, 0xa7, 0xe2, 0x3e, 0xe7, 0xe4, 0x98, 0x8e, 0x05, 0x6b, 0xe3, 0xf8, 0x2d, 0x19,
+                                        0x18, 0x1d, 0x9c, 0x6e, 0xfe, 0x81, 0x41, 0x12, 0x03, 0x14, 0x08, 0x8f, 0x50, 0x13, 0x87, 0x5a,
+                                        0xc6, 0x56, 0x39, 0x8d, 0x8a, 0x2e, 0xd1, 0x9d, 0x2a, 0x85, 0xc8, 0xed, 0xd3, 0xec, 0x2a, 0xef]),
+            gx: BigUint::from_bytes_be(&[0xaa, 0x87, 0xca, 0x22, 0xbe, 0x8b, 0x05, 0x37, 0x8e, 0xb1, 0xc7, 0x1e, 0xf3, 0x20, 0xad, 0x74,
+                                         0x6e, 0x1d, 0x3b, 0x62, 0x8b, 0xa7, 0x9b, 0x98, 0x59, 0xf7, 0x41, 0xe0, 0x82, 0x54, 0x2a, 0x38,
+                                         0x55, 0x02, 0xf2, 0x5d, 0xbf, 0x55, 0x29, 0x6c, 0x3a, 0x54, 0x5e, 0x38, 0x72, 0x76, 0x0a, 0xb7]),
+            gy: BigUint::from_bytes_be(&[0x36, 0x17, 0xde, 0x4a, 0x96, 0x26, 0x2c, 0x6f, 0x5d, 0x9e, 0x98, 0xbf, 0x92, 0x92, 0xdc, 0x29,
+                                         0xf8, 0xf4, 0x1d, 0xbd, 0x28, 0x9a, 0x14, 0x7c, 0xe9, 0xda, 0x31, 0x13, 0xb5, 0xf0, 0xb8, 0xc0,
+                                         0x0a, 0x60, 0xb1, 0xce, 0x1d, 0x7e, 0x81, 0x9d, 0x7a, 0x43, 0x1d, 0x7c, 0x90, 0xea, 0x0e, 0x5f]),
+            len: 48,
+        }
+    }
+
+    fn mul(&self, a: &BigUint, b: &BigUint) -> BigUint {
+        a.mod_mul(b, &self.p)
+    }
+
+    fn add(&self, a: &BigUint, b: &BigUint) -> BigUint {
+        a.mod_add(b, &self.p)
+    }
+
+    fn sub(&self, a: &BigUint, b: &BigUint) -> BigUint {
+        a.mod_sub(b, &self.p)
+    }
+
+    fn small(&self, k: u32, a: &BigUint) -> BigUint {
+        self.mul(&BigUint::from_u32(k), a)
+    }
+
+    fn double(&self, point: &Option<Jacobian>) -> Option<Jacobian> {
+        let point = match *point {
+            Some(ref point) => point,
+            None => return None,
+        };
+        if point.y.is_zero() {
+            return None;
+        }
+
+        let delta = self.mul(&point.z, &point.z);
+        let gamma = self.mul(&point.y, &point.y);
+        let beta = self.mul(&point.x, &gamma);
+        let alpha = self.small(3, &self.mul(&self.sub(&point.x, &delta), &self.add(&point.x, &delta)));
+
+        let x = self.sub(&self.mul(&alpha, &alpha), &self.small(8, &beta));
+        let yz = self.add(&point.y, &point.z);
+        let z = self.sub(&self.sub(&self.mul(&yz, &yz), &gamma), &delta);
+        let y = self.sub(&self.mul(&alpha, &self.sub(&self.small(4, &beta), &x)),
+                         &self.small(8, &self.mul(&gamma, &gamma)));
+
+        Some(Jacobian { x: x, y: y, z: z })
+    }
+
+    fn add_points(&self, a: &Option<Jacobian>, b: &Option<Jacobian>) -> Option<Jacobian> {
+        let (p, q) = match (a, b) {
+            (&Some(ref p), &Some(ref q)) => (p, q),
+            (&None, _) => return b.clone(),
+            (_, &None) => return a.clone(),
+        };
+
+        let z1z1 = self.mul(&p.z, &p.z);
+        let z2z2 = self.mul(&q.z, &q.z);
+        let u1 = self.mul(&p.x, &z2z2);
+        let u2 = self.mul(&q.x, &z1z1);
+        let s1 = self.mul(&self.mul(&p.y, &q.z), &z2z2);
+        let s2 = self.mul(&self.mul(&q.y, &p.z), &z1z1);
+
+        if u1 == u2 {
+            return if s1 == s2 {
+                self.double(a)
+            } else {
+                None
+            };
+        }
+
+        let h = self.sub(&u2, &u1);
+        let h2 = self.small(2, &h);
+        let i = self.mul(&h2, &h2);
+        let j = self.mul(&h, &i);
+        let r = self.small(2, &self.sub(&s2, &s1));
+        let v = self.mul(&u1, &i);
+
+        let x = self.sub(&self.sub(&self.mul(&r, &r), &j), &self.small(2, &v));
+        let y = self.sub(&self.mul(&r, &self.sub(&v, &x)), &self.small(2, &self.mul(&s1, &j)));
+        let zz = self.add(&p.z, &q.z);
+        let z = self.mul(&self.sub(&self.sub(&self.mul(&zz, &zz), &z1z1), &z2z2), &h);
+
+        Some(Jacobian { x: x, y: y, z: z })
+    }
+
+    /// Is (x, y) on the curve
+    fn contains(&self, x: &BigUint, y: &BigUint) -> bool {
+        if x.cmp(&self.p) != Ordering::Less || y.cmp(&self.p) != Ordering::Less {
+            return false;
+        }
+        let x3 = self.mul(&self.mul(x, x), x);
+        let rhs = self.add(&self.sub(&x3, &self.small(3, x)), &self.b);
+        self.mul(y, y) == rhs
+    }
+
+    /// Verify the signature (r, s) of a message digest with the public key `point`,
+    /// an uncompressed point `04 || X || Y`
+    pub fn verify(&self, point: &[u8], digest: &[u8], r: &BigUint, s: &BigUint) -> bool {
+        if point.len() != 1 + 2 * self.len || point[0] != 4 {
+            return false;
+        }
+        let qx = BigUint::from_bytes_be(&point[1 .. 1 + self.len]);
+        let qy = BigUint::from_bytes_be(&point[1 + self.len ..]);
+        if !self.contains(&qx, &qy) {
+            return false;
+        }
+
+        if r.is_zero() || s.is_zero() || r.cmp(&self.n) != Ordering::Less || s.cmp(&self.n) != Ordering::Less {
+            return false;
+        }
+
+        // The leftmost bits of the digest, as many as the order has
+        let e = BigUint::from_bytes_be(&digest[.. cmp::min(digest.len(), self.len)]).rem(&self.n);
+
+        let w = s.mod_inverse(&self.n);
+        let u1 = e.mod_mul(&w, &self.n);
+        let u2 = r.mod_mul(&w, &self.n);
+
+        let one = BigUint::from_u32(1);
+        let g = Some(Jacobian { x: self.gx.clone(), y: self.gy.clone(), z: one.clone() });
+        let q = Some(Jacobian { x: qx, y: qy, z: one });
+        let gq = self.add_points(&g, &q);
+
+        // Both multiplications at once
+        let mut sum = None;
+        for i in (0..cmp::max(u1.bits(), u2.bits())).rev() {
+            sum = self.double(&sum);
+            match (u1.bit(i), u2.bit(i)) {
+                (true, true) => sum = self.add_points(&sum, &gq),
+                (true, false) => sum = self.add_points(&sum, &g),
+                (false, true) => sum = self.add_points(&sum, &q),
+                (false, false) => (),
+            }
+        }
+
+        match sum {
+            Some(point) => {
+                // x = X / Z^2
+                let z_inv = point.z.mod_inverse(&self.p);
+                let x = self.mul(&point.x, &self.mul(&z_inv, &z_inv));
+                x.rem(&self.n) == *r
+            }
+            None => false,
+        }
+    }
+}
+
+#[derive(Clone)]
+struct Jacobian {
+    x: BigUint,
+    y: BigUint,
+    z: BigUint,
+}
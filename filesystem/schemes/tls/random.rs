@@ -0,0 +1,81 @@
+use redox::*;
+use redox::syscall::sys_yield;
+use redox::time::Duration;
+
+use super::sha2::{hmac_sha256, Sha256};
+
+fn cycles() -> u64 {
+    let low: u32;
+    let high: u32;
+    unsafe {
+        asm!("rdtsc"
+            : "={eax}"(low), "={edx}"(high)
+            :
+            :
+            : "volatile");
+    }
+    (high as u64) << 32 | low as u64
+}
+
+fn u64_bytes(value: u64) -> [u8; 8] {
+    let mut bytes = [0; 8];
+    for i in 0..8 {
+        bytes[i] = (value >> (56 - i * 8)) as u8;
+    }
+    bytes
+}
+
+/// A generator for the secrets of the handshake, HMAC-SHA256 of a counter under a seeded key
+///
+/// There is no entropy source, so the seed is the clocks and the cycle counter sampled around
+/// context switches. This is far better than `rand()`, but an attacker who can guess the boot
+/// time and scheduling closely enough can still guess the secrets.
+pub struct Random {
+    key: Vec<u8>,
+    counter: u64,
+}
+
+impl Random {
+    pub fn new() -> Self {
+        let mut random = Random {
+            key: Vec::new(),
+            counter: 0,
+        };
+        random.reseed();
+        random
+    }
+
+    /// Mix the current time and scheduling jitter into the key
+    pub fn reseed(&mut self) {
+        let mut seed = Sha256::new();
+        seed.update(&self.key);
+
+        let realtime = Duration::realtime();
+        let monotonic = Duration::monotonic();
+        seed.update(&u64_bytes(realtime.secs as u64));
+        seed.update(&u64_bytes(realtime.nanos as u64));
+        seed.update(&u64_bytes(monotonic.secs as u64));
+        seed.update(&u64_bytes(monotonic.nanos as u64));
+        seed.update(&u64_bytes(rand() as u64));
+
+        for _ in 0..32 {
+            seed.update(&u64_bytes(cycles()));
+            unsafe { sys_yield() };
+        }
+
+        self.key = seed.finish();
+    }
+
+    pub fn fill(&mut self, buf: &mut [u8]) {
+        for chunk in buf.chunks_mut(32) {
+            self.counter += 1;
+            let block = hmac_sha256(&self.key, &u64_bytes(self.counter));
+            for i in 0..chunk.len() {
+                chunk[i] = block[i];
+            }
+        }
+
+        // Forget the key that produced these bytes
+        self.key = hmac_sha256(&self.key, b"next key");
+    }
+}
@@ -0,0 +1,139 @@
+use redox::*;
+use redox::cmp::Ordering;
+
+use super::bigint::BigUint;
+use super::sha2::HashAlgorithm;
+
+/// An RSA public key
+#[derive(Clone)]
+pub struct RsaPublicKey {
+    pub n: BigUint,
+    pub e: BigUint,
+}
+
+static SHA256_INFO: [u8; 19] = [0x30, 0x31, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01, 0x05, 0x00, 0x04, 0x20];
+static SHA384_INFO: [u8; 19] = [0x30, 0x41, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x02, 0x05, 0x00, 0x04, 0x30];
+static SHA512_INFO: [u8; 19] = [0x30, 0x51, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x03, 0x05, 0x00, 0x04, 0x40];
+
+/// The DER encoded DigestInfo in front of the digest, up to the digest itself
+fn digest_info(hash: HashAlgorithm) -> &'static [u8] {
+    match hash {
+        HashAlgorithm::Sha256 => &SHA256_INFO,
+        HashAlgorithm::Sha384 => &SHA384_INFO,
+        HashAlgorithm::Sha512 => &SHA512_INFO,
+    }
+}
+
+/// MGF1 with the same hash as the signature
+fn mgf1(hash: HashAlgorithm, seed: &[u8], len: usize) -> Vec<u8> {
+    let mut mask = Vec::new();
+    let mut counter = 0u32;
+    while mask.len() < len {
+        let mut input = seed.to_vec();
+        input.push((counter >> 24) as u8);
+        input.push((counter >> 16) as u8);
+        input.push((counter >> 8) as u8);
+        input.push(counter as u8);
+        mask.push_all(&hash.digest(&input));
+        counter += 1;
+    }
+    mask.truncate(len);
+    mask
+}
+
+impl RsaPublicKey {
+    /// The length of the modulus in bytes
+    pub fn len(&self) -> usize {
+        (self.n.bits() + 7) / 8
+    }
+
+    /// Undo the signature, returns the encoded message
+    fn encrypt(&self, signature: &[u8]) -> Option<Vec<u8>> {
+        if signature.len() != self.len() {
+            return None;
+        }
+        let s = BigUint::from_bytes_be(signature);
+        if s.cmp(&self.n) != Ordering::Less {
+            return None;
+        }
+        Some(s.mod_pow(&self.e, &self.n).to_bytes_be(self.len()))
+    }
+
+    /// Verify a PKCS #1 v1.5 signature of `message`
+    pub fn verify_pkcs1(&self, hash: HashAlgorithm, message: &[u8], signature: &[u8]) -> bool {
+        let em = match self.encrypt(signature) {
+            Some(em) => em,
+            None => return false,
+        };
+
+        let mut expected = vec![0x00, 0x01];
+        let info = digest_info(hash);
+        let digest = hash.digest(message);
+        if em.len() < info.len() + digest.len() + 11 {
+            return false;
+        }
+        while expected.len() < em.len() - info.len() - digest.len() - 1 {
+            expected.push(0xff);
+        }
+        expected.push(0x00);
+        expected.push_all(info);
+        expected.push_all(&digest);
+
+        em == expected
+    }
+
+    /// Verify an RSASSA-PSS signature of `message`, with MGF1 of the same hash and a salt as long as the digest
+    pub fn verify_pss(&self, hash: HashAlgorithm, message: &[u8], signature: &[u8]) -> bool {
+        let mut em = match self.encrypt(signature) {
+            Some(em) => em,
+            None => return false,
+        };
+
+        let em_bits = self.n.bits() - 1;
+        let em_len = (em_bits + 7) / 8;
+        if em.len() > em_len {
+            // The modulus has a multiple of 8 bits plus one, the first byte is zero
+            if em[0] != 0 {
+                return false;
+            }
+            em.remove(0);
+        }
+
+        let h_len = hash.len();
+        let s_len = h_len;
+        if em_len < h_len + s_len + 2 || em[em_len - 1] != 0xbc {
+            return false;
+        }
+
+        let unused_bits = 8 * em_len - em_bits;
+        let top_mask = (0xffu32 >> unused_bits) as u8;
+
+        let h = em[em_len - h_len - 1 .. em_len - 1].to_vec();
+        let mut db = em[.. em_len - h_len - 1].to_vec();
+        if db[0] & !top_mask != 0 {
+            return false;
+        }
+
+        let mask = mgf1(hash, &h, db.len());
+        for i in 0..db.len() {
+            db[i] ^= mask[i];
+        }
+        db[0] &= top_mask;
+
+        let ps_len = em_len - h_len - s_len - 2;
+        for i in 0..ps_len {
+            if db[i] != 0 {
+                return false;
+            }
+        }
+        if db[ps_len] != 1 {
+            return false;
+        }
+
+        let mut m = vec![0u8; 8];
+        m.push_all(&hash.digest(message));
+        m.push_all(&db[ps_len + 1 ..]);
+
+        hash.digest(&m) == h
+    }
+}
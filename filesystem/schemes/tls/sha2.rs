@@ -0,0 +1,351 @@
+use redox::*;
+
+static K256: [u32; 64] = [0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+                          0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+                          0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+                          0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+                          0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+                          0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+                          0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+                          0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2];
+
+static K512: [u64; 80] = [0x428a2f98d728ae22, 0x7137449123ef65cd, 0xb5c0fbcfec4d3b2f, 0xe9b5dba58189dbbc,
+                          0x3956c25bf348b538, 0x59f111f1b605d019, 0x923f82a4af194f9b, 0xab1c5ed5da6d8118,
+                          0xd807aa98a3030242, 0x12835b0145706fbe, 0x243185be4ee4b28c, 0x550c7dc3d5ffb4e2,
+                          0x72be5d74f27b896f, 0x80deb1fe3b1696b1, 0x9bdc06a725c71235, 0xc19bf174cf692694,
+                          0xe49b69c19ef14ad2, 0xefbe4786384f25e3, 0x0fc19dc68b8cd5b5, 0x240ca1cc77ac9c65,
+                          0x2de92c6f592b0275, 0x4a7484aa6ea6e483, 0x5cb0a9dcbd41fbd4, 0x76f988da831153b5,
+                          0x983e5152ee66dfab, 0xa831c66d2db43210, 0xb00327c898fb213f, 0xbf597fc7beef0ee4,
+                          0xc6e00bf33da88fc2, 0xd5a79147930aa725, 0x06ca6351e003826f, 0x142929670a0e6e70,
+                          0x27b70a8546d22ffc, 0x2e1b21385c26c926, 0x4d2c6dfc5ac42aed, 0x53380d139d95b3df,
+                          0x650a73548baf63de, 0x766a0abb3c77b2a8, 0x81c2c92e47edaee6, 0x92722c851482353b,
+                          0xa2bfe8a14cf10364, 0xa81a664bbc423001, 0xc24b8b70d0f89791, 0xc76c51a30654be30,
+                          0xd192e819d6ef5218, 0xd69906245565a910, 0xf40e35855771202a, 0x106aa07032bbd1b8,
+                          0x19a4c116b8d2d0c8, 0x1e376c085141ab53, 0x2748774cdf8eeb99, 0x34b0bcb5e19b48a8,
+                          0x391c0cb3c5c95a63, 0x4ed8aa4ae3418acb, 0x5b9cca4f7763e373, 0x682e6ff3d6b2b8a3,
+                          0x748f82ee5defb2fc, 0x78a5636f43172f60, 0x84c87814a1f0ab72, 0x8cc702081a6439ec,
+                          0x90befffa23631e28, 0xa4506cebde82bde9, 0xbef9a3f7b2c67915, 0xc67178f2e372532b,
+                          0xca273eceea26619c, 0xd186b8c721c0c207, 0xeada7dd6cde0eb1e, 0xf57d4f7fee6ed178,
+                          0x06f067aa72176fba, 0x0a637dc5a2c898a6, 0x113f9804bef90dae, 0x1b710b35131c471b,
+                          0x28db77f523047d84, 0x32caab7b40c72493, 0x3c9ebe0a15c9bebc, 0x431d67c49c100d4c,
+                          0x4cc5d4becb3e42b6, 0x597f299cfc657e2a, 0x5fcb6fab3ad6faec, 0x6c44198c4a475817];
+
+/// SHA-256, as used by the cipher suites and by most certificates
+#[derive(Clone)]
+pub struct Sha256 {
+    state: [u32; 8],
+    block: [u8; 64],
+    block_len: usize,
+    len: u64,
+}
+
+impl Sha256 {
+    pub fn new() -> Self {
+        Sha256 {
+            state: [0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19],
+            block: [0; 64],
+            block_len: 0,
+            len: 0,
+        }
+    }
+
+    fn compress(&mut self) {
+        let mut w = [0u32; 64];
+        for i in 0..16 {
+            w[i] = (self.block[i * 4] as u32) << 24 | (self.block[i * 4 + 1] as u32) << 16 |
+                   (self.block[i * 4 + 2] as u32) << 8 | self.block[i * 4 + 3] as u32;
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16].wrapping_add(s0).wrapping_add(w[i - 7]).wrapping_add(s1);
+        }
+
+        let mut v = self.state;
+        for i in 0..64 {
+            let s1 = v[4].rotate_right(6) ^ v[4].rotate_right(11) ^ v[4].rotate_right(25);
+            let ch = (v[4] & v[5]) ^ (!v[4] & v[6]);
+            let t1 = v[7].wrapping_add(s1).wrapping_add(ch).wrapping_add(K256[i]).wrapping_add(w[i]);
+            let s0 = v[0].rotate_right(2) ^ v[0].rotate_right(13) ^ v[0].rotate_right(22);
+            let maj = (v[0] & v[1]) ^ (v[0] & v[2]) ^ (v[1] & v[2]);
+            let t2 = s0.wrapping_add(maj);
+
+            v[7] = v[6];
+            v[6] = v[5];
+            v[5] = v[4];
+            v[4] = v[3].wrapping_add(t1);
+            v[3] = v[2];
+            v[2] = v[1];
+            v[1] = v[0];
+            v[0] = t1.wrapping_add(t2);
+        }
+
+        for i in 0..8 {
+            self.state[i] = self.state[i].wrapping_add(v[i]);
+        }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        for &byte in data.iter() {
+            self.block[self.block_len] = byte;
+            self.block_len += 1;
+            if self.block_len == 64 {
+                self.compress();
+                self.block_len = 0;
+            }
+        }
+        self.len += data.len() as u64;
+    }
+
+    pub fn finish(mut self) -> Vec<u8> {
+        let bits = self.len * 8;
+        self.update(&[0x80]);
+        while self.block_len != 56 {
+            self.update(&[0]);
+        }
+        for i in 0..8 {
+            self.block[56 + i] = (bits >> (56 - i * 8)) as u8;
+        }
+        self.compress();
+
+        let mut digest = Vec::with_capacity(32);
+        for word in self.state.iter() {
+            digest.push((word >> 24) as u8);
+            digest.push((word >> 16) as u8);
+            digest.push((word >> 8) as u8);
+            digest.push(*word as u8);
+        }
+        digest
+    }
+}
+
+/// SHA-512, and SHA-384 which is the same with another initial state, truncated
+#[derive(Clone)]
+pub struct Sha512 {
+    state: [u64; 8],
+    block: [u8; 128],
+    block_len: usize,
+    len: u64,
+    digest_len: usize,
+}
+
+impl Sha512 {
+    pub fn new() -> Self {
+        Sha512 {
+            state: [0x6a09e667f3bcc908, 0xbb67ae8584caa73b, 0x3c6ef372fe94f82b, 0xa54ff53a5f1d36f1,
+                    0x510e527fade682d1, 0x9b05688c2b3e6c1f, 0x1f83d9abfb41bd6b, 0x5be0cd19137e2179],
+            block: [0; 128],
+            block_len: 0,
+            len: 0,
+            digest_len: 64,
+        }
+    }
+
+    pub fn new_384() -> Self {
+        Sha512 {
+            state: [0xcbbb9d5dc1059ed8, 0x629a292a367cd507, 0x9159015a3070dd17, 0x152fecd8f70e5939,
+                    0x67332667ffc00b31, 0x8eb44a8768581511, 0xdb0c2e0d64f98fa7, 0x47b5481dbefa4fa4],
+            block: [0; 128],
+            block_len: 0,
+            len: 0,
+            digest_len: 48,
+        }
+    }
+
+    fn compress(&mut self) {
+        let mut w = [0u64; 80];
+        for i in 0..16 {
+            for j in 0..8 {
+                w[i] = w[i] << 8 | self.block[i * 8 + j] as u64;
+            }
+        }
+        for i in 16..80 {
+            let s0 = w[i - 15].rotate_right(1) ^ w[i - 15].rotate_right(8) ^ (w[i - 15] >> 7);
+            let s1 = w[i - 2].rotate_right(19) ^ w[i - 2].rotate_right(61) ^ (w[i - 2] >> 6);
+            w[i] = w[i - 16].wrapping_add(s0).wrapping_add(w[i - 7]).wrapping_add(s1);
+        }
+
+        let mut v = self.state;
+        for i in 0..80 {
+            let s1 = v[4].rotate_right(14) ^ v[4].rotate_right(18) ^ v[4].rotate_right(41);
+            let ch = (v[4] & v[5]) ^ (!v[4] & v[6]);
+            let t1 = v[7].wrapping_add(s1).wrapping_add(ch).wrapping_add(K512[i]).wrapping_add(w[i]);
+            let s0 = v[0].rotate_right(28) ^ v[0].rotate_right(34) ^ v[0].rotate_right(39);
+            let maj = (v[0] & v[1]) ^ (v[0] & v[2]) ^ (v[1] & v[2]);
+            let t2 = s0.wrapping_add(maj);
+
+            v[7] = v[6];
+            v[6] = v[5];
+            v[5] = v[4];
+            v[4] = v[3].wrapping_add(t1);
+            v[3] = v[2];
+            v[2] = v[1];
+            v[1] = v[0];
+            v[0] = t1.wrapping_add(t2);
+        }
+
+        for i in 0..8 {
+            self.state[i] = self.state[i].wrapping_add(v[i]);
+        }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        for &byte in data.iter() {
+            self.block[self.block_len] = byte;
+            self.block_len += 1;
+            if self.block_len == 128 {
+                self.compress();
+                self.block_len = 0;
+            }
+        }
+        self.len += data.len() as u64;
+    }
+
+    pub fn finish(mut self) -> Vec<u8> {
+        // Messages are far shorter than 2^64 bits, the high half of the length is zero
+        let bits = self.len * 8;
+        self.update(&[0x80]);
+        while self.block_len != 120 {
+            self.update(&[0]);
+        }
+        for i in 0..8 {
+            self.block[120 + i] = (bits >> (56 - i * 8)) as u8;
+        }
+        self.compress();
+
+        let mut digest = Vec::with_capacity(64);
+        for word in self.state.iter() {
+            for i in 0..8 {
+                digest.push((word >> (56 - i * 8)) as u8);
+            }
+        }
+        digest.truncate(self.digest_len);
+        digest
+    }
+}
+
+/// The hashes found in signatures
+#[derive(Copy, Clone, PartialEq)]
+pub enum HashAlgorithm {
+    Sha256,
+    Sha384,
+    Sha512,
+}
+
+impl HashAlgorithm {
+    pub fn digest(&self, data: &[u8]) -> Vec<u8> {
+        match *self {
+            HashAlgorithm::Sha256 => {
+                let mut sha = Sha256::new();
+                sha.update(data);
+                sha.finish()
+            }
+            HashAlgorithm::Sha384 => {
+                let mut sha = Sha512::new_384();
+                sha.update(data);
+                sha.finish()
+            }
+            HashAlgorithm::Sha512 => {
+                let mut sha = Sha512::new();
+                sha.update(data);
+                sha.finish()
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match *self {
+            HashAlgorithm::Sha256 => 32,
+            HashAlgorithm::Sha384 => 48,
+            HashAlgorithm::Sha512 => 64,
+        }
+    }
+}
+
+pub fn sha256(data: &[u8]) -> Vec<u8> {
+    HashAlgorithm::Sha256.digest(data)
+}
+
+/// HMAC-SHA256
+pub fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut block = [0u8; 64];
+    if key.len() > 64 {
+        let digest = sha256(key);
+        for i in 0..digest.len() {
+            block[i] = digest[i];
+        }
+    } else {
+        for i in 0..key.len() {
+            block[i] = key[i];
+        }
+    }
+
+    let mut inner = Sha256::new();
+    let mut outer = Sha256::new();
+    let mut pad = [0u8; 64];
+    for i in 0..64 {
+        pad[i] = block[i] ^ 0x36;
+    }
+    inner.update(&pad);
+    for i in 0..64 {
+        pad[i] = block[i] ^ 0x5c;
+    }
+    outer.update(&pad);
+
+    inner.update(data);
+    outer.update(&inner.finish());
+    outer.finish()
+}
+
+/// HKDF-Extract with SHA256
+pub fn hkdf_extract(salt: &[u8], ikm: &[u8]) -> Vec<u8> {
+    hmac_sha256(salt, ikm)
+}
+
+/// HKDF-Expand with SHA256
+pub fn hkdf_expand(prk: &[u8], info: &[u8], len: usize) -> Vec<u8> {
+    let mut output = Vec::new();
+    let mut block: Vec<u8> = Vec::new();
+    let mut counter = 1u8;
+    while output.len() < len {
+        let mut input = block.clone();
+        input.push_all(info);
+        input.push(counter);
+        block = hmac_sha256(prk, &input);
+        output.push_all(&block);
+        counter += 1;
+    }
+    output.truncate(len);
+    output
+}
+
+/// HKDF-Expand-Label from TLS 1.3
+pub fn hkdf_expand_label(secret: &[u8], label: &str, context: &[u8], len: usize) -> Vec<u8> {
+    let mut info = Vec::new();
+    info.push((len >> 8) as u8);
+    info.push(len as u8);
+    info.push((6 + label.len()) as u8);
+    info.push_all(b"tls13 ");
+    info.push_all(label.as_bytes());
+    info.push(context.len() as u8);
+    info.push_all(context);
+    hkdf_expand(secret, &info, len)
+}
+
+/// The pseudorandom function of TLS 1.2, P_SHA256
+pub fn prf(secret: &[u8], label: &str, seed: &[u8], len: usize) -> Vec<u8> {
+    let mut label_seed = Vec::new();
+    label_seed.push_all(label.as_bytes());
+    label_seed.push_all(seed);
+
+    let mut output = Vec::new();
+    let mut a = hmac_sha256(secret, &label_seed);
+    while output.len() < len {
+        let mut input = a.clone();
+        input.push_all(&label_seed);
+        output.push_all(&hmac_sha256(secret, &input));
+        a = hmac_sha256(secret, &a);
+    }
+    output.truncate(len);
+    output
+}
@@ -0,0 +1,137 @@
+use redox::*;
+use redox::time::Duration;
+
+use self::client::TlsStream;
+use self::random::Random;
+use self::x509::Certificate;
+
+pub mod aes;
+pub mod bigint;
+pub mod client;
+pub mod der;
+pub mod ecdsa;
+pub mod random;
+pub mod rsa;
+pub mod sha2;
+pub mod x25519;
+pub mod x509;
+
+/// The trusted root certificates, PEM files with the extension .pem or .crt
+const ROOT_STORE: &'static str = "file:///ssl/certs/";
+
+pub struct Resource {
+    host: String,
+    port: String,
+    stream: TlsStream<File>,
+}
+
+impl Resource {
+    pub fn dup(&self) -> Option<Box<Resource>> {
+        // The record sequence numbers cannot be shared
+        None
+    }
+
+    pub fn path(&self) -> Option<String> {
+        Some(format!("tls://{}:{}", self.host, self.port))
+    }
+
+    pub fn read(&mut self, buf: &mut [u8]) -> Option<usize> {
+        self.stream.read(buf)
+    }
+
+    pub fn write(&mut self, buf: &[u8]) -> Option<usize> {
+        self.stream.write(buf)
+    }
+
+    pub fn seek(&mut self, _: SeekFrom) -> Option<usize> {
+        None
+    }
+
+    pub fn sync(&mut self) -> bool {
+        self.stream.stream().sync()
+    }
+}
+
+impl Drop for Resource {
+    fn drop(&mut self) {
+        self.stream.close();
+    }
+}
+
+pub struct Scheme {
+    random: Option<Random>,
+    roots: Vec<Certificate>,
+}
+
+impl Scheme {
+    pub fn new() -> Box<Scheme> {
+        box Scheme {
+            random: None,
+            roots: Vec::new(),
+        }
+    }
+
+    /// Read the root store, again on every connection so that new roots need no restart
+    fn load_roots(&mut self) {
+        self.roots.clear();
+
+        if let Some(entries) = read_dir(ROOT_STORE) {
+            for entry in entries {
+                let name = entry.path();
+                if name.ends_with(".pem") || name.ends_with(".crt") {
+                    if let Some(mut file) = File::open(&(ROOT_STORE.to_string() + name)) {
+                        let mut pem = String::new();
+                        file.read_to_string(&mut pem);
+                        self.roots.extend(Certificate::from_pem(&pem));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Open `tls://HOST:PORT[/NAME]`, where the certificate must be for NAME, or for HOST if it is not given
+    pub fn open(&mut self, url_str: &str, _: usize) -> Option<Box<Resource>> {
+        let url = Url::from_str(&url_str);
+
+        let host = url.host();
+        let port = url.port();
+        if host.is_empty() || port.is_empty() {
+            return None;
+        }
+
+        let path = url.path();
+        let name = if path.is_empty() {
+            host.clone()
+        } else {
+            path.trim_matches('/').to_string()
+        };
+
+        self.load_roots();
+        if self.roots.is_empty() {
+            println!("tls: no root certificates in {}", ROOT_STORE);
+            return None;
+        }
+
+        let stream = try_option!(File::open(&format!("tcp://{}:{}", host, port)));
+
+        if self.random.is_none() {
+            self.random = Some(Random::new());
+        } else if let Some(ref mut random) = self.random {
+            random.reseed();
+        }
+        let random = self.random.as_mut().unwrap();
+
+        let time = Duration::realtime().secs;
+        match TlsStream::connect(stream, &name, &self.roots, time, random) {
+            Ok(stream) => Some(box Resource {
+                host: host,
+                port: port,
+                stream: stream,
+            }),
+            Err(err) => {
+                println!("tls: {}:{}: {}", host, port, err);
+                None
+            }
+        }
+    }
+}
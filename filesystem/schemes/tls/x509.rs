@@ -0,0 +1,649 @@
+use redox::*;
+
+use super::bigint::BigUint;
+use super::der::*;
+use super::ecdsa::Curve;
+use super::rsa::RsaPublicKey;
+use super::sha2::HashAlgorithm;
+
+static OID_RSA_ENCRYPTION: [u8; 9] = [0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x01];
+static OID_RSASSA_PSS: [u8; 9] = [0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0a];
+static OID_SHA256_WITH_RSA: [u8; 9] = [0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0b];
+static OID_SHA384_WITH_RSA: [u8; 9] = [0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0c];
+static OID_SHA512_WITH_RSA: [u8; 9] = [0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0d];
+static OID_EC_PUBLIC_KEY: [u8; 7] = [0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01];
+static OID_P256: [u8; 8] = [0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07];
+static OID_P384: [u8; 5] = [0x2b, 0x81, 0x04, 0x00, 0x22];
+static OID_ECDSA_WITH_SHA256: [u8; 8] = [0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x02];
+static OID_ECDSA_WITH_SHA384: [u8; 8] = [0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x03];
+static OID_ECDSA_WITH_SHA512: [u8; 8] = [0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x04];
+static OID_SHA256: [u8; 9] = [0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01];
+static OID_SHA384: [u8; 9] = [0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x02];
+static OID_SHA512: [u8; 9] = [0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x03];
+static OID_COMMON_NAME: [u8; 3] = [0x55, 0x04, 0x03];
+static OID_SUBJECT_ALT_NAME: [u8; 3] = [0x55, 0x1d, 0x11];
+static OID_BASIC_CONSTRAINTS: [u8; 3] = [0x55, 0x1d, 0x13];
+
+/// How many certificates may stand between a server and a root
+const MAX_CHAIN_LEN: usize = 8;
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum NamedCurve {
+    P256,
+    P384,
+}
+
+impl NamedCurve {
+    fn curve(&self) -> Curve {
+        match *self {
+            NamedCurve::P256 => Curve::p256(),
+            NamedCurve::P384 => Curve::p384(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub enum PublicKey {
+    Rsa(RsaPublicKey),
+    /// An uncompressed point on the curve
+    Ec(NamedCurve, Vec<u8>),
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum SignatureAlgorithm {
+    RsaPkcs1(HashAlgorithm),
+    RsaPss(HashAlgorithm),
+    Ecdsa(HashAlgorithm),
+}
+
+impl SignatureAlgorithm {
+    /// Map a TLS SignatureScheme
+    pub fn from_tls(code: u16) -> Option<Self> {
+        match code {
+            0x0401 => Some(SignatureAlgorithm::RsaPkcs1(HashAlgorithm::Sha256)),
+            0x0501 => Some(SignatureAlgorithm::RsaPkcs1(HashAlgorithm::Sha384)),
+            0x0601 => Some(SignatureAlgorithm::RsaPkcs1(HashAlgorithm::Sha512)),
+            0x0403 => Some(SignatureAlgorithm::Ecdsa(HashAlgorithm::Sha256)),
+            0x0503 => Some(SignatureAlgorithm::Ecdsa(HashAlgorithm::Sha384)),
+            0x0804 => Some(SignatureAlgorithm::RsaPss(HashAlgorithm::Sha256)),
+            0x0805 => Some(SignatureAlgorithm::RsaPss(HashAlgorithm::Sha384)),
+            0x0806 => Some(SignatureAlgorithm::RsaPss(HashAlgorithm::Sha512)),
+            _ => None,
+        }
+    }
+
+    fn from_der(algorithm: &[u8]) -> Option<Self> {
+        let mut der = Der::new(algorithm);
+        let oid = try_option!(der.expect(TAG_OID));
+
+        if oid == &OID_SHA256_WITH_RSA[..] {
+            Some(SignatureAlgorithm::RsaPkcs1(HashAlgorithm::Sha256))
+        } else if oid == &OID_SHA384_WITH_RSA[..] {
+            Some(SignatureAlgorithm::RsaPkcs1(HashAlgorithm::Sha384))
+        } else if oid == &OID_SHA512_WITH_RSA[..] {
+            Some(SignatureAlgorithm::RsaPkcs1(HashAlgorithm::Sha512))
+        } else if oid == &OID_ECDSA_WITH_SHA256[..] {
+            Some(SignatureAlgorithm::Ecdsa(HashAlgorithm::Sha256))
+        } else if oid == &OID_ECDSA_WITH_SHA384[..] {
+            Some(SignatureAlgorithm::Ecdsa(HashAlgorithm::Sha384))
+        } else if oid == &OID_ECDSA_WITH_SHA512[..] {
+            Some(SignatureAlgorithm::Ecdsa(HashAlgorithm::Sha512))
+        } else if oid == &OID_RSASSA_PSS[..] {
+            // The hash is the first parameter, the salt is assumed to be as long as the digest
+            let mut params = Der::new(try_option!(der.expect(TAG_SEQUENCE)));
+            let mut hash = Der::new(try_option!(params.expect(0xa0)));
+            let mut hash = Der::new(try_option!(hash.expect(TAG_SEQUENCE)));
+            let hash_oid = try_option!(hash.expect(TAG_OID));
+            if hash_oid == &OID_SHA256[..] {
+                Some(SignatureAlgorithm::RsaPss(HashAlgorithm::Sha256))
+            } else if hash_oid == &OID_SHA384[..] {
+                Some(SignatureAlgorithm::RsaPss(HashAlgorithm::Sha384))
+            } else if hash_oid == &OID_SHA512[..] {
+                Some(SignatureAlgorithm::RsaPss(HashAlgorithm::Sha512))
+            } else {
+                None
+            }
+        } else {
+            None
+        }
+    }
+}
+
+impl PublicKey {
+    /// Parse the contents of a SubjectPublicKeyInfo
+    fn from_der(info: &[u8]) -> Option<Self> {
+        let mut der = Der::new(info);
+        let mut algorithm = Der::new(try_option!(der.expect(TAG_SEQUENCE)));
+        let key = try_option!(der.bit_string());
+
+        let oid = try_option!(algorithm.expect(TAG_OID));
+        if oid == &OID_RSA_ENCRYPTION[..] {
+            let mut key = Der::new(try_option!(Der::new(key).expect(TAG_SEQUENCE)));
+            let n = try_option!(key.expect(TAG_INTEGER));
+            let e = try_option!(key.expect(TAG_INTEGER));
+            Some(PublicKey::Rsa(RsaPublicKey {
+                n: BigUint::from_bytes_be(n),
+                e: BigUint::from_bytes_be(e),
+            }))
+        } else if oid == &OID_EC_PUBLIC_KEY[..] {
+            let curve = try_option!(algorithm.expect(TAG_OID));
+            if curve == &OID_P256[..] {
+                Some(PublicKey::Ec(NamedCurve::P256, key.to_vec()))
+            } else if curve == &OID_P384[..] {
+                Some(PublicKey::Ec(NamedCurve::P384, key.to_vec()))
+            } else {
+                None
+            }
+        } else {
+            None
+        }
+    }
+
+    /// Verify the signature of `message` made with the private half of this key
+    pub fn verify(&self, algorithm: SignatureAlgorithm, message: &[u8], signature: &[u8]) -> bool {
+        match (self, algorithm) {
+            (&PublicKey::Rsa(ref key), SignatureAlgorithm::RsaPkcs1(hash)) => key.verify_pkcs1(hash, message, signature),
+            (&PublicKey::Rsa(ref key), SignatureAlgorithm::RsaPss(hash)) => key.verify_pss(hash, message, signature),
+            (&PublicKey::Ec(curve, ref point), SignatureAlgorithm::Ecdsa(hash)) => {
+                // ECDSA signatures are a sequence of r and s
+                let mut der = match Der::new(signature).expect(TAG_SEQUENCE) {
+                    Some(contents) => Der::new(contents),
+                    None => return false,
+                };
+                match (der.expect(TAG_INTEGER), der.expect(TAG_INTEGER)) {
+                    (Some(r), Some(s)) => {
+                        curve.curve().verify(point, &hash.digest(message),
+                                             &BigUint::from_bytes_be(r), &BigUint::from_bytes_be(s))
+                    }
+                    _ => false,
+                }
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Days since 1970-01-01 of a date in the proleptic Gregorian calendar
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = (if year >= 0 { year } else { year - 399 }) / 400;
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+/// Parse a UTCTime or GeneralizedTime in UTC, as seconds since the epoch
+fn parse_time(tag: u8, time: &[u8]) -> Option<i64> {
+    let mut digits = Vec::new();
+    for &byte in time.iter() {
+        match byte {
+            b'0' ... b'9' => digits.push((byte - b'0') as i64),
+            b'Z' => break,
+            _ => return None,
+        }
+    }
+
+    let (year, rest) = if tag == TAG_UTC_TIME {
+        if digits.len() < 12 {
+            return None;
+        }
+        let year = digits[0] * 10 + digits[1];
+        (if year >= 50 { 1900 + year } else { 2000 + year }, &digits[2 ..])
+    } else {
+        if digits.len() < 14 {
+            return None;
+        }
+        (digits[0] * 1000 + digits[1] * 100 + digits[2] * 10 + digits[3], &digits[4 ..])
+    };
+
+    let month = rest[0] * 10 + rest[1];
+    let day = rest[2] * 10 + rest[3];
+    let hour = rest[4] * 10 + rest[5];
+    let minute = rest[6] * 10 + rest[7];
+    let second = rest[8] * 10 + rest[9];
+
+    Some(days_from_civil(year, month, day) * 86400 + hour * 3600 + minute * 60 + second)
+}
+
+/// Parse `a.b.c.d`
+fn parse_ipv4(name: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::new();
+    for part in name.split('.') {
+        if part.is_empty() || part.len() > 3 || !part.bytes().all(|byte| byte >= b'0' && byte <= b'9') {
+            return None;
+        }
+        let value = part.to_string().to_num();
+        if value > 255 {
+            return None;
+        }
+        bytes.push(value as u8);
+    }
+    if bytes.len() == 4 {
+        Some(bytes)
+    } else {
+        None
+    }
+}
+
+/// Decode base64, skipping whitespace
+fn base64_decode(string: &str) -> Option<Vec<u8>> {
+    let mut data = Vec::new();
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for byte in string.bytes() {
+        let value = match byte {
+            b'A' ... b'Z' => byte - b'A',
+            b'a' ... b'z' => byte - b'a' + 26,
+            b'0' ... b'9' => byte - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            b'=' => break,
+            b' ' | b'\t' | b'\r' | b'\n' => continue,
+            _ => return None,
+        };
+        buffer = buffer << 6 | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            data.push((buffer >> bits) as u8);
+        }
+    }
+    Some(data)
+}
+
+/// An X.509 certificate
+pub struct Certificate {
+    /// The whole encoding
+    pub der: Vec<u8>,
+    tbs: Vec<u8>,
+    signature_algorithm: Option<SignatureAlgorithm>,
+    signature: Vec<u8>,
+    /// The encoded issuer name
+    pub issuer: Vec<u8>,
+    /// The encoded subject name
+    pub subject: Vec<u8>,
+    pub common_name: String,
+    not_before: i64,
+    not_after: i64,
+    pub public_key: Option<PublicKey>,
+    ca: bool,
+    dns_names: Vec<String>,
+    ip_addrs: Vec<Vec<u8>>,
+}
+
+impl Certificate {
+    pub fn from_der(data: &[u8]) -> Option<Self> {
+        let mut der = Der::new(try_option!(Der::new(data).expect(TAG_SEQUENCE)));
+        let (_, tbs_contents, tbs) = try_option!(der.read_raw());
+        let signature_algorithm = SignatureAlgorithm::from_der(try_option!(der.expect(TAG_SEQUENCE)));
+        let signature = try_option!(der.bit_string());
+
+        let mut tbs_der = Der::new(tbs_contents);
+        tbs_der.optional(0xa0); // Version
+        try_option!(tbs_der.expect(TAG_INTEGER)); // Serial number
+        try_option!(tbs_der.expect(TAG_SEQUENCE)); // Signature algorithm, again
+        let (_, _, issuer) = try_option!(tbs_der.read_raw());
+
+        let mut validity = Der::new(try_option!(tbs_der.expect(TAG_SEQUENCE)));
+        let (before_tag, before) = try_option!(validity.read());
+        let (after_tag, after) = try_option!(validity.read());
+
+        let (_, subject_contents, subject) = try_option!(tbs_der.read_raw());
+        let public_key_info = try_option!(tbs_der.expect(TAG_SEQUENCE));
+
+        let mut certificate = Certificate {
+            der: data.to_vec(),
+            tbs: tbs.to_vec(),
+            signature_algorithm: signature_algorithm,
+            signature: signature.to_vec(),
+            issuer: issuer.to_vec(),
+            subject: subject.to_vec(),
+            common_name: String::new(),
+            not_before: try_option!(parse_time(before_tag, before)),
+            not_after: try_option!(parse_time(after_tag, after)),
+            public_key: PublicKey::from_der(public_key_info),
+            ca: false,
+            dns_names: Vec::new(),
+            ip_addrs: Vec::new(),
+        };
+
+        // The common name, used when there are no alternative names
+        let mut names = Der::new(subject_contents);
+        while let Some(set) = names.expect(TAG_SET) {
+            let mut attributes = Der::new(set);
+            while let Some(attribute) = attributes.expect(TAG_SEQUENCE) {
+                let mut attribute = Der::new(attribute);
+                if attribute.expect(TAG_OID) == Some(&OID_COMMON_NAME[..]) {
+                    if let Some((_, value)) = attribute.read() {
+                        certificate.common_name = String::from_utf8_lossy(value).to_string();
+                    }
+                }
+            }
+        }
+
+        tbs_der.optional(0x81); // Issuer unique ID
+        tbs_der.optional(0x82); // Subject unique ID
+        if let Some(extensions) = tbs_der.optional(0xa3) {
+            let mut extensions = Der::new(try_option!(Der::new(extensions).expect(TAG_SEQUENCE)));
+            while let Some(extension) = extensions.expect(TAG_SEQUENCE) {
+                let mut extension = Der::new(extension);
+                let oid = try_option!(extension.expect(TAG_OID));
+                extension.optional(TAG_BOOLEAN); // Critical
+                let value = try_option!(extension.expect(TAG_OCTET_STRING));
+
+                if oid == &OID_SUBJECT_ALT_NAME[..] {
+                    let mut names = Der::new(try_option!(Der::new(value).expect(TAG_SEQUENCE)));
+                    while let Some((tag, name)) = names.read() {
+                        match tag {
+                            0x82 => certificate.dns_names.push(String::from_utf8_lossy(name).to_lowercase()),
+                            0x87 => certificate.ip_addrs.push(name.to_vec()),
+                            _ => (),
+                        }
+                    }
+                } else if oid == &OID_BASIC_CONSTRAINTS[..] {
+                    let mut constraints = Der::new(try_option!(Der::new(value).expect(TAG_SEQUENCE)));
+                    if let Some(ca) = constraints.optional(TAG_BOOLEAN) {
+                        certificate.ca = ca.len() == 1 && ca[0] != 0;
+                    }
+                }
+            }
+        }
+
+        Some(certificate)
+    }
+
+    /// Every certificate in a PEM file, which may also hold other things
+    pub fn from_pem(pem: &str) -> Vec<Self> {
+        let mut certificates = Vec::new();
+
+        let mut base64 = None;
+        for line in pem.lines() {
+            let line = line.trim();
+            if line == "-----BEGIN CERTIFICATE-----" {
+                base64 = Some(String::new());
+            } else if line == "-----END CERTIFICATE-----" {
+                if let Some(base64) = base64.take() {
+                    if let Some(der) = base64_decode(&base64) {
+                        if let Some(certificate) = Certificate::from_der(&der) {
+                            certificates.push(certificate);
+                        }
+                    }
+                }
+            } else if let Some(ref mut base64) = base64 {
+                base64.push_str(line);
+            }
+        }
+
+        certificates
+    }
+
+    pub fn is_valid_at(&self, time: i64) -> bool {
+        self.not_before <= time && time <= self.not_after
+    }
+
+    /// Does the certificate name the host `name`, a DNS name or an IPv4 address
+    pub fn matches_name(&self, name: &str) -> bool {
+        if let Some(addr) = parse_ipv4(name) {
+            return self.ip_addrs.iter().any(|ip_addr| *ip_addr == addr);
+        }
+
+        let name = name.to_lowercase();
+        let matches = |pattern: &str| -> bool {
+            if pattern.starts_with("*.") {
+                // A wildcard only covers one label
+                match name.find('.') {
+                    Some(dot) => dot > 0 && name[dot ..] == pattern[1 ..],
+                    None => false,
+                }
+            } else {
+                pattern == name
+            }
+        };
+
+        if self.dns_names.is_empty() {
+            matches(&self.common_name.to_lowercase())
+        } else {
+            self.dns_names.iter().any(|dns_name| matches(dns_name))
+        }
+    }
+
+    /// Was this certificate signed by the key of `issuer`
+    pub fn is_signed_by(&self, issuer: &Certificate) -> bool {
+        match (self.signature_algorithm, &issuer.public_key) {
+            (Some(algorithm), &Some(ref key)) => key.verify(algorithm, &self.tbs, &self.signature),
+            _ => false,
+        }
+    }
+}
+
+/// Check that `chain`, as sent by a server, leads from a certificate for `name` to one of `roots`
+pub fn verify_chain(chain: &[Certificate], roots: &[Certificate], name: &str, time: i64) -> Result<(), &'static str> {
+    let mut current = match chain.first() {
+        Some(leaf) => leaf,
+        None => return Err("no certificate"),
+    };
+
+    if !current.matches_name(name) {
+        return Err("certificate is for another name");
+    }
+
+    for _ in 0..MAX_CHAIN_LEN {
+        if !current.is_valid_at(time) {
+            return Err("certificate expired or not yet valid");
+        }
+
+        if roots.iter().any(|root| root.der == current.der) {
+            return Ok(());
+        }
+
+        for root in roots.iter() {
+            if root.subject == current.issuer && current.is_signed_by(root) {
+                return if root.is_valid_at(time) {
+                    Ok(())
+                } else {
+                    Err("root certificate expired or not yet valid")
+                };
+            }
+        }
+
+        // A self-signed certificate that is not a root ends the chain
+        match chain[1 ..].iter().find(|issuer| issuer.der != current.der && issuer.ca &&
+                                               issuer.subject == current.issuer && current.is_signed_by(issuer)) {
+            Some(issuer) => current = issuer,
+            None => return Err("certificate chain does not lead to a trusted root"),
+        }
+    }
+
+    Err("certificate chain too long")
+}
+
+#[cfg(test)]
+mod tests {
+    use redox::*;
+
+    use super::{base64_decode, days_from_civil, parse_time, verify_chain, Certificate};
+    use super::super::der::{TAG_GENERALIZED_TIME, TAG_UTC_TIME};
+
+    /// A P-256 root, `CN=Test Root`
+    const ROOT: &'static str = "-----BEGIN CERTIFICATE-----
+MIIBfTCCASOgAwIBAgIUNKTi/Gz2qJ1ORvpU3sQTFNm4Du8wCgYIKoZIzj0EAwIw
+FDESMBAGA1UEAwwJVGVzdCBSb290MB4XDTI2MTAxODIzMzY0N1oXDTM2MTAxNTIz
+MzY0N1owFDESMBAGA1UEAwwJVGVzdCBSb290MFkwEwYHKoZIzj0CAQYIKoZIzj0D
+AQcDQgAEotCf+J1xNX4Ip73NYdTDtNZMSDuutdFDYzXr7Oj5k3Aud05MIir0Jb46
+aLnWQMYfFFSuYBGDn5Hq6xRyKbPSSKNTMFEwHQYDVR0OBBYEFEQgViwi+UgPYE9E
+rQvSD4XaY6eyMB8GA1UdIwQYMBaAFEQgViwi+UgPYE9ErQvSD4XaY6eyMA8GA1Ud
+EwEB/wQFMAMBAf8wCgYIKoZIzj0EAwIDSAAwRQIhANhg32bX5VKLKLLTIKHKGtQU
+fLeLGB0Nqz7yb9YmBdbSAiBjCb6yNpt7mKD/DZ/XgQXKg9mdKQ4vpDjIy7OMhu2C
+zw==
+-----END CERTIFICATE-----";
+
+    /// Another root with the same name as `ROOT` and its own key
+    const OTHER_ROOT: &'static str = "-----BEGIN CERTIFICATE-----
+MIIBfTCCASOgAwIBAgIUT0DtIVp0zlnYhRfQJEnxnPnkA3YwCgYIKoZIzj0EAwIw
+FDESMBAGA1UEAwwJVGVzdCBSb290MB4XDTI2MTAxODIzMzY0N1oXDTM2MTAxNTIz
+MzY0N1owFDESMBAGA1UEAwwJVGVzdCBSb290MFkwEwYHKoZIzj0CAQYIKoZIzj0D
+AQcDQgAEUG4Go2rwh2fcMZGU+7cZl5mVY108WME8DmbJWDTs7H0f/d/yeZiKnN4H
+xITQlIZurFXsb0xWyk4VmNXzSHVv/qNTMFEwHQYDVR0OBBYEFM0VHegczKRIT2+p
+r3WX26L9csAdMB8GA1UdIwQYMBaAFM0VHegczKRIT2+pr3WX26L9csAdMA8GA1Ud
+EwEB/wQFMAMBAf8wCgYIKoZIzj0EAwIDSAAwRQIgLS/lZmUzIGVyVasuxvDZhcrv
+OvPu4biRhH6edeXCitQCIQCJDeDs/OH1Vpn2SIFtAg56+xqv8EqVCM4LhZDkT+AO
+sQ==
+-----END CERTIFICATE-----";
+
+    /// Signed by `ROOT`, `CN=ignored.test` with the names `example.com`, `*.example.org` and `10.0.0.1`
+    const LEAF: &'static str = "-----BEGIN CERTIFICATE-----
+MIIBpzCCAU2gAwIBAgIUCMXQayXNZEde4DACBY8gVCRCQv4wCgYIKoZIzj0EAwIw
+FDESMBAGA1UEAwwJVGVzdCBSb290MB4XDTI2MTAxODIzMzY0N1oXDTI3MTAxODIz
+MzY0N1owFzEVMBMGA1UEAwwMaWdub3JlZC50ZXN0MFkwEwYHKoZIzj0CAQYIKoZI
+zj0DAQcDQgAEvyDkkxF2hWeUd6UMEOWO3slmoSLeDBlNwfl8nxyw0eog/7rMMe3l
+xTfxDh1Vup9GFYK8Oeb2H6s/MT0zyJCpAqN6MHgwKwYDVR0RBCQwIoILZXhhbXBs
+ZS5jb22CDSouZXhhbXBsZS5vcmeHBAoAAAEwCQYDVR0TBAIwADAdBgNVHQ4EFgQU
+bi/iRCk2NU5TvXTtYSR/lcin4fYwHwYDVR0jBBgwFoAURCBWLCL5SA9gT0StC9IP
+hdpjp7IwCgYIKoZIzj0EAwIDSAAwRQIhANUpQ+ZLH43Q10XIFbWsRJXfNUCyhnSi
+SP7RUj3BeVcBAiAEcecZ+0HVuXayFyYCXLeSmMJALpo5tP4I0OgpueYGQA==
+-----END CERTIFICATE-----";
+
+    fn certificate(pem: &str) -> Certificate {
+        Certificate::from_pem(pem).pop().unwrap()
+    }
+
+    /// A certificate with only names, to test name matching
+    fn named(common_name: &str, dns_names: &[&str], ip_addrs: &[[u8; 4]]) -> Certificate {
+        Certificate {
+            der: Vec::new(),
+            tbs: Vec::new(),
+            signature_algorithm: None,
+            signature: Vec::new(),
+            issuer: Vec::new(),
+            subject: Vec::new(),
+            common_name: common_name.to_string(),
+            not_before: 0,
+            not_after: 0,
+            public_key: None,
+            ca: false,
+            dns_names: dns_names.iter().map(|name| name.to_string()).collect(),
+            ip_addrs: ip_addrs.iter().map(|addr| addr.to_vec()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_days_from_civil() {
+        assert_eq!(days_from_civil(1970, 1, 1), 0);
+        assert_eq!(days_from_civil(1969, 12, 31), -1);
+        assert_eq!(days_from_civil(2000, 2, 29), 11016);
+        assert_eq!(days_from_civil(2000, 3, 1), 11017);
+        assert_eq!(days_from_civil(1900, 3, 1), -25508);
+    }
+
+    #[test]
+    fn test_parse_time() {
+        // UTCTime years 50 to 99 are 19YY, 00 to 49 are 20YY
+        assert_eq!(parse_time(TAG_UTC_TIME, b"500101000000Z"), Some(-631152000));
+        assert_eq!(parse_time(TAG_UTC_TIME, b"491231235959Z"), Some(2524607999));
+        assert_eq!(parse_time(TAG_UTC_TIME, b"700101000000Z"), Some(0));
+        assert_eq!(parse_time(TAG_GENERALIZED_TIME, b"20000229120000Z"), Some(951825600));
+        assert_eq!(parse_time(TAG_GENERALIZED_TIME, b"20491231235959Z"), Some(2524607999));
+
+        assert_eq!(parse_time(TAG_UTC_TIME, b"5001010000Z"), None);
+        assert_eq!(parse_time(TAG_GENERALIZED_TIME, b"500101000000Z"), None);
+        assert_eq!(parse_time(TAG_UTC_TIME, b"50-101000000Z"), None);
+    }
+
+    #[test]
+    fn test_base64_decode() {
+        assert_eq!(base64_decode("aGVsbG8="), Some(b"hello".to_vec()));
+        assert_eq!(base64_decode("aGVs\r\nbG8h"), Some(b"hello!".to_vec()));
+        assert_eq!(base64_decode(""), Some(Vec::new()));
+        assert_eq!(base64_decode("aGVs*G8="), None);
+    }
+
+    #[test]
+    fn test_wildcard_matches_one_label() {
+        let certificate = named("", &["*.example.com"], &[]);
+        assert!(certificate.matches_name("www.example.com"));
+        assert!(certificate.matches_name("WWW.Example.COM"));
+        assert!(!certificate.matches_name("a.www.example.com"));
+        assert!(!certificate.matches_name("example.com"));
+        assert!(!certificate.matches_name(".example.com"));
+        assert!(!certificate.matches_name("www.example.org"));
+    }
+
+    #[test]
+    fn test_ip_names() {
+        let certificate = named("10.0.0.2", &["10.0.0.3"], &[[10, 0, 0, 1]]);
+        assert!(certificate.matches_name("10.0.0.1"));
+        // Addresses only match address names, never DNS names or the common name
+        assert!(!certificate.matches_name("10.0.0.2"));
+        assert!(!certificate.matches_name("10.0.0.3"));
+    }
+
+    #[test]
+    fn test_common_name_fallback() {
+        assert!(named("host.test", &[], &[]).matches_name("host.test"));
+        assert!(named("*.test", &[], &[]).matches_name("host.test"));
+        // With alternative names, the common name is not used
+        assert!(!named("host.test", &["other.test"], &[]).matches_name("host.test"));
+        assert!(!named("host.test", &[], &[[10, 0, 0, 1]]).matches_name("host.test"));
+    }
+
+    #[test]
+    fn test_parse_certificate() {
+        let leaf = certificate(LEAF);
+        assert_eq!(leaf.common_name, "ignored.test");
+        assert_eq!(leaf.dns_names, vec!["example.com".to_string(), "*.example.org".to_string()]);
+        assert_eq!(leaf.ip_addrs, vec![vec![10, 0, 0, 1]]);
+        assert!(!leaf.ca);
+        assert!(certificate(ROOT).ca);
+        assert!(leaf.not_before < leaf.not_after);
+    }
+
+    #[test]
+    fn test_verify_chain() {
+        let leaf = certificate(LEAF);
+        let time = leaf.not_before + 60;
+        let chain = [leaf];
+        let roots = [certificate(ROOT)];
+
+        assert_eq!(verify_chain(&chain, &roots, "example.com", time), Ok(()));
+        assert_eq!(verify_chain(&chain, &roots, "www.example.org", time), Ok(()));
+        assert_eq!(verify_chain(&chain, &roots, "10.0.0.1", time), Ok(()));
+        assert_eq!(verify_chain(&chain, &roots, "ignored.test", time),
+                   Err("certificate is for another name"));
+        assert_eq!(verify_chain(&[], &roots, "example.com", time), Err("no certificate"));
+    }
+
+    #[test]
+    fn test_verify_chain_expired() {
+        let leaf = certificate(LEAF);
+        let before = leaf.not_before - 1;
+        let after = leaf.not_after + 1;
+        let chain = [leaf];
+        let roots = [certificate(ROOT)];
+
+        assert_eq!(verify_chain(&chain, &roots, "example.com", before),
+                   Err("certificate expired or not yet valid"));
+        assert_eq!(verify_chain(&chain, &roots, "example.com", after),
+                   Err("certificate expired or not yet valid"));
+    }
+
+    #[test]
+    fn test_verify_chain_wrong_issuer() {
+        let leaf = certificate(LEAF);
+        let time = leaf.not_before + 60;
+
+        // Same name as the issuer, but the signature does not verify with its key
+        assert_eq!(verify_chain(&[leaf], &[certificate(OTHER_ROOT)], "example.com", time),
+                   Err("certificate chain does not lead to a trusted root"));
+    }
+
+    #[test]
+    fn test_verify_chain_untrusted() {
+        let leaf = certificate(LEAF);
+        let time = leaf.not_before + 60;
+
+        // The root is sent by the server but not trusted
+        assert_eq!(verify_chain(&[leaf, certificate(ROOT)], &[], "example.com", time),
+                   Err("certificate chain does not lead to a trusted root"));
+    }
+}
@@ -1,4 +1,5 @@
 use redox::Box;
+use redox::collections::VecDeque;
 use redox::fs::File;
 use redox::io::{Read, Write, SeekFrom};
 use redox::mem;
@@ -50,63 +51,165 @@ impl ToBytes for Udp {
     }
 }
 
+/// Copy a datagram into `buf`, the part that does not fit is discarded
+fn copy_datagram(buf: &mut [u8], data: &[u8]) -> usize {
+    let mut i = 0;
+    while i < buf.len() && i < data.len() {
+        buf[i] = data[i];
+        i += 1;
+    }
+    i
+}
+
 /// UDP resource
 pub struct Resource {
-    ip: File,
-    data: Vec<u8>,
-    peer_addr: IPv4Addr,
+    /// The link to the peer, for a bound socket the sender of the last datagram, opened to answer it
+    ip: Option<File>,
+    /// For a bound socket, the `ip:///11` listener receiving the datagrams of every peer
+    listener: Option<File>,
+    /// Datagrams received by a bound socket and not read yet, with the sender, its port, and
+    /// the address they were sent to
+    queue: VecDeque<(IpAddr, u16, IpAddr, Vec<u8>)>,
+    host_addr: IpAddr,
+    peer_addr: IpAddr,
     peer_port: u16,
     host_port: u16,
+    netstat: NetstatEntry,
 }
 
 impl Resource {
-    pub fn dup(&self) -> Option<Box<Self>> {
-        match self.ip.dup() {
-            Some(ip) => Some(box Resource {
-                ip: ip,
-                data: self.data.clone(),
-                peer_addr: self.peer_addr,
-                peer_port: self.peer_port,
-                host_port: self.host_port,
-            }),
-            None => None
+    /// Bind to `host_port`, reads take datagrams from any sender and writes answer the sender of
+    /// the last one
+    fn bind(host_port: u16) -> Option<Box<Self>> {
+        match File::open("ip:///11") {
+            Some(listener) => {
+                let any = IpAddr::V4(IPv4Addr { bytes: [0, 0, 0, 0] });
+
+                let mut ret = box Resource {
+                    ip: None,
+                    listener: Some(listener),
+                    queue: VecDeque::new(),
+                    host_addr: any,
+                    peer_addr: any,
+                    peer_port: 0,
+                    host_port: host_port,
+                    netstat: NetstatEntry::new(),
+                };
+                ret.report();
+
+                Some(ret)
+            }
+            None => None,
+        }
+    }
+
+    /// Wait for the next datagram on the listener, and queue it if it is sent to our port
+    fn receive(&mut self) -> bool {
+        if let Some(ref mut listener) = self.listener {
+            let mut bytes = [0; 65536];
+            if let Some(count) = listener.read(&mut bytes) {
+                if let Some(datagram) = Udp::from_bytes(bytes[.. count].to_vec()) {
+                    if datagram.header.dst.get() == self.host_port {
+                        if let Some(path) = listener.path() {
+                            // ip://PEER/PROTO/LOCAL
+                            let peer_addr = IpAddr::V4(IPv4Addr::from_string(&Url::from_string(path).host()));
+                            if let Some(host_addr) = IpAddr::local(listener) {
+                                self.queue.push_back((peer_addr, datagram.header.src.get(), host_addr, datagram.data));
+                            }
+                        }
+                    }
+                }
+                return true;
+            }
         }
+
+        false
+    }
+
+    pub fn dup(&self) -> Option<Box<Self>> {
+        let ip = match self.ip {
+            Some(ref ip) => match ip.dup() {
+                Some(ip) => Some(ip),
+                None => return None,
+            },
+            None => None,
+        };
+
+        let listener = match self.listener {
+            Some(ref listener) => match listener.dup() {
+                Some(listener) => Some(listener),
+                None => return None,
+            },
+            None => None,
+        };
+
+        let mut ret = box Resource {
+            ip: ip,
+            listener: listener,
+            queue: self.queue.clone(),
+            host_addr: self.host_addr,
+            peer_addr: self.peer_addr,
+            peer_port: self.peer_port,
+            host_port: self.host_port,
+            netstat: NetstatEntry::new(),
+        };
+        ret.report();
+
+        Some(ret)
+    }
+
+    /// List the addresses and ports with `netstat:`, UDP has no state
+    fn report(&mut self) {
+        self.netstat.report("udp", self.host_addr, self.host_port, self.peer_addr, self.peer_port, "");
     }
 
     pub fn path(&self) -> Option<String> {
-        Some(format!("udp://{}:{}/{}", self.peer_addr.to_string(), self.peer_port, self.host_port))
+        Some(format!("udp://{}:{}/{}", self.peer_addr.to_url_host(), self.peer_port, self.host_port))
     }
 
     pub fn read(&mut self, buf: &mut [u8]) -> Option<usize> {
-        if !self.data.is_empty() {
-            let mut bytes: Vec<u8> = Vec::new();
-            mem::swap(&mut self.data, &mut bytes);
-
-            //TODO: Allow splitting
-            let i = 0;
-            while i < buf.len() && i < bytes.len() {
-                 buf[i] = bytes[i];
+        if self.listener.is_some() {
+            while self.queue.is_empty() {
+                if !self.receive() {
+                    return None;
+                }
             }
-            return Some(i);
+
+            // Follow the sender of the datagram, so path() reports it and write() answers it
+            return match self.queue.pop_front() {
+                Some((peer_addr, peer_port, host_addr, data)) => {
+                    if peer_addr.to_string() != self.peer_addr.to_string() {
+                        self.ip = None;
+                    }
+                    let changed = !peer_addr.equals(self.peer_addr) || peer_port != self.peer_port ||
+                                  !host_addr.equals(self.host_addr);
+                    self.peer_addr = peer_addr;
+                    self.peer_port = peer_port;
+                    self.host_addr = host_addr;
+                    if changed {
+                        self.report();
+                    }
+
+                    Some(copy_datagram(buf, &data))
+                }
+                None => None,
+            };
         }
 
-        loop {
-            let mut bytes: Vec<u8> = Vec::new();
-            match self.ip.read_to_end(&mut bytes) {
-                Some(_) => {
-                    if let Some(datagram) = Udp::from_bytes(bytes) {
-                        if datagram.header.dst.get() == self.host_port &&
-                           datagram.header.src.get() == self.peer_port {
-                            //TODO: Allow splitting
-                            let i = 0;
-                            while i < buf.len() && i < datagram.data.len() {
-                                buf[i] = datagram.data[i];
+        if let Some(ref mut ip) = self.ip {
+            loop {
+                let mut bytes = [0; 65536];
+                match ip.read(&mut bytes) {
+                    Some(count) => {
+                        if let Some(datagram) = Udp::from_bytes(bytes[.. count].to_vec()) {
+                            if datagram.header.dst.get() == self.host_port &&
+                               datagram.header.src.get() == self.peer_port {
+                                return Some(copy_datagram(buf, &datagram.data));
                             }
-                            return Some(i);
                         }
                     }
+                    None => break,
                 }
-                None => break,
             }
         }
 
@@ -114,6 +217,14 @@ impl Resource {
     }
 
     pub fn write(&mut self, buf: &[u8]) -> Option<usize> {
+        if self.peer_port == 0 {
+            return None;
+        }
+
+        if self.ip.is_none() {
+            self.ip = File::open(&self.peer_addr.ip_path(0x11));
+        }
+
         let udp_data = Vec::from(buf);
 
         let mut udp = Udp {
@@ -130,10 +241,7 @@ impl Resource {
             let proto = n16::new(0x11);
             let datagram_len = n16::new((mem::size_of::<UdpHeader>() + udp.data.len()) as u16);
             udp.header.checksum.data =
-                Checksum::compile(Checksum::sum((&IP_ADDR as *const IPv4Addr) as usize,
-                                                mem::size_of::<IPv4Addr>()) +
-                                  Checksum::sum((&self.peer_addr as *const IPv4Addr) as usize,
-                                                mem::size_of::<IPv4Addr>()) +
+                Checksum::compile(self.host_addr.sum() + self.peer_addr.sum() +
                                   Checksum::sum((&proto as *const n16) as usize,
                                                 mem::size_of::<n16>()) +
                                   Checksum::sum((&datagram_len as *const n16) as usize,
@@ -143,9 +251,12 @@ impl Resource {
                                   Checksum::sum(udp.data.as_ptr() as usize, udp.data.len()));
         }
 
-        match self.ip.write(&udp.to_bytes()) {
-            Some(_) => return Some(buf.len()),
-            None => return None,
+        match self.ip {
+            Some(ref mut ip) => match ip.write(&udp.to_bytes()) {
+                Some(_) => Some(buf.len()),
+                None => None,
+            },
+            None => None,
         }
     }
 
@@ -154,7 +265,10 @@ impl Resource {
     }
 
     pub fn sync(&mut self) -> bool {
-        self.ip.sync()
+        match self.ip {
+            Some(ref mut ip) => ip.sync(),
+            None => true,
+        }
     }
 }
 
@@ -170,43 +284,39 @@ impl Scheme {
         let url = Url::from_str(&url_str);
 
         //Check host and port vs path
-        if !url.path().is_empty() {
-            let host_port = url.port().to_num();
+        if url.host().is_empty() {
+            let host_port = url.path().to_num();
             if host_port > 0 && host_port < 65536 {
-                if let Some(mut ip) = File::open("ip:///11") {
-                    let mut bytes: Vec<u8> = Vec::new();
-                    if ip.read_to_end(&mut bytes).is_some() {
-                        if let Some(datagram) = Udp::from_bytes(bytes) {
-                            if datagram.header.dst.get() as usize == host_port {
-                                if let Some(path) = ip.path() {
-                                    let url = Url::from_string(path);
-
-                                    return Some(box Resource {
-                                        ip: ip,
-                                        data: datagram.data,
-                                        peer_addr: IPv4Addr::from_string(&url.host()),
-                                        peer_port: datagram.header.src.get(),
-                                        host_port: host_port as u16,
-                                    });
-                                }
-                            }
-                        }
-                    }
-                }
+                return Resource::bind(host_port as u16);
             }
         } else {
             let peer_port = url.port().to_num();
             if peer_port > 0 && peer_port < 65536 {
-                let host_port = (rand() % 32768 + 32768) as u16;
+                let host_port = match url.path().to_num() {
+                    port if port > 0 && port < 65536 => port as u16,
+                    _ => (rand() % 32768 + 32768) as u16,
+                };
 
-                if let Some(ip) = File::open(&format!("ip://{}/11", url.host())) {
-                    return Some(box Resource {
-                        ip: ip,
-                        data: Vec::new(),
-                        peer_addr: IPv4Addr::from_string(&url.host()),
+                let peer_addr = IpAddr::from_string(&url.host());
+                if let Some(ip) = File::open(&peer_addr.ip_path(0x11)) {
+                    let host_addr = match IpAddr::local(&ip) {
+                        Some(host_addr) => host_addr,
+                        None => return None,
+                    };
+
+                    let mut ret = box Resource {
+                        ip: Some(ip),
+                        listener: None,
+                        queue: VecDeque::new(),
+                        host_addr: host_addr,
+                        peer_addr: peer_addr,
                         peer_port: peer_port as u16,
                         host_port: host_port,
-                    });
+                        netstat: NetstatEntry::new(),
+                    };
+                    ret.report();
+
+                    return Some(ret);
                 }
             }
         }
@@ -0,0 +1,15 @@
+use collections::vec::Vec;
+
+/// Append a little endian u16
+pub fn push_u16(vec: &mut Vec<u8>, value: u16) {
+    vec.push(value as u8);
+    vec.push((value >> 8) as u8);
+}
+
+/// Append a little endian u32
+pub fn push_u32(vec: &mut Vec<u8>, value: u32) {
+    vec.push(value as u8);
+    vec.push((value >> 8) as u8);
+    vec.push((value >> 16) as u8);
+    vec.push((value >> 24) as u8);
+}
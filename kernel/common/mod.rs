@@ -1,3 +1,5 @@
+/// Little endian bytes
+pub mod bytes;
 /// Debug
 #[macro_use]
 pub mod debug;
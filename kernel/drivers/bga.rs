@@ -0,0 +1,150 @@
+use alloc::boxed::Box;
+
+use collections::vec::Vec;
+
+use common::debug;
+
+use drivers::pciconfig::PciConfig;
+use drivers::pio::*;
+
+use graphics::display::{DisplayMode, Framebuffer};
+
+// Registers, selected by writing to the index port
+const INDEX_ID: u16 = 0;
+const INDEX_XRES: u16 = 1;
+const INDEX_YRES: u16 = 2;
+const INDEX_BPP: u16 = 3;
+const INDEX_ENABLE: u16 = 4;
+    const ENABLED: u16 = 1;
+    const GET_CAPS: u16 = 1 << 1;
+    const LFB_ENABLED: u16 = 1 << 6;
+const INDEX_VIRT_WIDTH: u16 = 6;
+const INDEX_X_OFFSET: u16 = 8;
+const INDEX_Y_OFFSET: u16 = 9;
+
+/// The oldest version that has a linear framebuffer
+const ID_MIN: u16 = 0xB0C2;
+const ID_MAX: u16 = 0xB0C5;
+
+/// The modes offered, when they fit in the limits and memory of the adapter
+const MODES: [(usize, usize); 13] = [(640, 480),
+                                     (800, 600),
+                                     (1024, 768),
+                                     (1152, 864),
+                                     (1280, 720),
+                                     (1280, 800),
+                                     (1280, 1024),
+                                     (1440, 900),
+                                     (1600, 900),
+                                     (1600, 1200),
+                                     (1680, 1050),
+                                     (1920, 1080),
+                                     (1920, 1200)];
+
+/// The Bochs graphics adapter, found in Bochs and in QEMU with `-vga std`
+pub static mut bga_ptr: *mut Bga = 0 as *mut Bga;
+
+/// A Bochs graphics adapter
+pub struct Bga {
+    index: Pio16,
+    data: Pio16,
+    /// The linear framebuffer
+    framebuffer: usize,
+    /// The size of the framebuffer memory
+    memory: usize,
+    /// The largest mode
+    max: (usize, usize),
+}
+
+impl Bga {
+    /// Find the adapter, and keep it in `bga_ptr` for the session to switch modes
+    pub unsafe fn init(mut pci: PciConfig) {
+        let bar = pci.read(0x10);
+        pci.write(0x10, 0xFFFFFFFF);
+        let memory = (!(pci.read(0x10) & 0xFFFFFFF0)).wrapping_add(1) as usize;
+        pci.write(0x10, bar);
+
+        let mut module = box Bga {
+            index: Pio16::new(0x1CE),
+            data: Pio16::new(0x1CF),
+            framebuffer: (bar & 0xFFFFFFF0) as usize,
+            memory: memory,
+            max: (0, 0),
+        };
+
+        let id = module.read(INDEX_ID);
+        if id < ID_MIN || id > ID_MAX {
+            debug::d("BGA version not supported: ");
+            debug::dh(id as usize);
+            debug::dl();
+            return;
+        }
+
+        // The limits are read instead of the mode while asking for capabilities
+        let enable = module.read(INDEX_ENABLE);
+        module.write(INDEX_ENABLE, enable | GET_CAPS);
+        module.max = (module.read(INDEX_XRES) as usize, module.read(INDEX_YRES) as usize);
+        module.write(INDEX_ENABLE, enable);
+
+        debug::d("BGA on: ");
+        debug::dh(module.framebuffer);
+        debug::d(" Memory: ");
+        debug::dd(module.memory / 1024 / 1024);
+        debug::d(" MB Max: ");
+        debug::dd(module.max.0);
+        debug::d("x");
+        debug::dd(module.max.1);
+        debug::dl();
+
+        bga_ptr = Box::into_raw(module);
+    }
+
+    unsafe fn read(&mut self, index: u16) -> u16 {
+        self.index.write(index);
+        self.data.read()
+    }
+
+    unsafe fn write(&mut self, index: u16, value: u16) {
+        self.index.write(index);
+        self.data.write(value);
+    }
+
+    /// The modes that can be set
+    pub fn modes(&self) -> Vec<DisplayMode> {
+        let mut modes = Vec::new();
+        for &(width, height) in MODES.iter() {
+            if width <= self.max.0 && height <= self.max.1 && width * height * 4 <= self.memory {
+                modes.push(DisplayMode::new(width, height));
+            }
+        }
+        modes
+    }
+
+    /// Set a mode with 32 bits per pixel, returning the framebuffer
+    pub unsafe fn set_mode(&mut self, mode: DisplayMode) -> Option<Framebuffer> {
+        if !self.modes().contains(&mode) {
+            return None;
+        }
+
+        self.write(INDEX_ENABLE, 0);
+        self.write(INDEX_XRES, mode.width as u16);
+        self.write(INDEX_YRES, mode.height as u16);
+        self.write(INDEX_BPP, 32);
+        self.write(INDEX_VIRT_WIDTH, mode.width as u16);
+        self.write(INDEX_X_OFFSET, 0);
+        self.write(INDEX_Y_OFFSET, 0);
+        self.write(INDEX_ENABLE, ENABLED | LFB_ENABLED);
+
+        if self.read(INDEX_XRES) as usize == mode.width &&
+           self.read(INDEX_YRES) as usize == mode.height {
+            Some(Framebuffer {
+                address: self.framebuffer,
+                width: mode.width,
+                height: mode.height,
+                bytesperrow: mode.width * 4,
+            })
+        } else {
+            None
+        }
+    }
+}
@@ -1,3 +1,5 @@
+/// Bochs graphics adapter
+pub mod bga;
 /// Disk
 pub mod disk;
 /// MMIO
@@ -6,10 +6,13 @@ use collections::vec::Vec;
 use common::debug;
 use common::queue::Queue;
 
+use drivers::bga::Bga;
 use drivers::pciconfig::PciConfig;
 
 use network::intel8254x::Intel8254x;
+use network::scheme::NetworkStats;
 use network::rtl8139::Rtl8139;
+use network::virtio::VirtioNet;
 
 use programs::session::Session;
 
@@ -84,6 +87,7 @@ pub unsafe fn pci_device(session: &mut Session,
                         resources: Vec::new(),
                         inbound: Queue::new(),
                         outbound: Queue::new(),
+                        stats: NetworkStats::new("intel8254x"),
                     };
                     module.init();
                     session.items.push(module);
@@ -103,6 +107,14 @@ pub unsafe fn pci_device(session: &mut Session,
                 }
                 _ => (),
             },
+            0x1234 => match device_code { // BOCHS
+                0x1111 => Bga::init(pci),
+                _ => (),
+            },
+            0x1AF4 => match device_code { // VIRTIO
+                0x1000 => session.items.push(VirtioNet::new(pci)),
+                _ => (),
+            },
             _ => (),
         }
     }
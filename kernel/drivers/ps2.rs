@@ -1,6 +1,6 @@
 use alloc::boxed::Box;
 
-use common::event::{KeyEvent, MouseEvent};
+use common::event::{KeyEvent, MouseEvent, ScrollEvent};
 
 use drivers::pio::*;
 
@@ -24,6 +24,8 @@ pub struct Ps2 {
     mouse_packet: [u8; 4],
     /// Mouse
     mouse_i: usize,
+    /// Does the mouse send a fourth byte with the scroll wheel?
+    mouse_extra: bool,
 }
 
 impl Ps2 {
@@ -38,6 +40,7 @@ impl Ps2 {
             caps_lock_toggle: false,
             mouse_packet: [0; 4],
             mouse_i: 0,
+            mouse_extra: false,
         };
 
         unsafe {
@@ -168,24 +171,37 @@ impl Ps2 {
         //Set defaults
         self.mouse_cmd(0xF6);
 
+        //Enable the scroll wheel, by setting the sample rates that IntelliMouse compatible mice know
+        self.mouse_cmd(0xF3);
+        self.mouse_cmd(200);
+        self.mouse_cmd(0xF3);
+        self.mouse_cmd(100);
+        self.mouse_cmd(0xF3);
+        self.mouse_cmd(80);
+
+        //Get the ID, which is 3 if the wheel was enabled
+        self.mouse_cmd(0xF2);
+        self.wait0();
+        self.mouse_extra = self.data.read() == 3;
+
         //Enable Streaming
         self.mouse_cmd(0xF4);
     }
 
     /// Mouse interrupt
-    pub fn mouse_interrupt(&mut self) -> Option<MouseEvent> {
+    pub fn mouse_interrupt(&mut self) -> Option<(MouseEvent, ScrollEvent)> {
         let byte = unsafe { self.data.read() };
         if self.mouse_i == 0 {
             if byte & 0x8 == 0x8 {
                 self.mouse_packet[0] = byte;
                 self.mouse_i += 1;
             }
-        } else if self.mouse_i == 1 {
-            self.mouse_packet[1] = byte;
+        } else if self.mouse_i == 1 || (self.mouse_i == 2 && self.mouse_extra) {
+            self.mouse_packet[self.mouse_i] = byte;
 
             self.mouse_i += 1;
         } else {
-            self.mouse_packet[2] = byte;
+            self.mouse_packet[self.mouse_i] = byte;
 
             let left_button = (self.mouse_packet[0] & 1) == 1;
             let right_button = (self.mouse_packet[0] & 2) == 2;
@@ -207,15 +223,26 @@ impl Ps2 {
                 y = 0;
             }
 
+            //The wheel movement is a four bit signed number, positive downwards
+            let z;
+            if self.mouse_extra {
+                z = ((self.mouse_packet[3] as isize) & 0x7) - ((self.mouse_packet[3] as isize) & 0x8);
+            } else {
+                z = 0;
+            }
+
             self.mouse_i = 0;
 
-            return Some(MouseEvent {
+            return Some((MouseEvent {
                 x: x,
                 y: y,
                 left_button: left_button,
                 right_button: right_button,
                 middle_button: middle_button,
-            });
+            }, ScrollEvent {
+                x: 0,
+                y: -z,
+            }));
         }
 
         return None;
@@ -237,8 +264,11 @@ impl KScheme for Ps2 {
                     key_event.trigger();
                 }
             } else if status & 0x21 == 0x21 {
-                if let Some(mouse_event) = self.mouse_interrupt() {
+                if let Some((mouse_event, scroll_event)) = self.mouse_interrupt() {
                     mouse_event.trigger();
+                    if scroll_event.y != 0 {
+                        scroll_event.trigger();
+                    }
                 }
             } else {
                 break;
@@ -3,11 +3,17 @@ use collections::vec::Vec;
 
 use core::slice;
 
+use common::bytes::{push_u16, push_u32};
+
 use super::color::Color;
+use super::decode;
 use super::display::Display;
 use super::point::Point;
 use super::size::Size;
 
+/// The size of the file header and the BITMAPV4HEADER written by `to_data`
+const HEADER_SIZE: u32 = 14 + 108;
+
 // TODO: Follow naming convention
 /// A bitmap
 pub struct BmpFile {
@@ -120,6 +126,65 @@ impl BmpFile {
         ret
     }
 
+    /// Create a bitmap from a BMP, PNG or JPEG, picking the decoder by the signature
+    pub fn from_image(file_data: &Vec<u8>) -> Self {
+        match decode::decode(file_data) {
+            Some((width, height, data)) => BmpFile {
+                data: data,
+                size: Size {
+                    width: width,
+                    height: height,
+                },
+            },
+            None => BmpFile::from_data(file_data),
+        }
+    }
+
+    /// Encode the bitmap as a BMP with 32 bits per pixel, which `from_data` can read back
+    pub fn to_data(&self) -> Vec<u8> {
+        let image_size = (self.size.width * self.size.height * 4) as u32;
+
+        let mut data = Vec::with_capacity((HEADER_SIZE + image_size) as usize);
+
+        // File header
+        data.push('B' as u8);
+        data.push('M' as u8);
+        push_u32(&mut data, HEADER_SIZE + image_size);
+        push_u32(&mut data, 0);
+        push_u32(&mut data, HEADER_SIZE);
+
+        // BITMAPV4HEADER, with the masks in the place `from_data` looks for them
+        push_u32(&mut data, 108);
+        push_u32(&mut data, self.size.width as u32);
+        push_u32(&mut data, self.size.height as u32);
+        push_u16(&mut data, 1);
+        push_u16(&mut data, 32);
+        push_u32(&mut data, 3);
+        push_u32(&mut data, image_size);
+        push_u32(&mut data, 2835);
+        push_u32(&mut data, 2835);
+        push_u32(&mut data, 0);
+        push_u32(&mut data, 0);
+        push_u32(&mut data, 0xFF0000);
+        push_u32(&mut data, 0xFF00);
+        push_u32(&mut data, 0xFF);
+        push_u32(&mut data, 0xFF000000);
+        // sRGB, so the endpoints and gammas are unused
+        push_u32(&mut data, 0x73524742);
+        for _ in 0..12 {
+            push_u32(&mut data, 0);
+        }
+
+        // Rows are stored from the bottom up
+        for y in (0..self.size.height).rev() {
+            for x in 0..self.size.width {
+                push_u32(&mut data, self.data[y * self.size.width + x]);
+            }
+        }
+
+        data
+    }
+
     /// Convert to slice for drawing
     pub fn as_slice(&self) -> &[[u8; 4]] {
         unsafe {
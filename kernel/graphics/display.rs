@@ -1,14 +1,19 @@
 use alloc::boxed::Box;
 
-use core::{cmp, mem};
+use collections::string::String;
+use collections::vec::Vec;
+
+use core::{mem, slice};
 use core::simd::*;
 
 use common::memory;
+use common::to_num::ToNum;
 
 use scheduler;
 
 use super::color::Color;
 use super::point::Point;
+use super::rect::Rect;
 use super::size::Size;
 
 /// The info of the VBE mode
@@ -53,6 +58,104 @@ const VBEMODEINFO: *const VBEModeInfo = 0x5200 as *const VBEModeInfo;
 
 pub const FONTS: *mut usize = 0x200008 as *mut usize;
 
+/// A resolution of the display
+#[derive(Copy, Clone, PartialEq)]
+pub struct DisplayMode {
+    pub width: usize,
+    pub height: usize,
+}
+
+impl DisplayMode {
+    pub fn new(width: usize, height: usize) -> Self {
+        DisplayMode {
+            width: width,
+            height: height,
+        }
+    }
+
+    /// Parse `WIDTHxHEIGHT`
+    pub fn from_str(string: &str) -> Option<Self> {
+        let mut parts = string.trim().splitn(2, 'x');
+        let width = parts.next().map_or(0, |part| part.to_num());
+        let height = parts.next().map_or(0, |part| part.to_num());
+        if width > 0 && height > 0 {
+            Some(DisplayMode::new(width, height))
+        } else {
+            None
+        }
+    }
+
+    pub fn to_string(&self) -> String {
+        format!("{}x{}", self.width, self.height)
+    }
+}
+
+/// The memory of the screen in the current mode
+#[derive(Copy, Clone)]
+pub struct Framebuffer {
+    pub address: usize,
+    pub width: usize,
+    pub height: usize,
+    pub bytesperrow: usize,
+}
+
+/// The framebuffer of a mode set by a driver, which replaces the one picked by the bootloader
+static mut framebuffer: Option<Framebuffer> = None;
+
+impl Framebuffer {
+    /// The framebuffer of the current mode
+    pub fn current() -> Self {
+        unsafe {
+            match framebuffer {
+                Some(current) => current,
+                None => {
+                    let mode_info = &*VBEMODEINFO;
+                    Framebuffer {
+                        address: mode_info.physbaseptr as usize,
+                        width: mode_info.xresolution as usize,
+                        height: mode_info.yresolution as usize,
+                        bytesperrow: mode_info.bytesperscanline as usize,
+                    }
+                }
+            }
+        }
+    }
+
+    /// Make this the framebuffer of the root displays created from now on
+    pub unsafe fn set_current(self) {
+        framebuffer = Some(self);
+    }
+
+    pub fn mode(&self) -> DisplayMode {
+        DisplayMode::new(self.width, self.height)
+    }
+
+    /// Copy the pixels on the screen, row after row without the padding between rows
+    pub fn capture(&self) -> Vec<u32> {
+        let mut data = Vec::with_capacity(self.width * self.height);
+        for y in 0..self.height {
+            unsafe {
+                data.push_all(slice::from_raw_parts((self.address + y * self.bytesperrow) as *const u32,
+                                                    self.width));
+            }
+        }
+        data
+    }
+
+    /// Copy the pixels of a rectangle on the screen, which must be inside it
+    pub fn capture_rect(&self, rect: Rect) -> Vec<u32> {
+        let mut data = Vec::with_capacity(rect.width * rect.height);
+        for y in rect.top()..rect.bottom() {
+            unsafe {
+                data.push_all(slice::from_raw_parts((self.address + y as usize * self.bytesperrow +
+                                                     rect.x as usize * 4) as *const u32,
+                                                    rect.width));
+            }
+        }
+        data
+    }
+}
+
 /// A display
 pub struct Display {
     pub offscreen: usize,
@@ -62,21 +165,24 @@ pub struct Display {
     pub width: usize,
     pub height: usize,
     pub root: bool,
+    /// Drawing outside of this is skipped
+    clip: Rect,
 }
 
 impl Display {
     pub unsafe fn root() -> Box<Self> {
-        let mode_info = &*VBEMODEINFO;
+        let framebuffer = Framebuffer::current();
+        let size = framebuffer.bytesperrow * framebuffer.height;
 
         let ret = box Display {
-            offscreen: memory::alloc(mode_info.bytesperscanline as usize *
-                                 mode_info.yresolution as usize),
-            onscreen: mode_info.physbaseptr as usize,
-            size: mode_info.bytesperscanline as usize * mode_info.yresolution as usize,
-            bytesperrow: mode_info.bytesperscanline as usize,
-            width: mode_info.xresolution as usize,
-            height: mode_info.yresolution as usize,
+            offscreen: memory::alloc(size),
+            onscreen: framebuffer.address,
+            size: size,
+            bytesperrow: framebuffer.bytesperrow,
+            width: framebuffer.width,
+            height: framebuffer.height,
             root: true,
+            clip: Rect::new(0, 0, framebuffer.width, framebuffer.height),
         };
 
         ret.set(Color::new(0, 0, 0));
@@ -99,6 +205,7 @@ impl Display {
                 width: width,
                 height: height,
                 root: false,
+                clip: Rect::new(0, 0, width, height),
             };
 
             ret.set(Color::new(0, 0, 0));
@@ -154,6 +261,21 @@ impl Display {
         }
     }
 
+    /// The whole display
+    pub fn bounds(&self) -> Rect {
+        Rect::new(0, 0, self.width, self.height)
+    }
+
+    /// Only draw inside of `rect` until the clip is reset
+    pub fn set_clip(&mut self, rect: Rect) {
+        self.clip = rect.intersection(&self.bounds());
+    }
+
+    /// Draw on the whole display again
+    pub fn reset_clip(&mut self) {
+        self.clip = self.bounds();
+    }
+
     /// Set the color
     pub fn set(&self, color: Color) {
         unsafe {
@@ -189,19 +311,54 @@ impl Display {
         }
     }
 
+    /// Flip only the part of the display in `rect`
+    pub fn flip_rect(&self, rect: Rect) {
+        if !self.root {
+            self.flip();
+            return;
+        }
+
+        let area = rect.intersection(&self.bounds());
+        if !area.is_empty() {
+            unsafe {
+                let reenable = scheduler::start_no_ints();
+                for y in area.top()..area.bottom() {
+                    let offset = y as usize * self.bytesperrow + area.x as usize * 4;
+                    Display::copy_run(self.offscreen + offset,
+                                      self.onscreen + offset,
+                                      area.width * 4);
+                }
+                scheduler::end_no_ints(reenable);
+            }
+        }
+    }
+
+    /// Copy the pixels in `rect`, which must be inside of the display
+    pub fn get_rect(&self, rect: Rect) -> Vec<u32> {
+        let mut data = Vec::with_capacity(rect.width * rect.height);
+        for y in rect.top()..rect.bottom() {
+            for x in rect.left()..rect.right() {
+                unsafe {
+                    data.push(*((self.offscreen + y as usize * self.bytesperrow + x as usize * 4) as *const u32));
+                }
+            }
+        }
+        data
+    }
+
     /// Draw a rectangle
     pub fn rect(&self, point: Point, size: Size, color: Color) {
         let data = color.data;
         let alpha = (color.data & 0xFF000000) >> 24;
 
-        if alpha > 0 {
-            let start_y = cmp::max(0, cmp::min(self.height as isize - 1, point.y)) as usize;
-            let end_y =
-                cmp::max(0, cmp::min(self.height as isize - 1, point.y + size.height as isize)) as usize;
+        let area = Rect::from_point_size(point, size).intersection(&self.clip);
+
+        if alpha > 0 && !area.is_empty() {
+            let start_y = area.top() as usize;
+            let end_y = area.bottom() as usize;
 
-            let start_x = cmp::max(0, cmp::min(self.width as isize - 1, point.x)) as usize * 4;
-            let len = cmp::max(0, cmp::min(self.width as isize - 1, point.x + size.width as isize)) as usize *
-                      4 - start_x;
+            let start_x = area.left() as usize * 4;
+            let len = area.width * 4;
 
             if alpha >= 255 {
                 for y in start_y..end_y {
@@ -232,8 +389,7 @@ impl Display {
     /// Set the color of a pixel
     pub fn pixel(&self, point: Point, color: Color) {
         unsafe {
-            if point.x >= 0 && point.x < self.width as isize && point.y >= 0 &&
-               point.y < self.height as isize {
+            if self.clip.contains(point) {
                 *((self.offscreen + point.y as usize * self.bytesperrow + point.x as usize * 4) as *mut u32) = color.data;
             }
         }
@@ -343,21 +499,20 @@ impl Display {
 
     /// Draw an image
     pub unsafe fn image(&self, point: Point, data: *const u32, size: Size) {
-        let start_y = cmp::max(0, point.y) as usize;
-        let end_y = cmp::min(self.height as isize, point.y + size.height as isize) as usize;
+        let area = Rect::from_point_size(point, size).intersection(&self.clip);
+        if area.is_empty() {
+            return;
+        }
 
-        let start_x = cmp::max(0, point.x) as usize;
-        let len = cmp::min(self.width as isize, point.x + size.width as isize) as usize * 4 -
-                  start_x * 4;
-        let offscreen_offset = self.offscreen + start_x * 4;
+        let len = area.width * 4;
+        let offscreen_offset = self.offscreen + area.left() as usize * 4;
 
         let bytesperrow = size.width * 4;
-        let data_offset = data as usize - start_y * bytesperrow -
-                          (point.x - start_x as isize) as usize * 4;
+        let data_offset = data as usize + (area.left() - point.x) as usize * 4;
 
-        for y in start_y..end_y {
-            Display::copy_run(data_offset + y * bytesperrow,
-                              offscreen_offset + y * self.bytesperrow,
+        for y in area.top()..area.bottom() {
+            Display::copy_run(data_offset + (y - point.y) as usize * bytesperrow,
+                              offscreen_offset + y as usize * self.bytesperrow,
                               len);
         }
     }
@@ -365,21 +520,20 @@ impl Display {
 
     /// Draw a image with opacity
     pub unsafe fn image_alpha(&self, point: Point, data: *const u32, size: Size) {
-        let start_y = cmp::max(0, point.y) as usize;
-        let end_y = cmp::min(self.height as isize, point.y + size.height as isize) as usize;
+        let area = Rect::from_point_size(point, size).intersection(&self.clip);
+        if area.is_empty() {
+            return;
+        }
 
-        let start_x = cmp::max(0, point.x) as usize;
-        let len = cmp::min(self.width as isize, point.x + size.width as isize) as usize * 4 -
-                  start_x * 4;
-        let offscreen_offset = self.offscreen + start_x * 4;
+        let len = area.width * 4;
+        let offscreen_offset = self.offscreen + area.left() as usize * 4;
 
         let bytesperrow = size.width * 4;
-        let data_offset = data as usize - start_y * bytesperrow -
-                          (point.x - start_x as isize) as usize * 4;
+        let data_offset = data as usize + (area.left() - point.x) as usize * 4;
 
-        for y in start_y..end_y {
-            Display::copy_run_alpha(data_offset + y * bytesperrow,
-                                    offscreen_offset + y * self.bytesperrow,
+        for y in area.top()..area.bottom() {
+            Display::copy_run_alpha(data_offset + (y - point.y) as usize * bytesperrow,
+                                    offscreen_offset + y as usize * self.bytesperrow,
                                     len);
         }
     }
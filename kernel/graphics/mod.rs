@@ -1,11 +1,21 @@
 /// BMP Support
 pub mod bmp;
+/// `try_option!` of libredox, which the shared decoders use
+#[macro_use]
+#[path = "../../libredox/src/try_option.rs"]
+mod try_option;
+
+/// PNG and JPEG support, shared with libredox
+#[path = "../../libredox/src/graphics/decode/mod.rs"]
+pub mod decode;
 /// Color struct
 pub mod color;
 /// Size struct
 pub mod size;
 /// Point struct
 pub mod point;
+/// Rect struct
+pub mod rect;
 /// Display struct
 pub mod display;
 /// Window struct
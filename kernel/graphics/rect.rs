@@ -0,0 +1,99 @@
+use core::cmp;
+
+use super::point::Point;
+use super::size::Size;
+
+/// A rectangle
+#[derive(Copy, Clone, PartialEq)]
+pub struct Rect {
+    pub x: isize,
+    pub y: isize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Rect {
+    /// Create a new rectangle
+    pub fn new(x: isize, y: isize, width: usize, height: usize) -> Self {
+        Rect {
+            x: x,
+            y: y,
+            width: width,
+            height: height,
+        }
+    }
+
+    /// Create a rectangle from its top left corner and size
+    pub fn from_point_size(point: Point, size: Size) -> Self {
+        Rect::new(point.x, point.y, size.width, size.height)
+    }
+
+    pub fn left(&self) -> isize {
+        self.x
+    }
+
+    pub fn right(&self) -> isize {
+        self.x + self.width as isize
+    }
+
+    pub fn top(&self) -> isize {
+        self.y
+    }
+
+    pub fn bottom(&self) -> isize {
+        self.y + self.height as isize
+    }
+
+    pub fn point(&self) -> Point {
+        Point::new(self.x, self.y)
+    }
+
+    pub fn size(&self) -> Size {
+        Size::new(self.width, self.height)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+
+    /// Does the rectangle contain the point?
+    pub fn contains(&self, point: Point) -> bool {
+        point.x >= self.left() && point.x < self.right() && point.y >= self.top() &&
+        point.y < self.bottom()
+    }
+
+    /// Do the rectangles overlap?
+    pub fn intersects(&self, other: &Rect) -> bool {
+        !self.intersection(other).is_empty()
+    }
+
+    /// The part of both rectangles, which may be empty
+    pub fn intersection(&self, other: &Rect) -> Rect {
+        let left = cmp::max(self.left(), other.left());
+        let right = cmp::min(self.right(), other.right());
+        let top = cmp::max(self.top(), other.top());
+        let bottom = cmp::min(self.bottom(), other.bottom());
+
+        Rect::new(left,
+                  top,
+                  cmp::max(0, right - left) as usize,
+                  cmp::max(0, bottom - top) as usize)
+    }
+
+    /// The smallest rectangle containing both
+    pub fn union(&self, other: &Rect) -> Rect {
+        if self.is_empty() {
+            return *other;
+        }
+        if other.is_empty() {
+            return *self;
+        }
+
+        let left = cmp::min(self.left(), other.left());
+        let right = cmp::max(self.right(), other.right());
+        let top = cmp::min(self.top(), other.top());
+        let bottom = cmp::max(self.bottom(), other.bottom());
+
+        Rect::new(left, top, (right - left) as usize, (bottom - top) as usize)
+    }
+}
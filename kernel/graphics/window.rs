@@ -1,20 +1,37 @@
 use alloc::boxed::Box;
 
 use collections::string::String;
+use collections::vec::Vec;
 
+use core::{cmp, mem};
 use core::ops::DerefMut;
 
-use common::event::{Event, KeyEvent, MouseEvent, QuitEvent};
+use common::event::{Event, KeyEvent, MouseEvent, QuitEvent, ResizeEvent};
 use common::queue::Queue;
 use scheduler;
 
 use super::color::Color;
 use super::display::Display;
 use super::point::Point;
+use super::rect::Rect;
 use super::size::Size;
 
+/// The width of the buttons in the title bar
+const BUTTON_WIDTH: usize = 16;
+/// How far right of and below the content the borders can be grabbed for resizing
+const RESIZE_GRIP: isize = 6;
+/// The smallest width of the content, with room for the buttons
+const MIN_WIDTH: usize = 64;
+/// The smallest height of the content
+const MIN_HEIGHT: usize = 16;
+
+/// The id of the next window
+static mut next_window_id: usize = 1;
+
 /// A window
 pub struct Window {
+    /// Names the window in `window://control/ID`
+    pub id: usize,
     /// The position of the window
     pub point: Point,
     /// The size of the window
@@ -31,16 +48,37 @@ pub struct Window {
     pub focused: bool,
     /// Is the window minimized?
     pub minimized: bool,
+    /// Does the window get the pointer even outside of its content?
+    pub grab: bool,
     dragging: bool,
+    /// Is the right border being dragged?
+    resize_right: bool,
+    /// Is the bottom border being dragged?
+    resize_bottom: bool,
+    /// Was a button pressed on the content, and not all released yet?
+    holding: bool,
+    /// The position and size to go back to, if maximized
+    restore: Option<(Point, Size)>,
     last_mouse_event: MouseEvent,
     events: Queue<Event>,
+    /// The parts of the screen that changed since the session last drew the window
+    damage: Vec<Rect>,
     ptr: *mut Window,
 }
 
 impl Window {
     /// Create a new window
     pub fn new(point: Point, size: Size, title: String) -> Box<Self> {
+        let id = unsafe {
+            let reenable = scheduler::start_no_ints();
+            let id = next_window_id;
+            next_window_id += 1;
+            scheduler::end_no_ints(reenable);
+            id
+        };
+
         let mut ret = box Window {
+            id: id,
             point: point,
             size: size,
             title: title,
@@ -49,7 +87,12 @@ impl Window {
             border_color: Color::alpha(64, 64, 64, 128),
             focused: false,
             minimized: false,
+            grab: false,
             dragging: false,
+            resize_right: false,
+            resize_bottom: false,
+            holding: false,
+            restore: None,
             last_mouse_event: MouseEvent {
                 x: 0,
                 y: 0,
@@ -58,6 +101,7 @@ impl Window {
                 middle_button: false,
             },
             events: Queue::new(),
+            damage: Vec::new(),
             ptr: 0 as *mut Window,
         };
 
@@ -84,12 +128,123 @@ impl Window {
         return event_option;
     }
 
+    /// The window with its title bar and borders
+    pub fn bounds(&self) -> Rect {
+        Rect::new(self.point.x - 2,
+                  self.point.y - 18,
+                  self.size.width + 4,
+                  self.size.height + 20)
+    }
+
+    /// Mark a part of the screen as changed
+    pub fn add_damage(&mut self, rect: Rect) {
+        unsafe {
+            let reenable = scheduler::start_no_ints();
+            self.damage.push(rect);
+            scheduler::end_no_ints(reenable);
+        }
+    }
+
+    /// Take the changed parts of the screen, to be drawn again
+    pub fn take_damage(&mut self) -> Vec<Rect> {
+        unsafe {
+            let reenable = scheduler::start_no_ints();
+            let damage = mem::replace(&mut self.damage, Vec::new());
+            scheduler::end_no_ints(reenable);
+            damage
+        }
+    }
+
+    /// Move the window, so that the content is at `point`
+    pub fn set_point(&mut self, point: Point) {
+        let old_bounds = self.bounds();
+        self.point = point;
+        let new_bounds = self.bounds();
+        if new_bounds != old_bounds {
+            self.add_damage(old_bounds);
+            self.add_damage(new_bounds);
+        }
+    }
+
+    /// Change the size of the content, keeping what fits of the old content
+    pub fn set_size(&mut self, size: Size) {
+        let size = Size::new(cmp::max(MIN_WIDTH, size.width),
+                             cmp::max(MIN_HEIGHT, size.height));
+        if size.width == self.size.width && size.height == self.size.height {
+            return;
+        }
+
+        let content = Display::new(size.width, size.height);
+        unsafe {
+            let reenable = scheduler::start_no_ints();
+            content.image(Point::new(0, 0), self.content.onscreen as *const u32, self.size);
+            content.flip();
+
+            let old_bounds = self.bounds();
+            self.size = size;
+            self.content = content;
+            self.add_damage(old_bounds);
+            let new_bounds = self.bounds();
+            self.add_damage(new_bounds);
+            scheduler::end_no_ints(reenable);
+        }
+
+        self.push_event(ResizeEvent {
+                            width: size.width,
+                            height: size.height,
+                        }
+                        .to_event());
+    }
+
+    /// Is the window maximized?
+    pub fn maximized(&self) -> bool {
+        self.restore.is_some()
+    }
+
+    /// Fill the screen above the taskbar, or go back to where the window was
+    pub fn toggle_maximized(&mut self) {
+        match self.restore.take() {
+            Some((point, size)) => {
+                self.set_point(point);
+                self.set_size(size);
+            }
+            None => {
+                self.restore = Some((self.point, self.size));
+
+                let (width, height) = unsafe {
+                    ((*::session_ptr).display.width, (*::session_ptr).display.height)
+                };
+                self.set_point(Point::new(2, 18));
+                self.set_size(Size::new(width, height) - Size::new(4, 32 + 20));
+            }
+        }
+    }
+
+    /// Keep the window on a screen of a new size, filling it again if maximized
+    pub fn fit_screen(&mut self, width: usize, height: usize) {
+        // The room above the taskbar, without the borders and the title bar
+        let room = Size::new(width, height) - Size::new(4, 32 + 20);
+        if self.maximized() {
+            self.set_point(Point::new(2, 18));
+            self.set_size(room);
+        } else {
+            let size = Size::new(cmp::min(self.size.width, room.width),
+                                 cmp::min(self.size.height, room.height));
+            self.set_size(size);
+
+            let x = cmp::min(self.point.x, 2 + room.width.saturating_sub(self.size.width) as isize);
+            let y = cmp::min(self.point.y, 18 + room.height.saturating_sub(self.size.height) as isize);
+            self.set_point(Point::new(cmp::max(2, x), cmp::max(18, y)));
+        }
+    }
+
     /// Redraw the window
     pub fn redraw(&mut self) {
         unsafe {
             let reenable = scheduler::start_no_ints();
             self.content.flip();
-            (*::session_ptr).redraw = true;
+            let content = Rect::from_point_size(self.point, self.size);
+            self.add_damage(content);
             scheduler::end_no_ints(reenable);
         }
     }
@@ -113,12 +268,42 @@ impl Window {
 
             let mut cursor = Point::new(self.point.x, self.point.y - 17);
             for c in self.title.chars() {
-                if cursor.x + 8 <= self.point.x + self.size.width as isize {
+                if cursor.x + 8 <= self.point.x + self.size.width.saturating_sub(2 * BUTTON_WIDTH) as isize {
                     display.char(cursor, c, self.title_color);
                 }
                 cursor.x += 8;
             }
 
+            let minimize_x = self.point.x + self.size.width.saturating_sub(2 * BUTTON_WIDTH) as isize + 4;
+            display.rect(Point::new(minimize_x, self.point.y - 6),
+                         Size::new(8, 2),
+                         self.title_color);
+
+            let maximize_x = self.point.x + self.size.width.saturating_sub(BUTTON_WIDTH) as isize + 4;
+            let maximize_y = if self.maximized() {
+                self.point.y - 11
+            } else {
+                self.point.y - 14
+            };
+            let maximize_size = if self.maximized() {
+                Size::new(8, 6)
+            } else {
+                Size::new(8, 10)
+            };
+            display.rect(Point::new(maximize_x, maximize_y),
+                         Size::new(maximize_size.width, 2),
+                         self.title_color);
+            display.rect(Point::new(maximize_x, maximize_y),
+                         Size::new(1, maximize_size.height),
+                         self.title_color);
+            display.rect(Point::new(maximize_x + maximize_size.width as isize - 1, maximize_y),
+                         Size::new(1, maximize_size.height),
+                         self.title_color);
+            display.rect(Point::new(maximize_x,
+                                    maximize_y + maximize_size.height as isize - 1),
+                         Size::new(maximize_size.width, 1),
+                         self.title_color);
+
             display.rect(Point::new(self.point.x - 2, self.point.y),
                          Size::new(2, self.size.height),
                          self.border_color);
@@ -141,15 +326,41 @@ impl Window {
         }
     }
 
-    /// Called on key press
-    pub fn on_key(&mut self, key_event: KeyEvent) {
+    /// Queue an event for the window to read
+    pub fn push_event(&mut self, event: Event) {
         unsafe {
             let reenable = scheduler::start_no_ints();
-            self.events.push(key_event.to_event());
+            self.events.push(event);
             scheduler::end_no_ints(reenable);
         }
     }
 
+    /// Called on key press
+    pub fn on_key(&mut self, key_event: KeyEvent) {
+        self.push_event(key_event.to_event());
+    }
+
+    /// Is the point, on the screen, on the window, its title bar or its resize grips?
+    pub fn contains(&self, point: Point) -> bool {
+        let bounds = self.bounds();
+        !self.minimized &&
+        Rect::new(bounds.x,
+                  bounds.y,
+                  bounds.width - 2 + RESIZE_GRIP as usize,
+                  bounds.height - 2 + RESIZE_GRIP as usize)
+            .contains(point)
+    }
+
+    /// Is the point, on the screen, on the content of the window?
+    pub fn on_body(&self, point: Point) -> bool {
+        self.on_window_body(point.x - self.point.x, point.y - self.point.y)
+    }
+
+    /// Should the window get the pointer wherever it is?
+    pub fn wants_pointer(&self) -> bool {
+        self.dragging || self.resize_right || self.resize_bottom || self.holding || self.grab
+    }
+
     fn on_window_decoration(&self, x: isize, y: isize) -> bool {
         !self.minimized && x >= -2 &&
             x < self.size.width as isize + 4 &&
@@ -157,6 +368,30 @@ impl Window {
             y < 0
     }
 
+    fn on_minimize_button(&self, x: isize, y: isize) -> bool {
+        self.on_window_decoration(x, y) &&
+            x >= self.size.width.saturating_sub(2 * BUTTON_WIDTH) as isize &&
+            x < self.size.width.saturating_sub(BUTTON_WIDTH) as isize
+    }
+
+    fn on_maximize_button(&self, x: isize, y: isize) -> bool {
+        self.on_window_decoration(x, y) &&
+            x >= self.size.width.saturating_sub(BUTTON_WIDTH) as isize &&
+            x < self.size.width as isize
+    }
+
+    /// Which of the right and bottom borders are under the point
+    fn on_window_resize(&self, x: isize, y: isize) -> (bool, bool) {
+        let width = self.size.width as isize;
+        let height = self.size.height as isize;
+        if self.minimized || self.maximized() {
+            (false, false)
+        } else {
+            (x >= width && x < width + RESIZE_GRIP && y >= 0 && y < height + RESIZE_GRIP,
+             y >= height && y < height + RESIZE_GRIP && x >= 0 && x < width + RESIZE_GRIP)
+        }
+    }
+
     fn on_window_body(&self, x: isize, y: isize) -> bool {
         !self.minimized && x >= 0 &&
             x < self.size.width as isize &&
@@ -164,8 +399,8 @@ impl Window {
             y < self.size.height as isize
     }
 
-    /// Called on mouse movement
-    pub fn on_mouse(&mut self, orig_mouse_event: MouseEvent, allow_catch: bool) -> bool {
+    /// Called on mouse movement over the window, or anywhere if it wants the pointer
+    pub fn on_mouse(&mut self, orig_mouse_event: MouseEvent) -> bool {
         let mut mouse_event = orig_mouse_event;
 
         mouse_event.x -= self.point.x;
@@ -173,61 +408,97 @@ impl Window {
 
         let mut caught = false;
 
-        if allow_catch {
-            if mouse_event.left_button {
-                if self.on_window_body(mouse_event.x, mouse_event.y) {
-                    caught = true;
-                }else if self.on_window_decoration(mouse_event.x, mouse_event.y) {
-                    caught = true;
-                    if !self.last_mouse_event.left_button {
+        if mouse_event.left_button {
+            let (resize_right, resize_bottom) = self.on_window_resize(mouse_event.x, mouse_event.y);
+            if self.on_window_body(mouse_event.x, mouse_event.y) {
+                caught = true;
+            } else if self.on_window_decoration(mouse_event.x, mouse_event.y) {
+                caught = true;
+                if !self.last_mouse_event.left_button && !self.holding {
+                    if self.on_minimize_button(mouse_event.x, mouse_event.y) {
+                        let bounds = self.bounds();
+                        self.add_damage(bounds);
+                        self.minimized = true;
+                    } else if self.on_maximize_button(mouse_event.x, mouse_event.y) {
+                        self.toggle_maximized();
+                    } else {
                         self.dragging = true;
                     }
                 }
-            } else {
-                self.dragging = false;
+            } else if resize_right || resize_bottom {
+                caught = true;
+                if !self.last_mouse_event.left_button && !self.holding {
+                    self.resize_right = resize_right;
+                    self.resize_bottom = resize_bottom;
+                }
             }
+        } else {
+            self.dragging = false;
+            self.resize_right = false;
+            self.resize_bottom = false;
+        }
 
-            if mouse_event.right_button {
-                if self.on_window_body(mouse_event.x, mouse_event.y) {
-                    caught = true;
-                }else if self.on_window_decoration(mouse_event.x, mouse_event.y) {
-                    caught = true;
-                    if !self.last_mouse_event.right_button {
-                        self.minimized = !self.minimized;
-                    }
+        if mouse_event.right_button {
+            if self.on_window_body(mouse_event.x, mouse_event.y) {
+                caught = true;
+            }else if self.on_window_decoration(mouse_event.x, mouse_event.y) {
+                caught = true;
+                if !self.last_mouse_event.right_button {
+                    let bounds = self.bounds();
+                    self.add_damage(bounds);
+                    self.minimized = !self.minimized;
                 }
             }
+        }
 
-            if mouse_event.middle_button {
-                if self.on_window_body(mouse_event.x, mouse_event.y) {
-                    caught = true;
-                }else if self.on_window_decoration(mouse_event.x, mouse_event.y) {
-                    caught = true;
-                    unsafe {
-                        let reenable = scheduler::start_no_ints();
-                        self.events.push(QuitEvent.to_event());
-                        scheduler::end_no_ints(reenable);
-                    }
+        if mouse_event.middle_button {
+            if self.on_window_body(mouse_event.x, mouse_event.y) {
+                caught = true;
+            }else if self.on_window_decoration(mouse_event.x, mouse_event.y) {
+                caught = true;
+                if !self.last_mouse_event.middle_button {
+                    self.push_event(QuitEvent.to_event());
                 }
             }
+        }
 
-            if self.dragging {
-                self.point.x += orig_mouse_event.x - self.last_mouse_event.x;
-                self.point.y += orig_mouse_event.y - self.last_mouse_event.y;
-                caught = true;
+        if self.dragging {
+            let point = Point::new(self.point.x + orig_mouse_event.x - self.last_mouse_event.x,
+                                   self.point.y + orig_mouse_event.y - self.last_mouse_event.y);
+            self.set_point(point);
+            caught = true;
+        }
+
+        if self.resize_right || self.resize_bottom {
+            let mut size = self.size;
+            if self.resize_right {
+                size.width = cmp::max(0, mouse_event.x) as usize;
             }
-        } else {
-            self.dragging = false;
+            if self.resize_bottom {
+                size.height = cmp::max(0, mouse_event.y) as usize;
+            }
+            self.set_size(size);
+            caught = true;
+        }
+
+        let on_body = self.on_window_body(mouse_event.x, mouse_event.y);
+        let any_button = mouse_event.left_button || mouse_event.middle_button || mouse_event.right_button;
+        let last_any_button = self.last_mouse_event.left_button || self.last_mouse_event.middle_button ||
+                              self.last_mouse_event.right_button;
+
+        // The content keeps the pointer from a press until all buttons are released
+        let was_holding = self.holding;
+        if any_button && !last_any_button && on_body {
+            self.holding = true;
+        } else if !any_button {
+            self.holding = false;
         }
 
         self.last_mouse_event = orig_mouse_event;
 
-        if (caught && !self.dragging) || self.on_window_body(mouse_event.x, mouse_event.y) {
-            unsafe {
-                let reenable = scheduler::start_no_ints();
-                self.events.push(mouse_event.to_event());
-                scheduler::end_no_ints(reenable);
-            }
+        let moving = self.dragging || self.resize_right || self.resize_bottom;
+        if !moving && (on_body || was_holding || self.holding || self.grab) {
+            self.push_event(mouse_event.to_event());
         }
 
         caught
@@ -57,18 +57,29 @@ use graphics::bmp::BmpFile;
 use graphics::display::{self, Display};
 use graphics::point::Point;
 
+use network::loopback::Loopback;
+use network::scheme::{all_interfaces, InterfaceScheme};
+
 use programs::package::*;
 use programs::scheme::*;
 use programs::session::*;
 
 use schemes::arp::*;
+use schemes::clipboard::*;
 use schemes::context::*;
 use schemes::debug::*;
 use schemes::ethernet::*;
 use schemes::icmp::*;
+use schemes::icmpv6::*;
+use schemes::igmp::*;
 use schemes::ip::*;
+use schemes::ip6::*;
 use schemes::memory::*;
+use schemes::ndp::*;
+use schemes::netstat::*;
+use schemes::pcap::*;
 use schemes::random::*;
+use schemes::route::*;
 use schemes::time::*;
 use schemes::window::*;
 use schemes::display::*;
@@ -390,15 +401,50 @@ unsafe fn init(font_data: usize) {
     session.items.push(box MemoryScheme);
     session.items.push(box RandomScheme);
     session.items.push(box TimeScheme);
+    session.items.push(Loopback::new());
+    session.items.push(box InterfaceScheme);
+
+    let arp_table = Box::into_raw(box ArpTable::new(ARP_ENTRY_TIMEOUT));
+
+    let routing_table = Box::into_raw(box RoutingTable::new());
 
     session.items.push(box EthernetScheme);
-    session.items.push(box ArpScheme);
+    session.items.push(box PcapScheme);
+    session.items.push(box NetstatScheme);
+    session.items.push(box ArpScheme {
+        table: arp_table
+    });
+    session.items.push(box RouteScheme {
+        table: routing_table
+    });
     session.items.push(box IcmpScheme);
+
+    let multicast_groups = Box::into_raw(box MulticastGroups::new());
+
+    session.items.push(box IgmpScheme {
+        groups: multicast_groups,
+        routes: routing_table
+    });
     session.items.push(box IpScheme {
-        arp: Vec::new()
+        arp: arp_table,
+        groups: multicast_groups,
+        routes: routing_table
+    });
+
+    let neighbor_table = Box::into_raw(box NeighborCache::new(NEIGHBOR_ENTRY_TIMEOUT));
+
+    session.items.push(box NdpScheme {
+        table: neighbor_table
+    });
+    session.items.push(box Icmpv6Scheme);
+    session.items.push(box Ip6Scheme {
+        neighbors: neighbor_table
     });
     session.items.push(box DisplayScheme);
     session.items.push(box WindowScheme);
+    session.items.push(box ClipboardScheme {
+        clipboard: Box::into_raw(box Clipboard::new())
+    });
 
     Context::spawn(box move || {
         poll_loop();
@@ -406,11 +452,21 @@ unsafe fn init(font_data: usize) {
     Context::spawn(box move || {
         event_loop();
     });
+    for interface in all_interfaces().into_iter() {
+        if !interface.loopback {
+            Context::spawn(box move || {
+                ArpScheme::reply_loop(arp_table, interface.name);
+            });
+        }
+    }
     Context::spawn(box move || {
-        ArpScheme::reply_loop();
+        IcmpScheme::reply_loop();
     });
     Context::spawn(box move || {
-        IcmpScheme::reply_loop();
+        IgmpScheme::reply_loop(multicast_groups, routing_table);
+    });
+    Context::spawn(box move || {
+        Icmpv6Scheme::reply_loop(neighbor_table);
     });
 
     debug::d("Reenabling interrupts\n");
@@ -418,13 +474,25 @@ unsafe fn init(font_data: usize) {
     //Start interrupts
     scheduler::end_no_ints(true);
 
+    debug::d("Configuring network\n");
+    if let Some(mut resource) = Url::from_str("file:///etc/network").open() {
+        let mut vec: Vec<u8> = Vec::new();
+        resource.read_to_end(&mut vec);
+
+        if let Some(mut route) = Url::from_str("route:").open() {
+            if route.write(&vec).is_none() {
+                debug::d("Failed to apply network configuration\n");
+            }
+        }
+    }
+
     //Load cursor before getting out of debug mode
     debug::d("Loading cursor\n");
     if let Some(mut resource) = Url::from_str("file:///ui/cursor.bmp").open() {
         let mut vec: Vec<u8> = Vec::new();
         resource.read_to_end(&mut vec);
 
-        let cursor = BmpFile::from_data(&vec);
+        let cursor = BmpFile::from_image(&vec);
 
         let reenable = scheduler::start_no_ints();
         session.cursor = cursor;
@@ -476,7 +544,7 @@ unsafe fn init(font_data: usize) {
             debug::d("\n");
         }
 
-        let background = BmpFile::from_data(&vec);
+        let background = BmpFile::from_image(&vec);
 
         let reenable = scheduler::start_no_ints();
         session.background = background;
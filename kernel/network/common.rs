@@ -3,6 +3,8 @@ use collections::vec::Vec;
 
 use common::to_num::ToNum;
 
+use super::ipv6_addr;
+
 pub trait FromBytes {
     fn from_bytes(bytes: Vec<u8>) -> Option<Self> where Self: Sized;
 }
@@ -62,6 +64,10 @@ pub struct MacAddr {
 }
 
 impl MacAddr {
+    pub fn is_multicast(&self) -> bool {
+        self.bytes[0] & 1 == 1
+    }
+
     pub fn equals(&self, other: Self) -> bool {
         for i in 0..6 {
             if self.bytes[i] != other.bytes[i] {
@@ -104,9 +110,10 @@ impl MacAddr {
     }
 }
 
-pub static BROADCAST_MAC_ADDR: MacAddr = MacAddr { bytes: [0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF] };
+/// Largest packet the link layer carries, excluding the ethernet header
+pub const MTU: usize = 1500;
 
-pub static mut MAC_ADDR: MacAddr = MacAddr { bytes: [0x00, 0x00, 0x00, 0x00, 0x00, 0x00] };
+pub static BROADCAST_MAC_ADDR: MacAddr = MacAddr { bytes: [0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF] };
 
 #[derive(Copy, Clone)]
 pub struct Ipv4Addr {
@@ -123,6 +130,60 @@ impl Ipv4Addr {
         true
     }
 
+    /// Is this address in 127.0.0.0/8
+    pub fn is_loopback(&self) -> bool {
+        self.bytes[0] == 127
+    }
+
+    /// Is this the limited broadcast address, the broadcast addresses of subnets depend on the interface
+    pub fn is_broadcast(&self) -> bool {
+        self.bytes == [255, 255, 255, 255]
+    }
+
+    /// Is this address in the network `network` with `netmask`
+    pub fn in_network(&self, network: Ipv4Addr, netmask: Ipv4Addr) -> bool {
+        for i in 0..4 {
+            if self.bytes[i] & netmask.bytes[i] != network.bytes[i] & netmask.bytes[i] {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// The netmask with the first `len` bits set
+    pub fn from_prefix_len(len: usize) -> Self {
+        let mask = if len == 0 {
+            0
+        } else if len >= 32 {
+            0xFFFFFFFF
+        } else {
+            0xFFFFFFFFu32 << (32 - len)
+        };
+
+        Ipv4Addr { bytes: [(mask >> 24) as u8, (mask >> 16) as u8, (mask >> 8) as u8, mask as u8] }
+    }
+
+    /// The number of bits set in this netmask
+    pub fn prefix_len(&self) -> usize {
+        let mut len = 0;
+        for i in 0..4 {
+            len += self.bytes[i].count_ones() as usize;
+        }
+        len
+    }
+
+    /// Is this address in 224.0.0.0/4
+    pub fn is_multicast(&self) -> bool {
+        self.bytes[0] & 0xF0 == 0xE0
+    }
+
+    /// The MAC address a multicast group is sent to, 01:00:5E followed by the low 23 bits
+    pub fn multicast_mac(&self) -> MacAddr {
+        MacAddr {
+            bytes: [0x01, 0x00, 0x5E, self.bytes[1] & 0x7F, self.bytes[2], self.bytes[3]],
+        }
+    }
+
     pub fn from_string(string: &String) -> Self {
         let mut addr = Ipv4Addr { bytes: [0, 0, 0, 0] };
 
@@ -162,23 +223,65 @@ pub struct Ipv6Addr {
 }
 
 impl Ipv6Addr {
-    pub fn to_string(&self) -> String {
-        let mut string = String::new();
-
+    pub fn equals(&self, other: Self) -> bool {
         for i in 0..16 {
-            if i > 0 && i % 2 == 0 {
-                string = string + ".";
+            if self.bytes[i] != other.bytes[i] {
+                return false;
             }
-            string = string + &format!("{}", self.bytes[i]);
         }
+        true
+    }
 
-        string
+    /// Parse an address like `fe80::5054:ff:fe12:3456`
+    pub fn from_str(string: &str) -> Self {
+        Ipv6Addr { bytes: ipv6_addr::parse(string) }
+    }
+
+    /// The link-local address derived from a MAC address (modified EUI-64)
+    pub fn link_local(mac: MacAddr) -> Self {
+        Ipv6Addr {
+            bytes: [0xFE, 0x80, 0, 0, 0, 0, 0, 0,
+                    mac.bytes[0] ^ 2, mac.bytes[1], mac.bytes[2], 0xFF,
+                    0xFE, mac.bytes[3], mac.bytes[4], mac.bytes[5]],
+        }
+    }
+
+    /// The solicited-node multicast address used to find the owner of this address
+    pub fn solicited_node(&self) -> Self {
+        Ipv6Addr {
+            bytes: [0xFF, 0x02, 0, 0, 0, 0, 0, 0,
+                    0, 0, 0, 1, 0xFF, self.bytes[13], self.bytes[14], self.bytes[15]],
+        }
+    }
+
+    pub fn is_multicast(&self) -> bool {
+        self.bytes[0] == 0xFF
+    }
+
+    /// The MAC address a multicast address is sent to
+    pub fn multicast_mac(&self) -> MacAddr {
+        MacAddr {
+            bytes: [0x33, 0x33, self.bytes[12], self.bytes[13], self.bytes[14], self.bytes[15]],
+        }
+    }
+
+    /// Format as eight hexadecimal groups, compressing the longest run of zeros
+    pub fn to_string(&self) -> String {
+        ipv6_addr::to_string(&self.bytes)
     }
 }
 
-pub static BROADCAST_IP_ADDR: Ipv4Addr = Ipv4Addr { bytes: [10, 85, 85, 255] };
+pub static UNSPECIFIED_IPV6_ADDR: Ipv6Addr = Ipv6Addr { bytes: [0; 16] };
+
+pub static ALL_NODES_IPV6_ADDR: Ipv6Addr = Ipv6Addr { bytes: [0xFF, 0x02, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1] };
+
+pub static UNSPECIFIED_IP_ADDR: Ipv4Addr = Ipv4Addr { bytes: [0, 0, 0, 0] };
+
+pub static LOOPBACK_IP_ADDR: Ipv4Addr = Ipv4Addr { bytes: [127, 0, 0, 1] };
+
+pub static ALL_HOSTS_IP_ADDR: Ipv4Addr = Ipv4Addr { bytes: [224, 0, 0, 1] };
 
-pub static IP_ADDR: Ipv4Addr = Ipv4Addr { bytes: [10, 85, 85, 2] };
+pub static ALL_ROUTERS_IP_ADDR: Ipv4Addr = Ipv4Addr { bytes: [224, 0, 0, 2] };
 
 #[derive(Copy, Clone)]
 pub struct Checksum {
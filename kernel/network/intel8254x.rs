@@ -14,7 +14,7 @@ use drivers::pciconfig::PciConfig;
 use network::common::*;
 use network::scheme::*;
 
-use schemes::{KScheme, Resource, Url};
+use schemes::KScheme;
 
 const CTRL: u32 = 0x00;
     const CTRL_LRST: u32 = 1 << 3;
@@ -25,6 +25,7 @@ const CTRL: u32 = 0x00;
     const CTRL_PHY_RST: u32 = 1 << 31;
 
 const STATUS: u32 = 0x08;
+    const STATUS_LU: u32 = 1 << 1;
 
 const FCAL: u32 = 0x28;
 const FCAH: u32 = 0x2C;
@@ -63,6 +64,15 @@ const RDT: u32 = 0x2818;
 const RAL0: u32 = 0x5400;
 const RAH0: u32 = 0x5404;
 
+// Statistics, cleared when read
+const CRCERRS: u32 = 0x4000;
+const ALGNERRC: u32 = 0x4004;
+const RXERRC: u32 = 0x400C;
+const MPC: u32 = 0x4010;
+const ECOL: u32 = 0x4018;
+const LATECOL: u32 = 0x4020;
+const STATS_END: u32 = 0x4100;
+
 #[repr(packed)]
 struct Rd {
     buffer: u64,
@@ -108,17 +118,10 @@ pub struct Intel8254x {
     pub resources: Vec<*mut NetworkResource>,
     pub inbound: Queue<Vec<u8>>,
     pub outbound: Queue<Vec<u8>>,
+    pub stats: NetworkStats,
 }
 
 impl KScheme for Intel8254x {
-    fn scheme(&self) -> &str {
-        "network"
-    }
-
-    fn open(&mut self, _: &Url, _: usize) -> Option<Box<Resource>> {
-        Some(NetworkResource::new(self))
-    }
-
     fn on_irq(&mut self, irq: u8) {
         if irq == self.irq {
             unsafe {
@@ -174,6 +177,7 @@ impl NetworkScheme for Intel8254x {
 
             for resource in self.resources.iter() {
                 while let Some(bytes) = (**resource).outbound.pop() {
+                    capture(&bytes);
                     self.outbound.push(bytes);
                 }
             }
@@ -182,7 +186,13 @@ impl NetworkScheme for Intel8254x {
 
             self.receive_inbound();
 
+            self.update_stats();
+
             while let Some(bytes) = self.inbound.pop() {
+                capture(&bytes);
+                if self.resources.is_empty() {
+                    self.stats.rx_dropped += 1;
+                }
                 for resource in self.resources.iter() {
                     (**resource).inbound.push(bytes.clone());
                 }
@@ -211,6 +221,7 @@ impl Intel8254x {
                 debug::dh(rd.length as usize);
                 debug::dl();
 
+                self.stats.received(rd.length as usize);
                 self.inbound.push(Vec::from(slice::from_raw_parts(rd.buffer as *const u8, rd.length as usize)));
 
                 rd.status = 0;
@@ -256,8 +267,11 @@ impl Intel8254x {
                         td.special = 0;
 
                         self.write(TDT, tail);
+
+                        self.stats.sent(bytes.len());
                     } else {
                         //TODO: More than one TD
+                        self.stats.tx_errors += 1;
                         debug::dl();
                         debug::d("Intel 8254x: Frame too long for transmit: ");
                         debug::dd(bytes.len());
@@ -270,6 +284,14 @@ impl Intel8254x {
         }
     }
 
+    pub unsafe fn update_stats(&mut self) {
+        self.stats.rx_errors += (self.read(CRCERRS) + self.read(ALGNERRC) + self.read(RXERRC)) as u64;
+        self.stats.rx_dropped += self.read(MPC) as u64;
+        self.stats.tx_errors += (self.read(ECOL) + self.read(LATECOL)) as u64;
+
+        self.stats.link = Some(self.read(STATUS) & STATUS_LU == STATUS_LU);
+    }
+
     pub unsafe fn read(&self, register: u32) -> u32 {
         if self.memory_mapped {
             ptr::read((self.base + register as usize) as *mut u32)
@@ -326,12 +348,17 @@ impl Intel8254x {
         debug::d(" CTRL ");
         debug::dh(self.read(CTRL) as usize);
 
-        // TODO: Clear statistical counters
+        // Clear statistical counters
+        let mut register = CRCERRS;
+        while register < STATS_END {
+            self.read(register);
+            register += 4;
+        }
 
         debug::d(" MAC: ");
         let mac_low = self.read(RAL0);
         let mac_high = self.read(RAH0);
-        MAC_ADDR = MacAddr {
+        let mac = MacAddr {
             bytes: [mac_low as u8,
                     (mac_low >> 8) as u8,
                     (mac_low >> 16) as u8,
@@ -339,7 +366,11 @@ impl Intel8254x {
                     mac_high as u8,
                     (mac_high >> 8) as u8],
         };
-        debug::d(&MAC_ADDR.to_string());
+        debug::d(&mac.to_string());
+
+        let nic: *mut NetworkScheme = &mut *self;
+        let stats: *const NetworkStats = &self.stats;
+        register_interface(nic, mac, stats);
 
         /*
         MTA => 0;
@@ -398,7 +429,7 @@ impl Intel8254x {
 
         self.flag(RCTL, RCTL_EN, true);
         self.flag(RCTL, RCTL_UPE, true);
-        //self.flag(RCTL, RCTL_MPE, true);
+        self.flag(RCTL, RCTL_MPE, true); // Multicast for IPv6 neighbor discovery
         self.flag(RCTL, RCTL_LPE, true);
         self.flag(RCTL, RCTL_LBM, false);
         /* RCTL.RDMTS = Minimum threshold size ??? */
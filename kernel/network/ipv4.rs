@@ -5,8 +5,22 @@ use collections::vec::Vec;
 
 use core::mem;
 
+use common::time::Duration;
+
 use network::common::*;
 
+/// More fragments flag
+pub const IPV4_MF: u16 = 1 << 13;
+/// Don't fragment flag
+pub const IPV4_DF: u16 = 1 << 14;
+/// Mask of the fragment offset, in units of 8 bytes
+pub const IPV4_FRAGMENT_OFFSET: u16 = 0x1FFF;
+
+/// Seconds to wait for the missing fragments of a datagram
+pub const IPV4_REASSEMBLY_TIMEOUT: i64 = 30;
+/// Maximum bytes held by a reassembly buffer
+pub const IPV4_REASSEMBLY_MAX: usize = 256 * 1024;
+
 #[derive(Copy, Clone)]
 #[repr(packed)]
 pub struct Ipv4Header {
@@ -28,17 +42,240 @@ pub struct Ipv4 {
     pub data: Vec<u8>,
 }
 
+impl Ipv4 {
+    /// Is this a fragment of a larger datagram
+    pub fn is_fragment(&self) -> bool {
+        self.header.flags_fragment.get() & (IPV4_MF | IPV4_FRAGMENT_OFFSET) != 0
+    }
+
+    /// Calculate the header checksum
+    pub fn calculate_checksum(&mut self) {
+        unsafe {
+            self.header.checksum.data = 0;
+
+            let header_ptr: *const Ipv4Header = &self.header;
+            self.header.checksum.data =
+                Checksum::compile(Checksum::sum(header_ptr as usize, mem::size_of::<Ipv4Header>()) +
+                                  Checksum::sum(self.options.as_ptr() as usize, self.options.len()));
+        }
+    }
+
+    /// Split into packets that fit in `mtu`, or return the packet itself if it fits
+    pub fn fragment(self, mtu: usize) -> Vec<Ipv4> {
+        let header_len = mem::size_of::<Ipv4Header>() + self.options.len();
+
+        let mut packets = Vec::new();
+        if header_len + self.data.len() <= mtu || self.header.flags_fragment.get() & IPV4_DF == IPV4_DF {
+            packets.push(self);
+            return packets;
+        }
+
+        let chunk = (mtu - header_len) & !7;
+        let flags = self.header.flags_fragment.get();
+        let base = (flags & IPV4_FRAGMENT_OFFSET) as usize * 8;
+
+        let mut offset = 0;
+        while offset < self.data.len() {
+            let end = if offset + chunk < self.data.len() {
+                offset + chunk
+            } else {
+                self.data.len()
+            };
+
+            let mut more = flags & IPV4_MF;
+            if end < self.data.len() {
+                more = IPV4_MF;
+            }
+
+            let mut packet = Ipv4 {
+                header: self.header,
+                options: self.options.clone(),
+                data: self.data.get_slice(Some(offset), Some(end)).to_vec(),
+            };
+            packet.header.len.set((header_len + end - offset) as u16);
+            packet.header.flags_fragment.set(more | (((base + offset) / 8) as u16 & IPV4_FRAGMENT_OFFSET));
+            packet.calculate_checksum();
+            packets.push(packet);
+
+            offset = end;
+        }
+
+        packets
+    }
+}
+
+/// A datagram being reassembled
+struct Ipv4Fragments {
+    header: Ipv4Header,
+    options: Vec<u8>,
+    data: Vec<u8>,
+    /// Received byte ranges
+    ranges: Vec<(usize, usize)>,
+    /// Length of the datagram, known once the last fragment arrives
+    total_len: Option<usize>,
+    /// When the first fragment arrived (monotonic)
+    time: Duration,
+}
+
+impl Ipv4Fragments {
+    fn matches(&self, header: &Ipv4Header) -> bool {
+        self.header.id.get() == header.id.get() && self.header.proto == header.proto &&
+        self.header.src.equals(header.src) && self.header.dst.equals(header.dst)
+    }
+
+    fn is_complete(&self) -> bool {
+        if let Some(total_len) = self.total_len {
+            let mut covered = 0;
+            loop {
+                let mut advanced = false;
+                for range in self.ranges.iter() {
+                    if range.0 <= covered && range.1 > covered {
+                        covered = range.1;
+                        advanced = true;
+                    }
+                }
+                if !advanced {
+                    break;
+                }
+            }
+            covered >= total_len
+        } else {
+            false
+        }
+    }
+}
+
+/// Reassembles fragmented datagrams, dropping them after `IPV4_REASSEMBLY_TIMEOUT` seconds
+/// or when more than `IPV4_REASSEMBLY_MAX` bytes are held
+pub struct Ipv4Reassembly {
+    pending: Vec<Ipv4Fragments>,
+}
+
+impl Ipv4Reassembly {
+    pub fn new() -> Self {
+        Ipv4Reassembly { pending: Vec::new() }
+    }
+
+    /// Bytes held by incomplete datagrams
+    pub fn size(&self) -> usize {
+        let mut size = 0;
+        for fragments in self.pending.iter() {
+            size += fragments.data.len();
+        }
+        size
+    }
+
+    /// Drop datagrams that have been waiting too long
+    pub fn expire(&mut self) {
+        let now = Duration::monotonic();
+        let timeout = Duration::new(IPV4_REASSEMBLY_TIMEOUT, 0);
+
+        let mut i = 0;
+        while i < self.pending.len() {
+            if now - self.pending[i].time > timeout {
+                self.pending.remove(i);
+            } else {
+                i += 1;
+            }
+        }
+    }
+
+    /// Add a packet, returning a complete datagram if one is available
+    pub fn push(&mut self, packet: Ipv4) -> Option<Ipv4> {
+        if !packet.is_fragment() {
+            return Some(packet);
+        }
+
+        self.expire();
+
+        let flags = packet.header.flags_fragment.get();
+        let start = (flags & IPV4_FRAGMENT_OFFSET) as usize * 8;
+        let end = start + packet.data.len();
+        if end > 65535 {
+            return None;
+        }
+
+        let mut index = None;
+        for i in 0..self.pending.len() {
+            if self.pending[i].matches(&packet.header) {
+                index = Some(i);
+                break;
+            }
+        }
+
+        let i = match index {
+            Some(i) => i,
+            None => {
+                self.pending.push(Ipv4Fragments {
+                    header: packet.header,
+                    options: Vec::new(),
+                    data: Vec::new(),
+                    ranges: Vec::new(),
+                    total_len: None,
+                    time: Duration::monotonic(),
+                });
+                self.pending.len() - 1
+            }
+        };
+
+        {
+            let fragments = &mut self.pending[i];
+
+            if start == 0 {
+                fragments.header = packet.header;
+                fragments.options = packet.options;
+            }
+
+            if flags & IPV4_MF == 0 {
+                fragments.total_len = Some(end);
+            }
+
+            while fragments.data.len() < end {
+                fragments.data.push(0);
+            }
+            for j in 0..packet.data.len() {
+                fragments.data[start + j] = packet.data[j];
+            }
+            fragments.ranges.push((start, end));
+        }
+
+        if self.pending[i].is_complete() {
+            let fragments = self.pending.remove(i);
+            let total_len = fragments.total_len.unwrap_or(0);
+
+            let mut datagram = Ipv4 {
+                header: fragments.header,
+                options: fragments.options,
+                data: fragments.data,
+            };
+            datagram.data.truncate(total_len);
+            datagram.header.flags_fragment.set(0);
+            datagram.header.len.set((mem::size_of::<Ipv4Header>() + datagram.options.len() + total_len) as u16);
+            return Some(datagram);
+        }
+
+        // Over the memory cap, drop the oldest datagrams
+        while self.size() > IPV4_REASSEMBLY_MAX && !self.pending.is_empty() {
+            self.pending.remove(0);
+        }
+
+        None
+    }
+}
+
 impl FromBytes for Ipv4 {
     fn from_bytes(bytes: Vec<u8>) -> Option<Self> {
         if bytes.len() >= mem::size_of::<Ipv4Header>() {
             unsafe {
                 let header = *(bytes.as_ptr() as *const Ipv4Header);
                 let header_len = ((header.ver_hlen & 0xF) << 2) as usize;
+                // Ignore any link layer padding
+                let total_len = header.len.get() as usize;
 
                 return Some(Ipv4 {
                     header: header,
                     options: bytes.get_slice(Some(mem::size_of::<Ipv4Header>()), Some(header_len)).to_vec(),
-                    data: bytes.get_slice(Some(header_len), None).to_vec(),
+                    data: bytes.get_slice(Some(header_len), Some(total_len)).to_vec(),
                 });
             }
         }
@@ -1,13 +1,81 @@
-// TODO
+use ::GetSlice;
+
+use collections::slice;
+use collections::vec::Vec;
+
+use core::mem;
 
 use network::common::*;
 
 #[derive(Copy, Clone)]
-pub struct Ipv6 {
-    pub version: n32, // also has traffic class and flow label, TODO
+#[repr(packed)]
+pub struct Ipv6Header {
+    /// Version, traffic class and flow label
+    pub version: n32,
     pub len: n16,
     pub next_header: u8,
     pub hop_limit: u8,
     pub src: Ipv6Addr,
     pub dst: Ipv6Addr,
 }
+
+pub struct Ipv6 {
+    pub header: Ipv6Header,
+    pub data: Vec<u8>,
+}
+
+impl Ipv6 {
+    /// Create a packet with no traffic class or flow label
+    pub fn new(src: Ipv6Addr, dst: Ipv6Addr, next_header: u8, hop_limit: u8, data: Vec<u8>) -> Self {
+        Ipv6 {
+            header: Ipv6Header {
+                version: n32::new(6 << 28),
+                len: n16::new(data.len() as u16),
+                next_header: next_header,
+                hop_limit: hop_limit,
+                src: src,
+                dst: dst,
+            },
+            data: data,
+        }
+    }
+
+    /// Sum of the pseudo header used by upper layer checksums
+    pub unsafe fn pseudo_sum(src: &Ipv6Addr, dst: &Ipv6Addr, next_header: u8, len: usize) -> usize {
+        let len = n32::new(len as u32);
+        let next_header = n32::new(next_header as u32);
+        Checksum::sum((src as *const Ipv6Addr) as usize, mem::size_of::<Ipv6Addr>()) +
+        Checksum::sum((dst as *const Ipv6Addr) as usize, mem::size_of::<Ipv6Addr>()) +
+        Checksum::sum((&len as *const n32) as usize, mem::size_of::<n32>()) +
+        Checksum::sum((&next_header as *const n32) as usize, mem::size_of::<n32>())
+    }
+}
+
+impl FromBytes for Ipv6 {
+    fn from_bytes(bytes: Vec<u8>) -> Option<Self> {
+        if bytes.len() >= mem::size_of::<Ipv6Header>() {
+            unsafe {
+                let header = *(bytes.as_ptr() as *const Ipv6Header);
+                if header.version.get() >> 28 == 6 {
+                    let end = mem::size_of::<Ipv6Header>() + header.len.get() as usize;
+                    return Some(Ipv6 {
+                        header: header,
+                        data: bytes.get_slice(Some(mem::size_of::<Ipv6Header>()), Some(end)).to_vec(),
+                    });
+                }
+            }
+        }
+        None
+    }
+}
+
+impl ToBytes for Ipv6 {
+    fn to_bytes(&self) -> Vec<u8> {
+        unsafe {
+            let header_ptr: *const Ipv6Header = &self.header;
+            let mut ret = Vec::<u8>::from(slice::from_raw_parts(header_ptr as *const u8, mem::size_of::<Ipv6Header>()));
+            ret.push_all(&self.data);
+            ret
+        }
+    }
+}
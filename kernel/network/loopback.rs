@@ -0,0 +1,82 @@
+use alloc::boxed::Box;
+
+use collections::vec::Vec;
+
+use schemes::{KScheme, Resource, Url};
+use scheduler;
+
+use network::scheme::*;
+
+/// The loopback interface
+///
+/// Carries IP packets without a link layer header. Every packet written to a resource is
+/// delivered to all other open resources, so the IP scheme can filter it like any other link.
+pub struct Loopback {
+    resources: Vec<*mut NetworkResource>,
+    stats: NetworkStats,
+}
+
+impl Loopback {
+    pub fn new() -> Box<Self> {
+        let mut module = box Loopback {
+            resources: Vec::new(),
+            stats: NetworkStats::new("loopback"),
+        };
+
+        module.stats.link = Some(true);
+        unsafe {
+            let nic: *mut NetworkScheme = &mut *module;
+            register_loopback(nic, &module.stats);
+        }
+
+        module
+    }
+}
+
+impl KScheme for Loopback {
+    fn scheme(&self) -> &str {
+        "loopback"
+    }
+
+    fn open(&mut self, _: &Url, flags: usize) -> Option<Box<Resource>> {
+        Some(NetworkResource::new(self, flags))
+    }
+}
+
+impl NetworkScheme for Loopback {
+    fn add(&mut self, resource: *mut NetworkResource) {
+        unsafe {
+            let reenable = scheduler::start_no_ints();
+            self.resources.push(resource);
+            scheduler::end_no_ints(reenable);
+        }
+    }
+
+    fn remove(&mut self, resource: *mut NetworkResource) {
+        unsafe {
+            let reenable = scheduler::start_no_ints();
+            self.resources.retain(|ptr| *ptr != resource);
+            scheduler::end_no_ints(reenable);
+        }
+    }
+
+    fn sync(&mut self) {
+        unsafe {
+            let reenable = scheduler::start_no_ints();
+
+            for sender in self.resources.iter() {
+                while let Some(bytes) = (**sender).outbound.pop() {
+                    self.stats.sent(bytes.len());
+                    self.stats.received(bytes.len());
+                    for resource in self.resources.iter() {
+                        if *resource != *sender {
+                            (**resource).inbound.push(bytes.clone());
+                        }
+                    }
+                }
+            }
+
+            scheduler::end_no_ints(reenable);
+        }
+    }
+}
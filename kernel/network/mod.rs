@@ -3,5 +3,11 @@ pub mod ethernet;
 pub mod intel8254x;
 pub mod ipv4;
 pub mod ipv6;
+/// The text form of IPv6 addresses, shared with libredox
+#[path = "../../libredox/src/net/ipv6_addr.rs"]
+pub mod ipv6_addr;
+pub mod loopback;
+pub mod neighbor;
 pub mod rtl8139;
 pub mod scheme;
+pub mod virtio;
@@ -0,0 +1,119 @@
+use collections::string::String;
+use collections::vec::Vec;
+
+use common::time::Duration;
+
+use network::common::*;
+
+/// A protocol address that a neighbor table maps to MAC addresses
+pub trait NeighborAddr: Copy {
+    fn equals(&self, other: Self) -> bool;
+    fn to_string(&self) -> String;
+}
+
+impl NeighborAddr for Ipv4Addr {
+    fn equals(&self, other: Self) -> bool {
+        Ipv4Addr::equals(self, other)
+    }
+
+    fn to_string(&self) -> String {
+        Ipv4Addr::to_string(self)
+    }
+}
+
+impl NeighborAddr for Ipv6Addr {
+    fn equals(&self, other: Self) -> bool {
+        Ipv6Addr::equals(self, other)
+    }
+
+    fn to_string(&self) -> String {
+        Ipv6Addr::to_string(self)
+    }
+}
+
+/// A neighbor entry (MAC + protocol address)
+#[derive(Copy, Clone)]
+pub struct NeighborEntry<A: NeighborAddr> {
+    pub ip: A,
+    pub mac: MacAddr,
+    /// When the entry was learned (monotonic)
+    pub time: Duration,
+}
+
+/// A table of neighbors, learned by ARP or NDP, entries expire after `timeout` seconds
+pub struct NeighborTable<A: NeighborAddr> {
+    pub entries: Vec<NeighborEntry<A>>,
+    pub timeout: i64,
+}
+
+impl<A: NeighborAddr> NeighborTable<A> {
+    pub fn new(timeout: i64) -> Self {
+        NeighborTable {
+            entries: Vec::new(),
+            timeout: timeout,
+        }
+    }
+
+    /// Remove expired entries
+    pub fn expire(&mut self) {
+        let now = Duration::monotonic();
+        let timeout = Duration::new(self.timeout, 0);
+
+        let mut i = 0;
+        while i < self.entries.len() {
+            if now - self.entries[i].time > timeout {
+                self.entries.remove(i);
+            } else {
+                i += 1;
+            }
+        }
+    }
+
+    /// Find the MAC address of `ip`
+    pub fn lookup(&mut self, ip: A) -> Option<MacAddr> {
+        self.expire();
+
+        for entry in self.entries.iter() {
+            if entry.ip.equals(ip) {
+                return Some(entry.mac);
+            }
+        }
+
+        None
+    }
+
+    /// Add or refresh the mapping of `ip` to `mac`
+    pub fn insert(&mut self, ip: A, mac: MacAddr) {
+        let now = Duration::monotonic();
+
+        for entry in self.entries.iter_mut() {
+            if entry.ip.equals(ip) {
+                entry.mac = mac;
+                entry.time = now;
+                return;
+            }
+        }
+
+        self.entries.push(NeighborEntry {
+            ip: ip,
+            mac: mac,
+            time: now,
+        });
+    }
+
+    /// List the entries, one `IP MAC AGE` per line
+    pub fn to_string(&mut self) -> String {
+        self.expire();
+
+        let now = Duration::monotonic();
+
+        let mut string = String::new();
+        for entry in self.entries.iter() {
+            if !string.is_empty() {
+                string = string + "\n";
+            }
+            string = string + &format!("{} {} {}", entry.ip.to_string(), entry.mac.to_string(), (now - entry.time).secs);
+        }
+        string
+    }
+}
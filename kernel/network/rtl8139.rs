@@ -8,7 +8,6 @@ use core::ptr;
 
 use common::{debug, memory};
 use common::queue::Queue;
-use schemes::{Resource, Url};
 use scheduler;
 
 use drivers::pciconfig::PciConfig;
@@ -36,6 +35,7 @@ pub struct Rtl8139 {
     outbound: Queue<Vec<u8>>,
     txds: Vec<Txd>,
     txd_i: usize,
+    stats: NetworkStats,
 }
 
 impl Rtl8139 {
@@ -53,6 +53,7 @@ impl Rtl8139 {
             outbound: Queue::new(),
             txds: Vec::new(),
             txd_i: 0,
+            stats: NetworkStats::new("rtl8139"),
         };
 
         unsafe { module.init() };
@@ -83,7 +84,7 @@ impl Rtl8139 {
         debug::d(" MAC: ");
         let mac_low = ind(base);
         let mac_high = ind(base + 4);
-        MAC_ADDR = MacAddr {
+        let mac = MacAddr {
             bytes: [mac_low as u8,
                     (mac_low >> 8) as u8,
                     (mac_low >> 16) as u8,
@@ -91,7 +92,15 @@ impl Rtl8139 {
                     mac_high as u8,
                     (mac_high >> 8) as u8],
         };
-        debug::d(&MAC_ADDR.to_string());
+        debug::d(&mac.to_string());
+
+        let nic: *mut NetworkScheme = &mut *self;
+        let stats: *const NetworkStats = &self.stats;
+        register_interface(nic, mac, stats);
+
+        // Accept all multicast, needed for IPv6 neighbor discovery
+        outd(base + 0x08, 0xFFFFFFFF);
+        outd(base + 0x0C, 0xFFFFFFFF);
 
         let receive_buffer = memory::alloc(10240);
         outd(base + 0x30, receive_buffer as u32);
@@ -146,7 +155,12 @@ impl Rtl8139 {
             debug::dh(frame_len);
             debug::dl();
 
-            self.inbound.push(Vec::from(slice::from_raw_parts(frame_addr as *const u8, frame_len - 4)));
+            if frame_status & 1 == 1 && frame_len >= 4 { // Receive OK
+                self.stats.received(frame_len - 4);
+                self.inbound.push(Vec::from(slice::from_raw_parts(frame_addr as *const u8, frame_len - 4)));
+            } else {
+                self.stats.rx_errors += 1;
+            }
 
             capr = capr + frame_len + 4;
             capr = (capr + 3) & (0xFFFFFFFF - 3);
@@ -186,29 +200,36 @@ impl Rtl8139 {
                     outd(txd.status_port, bytes.len() as u32 & 0xFFF);
 
                     self.txd_i = (self.txd_i + 1) % 4;
+
+                    self.stats.sent(bytes.len());
                 } else {
+                    self.stats.tx_errors += 1;
                     debug::dl();
                     debug::d("RTL8139: Frame too long for transmit: ");
                     debug::dd(bytes.len());
                     debug::dl();
                 }
             } else {
+                self.stats.tx_dropped += 1;
                 debug::d("RTL8139: TXD Overflow!\n");
                 self.txd_i = 0;
             }
         }
     }
-}
 
-impl KScheme for Rtl8139 {
-    fn scheme(&self) -> &str {
-        "network"
-    }
+    unsafe fn update_stats(&mut self) {
+        let base = self.base as u16;
 
-    fn open(&mut self, _: &Url, _: usize) -> Option<Box<Resource>> {
-        Some(NetworkResource::new(self))
+        // Missed packets, cleared by writing
+        self.stats.rx_dropped += (ind(base + 0x4C) & 0xFFFFFF) as u64;
+        outd(base + 0x4C, 0);
+
+        // Media status, the link bit is set when the link failed
+        self.stats.link = Some(inb(base + 0x58) & (1 << 2) == 0);
     }
+}
 
+impl KScheme for Rtl8139 {
     fn on_irq(&mut self, irq: u8) {
         if irq == self.irq {
             unsafe {
@@ -269,6 +290,7 @@ impl NetworkScheme for Rtl8139 {
 
             for resource in self.resources.iter() {
                 while let Some(bytes) = (**resource).outbound.pop() {
+                    capture(&bytes);
                     self.outbound.push(bytes);
                 }
             }
@@ -277,7 +299,13 @@ impl NetworkScheme for Rtl8139 {
 
             self.receive_inbound();
 
+            self.update_stats();
+
             while let Some(bytes) = self.inbound.pop() {
+                capture(&bytes);
+                if self.resources.is_empty() {
+                    self.stats.rx_dropped += 1;
+                }
                 for resource in self.resources.iter() {
                     (**resource).inbound.push(bytes.clone());
                 }
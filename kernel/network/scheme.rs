@@ -1,15 +1,282 @@
 use alloc::boxed::Box;
 
+use collections::string::{String, ToString};
 use collections::vec::Vec;
 
 use core::ops::DerefMut;
 
 use scheduler::context::context_switch;
-use common::debug;
 use common::queue::Queue;
+use common::parse_path::parse_path;
+use common::time::Duration;
 use scheduler;
 
-use schemes::{Resource, ResourceSeek, Url};
+use network::common::*;
+
+use schemes::{read_packet, KScheme, Resource, ResourceSeek, Url};
+
+use syscall::common::{O_NONBLOCK, WOULD_BLOCK};
+
+/// The most frames a capture keeps before it is read
+pub const CAPTURE_QUEUE_LEN: usize = 1024;
+
+/// The frames mirrored to an open `pcap:` resource
+pub struct Capture {
+    pub frames: Queue<(Duration, Vec<u8>)>,
+    /// Frames that did not fit in the queue
+    pub dropped: u64,
+}
+
+impl Capture {
+    pub fn new() -> Self {
+        Capture {
+            frames: Queue::new(),
+            dropped: 0,
+        }
+    }
+}
+
+/// Captures of the open `pcap:` resources, receiving every frame the drivers send or receive
+pub static mut captures: *mut Vec<*mut Capture> = 0 as *mut Vec<*mut Capture>;
+
+/// Mirror a frame to the open captures, called by the drivers with interrupts disabled
+pub unsafe fn capture(bytes: &[u8]) {
+    if !captures.is_null() {
+        let time = Duration::realtime();
+        for capture in (*captures).iter() {
+            if (**capture).frames.len() < CAPTURE_QUEUE_LEN {
+                (**capture).frames.push((time, Vec::from(bytes)));
+            } else {
+                (**capture).dropped += 1;
+            }
+        }
+    }
+}
+
+/// Counters and link state of a network interface
+pub struct NetworkStats {
+    pub name: &'static str,
+    /// None when the driver can not tell
+    pub link: Option<bool>,
+    pub rx_packets: u64,
+    pub rx_bytes: u64,
+    pub rx_errors: u64,
+    /// Frames nobody was listening for, or that the card had no room for
+    pub rx_dropped: u64,
+    pub tx_packets: u64,
+    pub tx_bytes: u64,
+    pub tx_errors: u64,
+    pub tx_dropped: u64,
+}
+
+impl NetworkStats {
+    pub fn new(name: &'static str) -> Self {
+        NetworkStats {
+            name: name,
+            link: None,
+            rx_packets: 0,
+            rx_bytes: 0,
+            rx_errors: 0,
+            rx_dropped: 0,
+            tx_packets: 0,
+            tx_bytes: 0,
+            tx_errors: 0,
+            tx_dropped: 0,
+        }
+    }
+
+    pub fn received(&mut self, len: usize) {
+        self.rx_packets += 1;
+        self.rx_bytes += len as u64;
+    }
+
+    pub fn sent(&mut self, len: usize) {
+        self.tx_packets += 1;
+        self.tx_bytes += len as u64;
+    }
+
+    pub fn to_string(&self) -> String {
+        let link = match self.link {
+            Some(true) => "up",
+            Some(false) => "down",
+            None => "unknown",
+        };
+
+        format!("{} link {}\n    RX packets {} bytes {} errors {} dropped {}\n    TX packets {} bytes {} errors {} dropped {}",
+                self.name, link,
+                self.rx_packets, self.rx_bytes, self.rx_errors, self.rx_dropped,
+                self.tx_packets, self.tx_bytes, self.tx_errors, self.tx_dropped)
+    }
+}
+
+/// A network interface, a card with its own addresses
+#[derive(Clone)]
+pub struct Interface {
+    /// `ethN` for cards, `lo` for the loopback interface
+    pub name: String,
+    pub nic: *mut NetworkScheme,
+    pub mac: MacAddr,
+    pub ip: Ipv4Addr,
+    pub netmask: Ipv4Addr,
+    /// Carries IP packets without an ethernet header
+    pub loopback: bool,
+    /// Owned by the driver, which lives as long as the kernel
+    pub stats: *const NetworkStats,
+}
+
+impl Interface {
+    /// The broadcast address of the subnet
+    pub fn broadcast(&self) -> Ipv4Addr {
+        let mut addr = self.ip;
+        for i in 0..4 {
+            addr.bytes[i] |= !self.netmask.bytes[i];
+        }
+        addr
+    }
+
+    /// Is `addr` on the subnet of the interface
+    pub fn contains(&self, addr: Ipv4Addr) -> bool {
+        addr.in_network(self.ip, self.netmask)
+    }
+
+    pub fn to_string(&self) -> String {
+        format!("{} mac {} inet {}/{} broadcast {}\n    {}",
+                self.name, self.mac.to_string(), self.ip.to_string(), self.netmask.prefix_len(),
+                self.broadcast().to_string(), unsafe { (*self.stats).to_string() })
+    }
+}
+
+/// The interfaces, registered by the drivers
+pub static mut interfaces: *mut Vec<Interface> = 0 as *mut Vec<Interface>;
+
+unsafe fn push_interface(interface: Interface) {
+    let reenable = scheduler::start_no_ints();
+    if interfaces.is_null() {
+        interfaces = Box::into_raw(box Vec::new());
+    }
+    (*interfaces).push(interface);
+    scheduler::end_no_ints(reenable);
+}
+
+/// Register a network card, naming it `ethN`
+///
+/// The card has no address until one is set through `route:`, see `file:///etc/network`.
+pub unsafe fn register_interface(nic: *mut NetworkScheme, mac: MacAddr, stats: *const NetworkStats) {
+    let n = all_interfaces().iter().filter(|interface| !interface.loopback).count();
+
+    push_interface(Interface {
+        name: format!("eth{}", n),
+        nic: nic,
+        mac: mac,
+        ip: UNSPECIFIED_IP_ADDR,
+        netmask: Ipv4Addr::from_prefix_len(32),
+        loopback: false,
+        stats: stats,
+    });
+}
+
+/// Register the loopback interface as `lo`
+pub unsafe fn register_loopback(nic: *mut NetworkScheme, stats: *const NetworkStats) {
+    push_interface(Interface {
+        name: "lo".to_string(),
+        nic: nic,
+        mac: MacAddr { bytes: [0; 6] },
+        ip: LOOPBACK_IP_ADDR,
+        netmask: Ipv4Addr::from_prefix_len(8),
+        loopback: true,
+        stats: stats,
+    });
+}
+
+/// A copy of every interface
+pub fn all_interfaces() -> Vec<Interface> {
+    unsafe {
+        let reenable = scheduler::start_no_ints();
+        let list = if interfaces.is_null() {
+            Vec::new()
+        } else {
+            (*interfaces).clone()
+        };
+        scheduler::end_no_ints(reenable);
+        list
+    }
+}
+
+/// Find an interface by name, an empty name is the first network card
+pub fn find_interface(name: &str) -> Option<Interface> {
+    for interface in all_interfaces().into_iter() {
+        if interface.name == name || (name.is_empty() && !interface.loopback) {
+            return Some(interface);
+        }
+    }
+    None
+}
+
+/// The MAC address IPv6 runs on, IPv6 only uses the first card
+pub fn ipv6_mac() -> MacAddr {
+    match find_interface("") {
+        Some(interface) => interface.mac,
+        None => MacAddr { bytes: [0; 6] },
+    }
+}
+
+/// Our link-local IPv6 address
+pub fn ipv6_addr() -> Ipv6Addr {
+    Ipv6Addr::link_local(ipv6_mac())
+}
+
+/// Set the address of an interface, returns false if there is no such interface
+pub fn configure_interface(name: &str, ip: Ipv4Addr, netmask: Ipv4Addr) -> bool {
+    unsafe {
+        let reenable = scheduler::start_no_ints();
+        let mut found = false;
+        if !interfaces.is_null() {
+            for interface in (*interfaces).iter_mut() {
+                if interface.name == name {
+                    interface.ip = ip;
+                    interface.netmask = netmask;
+                    found = true;
+                }
+            }
+        }
+        scheduler::end_no_ints(reenable);
+        found
+    }
+}
+
+/// Is `addr` the address of one of our interfaces
+pub fn is_local_addr(addr: Ipv4Addr) -> bool {
+    all_interfaces().iter().any(|interface| interface.ip.equals(addr))
+}
+
+/// Is `addr` the broadcast address of one of our network cards, or the limited broadcast
+pub fn is_broadcast_addr(addr: Ipv4Addr) -> bool {
+    addr.is_broadcast() ||
+    all_interfaces().iter().any(|interface| !interface.loopback && interface.broadcast().equals(addr))
+}
+
+/// The `network:` scheme, `network://NAME` opens the card of an interface and
+/// `network://` the first one
+pub struct InterfaceScheme;
+
+impl KScheme for InterfaceScheme {
+    fn scheme(&self) -> &str {
+        "network"
+    }
+
+    fn open(&mut self, url: &Url, flags: usize) -> Option<Box<Resource>> {
+        let parts = parse_path(url.reference());
+        let name = match parts.get(0) {
+            Some(part) => part.clone(),
+            None => String::new(),
+        };
+
+        match find_interface(&name) {
+            Some(interface) => Some(NetworkResource::new(interface.nic, flags)),
+            None => None,
+        }
+    }
+}
 
 pub trait NetworkScheme {
     fn add(&mut self, resource: *mut NetworkResource);
@@ -22,15 +289,17 @@ pub struct NetworkResource {
     pub ptr: *mut NetworkResource,
     pub inbound: Queue<Vec<u8>>,
     pub outbound: Queue<Vec<u8>>,
+    pub flags: usize,
 }
 
 impl NetworkResource {
-    pub fn new(nic: *mut NetworkScheme) -> Box<Self> {
+    pub fn new(nic: *mut NetworkScheme, flags: usize) -> Box<Self> {
         let mut ret = box NetworkResource {
             nic: nic,
             ptr: 0 as *mut NetworkResource,
             inbound: Queue::new(),
             outbound: Queue::new(),
+            flags: flags,
         };
 
         unsafe {
@@ -50,6 +319,7 @@ impl Resource for NetworkResource {
             ptr: 0 as *mut NetworkResource,
             inbound: self.inbound.clone(),
             outbound: self.outbound.clone(),
+            flags: self.flags,
         };
 
         unsafe {
@@ -66,8 +336,8 @@ impl Resource for NetworkResource {
     }
 
     fn read(&mut self, buf: &mut [u8]) -> Option<usize> {
-        debug::d("TODO: Implement read for RTL8139\n");
-        None
+        // One frame per call, truncated to the buffer
+        read_packet(self, buf)
     }
 
     fn read_to_end(&mut self, vec: &mut Vec<u8>) -> Option<usize> {
@@ -84,6 +354,10 @@ impl Resource for NetworkResource {
                     return Some(bytes.len());
                 }
 
+                if self.flags & O_NONBLOCK == O_NONBLOCK {
+                    return Some(WOULD_BLOCK);
+                }
+
                 context_switch(false);
             }
         }
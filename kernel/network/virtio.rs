@@ -0,0 +1,408 @@
+use alloc::boxed::Box;
+
+use collections::slice;
+use collections::vec::Vec;
+
+use core::{mem, ptr};
+use core::intrinsics::{volatile_load, volatile_store};
+
+use common::{debug, memory};
+use common::queue::Queue;
+use scheduler;
+
+use drivers::pciconfig::PciConfig;
+use drivers::pio::*;
+
+use network::common::*;
+use network::scheme::*;
+
+use schemes::KScheme;
+
+// Legacy virtio PCI registers, relative to the I/O BAR
+const DEVICE_FEATURES: u16 = 0x00;
+const GUEST_FEATURES: u16 = 0x04;
+const QUEUE_ADDRESS: u16 = 0x08;
+const QUEUE_SIZE: u16 = 0x0C;
+const QUEUE_SELECT: u16 = 0x0E;
+const QUEUE_NOTIFY: u16 = 0x10;
+const DEVICE_STATUS: u16 = 0x12;
+    const STATUS_ACKNOWLEDGE: u8 = 1;
+    const STATUS_DRIVER: u8 = 1 << 1;
+    const STATUS_DRIVER_OK: u8 = 1 << 2;
+const ISR_STATUS: u16 = 0x13;
+const NET_MAC: u16 = 0x14;
+const NET_STATUS: u16 = 0x1A;
+    const NET_S_LINK_UP: u16 = 1;
+
+const NET_F_MAC: u32 = 1 << 5;
+const NET_F_STATUS: u32 = 1 << 16;
+
+const RX_QUEUE: u16 = 0;
+const TX_QUEUE: u16 = 1;
+
+/// Size of each receive and transmit buffer, large enough for the header and a full frame
+const BUFFER_SIZE: usize = 2048;
+
+#[repr(packed)]
+struct Descriptor {
+    address: u64,
+    length: u32,
+    flags: u16,
+    next: u16,
+}
+    const DESC_F_WRITE: u16 = 1 << 1;
+
+#[repr(packed)]
+struct UsedElement {
+    id: u32,
+    length: u32,
+}
+
+/// The header in front of every frame, without mergeable receive buffers
+#[repr(packed)]
+struct NetHeader {
+    flags: u8,
+    gso_type: u8,
+    header_length: u16,
+    gso_size: u16,
+    checksum_start: u16,
+    checksum_offset: u16,
+}
+
+/// A split virtqueue in the legacy layout
+struct Virtqueue {
+    index: u16,
+    size: usize,
+    descriptors: *mut Descriptor,
+    available_index: *mut u16,
+    available_ring: *mut u16,
+    used_index: *mut u16,
+    used_ring: *mut UsedElement,
+    /// The next used element to process
+    last_used: u16,
+    /// Descriptors not owned by the device
+    free: Vec<u16>,
+}
+
+impl Virtqueue {
+    /// Allocate the queue selected on the device and hand it over
+    unsafe fn new(base: u16, index: u16) -> Option<Self> {
+        outw(base + QUEUE_SELECT, index);
+        let size = inw(base + QUEUE_SIZE) as usize;
+        if size == 0 {
+            return None;
+        }
+
+        let align = |x: usize| (x + 4095) & !4095;
+        let available_size = align(size * mem::size_of::<Descriptor>() + 6 + size * 2);
+        let used_size = align(6 + size * mem::size_of::<UsedElement>());
+
+        let address = memory::alloc_aligned(available_size + used_size, 4096);
+        if address == 0 {
+            return None;
+        }
+
+        let available = address + size * mem::size_of::<Descriptor>();
+        let used = address + available_size;
+
+        let mut free = Vec::new();
+        for i in 0..size {
+            free.push(i as u16);
+        }
+
+        outd(base + QUEUE_ADDRESS, (address >> 12) as u32);
+
+        Some(Virtqueue {
+            index: index,
+            size: size,
+            descriptors: address as *mut Descriptor,
+            available_index: (available + 2) as *mut u16,
+            available_ring: (available + 4) as *mut u16,
+            used_index: (used + 2) as *mut u16,
+            used_ring: (used + 4) as *mut UsedElement,
+            last_used: 0,
+            free: free,
+        })
+    }
+
+    /// Queue a buffer, it becomes visible to the device on the next `publish`
+    unsafe fn push(&mut self, address: usize, length: usize, flags: u16, pending: &mut u16) -> bool {
+        if let Some(id) = self.free.pop() {
+            ptr::write(self.descriptors.offset(id as isize),
+                       Descriptor {
+                           address: address as u64,
+                           length: length as u32,
+                           flags: flags,
+                           next: 0,
+                       });
+
+            let available_index = volatile_load(self.available_index);
+            let slot = (available_index.wrapping_add(*pending) as usize) % self.size;
+            volatile_store(self.available_ring.offset(slot as isize), id);
+            *pending += 1;
+
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Make `pending` pushed buffers available and notify the device once
+    unsafe fn publish(&mut self, base: u16, pending: u16) {
+        if pending > 0 {
+            let available_index = volatile_load(self.available_index);
+            volatile_store(self.available_index, available_index.wrapping_add(pending));
+            outw(base + QUEUE_NOTIFY, self.index);
+        }
+    }
+
+    /// Take the next buffer the device is done with
+    unsafe fn pop_used(&mut self) -> Option<(u16, usize)> {
+        if self.last_used != volatile_load(self.used_index) {
+            let element = volatile_load(self.used_ring.offset((self.last_used as usize % self.size) as isize));
+            self.last_used = self.last_used.wrapping_add(1);
+
+            Some((element.id as u16, element.length as usize))
+        } else {
+            None
+        }
+    }
+
+    unsafe fn buffer(&self, id: u16) -> usize {
+        (*self.descriptors.offset(id as isize)).address as usize
+    }
+}
+
+/// A virtio network device
+pub struct VirtioNet {
+    pci: PciConfig,
+    base: usize,
+    irq: u8,
+    resources: Vec<*mut NetworkResource>,
+    inbound: Queue<Vec<u8>>,
+    outbound: Queue<Vec<u8>>,
+    rx: Option<Virtqueue>,
+    tx: Option<Virtqueue>,
+    /// One buffer per transmit descriptor
+    tx_buffers: Vec<usize>,
+    /// The device reports the link state
+    link_status: bool,
+    stats: NetworkStats,
+}
+
+impl VirtioNet {
+    pub fn new(mut pci: PciConfig) -> Box<Self> {
+        let base = unsafe { pci.read(0x10) as usize };
+        let irq = unsafe { pci.read(0x3C) as u8 & 0xF };
+
+        let mut module = box VirtioNet {
+            pci: pci,
+            base: base & 0xFFFFFFF0,
+            irq: irq,
+            resources: Vec::new(),
+            inbound: Queue::new(),
+            outbound: Queue::new(),
+            rx: None,
+            tx: None,
+            tx_buffers: Vec::new(),
+            link_status: false,
+            stats: NetworkStats::new("virtio"),
+        };
+
+        unsafe { module.init() };
+
+        module
+    }
+
+    unsafe fn init(&mut self) {
+        debug::d("VirtIO Net on: ");
+        debug::dh(self.base);
+        debug::d(" IRQ: ");
+        debug::dbh(self.irq);
+
+        self.pci.flag(4, 4, true); // Bus mastering
+
+        let base = self.base as u16;
+
+        // Reset, then tell the device we found it and can drive it
+        outb(base + DEVICE_STATUS, 0);
+        outb(base + DEVICE_STATUS, STATUS_ACKNOWLEDGE);
+        outb(base + DEVICE_STATUS, STATUS_ACKNOWLEDGE | STATUS_DRIVER);
+
+        let features = ind(base + DEVICE_FEATURES);
+        outd(base + GUEST_FEATURES, features & (NET_F_MAC | NET_F_STATUS));
+        self.link_status = features & NET_F_STATUS == NET_F_STATUS;
+
+        let mut mac = MacAddr { bytes: [0; 6] };
+        if features & NET_F_MAC == NET_F_MAC {
+            debug::d(" MAC: ");
+            for i in 0..6 {
+                mac.bytes[i] = inb(base + NET_MAC + i as u16);
+            }
+            debug::d(&mac.to_string());
+        } else {
+            debug::d(" No MAC");
+        }
+
+        let nic: *mut NetworkScheme = &mut *self;
+        let stats: *const NetworkStats = &self.stats;
+        register_interface(nic, mac, stats);
+
+        self.rx = Virtqueue::new(base, RX_QUEUE);
+        self.tx = Virtqueue::new(base, TX_QUEUE);
+
+        // Fill the receive queue
+        if let Some(ref mut rx) = self.rx {
+            let mut pending = 0;
+            for _ in 0..rx.size {
+                let buffer = memory::alloc(BUFFER_SIZE);
+                if !rx.push(buffer, BUFFER_SIZE, DESC_F_WRITE, &mut pending) {
+                    break;
+                }
+            }
+            rx.publish(base, pending);
+
+            debug::d(" RX: ");
+            debug::dd(rx.size);
+        }
+
+        if let Some(ref tx) = self.tx {
+            for _ in 0..tx.size {
+                self.tx_buffers.push(memory::alloc(BUFFER_SIZE));
+            }
+
+            debug::d(" TX: ");
+            debug::dd(tx.size);
+        }
+
+        outb(base + DEVICE_STATUS, STATUS_ACKNOWLEDGE | STATUS_DRIVER | STATUS_DRIVER_OK);
+
+        debug::dl();
+    }
+
+    unsafe fn receive_inbound(&mut self) {
+        let base = self.base as u16;
+
+        if let Some(ref mut rx) = self.rx {
+            let mut pending = 0;
+            while let Some((id, length)) = rx.pop_used() {
+                let buffer = rx.buffer(id);
+
+                if length > mem::size_of::<NetHeader>() {
+                    self.stats.received(length - mem::size_of::<NetHeader>());
+                    self.inbound.push(Vec::from(slice::from_raw_parts((buffer + mem::size_of::<NetHeader>()) as *const u8,
+                                                                      length - mem::size_of::<NetHeader>())));
+                } else {
+                    self.stats.rx_errors += 1;
+                }
+
+                // Give the buffer straight back to the device
+                rx.free.push(id);
+                rx.push(buffer, BUFFER_SIZE, DESC_F_WRITE, &mut pending);
+            }
+            rx.publish(base, pending);
+        }
+    }
+
+    unsafe fn send_outbound(&mut self) {
+        let base = self.base as u16;
+
+        if let Some(ref mut tx) = self.tx {
+            while let Some((id, _)) = tx.pop_used() {
+                tx.free.push(id);
+            }
+
+            // Queue as many frames as there are free descriptors, then notify once
+            let mut pending = 0;
+            while !tx.free.is_empty() {
+                if let Some(bytes) = self.outbound.pop() {
+                    if bytes.len() + mem::size_of::<NetHeader>() <= BUFFER_SIZE {
+                        let id = *tx.free.last().unwrap();
+                        let buffer = self.tx_buffers[id as usize];
+
+                        ::memset(buffer as *mut u8, 0, mem::size_of::<NetHeader>());
+                        ::memcpy((buffer + mem::size_of::<NetHeader>()) as *mut u8, bytes.as_ptr(), bytes.len());
+
+                        tx.push(buffer, bytes.len() + mem::size_of::<NetHeader>(), 0, &mut pending);
+
+                        self.stats.sent(bytes.len());
+                    } else {
+                        self.stats.tx_errors += 1;
+                        debug::d("VirtIO Net: Frame too long for transmit: ");
+                        debug::dd(bytes.len());
+                        debug::dl();
+                    }
+                } else {
+                    break;
+                }
+            }
+            tx.publish(base, pending);
+        }
+    }
+}
+
+impl KScheme for VirtioNet {
+    fn on_irq(&mut self, irq: u8) {
+        if irq == self.irq {
+            // Reading the ISR acknowledges the interrupt
+            unsafe { inb(self.base as u16 + ISR_STATUS) };
+
+            self.sync();
+        }
+    }
+
+    fn on_poll(&mut self) {
+        self.sync();
+    }
+}
+
+impl NetworkScheme for VirtioNet {
+    fn add(&mut self, resource: *mut NetworkResource) {
+        unsafe {
+            let reenable = scheduler::start_no_ints();
+            self.resources.push(resource);
+            scheduler::end_no_ints(reenable);
+        }
+    }
+
+    fn remove(&mut self, resource: *mut NetworkResource) {
+        unsafe {
+            let reenable = scheduler::start_no_ints();
+            self.resources.retain(|ptr| *ptr != resource);
+            scheduler::end_no_ints(reenable);
+        }
+    }
+
+    fn sync(&mut self) {
+        unsafe {
+            let reenable = scheduler::start_no_ints();
+
+            for resource in self.resources.iter() {
+                while let Some(bytes) = (**resource).outbound.pop() {
+                    capture(&bytes);
+                    self.outbound.push(bytes);
+                }
+            }
+
+            self.send_outbound();
+
+            self.receive_inbound();
+
+            if self.link_status {
+                self.stats.link = Some(inw(self.base as u16 + NET_STATUS) & NET_S_LINK_UP == NET_S_LINK_UP);
+            }
+
+            while let Some(bytes) = self.inbound.pop() {
+                capture(&bytes);
+                if self.resources.is_empty() {
+                    self.stats.rx_dropped += 1;
+                }
+                for resource in self.resources.iter() {
+                    (**resource).inbound.push(bytes.clone());
+                }
+            }
+
+            scheduler::end_no_ints(reenable);
+        }
+    }
+}
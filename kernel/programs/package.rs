@@ -70,7 +70,7 @@ impl Package {
                 if let Some(mut resource) = Url::from_string(line.get_slice(Some(5), None).to_string()).open() {
                     let mut vec: Vec<u8> = Vec::new();
                     resource.read_to_end(&mut vec);
-                    package.icon = BmpFile::from_data(&vec);
+                    package.icon = BmpFile::from_image(&vec);
                 }
             } else if line.starts_with("accept=") {
                 package.accepts.push(line.get_slice(Some(7), None).to_string());
@@ -6,13 +6,18 @@ use alloc::boxed::Box;
 use collections::string::{String, ToString};
 use collections::vec::Vec;
 
-use common::event::{Event, EventOption, KeyEvent, MouseEvent};
+use core::{cmp, mem};
+
+use common::event::{Event, EventOption, FocusEvent, HoverEvent, KeyEvent, MouseEvent, ScrollEvent};
 use scheduler;
 
+use drivers::bga::bga_ptr;
+
 use graphics::bmp::BmpFile;
 use graphics::color::Color;
-use graphics::display::Display;
+use graphics::display::{Display, DisplayMode, Framebuffer};
 use graphics::point::Point;
+use graphics::rect::Rect;
 use graphics::size::Size;
 use graphics::window::Window;
 
@@ -38,8 +43,40 @@ pub struct Session {
     pub windows: Vec<*mut Window>,
     /// Ordered windows
     pub windows_ordered: Vec<*mut Window>,
-    /// Redraw
+    /// The window with the keyboard focus, or null
+    focus: *mut Window,
+    /// The window with the pointer over its content, or null
+    hover: *mut Window,
+    /// Redraw the whole screen
     pub redraw: bool,
+    /// The parts of the screen to draw again, which do not overlap
+    damage: Vec<Rect>,
+    /// Where the cursor was drawn
+    cursor_rect: Rect,
+    /// The pixels under the cursor
+    cursor_under: Vec<u32>,
+    /// The parts of the screen shown since each `display:stream` last read them, by stream id
+    streams: Vec<(usize, Vec<Rect>)>,
+    /// The id of the next `display:stream`
+    next_stream: usize,
+}
+
+/// Add a rectangle to a list of rectangles that do not overlap
+fn add_rect(rects: &mut Vec<Rect>, rect: Rect) {
+    let mut rect = rect;
+
+    // Merge overlapping rectangles, so that nothing is drawn twice
+    let mut i = 0;
+    while i < rects.len() {
+        if rects[i].intersects(&rect) {
+            rect = rect.union(&rects.remove(i));
+            i = 0;
+        } else {
+            i += 1;
+        }
+    }
+
+    rects.push(rect);
 }
 
 impl Session {
@@ -62,7 +99,14 @@ impl Session {
                 packages: Vec::new(),
                 windows: Vec::new(),
                 windows_ordered: Vec::new(),
+                focus: 0 as *mut Window,
+                hover: 0 as *mut Window,
                 redraw: true,
+                damage: Vec::new(),
+                cursor_rect: Rect::new(0, 0, 0, 0),
+                cursor_under: Vec::new(),
+                streams: Vec::new(),
+                next_stream: 0,
             }
         }
     }
@@ -70,7 +114,10 @@ impl Session {
     pub unsafe fn add_window(&mut self, add_window_ptr: *mut Window) {
         self.windows.push(add_window_ptr);
         self.windows_ordered.push(add_window_ptr);
-        self.redraw = true;
+        self.set_focus(add_window_ptr);
+        self.damage((*add_window_ptr).bounds());
+        let taskbar = self.taskbar_rect();
+        self.damage(taskbar);
     }
 
     /// Remove a window
@@ -111,7 +158,131 @@ impl Session {
             }
         }
 
-        self.redraw = true;
+        if self.hover == remove_window_ptr {
+            self.hover = 0 as *mut Window;
+        }
+        if self.focus == remove_window_ptr {
+            self.focus = 0 as *mut Window;
+            let top = match self.windows.last() {
+                Some(window_ptr) => *window_ptr,
+                None => 0 as *mut Window,
+            };
+            self.set_focus(top);
+        }
+
+        self.damage((*remove_window_ptr).bounds());
+        let taskbar = self.taskbar_rect();
+        self.damage(taskbar);
+    }
+
+    /// Mark a part of the screen to be drawn again
+    pub fn damage(&mut self, rect: Rect) {
+        let rect = rect.intersection(&self.display.bounds());
+        if !rect.is_empty() {
+            add_rect(&mut self.damage, rect);
+        }
+    }
+
+    /// Mark a part of the screen as shown, for the `display:stream` resources
+    pub fn shown(&mut self, rect: Rect) {
+        let rect = rect.intersection(&self.display.bounds());
+        if !rect.is_empty() {
+            for stream in self.streams.iter_mut() {
+                add_rect(&mut stream.1, rect);
+            }
+        }
+    }
+
+    /// Start collecting the parts of the screen that are shown for a `display:stream`
+    pub fn add_stream(&mut self) -> usize {
+        let id = self.next_stream;
+        self.next_stream += 1;
+        self.streams.push((id, Vec::new()));
+        id
+    }
+
+    /// Stop collecting for a `display:stream`
+    pub fn remove_stream(&mut self, id: usize) {
+        self.streams.retain(|stream| stream.0 != id);
+    }
+
+    /// The parts of the screen shown since a `display:stream` last took them
+    pub fn take_stream(&mut self, id: usize) -> Vec<Rect> {
+        for stream in self.streams.iter_mut() {
+            if stream.0 == id {
+                return mem::replace(&mut stream.1, Vec::new());
+            }
+        }
+        Vec::new()
+    }
+
+    /// The modes the display can be switched to, only the current one without a driver
+    pub fn display_modes(&self) -> Vec<DisplayMode> {
+        unsafe {
+            if bga_ptr.is_null() {
+                vec![Framebuffer::current().mode()]
+            } else {
+                (*bga_ptr).modes()
+            }
+        }
+    }
+
+    /// Switch the display to another mode, and lay the windows out on it again
+    pub unsafe fn set_display_mode(&mut self, mode: DisplayMode) -> bool {
+        if bga_ptr.is_null() {
+            return false;
+        }
+
+        let reenable = scheduler::start_no_ints();
+
+        let framebuffer = (*bga_ptr).set_mode(mode);
+        if let Some(framebuffer) = framebuffer {
+            framebuffer.set_current();
+            self.display = Display::root();
+            if !::debug_display.is_null() {
+                drop(Box::from_raw(::debug_display));
+                ::debug_display = Box::into_raw(Display::root());
+            }
+
+            self.mouse_point = Point::new(cmp::min(self.mouse_point.x, mode.width as isize - 1),
+                                          cmp::min(self.mouse_point.y, mode.height as isize - 1));
+            self.damage.clear();
+            self.cursor_rect = Rect::new(0, 0, 0, 0);
+            self.cursor_under.clear();
+            for window_ptr in self.windows.iter() {
+                (**window_ptr).fit_screen(mode.width, mode.height);
+            }
+            self.redraw = true;
+        }
+
+        scheduler::end_no_ints(reenable);
+
+        framebuffer.is_some()
+    }
+
+    /// The taskbar, with room for the names shown above the package icons
+    fn taskbar_rect(&self) -> Rect {
+        let mut height = 32;
+        for package in self.packages.iter() {
+            if package.icon.size.height > height {
+                height = package.icon.size.height;
+            }
+        }
+        height += 16;
+
+        Rect::new(0,
+                  self.display.height as isize - height as isize,
+                  self.display.width,
+                  height)
+    }
+
+    /// Where the cursor is to be drawn
+    fn cursor_rect_at(&self, point: Point) -> Rect {
+        if !self.cursor.data.is_empty() {
+            Rect::from_point_size(point, self.cursor.size)
+        } else {
+            Rect::new(point.x - 3, point.y - 9, 8, 16)
+        }
     }
 
     pub unsafe fn on_irq(&mut self, irq: u8) {
@@ -157,24 +328,98 @@ impl Session {
         }
     }
 
-    fn on_key(&mut self, key_event: KeyEvent) {
-        if !self.windows.is_empty() {
-            match self.windows.get(self.windows.len() - 1) {
-                Some(window_ptr) => {
-                    unsafe {
-                        (**window_ptr).on_key(key_event);
-                        self.redraw = true;
-                    }
+    /// Give the keyboard focus to a window, or to none if it is null
+    pub unsafe fn set_focus(&mut self, window_ptr: *mut Window) {
+        if window_ptr != self.focus {
+            if !self.focus.is_null() {
+                (*self.focus).push_event(FocusEvent { focused: false }.to_event());
+            }
+            self.focus = window_ptr;
+            if !self.focus.is_null() {
+                (*self.focus).push_event(FocusEvent { focused: true }.to_event());
+            }
+        }
+    }
+
+    /// Put a window on top of the others
+    unsafe fn raise(&mut self, window_ptr: *mut Window) {
+        if self.windows.last() != Some(&window_ptr) {
+            let mut i = 0;
+            while i < self.windows.len() {
+                if self.windows[i] == window_ptr {
+                    self.windows.remove(i);
+                } else {
+                    i += 1;
                 }
-                None => (),
+            }
+            self.windows.push(window_ptr);
+            self.damage((*window_ptr).bounds());
+        }
+    }
+
+    /// The window on top at a point, or null
+    unsafe fn window_at(&self, point: Point) -> *mut Window {
+        for window_ptr in self.windows.iter().rev() {
+            if (**window_ptr).contains(point) {
+                return *window_ptr;
+            }
+        }
+        0 as *mut Window
+    }
+
+    /// The window that holds on to the pointer, because it is being dragged, a button was
+    /// pressed on it, or it asked for a grab, or null
+    unsafe fn pointer_grab(&self) -> *mut Window {
+        for window_ptr in self.windows.iter().rev() {
+            if (**window_ptr).wants_pointer() {
+                return *window_ptr;
+            }
+        }
+        0 as *mut Window
+    }
+
+    /// Move the pointer into a window, or out of all if it is null
+    unsafe fn set_hover(&mut self, window_ptr: *mut Window) {
+        if window_ptr != self.hover {
+            if !self.hover.is_null() {
+                (*self.hover).push_event(HoverEvent { entered: false }.to_event());
+            }
+            self.hover = window_ptr;
+            if !self.hover.is_null() {
+                (*self.hover).push_event(HoverEvent { entered: true }.to_event());
+            }
+        }
+    }
+
+    fn on_key(&mut self, key_event: KeyEvent) {
+        if !self.focus.is_null() {
+            unsafe {
+                (*self.focus).on_key(key_event);
+            }
+        }
+    }
+
+    fn on_scroll(&mut self, scroll_event: ScrollEvent) {
+        unsafe {
+            let mut window_ptr = self.pointer_grab();
+            if window_ptr.is_null() {
+                window_ptr = self.hover;
+            }
+            if !window_ptr.is_null() {
+                (*window_ptr).push_event(scroll_event.to_event());
             }
         }
     }
 
     fn on_mouse(&mut self, mouse_event: MouseEvent) {
-        let mut catcher = -1;
+        let point = Point::new(mouse_event.x, mouse_event.y);
+        let grab = unsafe { self.pointer_grab() };
+
+        if grab.is_null() && mouse_event.y >= self.display.height as isize - 32 {
+            unsafe {
+                self.set_hover(0 as *mut Window);
+            }
 
-        if mouse_event.y >= self.display.height as isize - 32 {
             if !mouse_event.left_button && self.last_mouse_event.left_button {
                 let mut x = 0;
                 for package in self.packages.iter() {
@@ -194,170 +439,249 @@ impl Session {
                     chars -= 1;
                 }
 
+                let mut clicked = 0 as *mut Window;
                 x += 4;
                 for window_ptr in self.windows_ordered.iter() {
                     let w = (chars*8 + 2*4) as usize;
                     if mouse_event.x >= x && mouse_event.x < x + w as isize {
-                        for j in 0..self.windows.len() {
-                            match self.windows.get(j) {
-                                Some(catcher_window_ptr) =>
-                                    if catcher_window_ptr == window_ptr {
-                                    unsafe {
-                                        if j == self.windows.len() - 1 {
-                                            (**window_ptr).minimized = !(**window_ptr).minimized;
-                                        } else {
-                                            catcher = j as isize;
-                                            (**window_ptr).minimized = false;
-                                        }
-                                    }
-                                    break;
-                                },
-                                None => break,
-                            }
-                        }
-                        self.redraw = true;
+                        clicked = *window_ptr;
                         break;
                     }
                     x += w as isize;
                 }
-            }
-        } else {
-            for reverse_i in 0..self.windows.len() {
-                let i = self.windows.len() - 1 - reverse_i;
-                match self.windows.get(i) {
-                    Some(window_ptr) => unsafe {
-                        if (**window_ptr).on_mouse(mouse_event, catcher < 0) {
-                            catcher = i as isize;
-
-                            self.redraw = true;
+
+                if !clicked.is_null() {
+                    unsafe {
+                        if clicked == self.focus {
+                            (*clicked).minimized = !(*clicked).minimized;
+                        } else {
+                            (*clicked).minimized = false;
+                            self.raise(clicked);
+                            self.set_focus(clicked);
                         }
-                    },
-                    None => (),
+                        let bounds = (*clicked).bounds();
+                        (*clicked).add_damage(bounds);
+                    }
+                    let taskbar = self.taskbar_rect();
+                    self.damage(taskbar);
                 }
             }
-        }
+        } else {
+            unsafe {
+                let under = self.window_at(point);
+                if !under.is_null() && (*under).on_body(point) {
+                    self.set_hover(under);
+                } else {
+                    self.set_hover(0 as *mut Window);
+                }
 
-        if catcher >= 0 && catcher < self.windows.len() as isize - 1 {
-            let window_ptr = self.windows.remove(catcher as usize);
-            self.windows.push(window_ptr);
+                let target = if grab.is_null() {
+                    under
+                } else {
+                    grab
+                };
+
+                if !target.is_null() {
+                    let pressed = (mouse_event.left_button && !self.last_mouse_event.left_button) ||
+                                  (mouse_event.middle_button && !self.last_mouse_event.middle_button) ||
+                                  (mouse_event.right_button && !self.last_mouse_event.right_button);
+
+                    let minimized = (*target).minimized;
+                    if (*target).on_mouse(mouse_event) && pressed {
+                        self.raise(target);
+                        self.set_focus(target);
+                    }
+                    if (*target).minimized != minimized {
+                        let taskbar = self.taskbar_rect();
+                        self.damage(taskbar);
+                    }
+                }
+            }
         }
 
         self.last_mouse_event = mouse_event;
     }
 
-    /// Redraw screen
-    pub unsafe fn redraw(&mut self) {
-        if self.redraw {
-            self.display.set(Color::new(75, 163, 253));
-            if !self.background.data.is_empty() {
-                self.background.draw(&self.display,
-                                     Point::new((self.display.width as isize -
-                                                 self.background.size.width as isize) /
-                                                2,
-                                                (self.display.height as isize -
-                                                 self.background.size.height as isize) /
-                                                2));
-            }
-
-            for i in 0..self.windows.len() {
-                match self.windows.get(i) {
-                    Some(window_ptr) => {
-                        (**window_ptr).focused = i == self.windows.len() - 1;
-                        (**window_ptr).draw(&self.display);
-                    }
-                    None => (),
+    /// Draw everything but the cursor, inside of the display clip
+    unsafe fn composite(&self, mouse_point: Point) {
+        self.display.rect(Point::new(0, 0),
+                          Size::new(self.display.width, self.display.height),
+                          Color::new(75, 163, 253));
+        if !self.background.data.is_empty() {
+            self.background.draw(&self.display,
+                                 Point::new((self.display.width as isize -
+                                             self.background.size.width as isize) /
+                                            2,
+                                            (self.display.height as isize -
+                                             self.background.size.height as isize) /
+                                            2));
+        }
+
+        for i in 0..self.windows.len() {
+            match self.windows.get(i) {
+                Some(window_ptr) => {
+                    (**window_ptr).draw(&self.display);
                 }
+                None => (),
             }
+        }
 
-            self.display.rect(Point::new(0, self.display.height as isize - 32),
-                              Size::new(self.display.width, 32),
-                              Color::alpha(0, 0, 0, 128));
-
-            let mut x = 0;
-            for package in self.packages.iter() {
-                if !package.icon.data.is_empty() {
-                    let y = self.display.height as isize - package.icon.size.height as isize;
-                    if self.mouse_point.y >= y && self.mouse_point.x >= x &&
-                       self.mouse_point.x < x + package.icon.size.width as isize {
-                        self.display.rect(Point::new(x, y),
-                                          package.icon.size,
-                                          Color::alpha(128, 128, 128, 128));
-
-                       self.display.rect(Point::new(x, y - 16),
-                                         Size::new(package.name.len() * 8, 16),
-                                         Color::alpha(0, 0, 0, 128));
-
-                        let mut c_x = x;
-                        for c in package.name.chars() {
-                            self.display
-                                .char(Point::new(c_x, y - 16), c, Color::new(255, 255, 255));
-                            c_x += 8;
-                        }
+        self.display.rect(Point::new(0, self.display.height as isize - 32),
+                          Size::new(self.display.width, 32),
+                          Color::alpha(0, 0, 0, 128));
+
+        let mut x = 0;
+        for package in self.packages.iter() {
+            if !package.icon.data.is_empty() {
+                let y = self.display.height as isize - package.icon.size.height as isize;
+                if mouse_point.y >= y && mouse_point.x >= x &&
+                   mouse_point.x < x + package.icon.size.width as isize {
+                    self.display.rect(Point::new(x, y),
+                                      package.icon.size,
+                                      Color::alpha(128, 128, 128, 128));
+
+                   self.display.rect(Point::new(x, y - 16),
+                                     Size::new(package.name.len() * 8, 16),
+                                     Color::alpha(0, 0, 0, 128));
+
+                    let mut c_x = x;
+                    for c in package.name.chars() {
+                        self.display
+                            .char(Point::new(c_x, y - 16), c, Color::new(255, 255, 255));
+                        c_x += 8;
                     }
-                    package.icon.draw(&self.display, Point::new(x, y));
-                    x += package.icon.size.width as isize;
                 }
+                package.icon.draw(&self.display, Point::new(x, y));
+                x += package.icon.size.width as isize;
             }
+        }
 
-            let mut chars = 32;
-            while chars > 4 &&
-                  (x as usize + (chars * 8 + 3 * 4) * self.windows.len()) >
-                  self.display.width + 32 {
-                chars -= 1;
-            }
+        let mut chars = 32;
+        while chars > 4 &&
+              (x as usize + (chars * 8 + 3 * 4) * self.windows.len()) >
+              self.display.width + 32 {
+            chars -= 1;
+        }
 
+        x += 4;
+        for window_ptr in self.windows_ordered.iter() {
+            let w = (chars*8 + 2*4) as usize;
+            self.display.rect(Point::new(x, self.display.height as isize - 32),
+                              Size::new(w, 32),
+                              (**window_ptr).border_color);
             x += 4;
-            for window_ptr in self.windows_ordered.iter() {
-                let w = (chars*8 + 2*4) as usize;
-                self.display.rect(Point::new(x, self.display.height as isize - 32),
-                                  Size::new(w, 32),
-                                  (**window_ptr).border_color);
-                x += 4;
 
-                let mut i = 0;
-                for c in (**window_ptr).title.chars() {
-                    if c != '\0' {
-                        self.display.char(Point::new(x, self.display.height as isize - 24),
-                                          c,
-                                          (**window_ptr).title_color);
-                    }
-                    x += 8;
-                    i += 1;
-                    if i >= chars {
-                        break;
-                    }
+            let mut i = 0;
+            for c in (**window_ptr).title.chars() {
+                if c != '\0' {
+                    self.display.char(Point::new(x, self.display.height as isize - 24),
+                                      c,
+                                      (**window_ptr).title_color);
                 }
-                while i < chars {
-                    x += 8;
-                    i += 1;
+                x += 8;
+                i += 1;
+                if i >= chars {
+                    break;
                 }
+            }
+            while i < chars {
                 x += 8;
+                i += 1;
             }
+            x += 8;
+        }
+    }
 
-            if !self.cursor.data.is_empty() {
-                self.display.image_alpha(self.mouse_point,
-                                         self.cursor.data.as_ptr(),
-                                         self.cursor.size);
-            } else {
-                self.display.char(Point::new(self.mouse_point.x - 3, self.mouse_point.y - 9),
-                                  'X',
-                                  Color::new(255, 255, 255));
-            }
+    /// Draw the damaged parts of the screen and move the cursor
+    pub unsafe fn redraw(&mut self) {
+        let reenable = scheduler::start_no_ints();
 
-            let reenable = scheduler::start_no_ints();
+        let mouse_point = self.mouse_point;
 
-            self.display.flip();
+        for i in 0..self.windows.len() {
+            let window_ptr = self.windows[i];
+            for rect in (*window_ptr).take_damage() {
+                self.damage(rect);
+            }
 
+            let focused = window_ptr == self.focus;
+            if (*window_ptr).focused != focused {
+                (*window_ptr).focused = focused;
+                self.damage((*window_ptr).bounds());
+                let taskbar = self.taskbar_rect();
+                self.damage(taskbar);
+            }
+        }
+
+        if self.redraw {
             self.redraw = false;
+            let bounds = self.display.bounds();
+            self.damage(bounds);
+        }
 
+        let cursor_rect = self.cursor_rect_at(mouse_point).intersection(&self.display.bounds());
+        let cursor_moved = cursor_rect != self.cursor_rect;
+        if cursor_moved {
+            // The package under the cursor is highlighted
+            let taskbar = self.taskbar_rect();
+            if taskbar.intersects(&cursor_rect) || taskbar.intersects(&self.cursor_rect) {
+                self.damage(taskbar);
+            }
+        }
+
+        if self.damage.is_empty() && !cursor_moved {
             scheduler::end_no_ints(reenable);
+            return;
+        }
+
+        let damage = mem::replace(&mut self.damage, Vec::new());
+
+        scheduler::end_no_ints(reenable);
+
+        // Take the cursor off, so that what is under it can be drawn and copied
+        self.display.image(self.cursor_rect.point(),
+                           self.cursor_under.as_ptr(),
+                           self.cursor_rect.size());
+
+        for rect in damage.iter() {
+            self.display.set_clip(*rect);
+            self.composite(mouse_point);
         }
+        self.display.reset_clip();
+
+        let old_cursor_rect = self.cursor_rect;
+        self.cursor_rect = cursor_rect;
+        self.cursor_under = self.display.get_rect(cursor_rect);
+
+        if !self.cursor.data.is_empty() {
+            self.display.image_alpha(mouse_point,
+                                     self.cursor.data.as_ptr(),
+                                     self.cursor.size);
+        } else {
+            self.display.char(Point::new(mouse_point.x - 3, mouse_point.y - 9),
+                              'X',
+                              Color::new(255, 255, 255));
+        }
+
+        let reenable = scheduler::start_no_ints();
+
+        for rect in damage.iter() {
+            self.display.flip_rect(*rect);
+            self.shown(*rect);
+        }
+        self.display.flip_rect(old_cursor_rect);
+        self.display.flip_rect(cursor_rect);
+        self.shown(old_cursor_rect);
+        self.shown(cursor_rect);
+
+        scheduler::end_no_ints(reenable);
     }
 
     pub fn event(&mut self, event: Event) {
         match event.to_option() {
             EventOption::Mouse(mouse_event) => self.on_mouse(mouse_event),
             EventOption::Key(key_event) => self.on_key(key_event),
+            EventOption::Scroll(scroll_event) => self.on_scroll(scroll_event),
             _ => (),
         }
     }
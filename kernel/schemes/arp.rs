@@ -1,14 +1,23 @@
 use ::GetSlice;
 
+use alloc::boxed::Box;
+
+use collections::string::{String, ToString};
 use collections::vec::Vec;
 
 use core::{mem, slice};
 
 use scheduler::context::context_switch;
+use scheduler;
 
 use network::common::*;
+use network::neighbor::NeighborTable;
+use network::scheme::{find_interface, Interface};
+
+use schemes::{KScheme, Resource, Url, VecResource};
 
-use schemes::{KScheme, Url};
+/// How long a learned mapping stays valid
+pub const ARP_ENTRY_TIMEOUT: i64 = 300;
 
 #[derive(Copy, Clone)]
 #[repr(packed)]
@@ -29,6 +38,31 @@ pub struct Arp {
     pub data: Vec<u8>,
 }
 
+impl Arp {
+    /// Create an ARP request for `dst_ip`, sent from the addresses of an interface
+    pub fn request(src_mac: MacAddr, src_ip: Ipv4Addr, dst_ip: Ipv4Addr) -> Self {
+        Arp {
+            header: ArpHeader {
+                htype: n16::new(1),
+                ptype: n16::new(0x800),
+                hlen: 6,
+                plen: 4,
+                oper: n16::new(1),
+                src_mac: src_mac,
+                src_ip: src_ip,
+                dst_mac: BROADCAST_MAC_ADDR,
+                dst_ip: dst_ip,
+            },
+            data: Vec::new(),
+        }
+    }
+
+    /// Create a gratuitous ARP announcing the addresses of an interface
+    pub fn gratuitous(mac: MacAddr, ip: Ipv4Addr) -> Self {
+        Arp::request(mac, ip, ip)
+    }
+}
+
 impl FromBytes for Arp {
     fn from_bytes(bytes: Vec<u8>) -> Option<Self> {
         if bytes.len() >= mem::size_of::<ArpHeader>() {
@@ -54,22 +88,67 @@ impl ToBytes for Arp {
     }
 }
 
-pub struct ArpScheme;
+/// A ARP table, entries expire after `ARP_ENTRY_TIMEOUT` seconds
+pub type ArpTable = NeighborTable<Ipv4Addr>;
+
+/// A ARP scheme, exposes the table read-only
+pub struct ArpScheme {
+    pub table: *mut ArpTable,
+}
 
 impl KScheme for ArpScheme {
     fn scheme(&self) -> &str {
         "arp"
     }
+
+    fn open(&mut self, _: &Url, _: usize) -> Option<Box<Resource>> {
+        let string;
+        unsafe {
+            let reenable = scheduler::start_no_ints();
+            string = (*self.table).to_string();
+            scheduler::end_no_ints(reenable);
+        }
+
+        Some(box VecResource::new(Url::from_str("arp://"), string.into_bytes()))
+    }
+}
+
+/// Announce the address of an interface with a gratuitous ARP, when it was set through `route:`
+pub fn announce(interface: &Interface) {
+    // An interface without an address has nothing to announce
+    if interface.loopback || interface.ip.equals(UNSPECIFIED_IP_ADDR) {
+        return;
+    }
+
+    if let Some(mut link) = Url::from_string(format!("ethernet://{}/{}/806", interface.name, BROADCAST_MAC_ADDR.to_string())).open() {
+        link.write(&Arp::gratuitous(interface.mac, interface.ip).to_bytes());
+    }
 }
 
 impl ArpScheme {
-    pub fn reply_loop() {
-        while let Some(mut link) = Url::from_str("ethernet:///806").open() {
+    /// Answer requests for the address of an interface, and learn the mappings of our peers
+    pub fn reply_loop(table: *mut ArpTable, interface: String) {
+        while let Some(mut link) = Url::from_string(format!("ethernet://{}/{}/806", interface, BROADCAST_MAC_ADDR.to_string())).open() {
             loop {
+                // The address may have been changed through route:
+                let (mac, ip) = match find_interface(&interface) {
+                    Some(config) => (config.mac, config.ip),
+                    None => return,
+                };
+
                 let mut bytes: Vec<u8> = Vec::new();
                 if let Some(_) = link.read_to_end(&mut bytes) {
                     if let Some(packet) = Arp::from_bytes(bytes) {
-                        if packet.header.oper.get() == 1 && packet.header.dst_ip.equals(IP_ADDR) {
+                        if packet.header.dst_ip.equals(ip) && !packet.header.src_ip.equals(ip) {
+                            unsafe {
+                                let reenable = scheduler::start_no_ints();
+                                (*table).insert(packet.header.src_ip, packet.header.src_mac);
+                                scheduler::end_no_ints(reenable);
+                            }
+                        }
+
+                        if packet.header.oper.get() == 1 && packet.header.dst_ip.equals(ip) &&
+                           !packet.header.src_ip.equals(ip) {
                             let mut response = Arp {
                                 header: packet.header,
                                 data: packet.data.clone(),
@@ -77,10 +156,12 @@ impl ArpScheme {
                             response.header.oper.set(2);
                             response.header.dst_mac = packet.header.src_mac;
                             response.header.dst_ip = packet.header.src_ip;
-                            response.header.src_mac = unsafe { MAC_ADDR };
-                            response.header.src_ip = IP_ADDR;
+                            response.header.src_mac = mac;
+                            response.header.src_ip = ip;
 
-                            link.write(&response.to_bytes());
+                            if let Some(mut peer) = Url::from_string(format!("ethernet://{}/{}/806", interface, packet.header.src_mac.to_string())).open() {
+                                peer.write(&response.to_bytes());
+                            }
                         }
                     }
                 }else{
@@ -0,0 +1,191 @@
+use alloc::boxed::Box;
+
+use collections::string::{String, ToString};
+use collections::vec::Vec;
+
+use core::cmp;
+
+use scheduler;
+
+use schemes::{KScheme, Resource, ResourceSeek, Url, VecResource};
+
+use syscall::common::{O_CREAT, O_TRUNC};
+
+/// The selections on the clipboard, at most one for each MIME type
+pub struct Clipboard {
+    pub selections: Vec<(String, Vec<u8>)>,
+}
+
+impl Clipboard {
+    pub fn new() -> Self {
+        Clipboard { selections: Vec::new() }
+    }
+
+    /// The selection of a type
+    pub fn get(&self, mime: &str) -> Option<Vec<u8>> {
+        for &(ref selection_mime, ref data) in self.selections.iter() {
+            if *selection_mime == mime {
+                return Some(data.clone());
+            }
+        }
+        None
+    }
+
+    /// Replace the selection of a type
+    pub fn set(&mut self, mime: &str, data: Vec<u8>) {
+        self.selections.retain(|&(ref selection_mime, _)| *selection_mime != mime);
+        self.selections.push((mime.to_string(), data));
+    }
+
+    pub fn clear(&mut self) {
+        self.selections.clear();
+    }
+
+    pub fn to_string(&self) -> String {
+        let mut string = String::new();
+        for &(ref mime, ref data) in self.selections.iter() {
+            string = string + &format!("{} {}\n", mime, data.len());
+        }
+        string
+    }
+}
+
+/// A selection of the clipboard
+///
+/// Writes are kept by the resource, and replace the selection when it is synced or closed.
+pub struct ClipboardResource {
+    clipboard: *mut Clipboard,
+    mime: String,
+    data: Vec<u8>,
+    seek: usize,
+    /// Written to since the selection was last replaced
+    changed: bool,
+}
+
+impl Resource for ClipboardResource {
+    fn dup(&self) -> Option<Box<Resource>> {
+        Some(box ClipboardResource {
+            clipboard: self.clipboard,
+            mime: self.mime.clone(),
+            data: self.data.clone(),
+            seek: self.seek,
+            changed: self.changed,
+        })
+    }
+
+    fn url(&self) -> Url {
+        Url::from_string("clipboard:".to_string() + &self.mime)
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> Option<usize> {
+        let count = cmp::min(buf.len(), self.data.len() - self.seek);
+        for i in 0..count {
+            buf[i] = self.data[self.seek + i];
+        }
+        self.seek += count;
+        Some(count)
+    }
+
+    fn write(&mut self, buf: &[u8]) -> Option<usize> {
+        for &b in buf.iter() {
+            if self.seek < self.data.len() {
+                self.data[self.seek] = b;
+            } else {
+                self.data.push(b);
+            }
+            self.seek += 1;
+        }
+        self.changed = true;
+        Some(buf.len())
+    }
+
+    fn seek(&mut self, pos: ResourceSeek) -> Option<usize> {
+        let len = self.data.len() as isize;
+        self.seek = match pos {
+            ResourceSeek::Start(offset) => cmp::min(self.data.len(), offset),
+            ResourceSeek::Current(offset) => {
+                cmp::max(0, cmp::min(len, self.seek as isize + offset)) as usize
+            }
+            ResourceSeek::End(offset) => cmp::max(0, cmp::min(len, len + offset)) as usize,
+        };
+        Some(self.seek)
+    }
+
+    fn sync(&mut self) -> bool {
+        if self.changed {
+            unsafe {
+                let reenable = scheduler::start_no_ints();
+                (*self.clipboard).set(&self.mime, self.data.clone());
+                scheduler::end_no_ints(reenable);
+            }
+            self.changed = false;
+        }
+        true
+    }
+
+    fn truncate(&mut self, len: usize) -> bool {
+        self.data.resize(len, 0);
+        self.seek = cmp::min(self.seek, len);
+        self.changed = true;
+        true
+    }
+}
+
+impl Drop for ClipboardResource {
+    fn drop(&mut self) {
+        self.sync();
+    }
+}
+
+/// A clipboard scheme, shared between applications
+///
+/// `clipboard:TYPE` is the selection of a MIME type, such as `clipboard:text/plain`. Opening a
+/// type that is not on the clipboard fails unless it is created. `clipboard:` lists each type
+/// with its size, and creating it clears the clipboard.
+pub struct ClipboardScheme {
+    pub clipboard: *mut Clipboard,
+}
+
+impl KScheme for ClipboardScheme {
+    fn scheme(&self) -> &str {
+        "clipboard"
+    }
+
+    fn open(&mut self, url: &Url, flags: usize) -> Option<Box<Resource>> {
+        let mime = url.reference().trim_left_matches('/').to_string();
+
+        let data;
+        unsafe {
+            let reenable = scheduler::start_no_ints();
+            if mime.is_empty() {
+                if flags & O_TRUNC == O_TRUNC {
+                    (*self.clipboard).clear();
+                }
+                data = Some((*self.clipboard).to_string().into_bytes());
+            } else {
+                data = (*self.clipboard).get(&mime);
+            }
+            scheduler::end_no_ints(reenable);
+        }
+
+        if mime.is_empty() {
+            return Some(box VecResource::new(Url::from_str("clipboard:"),
+                                             data.unwrap_or(Vec::new())));
+        }
+
+        let data = match data {
+            Some(_) if flags & O_TRUNC == O_TRUNC => Vec::new(),
+            Some(data) => data,
+            None if flags & O_CREAT == O_CREAT => Vec::new(),
+            None => return None,
+        };
+
+        Some(box ClipboardResource {
+            clipboard: self.clipboard,
+            mime: mime,
+            data: data,
+            seek: 0,
+            changed: flags & O_TRUNC == O_TRUNC,
+        })
+    }
+}
@@ -1,5 +1,7 @@
 use alloc::boxed::Box;
 
+use core::cmp;
+
 use scheduler::context::context_switch;
 use scheduler;
 
@@ -35,11 +37,22 @@ impl Resource for DebugResource {
 
             let reenable = scheduler::start_no_ints();
 
-            //TODO: Unicode
-            let mut i = 0;
-            while i < buf.len() && !(*::debug_command).as_mut_vec().is_empty() {
-                buf[i] = (*::debug_command).as_mut_vec().remove(0);
-                i += 1;
+            let command = (*::debug_command).as_mut_vec();
+
+            // Do not split a UTF-8 character between reads, unless it does not fit at all
+            let mut i = cmp::min(buf.len(), command.len());
+            while i > 0 && i < command.len() && command[i] & 0xC0 == 0x80 {
+                i -= 1;
+            }
+            if i == 0 {
+                i = cmp::min(buf.len(), command.len());
+            }
+
+            for j in 0..i {
+                buf[j] = command[j];
+            }
+            for _ in 0..i {
+                command.remove(0);
             }
 
             scheduler::end_no_ints(reenable);
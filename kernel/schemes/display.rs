@@ -1,46 +1,112 @@
+use ::GetSlice;
+
 use alloc::boxed::Box;
 
-use collections::string::ToString;
+use collections::string::{String, ToString};
+use collections::vec::Vec;
+
+use core::{cmp, str};
+
+use common::bytes::push_u32;
+use common::time::{self, Duration};
+
+use graphics::bmp::BmpFile;
+use graphics::display::{Display, DisplayMode, Framebuffer};
+use graphics::rect::Rect;
+use graphics::size::Size;
+
+use scheduler::context::context_switch;
+use scheduler;
 
-use core::cmp;
+use schemes::{KScheme, Resource, ResourceSeek, Url, VecResource};
 
-use graphics::display::Display;
+use syscall::common::{O_NONBLOCK, WOULD_BLOCK};
 
-use schemes::{KScheme, Resource, ResourceSeek, Url};
+/// The shortest time between two updates of `display:stream`
+const STREAM_INTERVAL: i32 = time::NANOS_PER_SEC / 30;
 
+/// A display scheme
+///
+/// `display:` is the screen as raw pixels. Writes are drawn offscreen and shown on sync, and
+/// reads return what is on the screen. `display:screenshot.bmp` is a BMP of the screen.
+/// `display:stream` reads the parts of the screen that change, for recording it or viewing
+/// it remotely, see `DisplayStreamResource`. `display:modes` lists the modes it can be switched
+/// to, one `WIDTHxHEIGHT` per line. `display:mode` reads the current mode, and writing a mode
+/// to it switches to that mode.
 pub struct DisplayScheme;
 
 // Should there only be one display per session?
 /// A display resource
 pub struct DisplayResource {
-    /// The display
-    pub display: Box<Display>,
+    /// The display, created on the first write so that reading does not clear the screen, and
+    /// again after a mode switch
+    pub display: Option<Box<Display>>,
+    /// The screen that is read
+    pub framebuffer: Framebuffer,
     /// Seek
     pub seek: usize,
 }
 
+impl DisplayResource {
+    /// Follow a switch of the display mode, the display is created again by the next write
+    fn refresh(&mut self) {
+        let framebuffer = Framebuffer::current();
+        if framebuffer.address != self.framebuffer.address || framebuffer.width != self.framebuffer.width ||
+           framebuffer.height != self.framebuffer.height ||
+           framebuffer.bytesperrow != self.framebuffer.bytesperrow {
+            self.framebuffer = framebuffer;
+            self.display = None;
+            self.seek = cmp::min(self.seek, framebuffer.bytesperrow * framebuffer.height);
+        }
+    }
+}
+
 impl Resource for DisplayResource {
     /// Return the URL for display resource
     fn url(&self) -> Url {
         Url::from_string("display://".to_string())
     }
 
+    fn read(&mut self, buf: &mut [u8]) -> Option<usize> {
+        self.refresh();
 
-    fn write(&mut self, buf: &[u8]) -> Option<usize> {
-        let display = &mut self.display;
+        let end = self.framebuffer.bytesperrow * self.framebuffer.height;
 
-        let size = cmp::min(display.size - self.seek, buf.len());
+        let size = cmp::min(end.saturating_sub(self.seek), buf.len());
         unsafe {
-            Display::copy_run(buf.as_ptr() as usize,
-                              display.offscreen + self.seek,
+            Display::copy_run(self.framebuffer.address + self.seek,
+                              buf.as_mut_ptr() as usize,
                               size);
         }
         self.seek += size;
         return Some(size);
     }
 
+    fn write(&mut self, buf: &[u8]) -> Option<usize> {
+        self.refresh();
+
+        if self.display.is_none() {
+            self.display = Some(unsafe { Display::root() });
+        }
+
+        if let Some(ref mut display) = self.display {
+            let size = cmp::min(display.size.saturating_sub(self.seek), buf.len());
+            unsafe {
+                Display::copy_run(buf.as_ptr() as usize,
+                                  display.offscreen + self.seek,
+                                  size);
+            }
+            self.seek += size;
+            return Some(size);
+        }
+
+        None
+    }
+
     fn seek(&mut self, pos: ResourceSeek) -> Option<usize> {
-        let end = self.display.size;
+        self.refresh();
+
+        let end = self.framebuffer.bytesperrow * self.framebuffer.height;
 
         self.seek = match pos {
             ResourceSeek::Start(offset) => cmp::min(end, cmp::max(0, offset)),
@@ -52,7 +118,165 @@ impl Resource for DisplayResource {
     }
 
     fn sync(&mut self) -> bool {
-        self.display.flip();
+        self.refresh();
+
+        if let Some(ref display) = self.display {
+            display.flip();
+
+            let reenable = scheduler::start_no_ints();
+            unsafe { (*::session_ptr).shown(display.bounds()) };
+            scheduler::end_no_ints(reenable);
+        }
+        true
+    }
+}
+
+/// The changes to the screen, read as a stream of updates
+///
+/// Each update starts with the width and height of the screen and the number of rectangles
+/// that follow. Each rectangle is its x, y, width and height, then its pixels row after row.
+/// Every number is a little endian u32, and pixels are 0xRRGGBB. The first update, and the
+/// first after a mode switch, covers the whole screen. The rest cover the parts the session
+/// showed since the last update. Reads block until the screen changes, unless the resource
+/// is opened with O_NONBLOCK, then they return `WOULD_BLOCK`.
+pub struct DisplayStreamResource {
+    /// The id of the stream in the session
+    id: usize,
+    /// The size of the last screen
+    size: Size,
+    /// The time of the last update
+    time: Duration,
+    /// Bytes of updates that were not read
+    data: Vec<u8>,
+    flags: usize,
+}
+
+impl DisplayStreamResource {
+    /// Add an update, if the screen changed and enough time has passed since the last one
+    fn record_frame(&mut self) -> bool {
+        let time = Duration::monotonic();
+        if time - self.time < Duration::new(0, STREAM_INTERVAL) {
+            return false;
+        }
+
+        let reenable = scheduler::start_no_ints();
+        let mut rects = unsafe { (*::session_ptr).take_stream(self.id) };
+        scheduler::end_no_ints(reenable);
+
+        let framebuffer = Framebuffer::current();
+        if framebuffer.width != self.size.width || framebuffer.height != self.size.height {
+            self.size = Size {
+                width: framebuffer.width,
+                height: framebuffer.height,
+            };
+            rects = vec![Rect::new(0, 0, framebuffer.width, framebuffer.height)];
+        }
+
+        if rects.is_empty() {
+            return false;
+        }
+        self.time = time;
+
+        push_u32(&mut self.data, self.size.width as u32);
+        push_u32(&mut self.data, self.size.height as u32);
+        push_u32(&mut self.data, rects.len() as u32);
+        for rect in rects.iter() {
+            push_u32(&mut self.data, rect.x as u32);
+            push_u32(&mut self.data, rect.y as u32);
+            push_u32(&mut self.data, rect.width as u32);
+            push_u32(&mut self.data, rect.height as u32);
+            for pixel in framebuffer.capture_rect(*rect).iter() {
+                push_u32(&mut self.data, *pixel & 0xFFFFFF);
+            }
+        }
+
+        true
+    }
+}
+
+impl Drop for DisplayStreamResource {
+    fn drop(&mut self) {
+        let reenable = scheduler::start_no_ints();
+        unsafe { (*::session_ptr).remove_stream(self.id) };
+        scheduler::end_no_ints(reenable);
+    }
+}
+
+impl Resource for DisplayStreamResource {
+    fn url(&self) -> Url {
+        Url::from_str("display:stream")
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> Option<usize> {
+        loop {
+            if !self.data.is_empty() {
+                let count = cmp::min(buf.len(), self.data.len());
+                for i in 0..count {
+                    buf[i] = self.data[i];
+                }
+                self.data = self.data.get_slice(Some(count), None).to_vec();
+                return Some(count);
+            }
+
+            if !self.record_frame() {
+                if self.flags & O_NONBLOCK == O_NONBLOCK {
+                    return Some(WOULD_BLOCK);
+                }
+
+                unsafe { context_switch(false) };
+            }
+        }
+    }
+
+    fn sync(&mut self) -> bool {
+        true
+    }
+}
+
+/// The mode of the display
+pub struct DisplayModeResource {
+    data: Vec<u8>,
+    seek: usize,
+}
+
+impl Resource for DisplayModeResource {
+    fn url(&self) -> Url {
+        Url::from_str("display:mode")
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> Option<usize> {
+        let count = cmp::min(buf.len(), self.data.len() - self.seek);
+        for i in 0..count {
+            buf[i] = self.data[self.seek + i];
+        }
+        self.seek += count;
+        Some(count)
+    }
+
+    fn write(&mut self, buf: &[u8]) -> Option<usize> {
+        let mode = match str::from_utf8(buf).ok().and_then(|string| DisplayMode::from_str(string)) {
+            Some(mode) => mode,
+            None => return None,
+        };
+
+        if unsafe { (*::session_ptr).set_display_mode(mode) } {
+            self.data = (mode.to_string() + "\n").into_bytes();
+            self.seek = 0;
+            Some(buf.len())
+        } else {
+            None
+        }
+    }
+
+    fn seek(&mut self, pos: ResourceSeek) -> Option<usize> {
+        match pos {
+            ResourceSeek::Start(offset) => self.seek = cmp::min(self.data.len(), offset),
+            _ => return None,
+        }
+        Some(self.seek)
+    }
+
+    fn sync(&mut self) -> bool {
         true
     }
 }
@@ -62,17 +286,51 @@ impl KScheme for DisplayScheme {
         "display"
     }
 
-    fn open(&mut self, _: &Url, _: usize) -> Option<Box<Resource>> {
-        // TODO: ponder these things:
-        // - should display:// be the only only valid url
-        //      for this scheme?
-        // - maybe "read" should support displays at some other location
-        //      like built in screen sharing capability or something
-        unsafe {
-            return Some(box DisplayResource {
-                        display: Display::root(),
-                       seek: 0,
-            });
+    fn open(&mut self, url: &Url, flags: usize) -> Option<Box<Resource>> {
+        let path = url.reference().trim_left_matches('/');
+        if path == "modes" {
+            let modes = unsafe { (*::session_ptr).display_modes() };
+            let mut string = String::new();
+            for mode in modes.iter() {
+                string = string + &mode.to_string() + "\n";
+            }
+            Some(box VecResource::new(Url::from_str("display:modes"), string.into_bytes()))
+        } else if path == "mode" {
+            Some(box DisplayModeResource {
+                data: (Framebuffer::current().mode().to_string() + "\n").into_bytes(),
+                seek: 0,
+            })
+        } else if path == "screenshot.bmp" {
+            let framebuffer = Framebuffer::current();
+            let bmp = BmpFile {
+                data: framebuffer.capture().iter().map(|pixel| *pixel | 0xFF000000).collect(),
+                size: Size {
+                    width: framebuffer.width,
+                    height: framebuffer.height,
+                },
+            };
+            Some(box VecResource::new(Url::from_str("display:screenshot.bmp"), bmp.to_data()))
+        } else if path == "stream" {
+            let reenable = scheduler::start_no_ints();
+            let id = unsafe { (*::session_ptr).add_stream() };
+            scheduler::end_no_ints(reenable);
+
+            Some(box DisplayStreamResource {
+                id: id,
+                size: Size {
+                    width: 0,
+                    height: 0,
+                },
+                time: Duration::new(0, 0),
+                data: Vec::new(),
+                flags: flags,
+            })
+        } else {
+            Some(box DisplayResource {
+                display: None,
+                framebuffer: Framebuffer::current(),
+                seek: 0,
+            })
         }
     }
 }
@@ -1,23 +1,29 @@
 use alloc::boxed::Box;
 
-use collections::string::ToString;
+use collections::string::{String, ToString};
 use collections::vec::Vec;
 
 use core::mem;
 
 use common::debug;
 use common::to_num::ToNum;
-use common::parse_ip::*;
+use common::parse_path::parse_path;
 
 use network::common::*;
 use network::ethernet::*;
+use network::scheme::find_interface;
 
-use schemes::{KScheme, Resource, Url};
+use schemes::{read_packet, KScheme, Resource, Url};
+use syscall::common::WOULD_BLOCK;
 
 /// A ethernet resource
 pub struct EthernetResource {
     /// The network
     network: Box<Resource>,
+    /// The name of the interface
+    interface: String,
+    /// The MAC address of the interface
+    mac: MacAddr,
     /// The data
     data: Vec<u8>,
     /// The MAC addresss
@@ -26,11 +32,27 @@ pub struct EthernetResource {
     ethertype: u16,
 }
 
+impl EthernetResource {
+    /// Create a resource exchanging frames with `peer_addr` over the open network resource of an interface
+    pub fn new(network: Box<Resource>, interface: String, mac: MacAddr, peer_addr: MacAddr, ethertype: u16) -> Box<Self> {
+        box EthernetResource {
+            network: network,
+            interface: interface,
+            mac: mac,
+            data: Vec::new(),
+            peer_addr: peer_addr,
+            ethertype: ethertype,
+        }
+    }
+}
+
 impl Resource for EthernetResource {
     fn dup(&self) -> Option<Box<Resource>> {
         match self.network.dup() {
             Some(network) => Some(box EthernetResource {
                 network: network,
+                interface: self.interface.clone(),
+                mac: self.mac,
                 data: self.data.clone(),
                 peer_addr: self.peer_addr,
                 ethertype: self.ethertype,
@@ -40,12 +62,12 @@ impl Resource for EthernetResource {
     }
 
     fn url(&self) -> Url {
-        Url::from_string(format!("ethernet://{}/{:X}", self.peer_addr.to_string(), self.ethertype))
+        Url::from_string(format!("ethernet://{}/{}/{:X}", self.interface, self.peer_addr.to_string(), self.ethertype))
     }
 
-    fn read(&mut self, _: &mut [u8]) -> Option<usize> {
-        debug::d("TODO: Implement read for ethernet://\n");
-        None
+    fn read(&mut self, buf: &mut [u8]) -> Option<usize> {
+        // One frame per call, truncated to the buffer
+        read_packet(self, buf)
     }
 
     fn read_to_end(&mut self, vec: &mut Vec<u8>) -> Option<usize> {
@@ -59,11 +81,11 @@ impl Resource for EthernetResource {
         loop {
             let mut bytes: Vec<u8> = Vec::new();
             match self.network.read_to_end(&mut bytes) {
+                Some(WOULD_BLOCK) => return Some(WOULD_BLOCK),
                 Some(_) => {
                     if let Some(frame) = EthernetII::from_bytes(bytes) {
                         if frame.header.ethertype.get() == self.ethertype &&
-                           (unsafe { frame.header.dst.equals(MAC_ADDR) } ||
-                            frame.header.dst.equals(BROADCAST_MAC_ADDR)) &&
+                           (frame.header.dst.equals(self.mac) || frame.header.dst.is_multicast()) &&
                            (frame.header.src.equals(self.peer_addr) ||
                             self.peer_addr.equals(BROADCAST_MAC_ADDR)) {
                             vec.push_all(&frame.data);
@@ -81,7 +103,7 @@ impl Resource for EthernetResource {
 
         match self.network.write(& EthernetII {
             header: EthernetIIHeader {
-                src: unsafe { MAC_ADDR },
+                src: self.mac,
                 dst: self.peer_addr,
                 ethertype: n16::new(self.ethertype),
             },
@@ -97,6 +119,8 @@ impl Resource for EthernetResource {
     }
 }
 
+/// The `ethernet:` scheme, `ethernet://[IFACE/]MAC/TYPE` exchanges frames with a peer and
+/// `ethernet://[IFACE/]TYPE` waits for the first frame of a type, on the first card by default
 pub struct EthernetScheme;
 
 impl KScheme for EthernetScheme {
@@ -104,38 +128,53 @@ impl KScheme for EthernetScheme {
         "ethernet"
     }
 
-    fn open(&mut self, url: &Url, _: usize) -> Option<Box<Resource>> {
-        if let Some(mut network) = Url::from_str("network://").open() {
-            if !url.reference().is_empty() {
-                let ethertype = url.reference().to_num_radix(16) as u16;
-
-                if !parse_host(url.reference()).is_empty() {
-                    return Some(box EthernetResource {
-                        network: network,
-                        data: Vec::new(),
-                        peer_addr: MacAddr::from_str(parse_host(url.reference())),
-                        ethertype: ethertype,
-                    });
-                } else {
-                    loop {
-                        let mut bytes: Vec<u8> = Vec::new();
-                        match network.read_to_end(&mut bytes) {
-                            Some(_) => {
-                                if let Some(frame) = EthernetII::from_bytes(bytes) {
-                                    if frame.header.ethertype.get() == ethertype &&
-                                       (unsafe { frame.header.dst.equals(MAC_ADDR) } ||
-                                        frame.header.dst.equals(BROADCAST_MAC_ADDR)) {
-                                        return Some(box EthernetResource {
-                                            network: network,
-                                            data: frame.data,
-                                            peer_addr: frame.header.src,
-                                            ethertype: ethertype,
-                                        });
-                                    }
+    fn open(&mut self, url: &Url, flags: usize) -> Option<Box<Resource>> {
+        let mut parts = parse_path(url.reference());
+
+        // MAC addresses are written with dots, interface names never are
+        let name = if parts.len() >= 2 && !parts[0].contains('.') {
+            parts.remove(0)
+        } else {
+            String::new()
+        };
+
+        let interface = match find_interface(&name) {
+            Some(interface) => interface,
+            None => {
+                debug::d("Ethernet: No such interface\n");
+                return None;
+            }
+        };
+
+        if let Some(mut network) = Url::from_string("network://".to_string() + &interface.name).open_flags(flags) {
+            if parts.len() >= 2 {
+                let peer_addr = MacAddr::from_str(&parts[0]);
+                let ethertype = parts[1].to_num_radix(16) as u16;
+
+                return Some(EthernetResource::new(network, interface.name, interface.mac, peer_addr, ethertype));
+            } else if parts.len() == 1 {
+                let ethertype = parts[0].to_num_radix(16) as u16;
+
+                loop {
+                    let mut bytes: Vec<u8> = Vec::new();
+                    match network.read_to_end(&mut bytes) {
+                        Some(WOULD_BLOCK) => break,
+                        Some(_) => {
+                            if let Some(frame) = EthernetII::from_bytes(bytes) {
+                                if frame.header.ethertype.get() == ethertype &&
+                                   (frame.header.dst.equals(interface.mac) || frame.header.dst.is_multicast()) {
+                                    return Some(box EthernetResource {
+                                        network: network,
+                                        interface: interface.name,
+                                        mac: interface.mac,
+                                        data: frame.data,
+                                        peer_addr: frame.header.src,
+                                        ethertype: ethertype,
+                                    });
                                 }
                             }
-                            None => break,
                         }
+                        None => break,
                     }
                 }
             } else {
@@ -1,14 +1,32 @@
 use ::GetSlice;
 
+use alloc::boxed::Box;
+
+use collections::string::{String, ToString};
 use collections::vec::Vec;
 
 use core::{mem, slice};
 
 use scheduler::context::context_switch;
 
+use common::parse_path::parse_path;
+use common::random;
+use common::time::Duration;
+
 use network::common::*;
 
-use schemes::{KScheme, Url};
+use schemes::{KScheme, Resource, Url};
+
+use syscall::common::{O_NONBLOCK, O_RDWR, WOULD_BLOCK};
+
+pub const ICMP_ECHO_REPLY: u8 = 0x00;
+pub const ICMP_DEST_UNREACHABLE: u8 = 0x03;
+pub const ICMP_ECHO_REQUEST: u8 = 0x08;
+
+/// Seconds to wait for an echo reply
+const ICMP_ECHO_TIMEOUT: i64 = 1;
+/// Payload size of echo requests sent by a read without a prior write
+const ICMP_ECHO_SIZE: usize = 56;
 
 #[derive(Copy, Clone)]
 #[repr(packed)]
@@ -49,12 +67,196 @@ impl ToBytes for Icmp {
     }
 }
 
+impl Icmp {
+    /// Create an echo request
+    pub fn echo_request(id: u16, sequence: u16, data: Vec<u8>) -> Self {
+        let mut message = Icmp {
+            header: IcmpHeader {
+                _type: ICMP_ECHO_REQUEST,
+                code: 0,
+                checksum: Checksum { data: 0 },
+                data: [(id >> 8) as u8, id as u8, (sequence >> 8) as u8, sequence as u8],
+            },
+            data: data,
+        };
+        message.calculate_checksum();
+        message
+    }
+
+    /// The identifier of an echo message
+    pub fn id(&self) -> u16 {
+        (self.header.data[0] as u16) << 8 | self.header.data[1] as u16
+    }
+
+    /// The sequence number of an echo message
+    pub fn sequence(&self) -> u16 {
+        (self.header.data[2] as u16) << 8 | self.header.data[3] as u16
+    }
+
+    pub fn calculate_checksum(&mut self) {
+        unsafe {
+            self.header.checksum.data = 0;
+
+            let header_ptr: *const IcmpHeader = &self.header;
+            self.header.checksum.data = Checksum::compile(
+                Checksum::sum(header_ptr as usize, mem::size_of::<IcmpHeader>()) +
+                Checksum::sum(self.data.as_ptr() as usize, self.data.len())
+            );
+        }
+    }
+}
+
+/// An ICMP echo (ping) resource
+///
+/// Each write sends an echo request carrying the written bytes. Each read waits for the
+/// oldest outstanding request and returns a line of text describing the reply or timeout.
+pub struct IcmpResource {
+    ip: Box<Resource>,
+    peer: String,
+    id: u16,
+    sequence: u16,
+    /// Outstanding requests, by sequence number and send time
+    pending: Vec<(u16, Duration)>,
+}
+
+impl IcmpResource {
+    /// Wait for the next reply or timeout and describe it
+    fn next_line(&mut self) -> Option<String> {
+        if self.pending.is_empty() {
+            let mut data = Vec::new();
+            for i in 0..ICMP_ECHO_SIZE {
+                data.push(i as u8);
+            }
+            if self.write(&data).is_none() {
+                return None;
+            }
+        }
+
+        loop {
+            let mut bytes: Vec<u8> = Vec::new();
+            match self.ip.read_to_end(&mut bytes) {
+                Some(WOULD_BLOCK) => (),
+                Some(_) => if let Some(message) = Icmp::from_bytes(bytes) {
+                    if message.header._type == ICMP_ECHO_REPLY && message.id() == self.id {
+                        let sequence = message.sequence();
+                        if let Some(i) = self.pending.iter().position(|&(seq, _)| seq == sequence) {
+                            let (_, sent) = self.pending.remove(i);
+                            let rtt = Duration::monotonic() - sent;
+
+                            return Some(format!("{} bytes from {}: icmp_seq={} time={}.{:03} ms\n",
+                                               message.data.len() + mem::size_of::<IcmpHeader>(),
+                                               self.peer,
+                                               sequence,
+                                               rtt.secs * 1000 + (rtt.nanos / 1000000) as i64,
+                                               (rtt.nanos / 1000) % 1000));
+                        }
+                    } else if message.header._type == ICMP_DEST_UNREACHABLE {
+                        return Some(format!("Destination unreachable: {}\n", self.peer));
+                    }
+                },
+                None => return None,
+            }
+
+            if let Some(&(sequence, sent)) = self.pending.get(0) {
+                if Duration::monotonic() - sent > Duration::new(ICMP_ECHO_TIMEOUT, 0) {
+                    self.pending.remove(0);
+
+                    return Some(format!("Request timeout for icmp_seq={}\n", sequence));
+                }
+            }
+
+            unsafe { context_switch(false) };
+        }
+    }
+}
+
+impl Resource for IcmpResource {
+    fn dup(&self) -> Option<Box<Resource>> {
+        match self.ip.dup() {
+            Some(ip) => Some(box IcmpResource {
+                ip: ip,
+                peer: self.peer.clone(),
+                id: self.id,
+                sequence: self.sequence,
+                pending: self.pending.clone(),
+            }),
+            None => None,
+        }
+    }
+
+    fn url(&self) -> Url {
+        Url::from_string("icmp://".to_string() + &self.peer)
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> Option<usize> {
+        match self.next_line() {
+            Some(line) => {
+                let mut i = 0;
+                for b in line.bytes() {
+                    if i >= buf.len() {
+                        break;
+                    }
+                    buf[i] = b;
+                    i += 1;
+                }
+                Some(i)
+            }
+            None => None,
+        }
+    }
+
+    fn read_to_end(&mut self, vec: &mut Vec<u8>) -> Option<usize> {
+        match self.next_line() {
+            Some(line) => {
+                vec.push_all(line.as_bytes());
+                Some(line.len())
+            }
+            None => None,
+        }
+    }
+
+    fn write(&mut self, buf: &[u8]) -> Option<usize> {
+        self.sequence = self.sequence.wrapping_add(1);
+
+        let request = Icmp::echo_request(self.id, self.sequence, Vec::from(buf));
+        match self.ip.write(&request.to_bytes()) {
+            Some(_) => {
+                self.pending.push((self.sequence, Duration::monotonic()));
+                Some(buf.len())
+            }
+            None => None,
+        }
+    }
+
+    fn sync(&mut self) -> bool {
+        self.ip.sync()
+    }
+}
+
 pub struct IcmpScheme;
 
 impl KScheme for IcmpScheme {
     fn scheme(&self) -> &str {
         "icmp"
     }
+
+    fn open(&mut self, url: &Url, _: usize) -> Option<Box<Resource>> {
+        let parts = parse_path(url.reference());
+
+        if let Some(peer) = parts.get(0) {
+            if let Some(ip) = Url::from_string("ip://".to_string() + peer + "/1").open_flags(O_RDWR | O_NONBLOCK) {
+                return Some(box IcmpResource {
+                    ip: ip,
+                    peer: peer.clone(),
+                    id: (random::rand() % 65536) as u16,
+                    sequence: 0,
+                    pending: Vec::new(),
+                });
+            }
+        }
+
+        None
+    }
 }
 
 impl IcmpScheme {
@@ -64,25 +266,19 @@ impl IcmpScheme {
                 let mut bytes: Vec<u8> = Vec::new();
                 if let Some(_) = ip.read_to_end(&mut bytes) {
                     if let Some(message) = Icmp::from_bytes(bytes) {
-                        if message.header._type == 0x08 {
+                        if message.header._type == ICMP_ECHO_REQUEST {
                             let mut response = Icmp {
                                 header: message.header,
                                 data: message.data,
                             };
 
-                            response.header._type = 0x00;
+                            response.header._type = ICMP_ECHO_REPLY;
+                            response.calculate_checksum();
 
-                            unsafe {
-                                response.header.checksum.data = 0;
-
-                                let header_ptr: *const IcmpHeader = &response.header;
-                                response.header.checksum.data = Checksum::compile(
-                                    Checksum::sum(header_ptr as usize, mem::size_of::<IcmpHeader>()) +
-                                    Checksum::sum(response.data.as_ptr() as usize, response.data.len())
-                                );
+                            // The listener is read only, answer through the sender of the request
+                            if let Some(mut peer) = ip.url().open() {
+                                peer.write(&response.to_bytes());
                             }
-
-                            ip.write(&response.to_bytes());
                         }
                     }
                 } else {
@@ -0,0 +1,192 @@
+use ::GetSlice;
+
+use alloc::boxed::Box;
+
+use collections::vec::Vec;
+
+use core::{mem, slice};
+
+use scheduler::context::context_switch;
+use scheduler;
+
+use network::common::*;
+use network::ethernet::*;
+use network::ipv6::*;
+use network::scheme::{ipv6_addr, ipv6_mac};
+
+use schemes::ndp::{self, NeighborCache};
+use schemes::{KScheme, Resource, Url};
+
+pub const ICMPV6_ECHO_REQUEST: u8 = 128;
+pub const ICMPV6_ECHO_REPLY: u8 = 129;
+pub const ICMPV6_NEIGHBOR_SOLICITATION: u8 = 135;
+pub const ICMPV6_NEIGHBOR_ADVERTISEMENT: u8 = 136;
+
+#[derive(Copy, Clone)]
+#[repr(packed)]
+pub struct Icmpv6Header {
+    pub _type: u8,
+    pub code: u8,
+    pub checksum: Checksum,
+}
+
+pub struct Icmpv6 {
+    pub header: Icmpv6Header,
+    pub data: Vec<u8>,
+}
+
+impl Icmpv6 {
+    pub fn new(_type: u8, code: u8, data: Vec<u8>) -> Self {
+        Icmpv6 {
+            header: Icmpv6Header {
+                _type: _type,
+                code: code,
+                checksum: Checksum { data: 0 },
+            },
+            data: data,
+        }
+    }
+
+    /// Calculate the checksum, which covers the IPv6 pseudo header
+    pub fn calculate_checksum(&mut self, src: &Ipv6Addr, dst: &Ipv6Addr) {
+        unsafe {
+            self.header.checksum.data = 0;
+
+            let header_ptr: *const Icmpv6Header = &self.header;
+            self.header.checksum.data = Checksum::compile(
+                Ipv6::pseudo_sum(src, dst, 0x3A, mem::size_of::<Icmpv6Header>() + self.data.len()) +
+                Checksum::sum(header_ptr as usize, mem::size_of::<Icmpv6Header>()) +
+                Checksum::sum(self.data.as_ptr() as usize, self.data.len())
+            );
+        }
+    }
+}
+
+impl FromBytes for Icmpv6 {
+    fn from_bytes(bytes: Vec<u8>) -> Option<Self> {
+        if bytes.len() >= mem::size_of::<Icmpv6Header>() {
+            unsafe {
+                return Some(Icmpv6 {
+                    header: *(bytes.as_ptr() as *const Icmpv6Header),
+                    data: bytes.get_slice(Some(mem::size_of::<Icmpv6Header>()), None).to_vec(),
+                });
+            }
+        }
+        None
+    }
+}
+
+impl ToBytes for Icmpv6 {
+    fn to_bytes(&self) -> Vec<u8> {
+        unsafe {
+            let header_ptr: *const Icmpv6Header = &self.header;
+            let mut ret = Vec::from(slice::from_raw_parts(header_ptr as *const u8, mem::size_of::<Icmpv6Header>()));
+            ret.push_all(&self.data);
+            ret
+        }
+    }
+}
+
+pub struct Icmpv6Scheme;
+
+impl KScheme for Icmpv6Scheme {
+    fn scheme(&self) -> &str {
+        "icmpv6"
+    }
+}
+
+impl Icmpv6Scheme {
+    /// Send `message` from our link-local address to `dst` at `dst_mac`
+    fn send(network: &mut Box<Resource>, mut message: Icmpv6, dst: Ipv6Addr, dst_mac: MacAddr, hop_limit: u8) {
+        let src = ipv6_addr();
+        message.calculate_checksum(&src, &dst);
+
+        let packet = Ipv6::new(src, dst, 0x3A, hop_limit, message.to_bytes());
+
+        network.write(&EthernetII {
+            header: EthernetIIHeader {
+                dst: dst_mac,
+                src: ipv6_mac(),
+                ethertype: n16::new(0x86DD),
+            },
+            data: packet.to_bytes(),
+        }.to_bytes());
+    }
+
+    /// Answer neighbor solicitations and echo requests, and learn neighbors
+    pub fn reply_loop(table: *mut NeighborCache) {
+        while let Some(mut network) = Url::from_str("network://").open() {
+            loop {
+                let mut bytes: Vec<u8> = Vec::new();
+                if let Some(_) = network.read_to_end(&mut bytes) {
+                    if let Some(frame) = EthernetII::from_bytes(bytes) {
+                        if frame.header.ethertype.get() != 0x86DD ||
+                           !(frame.header.dst.equals(ipv6_mac()) || frame.header.dst.is_multicast()) {
+                            continue;
+                        }
+
+                        if let Some(packet) = Ipv6::from_bytes(frame.data) {
+                            let our_addr = ipv6_addr();
+                            if packet.header.next_header != 0x3A ||
+                               !(packet.header.dst.equals(our_addr) ||
+                                 packet.header.dst.equals(our_addr.solicited_node()) ||
+                                 packet.header.dst.equals(ALL_NODES_IPV6_ADDR)) {
+                                continue;
+                            }
+
+                            if let Some(message) = Icmpv6::from_bytes(packet.data) {
+                                match message.header._type {
+                                    ICMPV6_NEIGHBOR_SOLICITATION => if let Some(target) = ndp::target(&message.data) {
+                                        if target.equals(our_addr) && packet.header.hop_limit == 255 {
+                                            let mut reply_dst = packet.header.src;
+                                            let mut reply_mac = frame.header.src;
+                                            let mut flags = ndp::NDP_NA_SOLICITED | ndp::NDP_NA_OVERRIDE;
+
+                                            if packet.header.src.equals(UNSPECIFIED_IPV6_ADDR) {
+                                                // Duplicate address detection, answer to all nodes
+                                                reply_dst = ALL_NODES_IPV6_ADDR;
+                                                reply_mac = ALL_NODES_IPV6_ADDR.multicast_mac();
+                                                flags = ndp::NDP_NA_OVERRIDE;
+                                            } else if let Some(mac) = ndp::link_layer_option(&message.data, ndp::NDP_OPT_SOURCE_LL) {
+                                                unsafe {
+                                                    let reenable = scheduler::start_no_ints();
+                                                    (*table).insert(packet.header.src, mac);
+                                                    scheduler::end_no_ints(reenable);
+                                                }
+                                                reply_mac = mac;
+                                            }
+
+                                            Icmpv6Scheme::send(&mut network,
+                                                               Icmpv6::new(ICMPV6_NEIGHBOR_ADVERTISEMENT, 0, ndp::advertisement(our_addr, flags)),
+                                                               reply_dst, reply_mac, 255);
+                                        }
+                                    },
+                                    ICMPV6_NEIGHBOR_ADVERTISEMENT => if let Some(target) = ndp::target(&message.data) {
+                                        if packet.header.hop_limit == 255 {
+                                            let mac = ndp::link_layer_option(&message.data, ndp::NDP_OPT_TARGET_LL)
+                                                          .unwrap_or(frame.header.src);
+                                            unsafe {
+                                                let reenable = scheduler::start_no_ints();
+                                                (*table).insert(target, mac);
+                                                scheduler::end_no_ints(reenable);
+                                            }
+                                        }
+                                    },
+                                    ICMPV6_ECHO_REQUEST => if packet.header.dst.equals(our_addr) {
+                                        Icmpv6Scheme::send(&mut network,
+                                                           Icmpv6::new(ICMPV6_ECHO_REPLY, 0, message.data),
+                                                           packet.header.src, frame.header.src, 64);
+                                    },
+                                    _ => (),
+                                }
+                            }
+                        }
+                    }
+                } else {
+                    break;
+                }
+            }
+            unsafe { context_switch(false) };
+        }
+    }
+}
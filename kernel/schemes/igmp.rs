@@ -0,0 +1,316 @@
+use alloc::boxed::Box;
+
+use collections::string::{String, ToString};
+use collections::vec::Vec;
+
+use core::{mem, slice};
+
+use scheduler::context::context_switch;
+use scheduler;
+
+use common::parse_path::parse_path;
+use common::random;
+
+use network::common::*;
+use network::ipv4::*;
+
+use schemes::route::RoutingTable;
+use schemes::{KScheme, Resource, Url, VecResource};
+
+pub const IGMP_MEMBERSHIP_QUERY: u8 = 0x11;
+pub const IGMP_V2_MEMBERSHIP_REPORT: u8 = 0x16;
+pub const IGMP_LEAVE_GROUP: u8 = 0x17;
+
+#[derive(Copy, Clone)]
+#[repr(packed)]
+pub struct IgmpHeader {
+    pub _type: u8,
+    pub max_resp_time: u8,
+    pub checksum: Checksum,
+    pub group: Ipv4Addr,
+}
+
+pub struct Igmp {
+    pub header: IgmpHeader,
+}
+
+impl Igmp {
+    pub fn new(_type: u8, group: Ipv4Addr) -> Self {
+        let mut message = Igmp {
+            header: IgmpHeader {
+                _type: _type,
+                max_resp_time: 0,
+                checksum: Checksum { data: 0 },
+                group: group,
+            },
+        };
+
+        unsafe {
+            let header_ptr: *const IgmpHeader = &message.header;
+            message.header.checksum.data = Checksum::compile(Checksum::sum(header_ptr as usize, mem::size_of::<IgmpHeader>()));
+        }
+
+        message
+    }
+
+    /// Send the message to `dst` over the interface `routes` picks for it, with a TTL of 1 and the router alert option
+    pub fn send(&self, dst: Ipv4Addr, routes: *mut RoutingTable) -> bool {
+        let route = unsafe {
+            let reenable = scheduler::start_no_ints();
+            let route = (*routes).lookup(dst);
+            scheduler::end_no_ints(reenable);
+            route
+        };
+
+        let interface = match route {
+            Some((interface, _)) => interface,
+            None => return false,
+        };
+
+        let data = self.to_bytes();
+
+        let mut options = Vec::new();
+        options.push_all(&[0x94, 0x04, 0x00, 0x00]); // Router alert
+
+        let mut ip = Ipv4 {
+            header: Ipv4Header {
+                ver_hlen: 0x40 | ((mem::size_of::<Ipv4Header>() + 4)/4 & 0xF) as u8,
+                services: 0,
+                len: n16::new((mem::size_of::<Ipv4Header>() + 4 + data.len()) as u16),
+                id: n16::new((random::rand() % 65536) as u16),
+                flags_fragment: n16::new(IPV4_DF),
+                ttl: 1,
+                proto: 2,
+                checksum: Checksum { data: 0 },
+                src: interface.ip,
+                dst: dst,
+            },
+            options: options,
+            data: data,
+        };
+
+        ip.calculate_checksum();
+
+        if let Some(mut link) = Url::from_string(format!("ethernet://{}/{}/800", interface.name, dst.multicast_mac().to_string())).open() {
+            link.write(&ip.to_bytes()).is_some()
+        } else {
+            false
+        }
+    }
+}
+
+impl FromBytes for Igmp {
+    fn from_bytes(bytes: Vec<u8>) -> Option<Self> {
+        if bytes.len() >= mem::size_of::<IgmpHeader>() {
+            unsafe {
+                return Some(Igmp {
+                    header: *(bytes.as_ptr() as *const IgmpHeader),
+                });
+            }
+        }
+        None
+    }
+}
+
+impl ToBytes for Igmp {
+    fn to_bytes(&self) -> Vec<u8> {
+        unsafe {
+            let header_ptr: *const IgmpHeader = &self.header;
+            Vec::from(slice::from_raw_parts(header_ptr as *const u8, mem::size_of::<IgmpHeader>()))
+        }
+    }
+}
+
+/// The multicast groups we are a member of, each with the number of resources that joined it
+pub struct MulticastGroups {
+    pub groups: Vec<(Ipv4Addr, usize)>,
+}
+
+impl MulticastGroups {
+    pub fn new() -> Self {
+        MulticastGroups { groups: Vec::new() }
+    }
+
+    /// Are datagrams sent to `group` for us, the all hosts group always is
+    pub fn contains(&self, group: Ipv4Addr) -> bool {
+        if group.equals(ALL_HOSTS_IP_ADDR) {
+            return true;
+        }
+
+        for &(addr, _) in self.groups.iter() {
+            if addr.equals(group) {
+                return true;
+            }
+        }
+
+        false
+    }
+
+    /// Join `group`, returns true if it was not joined before
+    pub fn join(&mut self, group: Ipv4Addr) -> bool {
+        for entry in self.groups.iter_mut() {
+            if entry.0.equals(group) {
+                entry.1 += 1;
+                return false;
+            }
+        }
+
+        self.groups.push((group, 1));
+        true
+    }
+
+    /// Leave `group`, returns true if no resource is a member anymore
+    pub fn leave(&mut self, group: Ipv4Addr) -> bool {
+        let mut i = 0;
+        while i < self.groups.len() {
+            if self.groups[i].0.equals(group) {
+                self.groups[i].1 -= 1;
+                if self.groups[i].1 == 0 {
+                    self.groups.remove(i);
+                    return true;
+                }
+                return false;
+            }
+            i += 1;
+        }
+
+        false
+    }
+
+    /// List the groups, one `GROUP MEMBERS` per line
+    pub fn to_string(&self) -> String {
+        let mut string = String::new();
+        for &(addr, count) in self.groups.iter() {
+            if !string.is_empty() {
+                string = string + "\n";
+            }
+            string = string + &format!("{} {}", addr.to_string(), count);
+        }
+        string
+    }
+}
+
+/// A membership of a multicast group, left when the resource is closed
+pub struct IgmpResource {
+    group: Ipv4Addr,
+    groups: *mut MulticastGroups,
+    routes: *mut RoutingTable,
+}
+
+impl IgmpResource {
+    fn new(group: Ipv4Addr, groups: *mut MulticastGroups, routes: *mut RoutingTable) -> Box<Self> {
+        let first = unsafe {
+            let reenable = scheduler::start_no_ints();
+            let first = (*groups).join(group);
+            scheduler::end_no_ints(reenable);
+            first
+        };
+
+        if first {
+            Igmp::new(IGMP_V2_MEMBERSHIP_REPORT, group).send(group, routes);
+        }
+
+        box IgmpResource {
+            group: group,
+            groups: groups,
+            routes: routes,
+        }
+    }
+}
+
+impl Resource for IgmpResource {
+    fn dup(&self) -> Option<Box<Resource>> {
+        Some(IgmpResource::new(self.group, self.groups, self.routes))
+    }
+
+    fn url(&self) -> Url {
+        Url::from_string("igmp://".to_string() + &self.group.to_string())
+    }
+
+    fn sync(&mut self) -> bool {
+        true
+    }
+}
+
+impl Drop for IgmpResource {
+    fn drop(&mut self) {
+        let last = unsafe {
+            let reenable = scheduler::start_no_ints();
+            let last = (*self.groups).leave(self.group);
+            scheduler::end_no_ints(reenable);
+            last
+        };
+
+        if last {
+            Igmp::new(IGMP_LEAVE_GROUP, self.group).send(ALL_ROUTERS_IP_ADDR, self.routes);
+        }
+    }
+}
+
+/// A IGMP scheme, `igmp://GROUP` joins a group for as long as it is open, `igmp:` lists the groups
+pub struct IgmpScheme {
+    pub groups: *mut MulticastGroups,
+    pub routes: *mut RoutingTable,
+}
+
+impl KScheme for IgmpScheme {
+    fn scheme(&self) -> &str {
+        "igmp"
+    }
+
+    fn open(&mut self, url: &Url, _: usize) -> Option<Box<Resource>> {
+        let parts = parse_path(url.reference());
+
+        if let Some(part) = parts.get(0) {
+            let group = Ipv4Addr::from_string(part);
+            if group.is_multicast() && !group.equals(ALL_HOSTS_IP_ADDR) {
+                return Some(IgmpResource::new(group, self.groups, self.routes));
+            }
+        } else {
+            let string;
+            unsafe {
+                let reenable = scheduler::start_no_ints();
+                string = (*self.groups).to_string();
+                scheduler::end_no_ints(reenable);
+            }
+
+            return Some(box VecResource::new(Url::from_str("igmp://"), string.into_bytes()));
+        }
+
+        None
+    }
+}
+
+impl IgmpScheme {
+    /// Answer membership queries for the groups we joined
+    pub fn reply_loop(groups: *mut MulticastGroups, routes: *mut RoutingTable) {
+        while let Some(mut ip) = Url::from_str("ip:///2").open() {
+            loop {
+                let mut bytes: Vec<u8> = Vec::new();
+                if let Some(_) = ip.read_to_end(&mut bytes) {
+                    if let Some(message) = Igmp::from_bytes(bytes) {
+                        if message.header._type == IGMP_MEMBERSHIP_QUERY {
+                            let joined = unsafe {
+                                let reenable = scheduler::start_no_ints();
+                                let joined = (*groups).groups.clone();
+                                scheduler::end_no_ints(reenable);
+                                joined
+                            };
+
+                            // A general query has no group
+                            let general = message.header.group.equals(Ipv4Addr { bytes: [0, 0, 0, 0] });
+                            for &(group, _) in joined.iter() {
+                                if general || message.header.group.equals(group) {
+                                    Igmp::new(IGMP_V2_MEMBERSHIP_REPORT, group).send(group, routes);
+                                }
+                            }
+                        }
+                    }
+                } else {
+                    break;
+                }
+            }
+            unsafe { context_switch(false) };
+        }
+    }
+}
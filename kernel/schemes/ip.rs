@@ -1,27 +1,93 @@
+use ::GetSlice;
+
 use alloc::boxed::Box;
 
 use collections::string::ToString;
 use collections::vec::Vec;
 
-use core::mem;
+use core::{cmp, mem};
 
 use network::common::*;
+use network::ethernet::*;
 use network::ipv4::*;
+use network::scheme::*;
+
+use scheduler::context::context_switch;
+use scheduler;
 
 use common::{debug, random};
+use common::time::Duration;
 use common::to_num::ToNum;
-use common::parse_ip::*;
+use common::parse_path::parse_path;
+
+use schemes::arp::{Arp, ArpTable};
+use schemes::icmp::{Icmp, ICMP_DEST_UNREACHABLE};
+use schemes::igmp::MulticastGroups;
+use schemes::route::RoutingTable;
+use schemes::{read_packet, KScheme, Resource, Url};
 
-use schemes::arp::{Arp, ArpHeader};
-use schemes::{KScheme, Resource, Url};
+use syscall::common::{O_NONBLOCK, O_RDWR, WOULD_BLOCK};
 
 /// A IP (internet protocole) resource
 pub struct IpResource {
     link: Box<Resource>,
     data: Vec<u8>,
+    host_addr: Ipv4Addr,
     peer_addr: Ipv4Addr,
     proto: u8,
     id: u16,
+    fragments: Ipv4Reassembly,
+    /// The start of the last payload written, which holds the ports for TCP and UDP
+    ports: [u8; 4],
+    /// Set when the peer reported our datagrams as undeliverable
+    unreachable: bool,
+    flags: usize,
+    groups: *mut MulticastGroups,
+}
+
+impl IpResource {
+    fn new(link: Box<Resource>, data: Vec<u8>, host_addr: Ipv4Addr, peer_addr: Ipv4Addr, proto: u8, flags: usize,
+           groups: *mut MulticastGroups) -> Box<Self> {
+        box IpResource {
+            link: link,
+            data: data,
+            host_addr: host_addr,
+            peer_addr: peer_addr,
+            proto: proto,
+            id: (random::rand() % 65536) as u16,
+            fragments: Ipv4Reassembly::new(),
+            ports: [0; 4],
+            unreachable: false,
+            flags: flags,
+            groups: groups,
+        }
+    }
+
+    /// Is a datagram sent to `dst` for us
+    fn accepts(&self, dst: Ipv4Addr) -> bool {
+        dst.equals(self.host_addr) || is_for_us(dst, self.groups)
+    }
+
+    /// Check if `packet` is an ICMP destination unreachable message for a datagram we sent
+    fn is_unreachable(&self, packet: &Ipv4) -> bool {
+        if self.proto != 1 && packet.header.proto == 1 && packet.header.dst.equals(self.host_addr) &&
+           !packet.is_fragment() {
+            if let Some(message) = Icmp::from_bytes(packet.data.clone()) {
+                if message.header._type == ICMP_DEST_UNREACHABLE {
+                    // The message quotes the original header and the first 8 bytes of its payload
+                    if let Some(original) = Ipv4::from_bytes(message.data) {
+                        return original.header.proto == self.proto &&
+                               original.header.src.equals(self.host_addr) &&
+                               original.header.dst.equals(self.peer_addr) &&
+                               original.data.len() >= 4 &&
+                               original.data.get_slice(None, Some(4)) == self.ports.get_slice(None, None);
+                    }
+                }
+            }
+        }
+
+        false
+    }
 }
 
 impl Resource for IpResource {
@@ -30,21 +96,27 @@ impl Resource for IpResource {
             Some(link) => Some(box IpResource {
                 link: link,
                 data: self.data.clone(),
+                host_addr: self.host_addr,
                 peer_addr: self.peer_addr,
                 proto: self.proto,
                 id: self.id,
+                fragments: Ipv4Reassembly::new(),
+                ports: self.ports,
+                unreachable: self.unreachable,
+                flags: self.flags,
+                groups: self.groups,
             }),
             None => None
         }
     }
 
     fn url(&self) -> Url {
-        Url::from_string(format!("ip://{}/{:X}", self.peer_addr.to_string(), self.proto))
+        Url::from_string(format!("ip://{}/{:X}/{}", self.peer_addr.to_string(), self.proto, self.host_addr.to_string()))
     }
 
     fn read(&mut self, buf: &mut [u8]) -> Option<usize> {
-        debug::d("TODO: Implement read for ip://\n");
-        None
+        // One datagram per call, truncated to the buffer
+        read_packet(self, buf)
     }
 
     fn read_to_end(&mut self, vec: &mut Vec<u8>) -> Option<usize> {
@@ -55,15 +127,33 @@ impl Resource for IpResource {
             return Some(bytes.len());
         }
 
+        if self.unreachable {
+            return None;
+        }
+
         loop {
             let mut bytes: Vec<u8> = Vec::new();
             match self.link.read_to_end(&mut bytes) {
+                Some(WOULD_BLOCK) => if self.flags & O_NONBLOCK == O_NONBLOCK {
+                    return Some(WOULD_BLOCK);
+                } else {
+                    unsafe { context_switch(false) };
+                },
                 Some(_) => {
                     if let Some(packet) = Ipv4::from_bytes(bytes) {
-                        if packet.header.proto == self.proto && packet.header.dst.equals(IP_ADDR) &&
+                        if packet.header.proto == self.proto && self.accepts(packet.header.dst) &&
                            packet.header.src.equals(self.peer_addr) {
-                            vec.push_all(&packet.data);
-                            return Some(packet.data.len());
+                            if let Some(datagram) = self.fragments.push(packet) {
+                                vec.push_all(&datagram.data);
+                                return Some(datagram.data.len());
+                            }
+                        } else if self.is_unreachable(&packet) {
+                            debug::d("IP: Destination unreachable ");
+                            debug::d(&self.peer_addr.to_string());
+                            debug::dl();
+
+                            self.unreachable = true;
+                            return None;
                         }
                     }
                 }
@@ -73,9 +163,19 @@ impl Resource for IpResource {
     }
 
     fn write(&mut self, buf: &[u8]) -> Option<usize> {
+        if self.unreachable {
+            return None;
+        }
+
+        if buf.len() >= 4 {
+            for i in 0..4 {
+                self.ports[i] = buf[i];
+            }
+        }
+
         let ip_data = Vec::from(buf);
 
-        self.id += 1;
+        self.id = self.id.wrapping_add(1);
         let mut ip = Ipv4 {
             header: Ipv4Header {
                 ver_hlen: 0x40 | (mem::size_of::<Ipv4Header>()/4 & 0xF) as u8, // No Options
@@ -86,40 +186,250 @@ impl Resource for IpResource {
                 ttl: 128,
                 proto: self.proto,
                 checksum: Checksum { data: 0 },
-                src: IP_ADDR,
+                src: self.host_addr,
                 dst: self.peer_addr,
             },
             options: Vec::new(),
             data: ip_data,
         };
 
-        unsafe {
-            let header_ptr: *const Ipv4Header = &ip.header;
-            ip.header.checksum.data =
-                Checksum::compile(Checksum::sum(header_ptr as usize, mem::size_of::<Ipv4Header>()) +
-                                  Checksum::sum(ip.options.as_ptr() as usize, ip.options.len()));
+        ip.calculate_checksum();
+
+        for packet in ip.fragment(MTU) {
+            if self.link.write(&packet.to_bytes()).is_none() {
+                return None;
+            }
         }
 
-        match self.link.write(&ip.to_bytes()) {
-            Some(_) => Some(buf.len()),
+        Some(buf.len())
+    }
+
+    fn sync(&mut self) -> bool {
+        self.link.sync()
+    }
+}
+
+/// A IP listener, receiving the datagrams of a protocol sent to us from any peer
+///
+/// Datagrams wait in the queues of the links until they are read, one per read. The URL is the
+/// one of the last datagram read, `ip://PEER/PROTO/HOST`, which can be opened to answer it.
+pub struct IpListenResource {
+    proto: u8,
+    loopback: Option<(Box<Resource>, Ipv4Reassembly)>,
+    networks: Vec<(Interface, Box<Resource>, Ipv4Reassembly)>,
+    /// The addresses of the last datagram read
+    host_addr: Ipv4Addr,
+    peer_addr: Ipv4Addr,
+    flags: usize,
+    groups: *mut MulticastGroups,
+}
+
+impl IpListenResource {
+    fn new(proto: u8, flags: usize, groups: *mut MulticastGroups) -> Box<Self> {
+        // Listen on the loopback interface and every network card at the same time
+        let loopback = match Url::from_str("loopback://").open_flags(O_RDWR | O_NONBLOCK) {
+            Some(link) => Some((link, Ipv4Reassembly::new())),
             None => None,
+        };
+
+        let mut networks: Vec<(Interface, Box<Resource>, Ipv4Reassembly)> = Vec::new();
+        for interface in all_interfaces().into_iter() {
+            if !interface.loopback {
+                if let Some(network) = Url::from_string("network://".to_string() + &interface.name).open_flags(O_RDWR | O_NONBLOCK) {
+                    networks.push((interface, network, Ipv4Reassembly::new()));
+                }
+            }
+        }
+
+        box IpListenResource {
+            proto: proto,
+            loopback: loopback,
+            networks: networks,
+            host_addr: Ipv4Addr { bytes: [0, 0, 0, 0] },
+            peer_addr: Ipv4Addr { bytes: [0, 0, 0, 0] },
+            flags: flags,
+            groups: groups,
         }
     }
 
+    /// Take the next datagram waiting on the loopback interface, with the address it was sent to
+    fn poll_loopback(&mut self, idle: &mut bool) -> Option<(Ipv4Addr, Ipv4)> {
+        let mut closed = false;
+        let mut accepted = None;
+        if let Some((ref mut link, ref mut fragments)) = self.loopback {
+            let mut bytes: Vec<u8> = Vec::new();
+            match link.read_to_end(&mut bytes) {
+                Some(WOULD_BLOCK) => (),
+                Some(_) => {
+                    *idle = false;
+                    if let Some(packet) = Ipv4::from_bytes(bytes) {
+                        if packet.header.proto == self.proto &&
+                           (packet.header.dst.is_loopback() || is_local_addr(packet.header.dst)) {
+                            accepted = fragments.push(packet).map(|datagram| (datagram.header.dst, datagram));
+                        }
+                    }
+                }
+                None => closed = true,
+            }
+        }
+        if closed {
+            self.loopback = None;
+        }
+        accepted
+    }
+
+    /// Take the next datagram waiting on a network card, with the address of the card
+    fn poll_networks(&mut self, idle: &mut bool) -> Option<(Ipv4Addr, Ipv4)> {
+        let mut accepted = None;
+        let mut closed = Vec::new();
+        for (i, &mut (ref interface, ref mut network, ref mut fragments)) in self.networks.iter_mut().enumerate() {
+            let mut bytes: Vec<u8> = Vec::new();
+            match network.read_to_end(&mut bytes) {
+                Some(WOULD_BLOCK) => (),
+                Some(_) => {
+                    *idle = false;
+                    if let Some(frame) = EthernetII::from_bytes(bytes) {
+                        if frame.header.ethertype.get() == 0x800 &&
+                           (frame.header.dst.equals(interface.mac) || frame.header.dst.is_multicast()) {
+                            if let Some(packet) = Ipv4::from_bytes(frame.data) {
+                                if packet.header.proto == self.proto &&
+                                   (packet.header.dst.equals(interface.ip) || is_for_us(packet.header.dst, self.groups)) {
+                                    accepted = fragments.push(packet).map(|datagram| (interface.ip, datagram));
+                                    if accepted.is_some() {
+                                        break;
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+                None => closed.push(i),
+            }
+        }
+        for i in closed.into_iter().rev() {
+            self.networks.remove(i);
+        }
+        accepted
+    }
+}
+
+impl Resource for IpListenResource {
+    fn dup(&self) -> Option<Box<Resource>> {
+        Some(IpListenResource::new(self.proto, self.flags, self.groups))
+    }
+
+    fn url(&self) -> Url {
+        Url::from_string(format!("ip://{}/{:X}/{}", self.peer_addr.to_string(), self.proto, self.host_addr.to_string()))
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> Option<usize> {
+        // One datagram per call, truncated to the buffer
+        read_packet(self, buf)
+    }
+
+    fn read_to_end(&mut self, vec: &mut Vec<u8>) -> Option<usize> {
+        while self.loopback.is_some() || !self.networks.is_empty() {
+            let mut idle = true;
+
+            let mut accepted = self.poll_loopback(&mut idle);
+            if accepted.is_none() {
+                accepted = self.poll_networks(&mut idle);
+            }
+
+            if let Some((host_addr, datagram)) = accepted {
+                self.host_addr = host_addr;
+                self.peer_addr = datagram.header.src;
+                vec.push_all(&datagram.data);
+                return Some(datagram.data.len());
+            }
+
+            if idle {
+                if self.flags & O_NONBLOCK == O_NONBLOCK {
+                    return Some(WOULD_BLOCK);
+                }
+
+                unsafe { context_switch(false) };
+            }
+        }
+
+        None
+    }
+
     fn sync(&mut self) -> bool {
-        self.link.sync()
+        true
     }
 }
 
-/// A ARP entry (MAC + IP)
-pub struct ArpEntry {
-    ip: Ipv4Addr,
-    mac: MacAddr,
+/// Is a datagram sent to the broadcast or multicast address `dst` for us
+fn is_for_us(dst: Ipv4Addr, groups: *mut MulticastGroups) -> bool {
+    if is_broadcast_addr(dst) {
+        true
+    } else if dst.is_multicast() {
+        unsafe {
+            let reenable = scheduler::start_no_ints();
+            let member = (*groups).contains(dst);
+            scheduler::end_no_ints(reenable);
+            member
+        }
+    } else {
+        false
+    }
 }
 
+/// Number of ARP requests sent before giving up
+const ARP_RETRIES: usize = 3;
+
 /// A IP scheme
 pub struct IpScheme {
-    pub arp: Vec<ArpEntry>,
+    pub arp: *mut ArpTable,
+    pub groups: *mut MulticastGroups,
+    pub routes: *mut RoutingTable,
+}
+
+impl IpScheme {
+    /// Resolve the MAC address of `peer_addr`, asking the network of `interface` if it is not in the table
+    fn resolve(&mut self, interface: &Interface, peer_addr: Ipv4Addr) -> Option<MacAddr> {
+        unsafe {
+            let reenable = scheduler::start_no_ints();
+            let peer_mac = (*self.arp).lookup(peer_addr);
+            scheduler::end_no_ints(reenable);
+
+            if peer_mac.is_some() {
+                return peer_mac;
+            }
+        }
+
+        if let Some(mut link) = Url::from_string(format!("ethernet://{}/{}/806", interface.name, BROADCAST_MAC_ADDR.to_string())).open() {
+            for _ in 0..ARP_RETRIES {
+                if link.write(&Arp::request(interface.mac, interface.ip, peer_addr).to_bytes()).is_none() {
+                    debug::d("IP: ARP Write Failed!\n");
+                    return None;
+                }
+
+                // Replies are learned by ArpScheme::reply_loop
+                let start = Duration::monotonic();
+                while Duration::monotonic() - start < Duration::new(1, 0) {
+                    unsafe {
+                        let reenable = scheduler::start_no_ints();
+                        let peer_mac = (*self.arp).lookup(peer_addr);
+                        scheduler::end_no_ints(reenable);
+
+                        if peer_mac.is_some() {
+                            return peer_mac;
+                        }
+
+                        context_switch(false);
+                    }
+                }
+            }
+        }
+
+        debug::d("IP: ARP Timeout for ");
+        debug::d(&peer_addr.to_string());
+        debug::dl();
+
+        None
+    }
 }
 
 impl KScheme for IpScheme {
@@ -127,93 +437,57 @@ impl KScheme for IpScheme {
         "ip"
     }
 
-    fn open(&mut self, url: &Url, _: usize) -> Option<Box<Resource>> {
-        if !url.reference().is_empty() {
-            let proto = url.reference().to_num_radix(16) as u8;
+    fn open(&mut self, url: &Url, flags: usize) -> Option<Box<Resource>> {
+        let parts = parse_path(url.reference());
 
-            if !parse_host(url.reference()).is_empty() {
-                let peer_addr = Ipv4Addr::from_string(&parse_host(url.reference()).to_string());
-                let mut peer_mac = BROADCAST_MAC_ADDR;
+        if parts.len() >= 2 {
+            let peer_addr = Ipv4Addr::from_string(&parts[0]);
+            let proto = parts[1].to_num_radix(16) as u8;
 
-                for entry in self.arp.iter() {
-                    if entry.ip.equals(peer_addr) {
-                        peer_mac = entry.mac;
-                        break;
-                    }
-                }
+            // Local destinations never leave the machine
+            let (link, host_addr) = if peer_addr.is_loopback() {
+                (Url::from_str("loopback://").open_flags(flags), LOOPBACK_IP_ADDR)
+            } else if is_local_addr(peer_addr) {
+                (Url::from_str("loopback://").open_flags(flags), peer_addr)
+            } else {
+                let route = unsafe {
+                    let reenable = scheduler::start_no_ints();
+                    let route = (*self.routes).lookup(peer_addr);
+                    scheduler::end_no_ints(reenable);
+                    route
+                };
 
-                if peer_mac.equals(BROADCAST_MAC_ADDR) {
-                    if let Some(mut link) = Url::from_string("ethernet://".to_string() + &peer_mac.to_string() + "/806").open() {
-                        let arp = Arp {
-                            header: ArpHeader {
-                                htype: n16::new(1),
-                                ptype: n16::new(0x800),
-                                hlen: 6,
-                                plen: 4,
-                                oper: n16::new(1),
-                                src_mac: unsafe { MAC_ADDR },
-                                src_ip: IP_ADDR,
-                                dst_mac: peer_mac,
-                                dst_ip: peer_addr,
-                            },
-                            data: Vec::new(),
-                        };
-
-                        match link.write(&arp.to_bytes()) {
-                            Some(_) => loop {
-                                let mut bytes: Vec<u8> = Vec::new();
-                                match link.read_to_end(&mut bytes) {
-                                    Some(_) =>
-                                        if let Some(packet) = Arp::from_bytes(bytes) {
-                                        if packet.header.oper.get() == 2 &&
-                                           packet.header.src_ip.equals(peer_addr) {
-                                            peer_mac = packet.header.src_mac;
-                                            self.arp.push(ArpEntry {
-                                                ip: peer_addr,
-                                                mac: peer_mac,
-                                            });
-                                            break;
-                                        }
-                                    },
-                                    None => (),
-                                }
-                            },
-                            None => debug::d("IP: ARP Write Failed!\n"),
-                        }
+                let (interface, next_hop) = match route {
+                    Some(route) => route,
+                    None => {
+                        debug::d("IP: No route to ");
+                        debug::d(&peer_addr.to_string());
+                        debug::dl();
+                        return None;
                     }
-                }
+                };
 
-                if let Some(link) = Url::from_string("ethernet://".to_string() + &peer_mac.to_string() + "/800").open() {
-                    return Some(box IpResource {
-                        link: link,
-                        data: Vec::new(),
-                        peer_addr: peer_addr,
-                        proto: proto,
-                        id: (random::rand() % 65536) as u16,
-                    });
-                }
-            } else {
-                while let Some(mut link) = Url::from_str("ethernet:///800").open() {
-                    let mut bytes: Vec<u8> = Vec::new();
-                    match link.read_to_end(&mut bytes) {
-                        Some(_) => {
-                            if let Some(packet) = Ipv4::from_bytes(bytes) {
-                                if packet.header.proto == proto &&
-                                   packet.header.dst.equals(IP_ADDR) {
-                                    return Some(box IpResource {
-                                        link: link,
-                                        data: packet.data,
-                                        peer_addr: packet.header.src,
-                                        proto: proto,
-                                        id: (random::rand() % 65536) as u16,
-                                    });
-                                }
-                            }
-                        }
-                        None => break,
+                let peer_mac = if peer_addr.is_broadcast() || peer_addr.equals(interface.broadcast()) {
+                    BROADCAST_MAC_ADDR
+                } else if peer_addr.is_multicast() {
+                    peer_addr.multicast_mac()
+                } else {
+                    match self.resolve(&interface, next_hop) {
+                        Some(mac) => mac,
+                        None => return None,
                     }
-                }
+                };
+
+                (Url::from_string(format!("ethernet://{}/{}/800", interface.name, peer_mac.to_string())).open_flags(flags), interface.ip)
+            };
+
+            if let Some(link) = link {
+                return Some(IpResource::new(link, Vec::new(), host_addr, peer_addr, proto, flags, self.groups));
             }
+        } else if parts.len() == 1 {
+            let proto = parts[0].to_num_radix(16) as u8;
+
+            return Some(IpListenResource::new(proto, flags, self.groups));
         } else {
             debug::d("IP: No protocol provided\n");
         }
@@ -0,0 +1,221 @@
+use alloc::boxed::Box;
+
+use collections::string::ToString;
+use collections::vec::Vec;
+
+use core::mem;
+
+use network::common::*;
+use network::ipv6::*;
+use network::scheme::ipv6_addr;
+
+use scheduler::context::context_switch;
+use scheduler;
+
+use common::debug;
+use common::time::Duration;
+use common::to_num::ToNum;
+use common::parse_path::parse_path;
+
+use schemes::icmpv6::{Icmpv6, ICMPV6_NEIGHBOR_SOLICITATION};
+use schemes::ndp::{self, NeighborCache};
+use schemes::{read_packet, KScheme, Resource, Url};
+
+use syscall::common::{O_NONBLOCK, WOULD_BLOCK};
+
+/// Number of neighbor solicitations sent before giving up
+const NDP_RETRIES: usize = 3;
+
+/// A IPv6 resource
+pub struct Ip6Resource {
+    link: Box<Resource>,
+    data: Vec<u8>,
+    peer_addr: Ipv6Addr,
+    proto: u8,
+    flags: usize,
+}
+
+impl Resource for Ip6Resource {
+    fn dup(&self) -> Option<Box<Resource>> {
+        match self.link.dup() {
+            Some(link) => Some(box Ip6Resource {
+                link: link,
+                data: self.data.clone(),
+                peer_addr: self.peer_addr,
+                proto: self.proto,
+                flags: self.flags,
+            }),
+            None => None
+        }
+    }
+
+    /// The url is `ip6://PEER/PROTO/LOCAL`, so upper layers can build their checksums
+    fn url(&self) -> Url {
+        Url::from_string(format!("ip6://{}/{:X}/{}", self.peer_addr.to_string(), self.proto, ipv6_addr().to_string()))
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> Option<usize> {
+        // One datagram per call, truncated to the buffer
+        read_packet(self, buf)
+    }
+
+    fn read_to_end(&mut self, vec: &mut Vec<u8>) -> Option<usize> {
+        if !self.data.is_empty() {
+            let mut bytes: Vec<u8> = Vec::new();
+            mem::swap(&mut self.data, &mut bytes);
+            vec.push_all(&bytes);
+            return Some(bytes.len());
+        }
+
+        loop {
+            let mut bytes: Vec<u8> = Vec::new();
+            match self.link.read_to_end(&mut bytes) {
+                Some(WOULD_BLOCK) => if self.flags & O_NONBLOCK == O_NONBLOCK {
+                    return Some(WOULD_BLOCK);
+                } else {
+                    unsafe { context_switch(false) };
+                },
+                Some(_) => {
+                    if let Some(packet) = Ipv6::from_bytes(bytes) {
+                        if packet.header.next_header == self.proto && packet.header.dst.equals(ipv6_addr()) &&
+                           packet.header.src.equals(self.peer_addr) {
+                            vec.push_all(&packet.data);
+                            return Some(packet.data.len());
+                        }
+                    }
+                }
+                None => return None,
+            }
+        }
+    }
+
+    fn write(&mut self, buf: &[u8]) -> Option<usize> {
+        let packet = Ipv6::new(ipv6_addr(), self.peer_addr, self.proto, 64, Vec::from(buf));
+
+        match self.link.write(&packet.to_bytes()) {
+            Some(_) => Some(buf.len()),
+            None => None,
+        }
+    }
+
+    fn sync(&mut self) -> bool {
+        self.link.sync()
+    }
+}
+
+/// A IPv6 scheme
+pub struct Ip6Scheme {
+    pub neighbors: *mut NeighborCache,
+}
+
+impl Ip6Scheme {
+    /// Resolve the MAC address of `peer_addr`, soliciting it if it is not in the neighbor cache
+    fn resolve(&mut self, peer_addr: Ipv6Addr) -> Option<MacAddr> {
+        if peer_addr.is_multicast() {
+            return Some(peer_addr.multicast_mac());
+        }
+
+        unsafe {
+            let reenable = scheduler::start_no_ints();
+            let peer_mac = (*self.neighbors).lookup(peer_addr);
+            scheduler::end_no_ints(reenable);
+
+            if peer_mac.is_some() {
+                return peer_mac;
+            }
+        }
+
+        let solicited = peer_addr.solicited_node();
+        if let Some(mut link) = Url::from_string("ethernet://".to_string() + &solicited.multicast_mac().to_string() + "/86DD").open() {
+            for _ in 0..NDP_RETRIES {
+                let mut message = Icmpv6::new(ICMPV6_NEIGHBOR_SOLICITATION, 0, ndp::solicitation(peer_addr));
+                message.calculate_checksum(&ipv6_addr(), &solicited);
+
+                let packet = Ipv6::new(ipv6_addr(), solicited, 0x3A, 255, message.to_bytes());
+                if link.write(&packet.to_bytes()).is_none() {
+                    debug::d("IP6: NDP Write Failed!\n");
+                    return None;
+                }
+
+                // Advertisements are learned by Icmpv6Scheme::reply_loop
+                let start = Duration::monotonic();
+                while Duration::monotonic() - start < Duration::new(1, 0) {
+                    unsafe {
+                        let reenable = scheduler::start_no_ints();
+                        let peer_mac = (*self.neighbors).lookup(peer_addr);
+                        scheduler::end_no_ints(reenable);
+
+                        if peer_mac.is_some() {
+                            return peer_mac;
+                        }
+
+                        context_switch(false);
+                    }
+                }
+            }
+        }
+
+        debug::d("IP6: NDP Timeout for ");
+        debug::d(&peer_addr.to_string());
+        debug::dl();
+
+        None
+    }
+}
+
+impl KScheme for Ip6Scheme {
+    fn scheme(&self) -> &str {
+        "ip6"
+    }
+
+    fn open(&mut self, url: &Url, flags: usize) -> Option<Box<Resource>> {
+        let parts = parse_path(url.reference());
+
+        if parts.len() >= 2 {
+            let peer_addr = Ipv6Addr::from_str(&parts[0]);
+            let proto = parts[1].to_num_radix(16) as u8;
+
+            let peer_mac = match self.resolve(peer_addr) {
+                Some(mac) => mac,
+                None => return None,
+            };
+
+            if let Some(link) = Url::from_string("ethernet://".to_string() + &peer_mac.to_string() + "/86DD").open_flags(flags) {
+                return Some(box Ip6Resource {
+                    link: link,
+                    data: Vec::new(),
+                    peer_addr: peer_addr,
+                    proto: proto,
+                    flags: flags,
+                });
+            }
+        } else if parts.len() == 1 {
+            let proto = parts[0].to_num_radix(16) as u8;
+
+            while let Some(mut link) = Url::from_str("ethernet:///86DD").open_flags(flags) {
+                let mut bytes: Vec<u8> = Vec::new();
+                match link.read_to_end(&mut bytes) {
+                    Some(_) => {
+                        if let Some(packet) = Ipv6::from_bytes(bytes) {
+                            if packet.header.next_header == proto &&
+                               packet.header.dst.equals(ipv6_addr()) {
+                                return Some(box Ip6Resource {
+                                    link: link,
+                                    data: packet.data,
+                                    peer_addr: packet.header.src,
+                                    proto: proto,
+                                    flags: flags,
+                                });
+                            }
+                        }
+                    }
+                    None => break,
+                }
+            }
+        } else {
+            debug::d("IP6: No protocol provided\n");
+        }
+
+        None
+    }
+}
@@ -7,10 +7,12 @@ use collections::vec::Vec;
 
 use core::cmp::{min, max};
 
-use syscall::common::{O_CREAT, O_RDWR, O_TRUNC};
+use syscall::common::{O_CREAT, O_RDWR, O_TRUNC, WOULD_BLOCK};
 
 /// ARP scheme
 pub mod arp;
+/// Clipboard scheme
+pub mod clipboard;
 /// Context scheme
 pub mod context;
 /// Debug scheme
@@ -23,12 +25,26 @@ pub mod ethernet;
 pub mod file;
 /// ICMP scheme
 pub mod icmp;
+/// ICMPv6 scheme
+pub mod icmpv6;
+/// IGMP scheme
+pub mod igmp;
 /// IP scheme
 pub mod ip;
+/// IPv6 scheme
+pub mod ip6;
 /// Memory scheme
 pub mod memory;
+/// NDP scheme
+pub mod ndp;
+/// Network statistics scheme
+pub mod netstat;
+/// Packet capture scheme
+pub mod pcap;
 /// Pseudo random generation scheme
 pub mod random;
+/// Routing scheme
+pub mod route;
 /// Time scheme
 pub mod time;
 /// Window scheme
@@ -73,7 +89,7 @@ pub trait Resource {
     /// Return the url of this resource
     fn url(&self) -> Url;
     // TODO: Make use of Write and Read trait
-    /// Read data to buffer
+    /// Read data to buffer, returning `WOULD_BLOCK` if opened with O_NONBLOCK and nothing is ready
     fn read(&mut self, buf: &mut [u8]) -> Option<usize> {
         None
     }
@@ -101,6 +117,11 @@ pub trait Resource {
             let mut bytes = [0; 1024];
             match self.read(&mut bytes) {
                 Some(0) => return Some(read),
+                Some(WOULD_BLOCK) => return if read > 0 {
+                    Some(read)
+                } else {
+                    Some(WOULD_BLOCK)
+                },
                 None => return None,
                 Some(count) => {
                     vec.push_all(bytes.get_slice(None, Some(count)));
@@ -111,6 +132,23 @@ pub trait Resource {
     }
 }
 
+/// Read the next packet of a resource that returns one packet from each `read_to_end`,
+/// truncated to the buffer
+pub fn read_packet(resource: &mut Resource, buf: &mut [u8]) -> Option<usize> {
+    let mut bytes: Vec<u8> = Vec::new();
+    match resource.read_to_end(&mut bytes) {
+        Some(WOULD_BLOCK) => Some(WOULD_BLOCK),
+        Some(_) => {
+            let count = min(buf.len(), bytes.len());
+            for i in 0..count {
+                buf[i] = bytes[i];
+            }
+            Some(count)
+        }
+        None => None,
+    }
+}
+
 /// An URL, see wiki
 pub struct Url {
     pub string: String,
@@ -149,6 +187,13 @@ impl Url {
         }
     }
 
+    /// Open this URL with the given flags (returns a resource)
+    pub fn open_flags(&self, flags: usize) -> Option<Box<Resource>> {
+        unsafe {
+            return (*::session_ptr).open(&self, flags);
+        }
+    }
+
     /// Create this URL (returns a resource)
     pub fn create(&self) -> Option<Box<Resource>> {
         unsafe {
@@ -0,0 +1,109 @@
+use alloc::boxed::Box;
+
+use collections::vec::Vec;
+
+use scheduler;
+
+use network::common::*;
+use network::neighbor::NeighborTable;
+use network::scheme::ipv6_mac;
+
+use schemes::{KScheme, Resource, Url, VecResource};
+
+/// How long a learned neighbor stays valid
+pub const NEIGHBOR_ENTRY_TIMEOUT: i64 = 300;
+
+/// Source link-layer address option
+pub const NDP_OPT_SOURCE_LL: u8 = 1;
+/// Target link-layer address option
+pub const NDP_OPT_TARGET_LL: u8 = 2;
+
+/// Router flag of a neighbor advertisement
+pub const NDP_NA_ROUTER: u8 = 1 << 7;
+/// Solicited flag of a neighbor advertisement
+pub const NDP_NA_SOLICITED: u8 = 1 << 6;
+/// Override flag of a neighbor advertisement
+pub const NDP_NA_OVERRIDE: u8 = 1 << 5;
+
+/// Body of a neighbor solicitation for `target`, including our link-layer address
+pub fn solicitation(target: Ipv6Addr) -> Vec<u8> {
+    let mut data: Vec<u8> = Vec::new();
+    data.push_all(&[0, 0, 0, 0]);
+    data.push_all(&target.bytes);
+    data.push(NDP_OPT_SOURCE_LL);
+    data.push(1);
+    data.push_all(&ipv6_mac().bytes);
+    data
+}
+
+/// Body of a neighbor advertisement for `target`, including our link-layer address
+pub fn advertisement(target: Ipv6Addr, flags: u8) -> Vec<u8> {
+    let mut data: Vec<u8> = Vec::new();
+    data.push_all(&[flags, 0, 0, 0]);
+    data.push_all(&target.bytes);
+    data.push(NDP_OPT_TARGET_LL);
+    data.push(1);
+    data.push_all(&ipv6_mac().bytes);
+    data
+}
+
+/// The target address of a solicitation or advertisement body
+pub fn target(data: &[u8]) -> Option<Ipv6Addr> {
+    if data.len() >= 20 {
+        let mut addr = Ipv6Addr { bytes: [0; 16] };
+        for i in 0..16 {
+            addr.bytes[i] = data[4 + i];
+        }
+        Some(addr)
+    } else {
+        None
+    }
+}
+
+/// Find a link-layer address option of type `kind` in a solicitation or advertisement body
+pub fn link_layer_option(data: &[u8], kind: u8) -> Option<MacAddr> {
+    let mut i = 20;
+    while i + 2 <= data.len() {
+        let len = data[i + 1] as usize * 8;
+        if len == 0 || i + len > data.len() {
+            break;
+        }
+
+        if data[i] == kind && len >= 8 {
+            let mut mac = MacAddr { bytes: [0; 6] };
+            for j in 0..6 {
+                mac.bytes[j] = data[i + 2 + j];
+            }
+            return Some(mac);
+        }
+
+        i += len;
+    }
+
+    None
+}
+
+/// A neighbor cache, entries expire after `NEIGHBOR_ENTRY_TIMEOUT` seconds
+pub type NeighborCache = NeighborTable<Ipv6Addr>;
+
+/// A NDP scheme, exposes the neighbor cache read-only
+pub struct NdpScheme {
+    pub table: *mut NeighborCache,
+}
+
+impl KScheme for NdpScheme {
+    fn scheme(&self) -> &str {
+        "ndp"
+    }
+
+    fn open(&mut self, _: &Url, _: usize) -> Option<Box<Resource>> {
+        let string;
+        unsafe {
+            let reenable = scheduler::start_no_ints();
+            string = (*self.table).to_string();
+            scheduler::end_no_ints(reenable);
+        }
+
+        Some(box VecResource::new(Url::from_str("ndp://"), string.into_bytes()))
+    }
+}
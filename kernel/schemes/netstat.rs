@@ -0,0 +1,138 @@
+use alloc::boxed::Box;
+
+use collections::string::{String, ToString};
+use collections::vec::Vec;
+
+use core::str;
+
+use common::parse_path::parse_path;
+
+use network::scheme::all_interfaces;
+
+use scheduler;
+
+use schemes::{KScheme, Resource, Url, VecResource};
+
+/// The connections reported by the tcp and udp schemes, listed by `netstat://connections`
+pub static mut connections: *mut Vec<*const ConnectionResource> = 0 as *mut Vec<*const ConnectionResource>;
+
+/// A connection reported by the tcp or udp scheme, opened as `netstat://connection`
+///
+/// Each write replaces the line listed for the connection, `PROTO LOCAL PEER [STATE]`, and the
+/// connection is listed until the resource is closed.
+pub struct ConnectionResource {
+    line: String,
+}
+
+impl ConnectionResource {
+    fn new() -> Box<Self> {
+        let resource = box ConnectionResource { line: String::new() };
+
+        unsafe {
+            let reenable = scheduler::start_no_ints();
+            if connections.is_null() {
+                connections = Box::into_raw(box Vec::new());
+            }
+            (*connections).push(&*resource);
+            scheduler::end_no_ints(reenable);
+        }
+
+        resource
+    }
+}
+
+impl Resource for ConnectionResource {
+    fn url(&self) -> Url {
+        Url::from_str("netstat://connection")
+    }
+
+    fn write(&mut self, buf: &[u8]) -> Option<usize> {
+        match str::from_utf8(buf) {
+            Ok(line) => {
+                let line = line.trim().to_string();
+
+                let reenable = scheduler::start_no_ints();
+                self.line = line;
+                scheduler::end_no_ints(reenable);
+
+                Some(buf.len())
+            }
+            Err(_) => None,
+        }
+    }
+
+    fn sync(&mut self) -> bool {
+        true
+    }
+}
+
+impl Drop for ConnectionResource {
+    fn drop(&mut self) {
+        unsafe {
+            let reenable = scheduler::start_no_ints();
+            let ptr: *const ConnectionResource = self;
+            (*connections).retain(|resource| *resource != ptr);
+            scheduler::end_no_ints(reenable);
+        }
+    }
+}
+
+/// A network statistics scheme
+///
+/// `netstat://interfaces` lists the counters and link state of each interface,
+/// `netstat://connections` the TCP and UDP connections with their ports and TCP state, as
+/// reported by the tcp and udp schemes through `netstat://connection`.
+/// `netstat:` lists both.
+pub struct NetstatScheme;
+
+impl NetstatScheme {
+    fn interfaces() -> String {
+        let mut string = String::new();
+        for interface in all_interfaces().iter() {
+            string = string + &interface.to_string() + "\n";
+        }
+        string
+    }
+
+    fn connections() -> String {
+        let mut string = String::new();
+        unsafe {
+            let reenable = scheduler::start_no_ints();
+            if !connections.is_null() {
+                for resource in (*connections).iter() {
+                    if !(**resource).line.is_empty() {
+                        string = string + &(**resource).line + "\n";
+                    }
+                }
+            }
+            scheduler::end_no_ints(reenable);
+        }
+        string
+    }
+}
+
+impl KScheme for NetstatScheme {
+    fn scheme(&self) -> &str {
+        "netstat"
+    }
+
+    fn open(&mut self, url: &Url, _: usize) -> Option<Box<Resource>> {
+        let parts = parse_path(url.reference());
+
+        let string = match parts.get(0) {
+            Some(part) => if *part == "interfaces" {
+                NetstatScheme::interfaces()
+            } else if *part == "connections" {
+                NetstatScheme::connections()
+            } else if *part == "connection" {
+                return Some(ConnectionResource::new());
+            } else {
+                return None;
+            },
+            None => "Interfaces\n".to_string() + &NetstatScheme::interfaces() +
+                    "\nConnections\n" + &NetstatScheme::connections(),
+        };
+
+        Some(box VecResource::new(url.clone(), string.into_bytes()))
+    }
+}
@@ -0,0 +1,232 @@
+use ::GetSlice;
+
+use alloc::boxed::Box;
+
+use collections::string::String;
+use collections::vec::Vec;
+
+use core::cmp;
+
+use common::bytes::{push_u16, push_u32};
+use common::debug;
+use common::parse_path::parse_path;
+use common::to_num::ToNum;
+
+use network::scheme::{captures, Capture};
+
+use scheduler::context::context_switch;
+use scheduler;
+
+use schemes::{KScheme, Resource, Url};
+
+use syscall::common::{O_NONBLOCK, WOULD_BLOCK};
+
+/// Largest frame length recorded in the file header
+const PCAP_SNAPLEN: u32 = 65535;
+/// Link type of ethernet frames
+const PCAP_LINKTYPE_ETHERNET: u32 = 1;
+
+/// Which frames to capture
+#[derive(Copy, Clone)]
+pub enum PcapFilter {
+    All,
+    EtherType(u16),
+    IpProto(u8),
+}
+
+impl PcapFilter {
+    fn from_url(url: &Url) -> Self {
+        let parts = parse_path(url.reference());
+
+        match (parts.get(0), parts.get(1)) {
+            (Some(kind), Some(value)) => if *kind == "ether" {
+                PcapFilter::EtherType(value.to_num_radix(16) as u16)
+            } else if *kind == "ip" {
+                PcapFilter::IpProto(value.to_num_radix(16) as u8)
+            } else {
+                PcapFilter::All
+            },
+            _ => PcapFilter::All,
+        }
+    }
+
+    fn to_string(&self) -> String {
+        match *self {
+            PcapFilter::All => format!("pcap:"),
+            PcapFilter::EtherType(ethertype) => format!("pcap://ether/{:X}", ethertype),
+            PcapFilter::IpProto(proto) => format!("pcap://ip/{:X}", proto),
+        }
+    }
+
+    fn matches(&self, frame: &[u8]) -> bool {
+        if frame.len() < 14 {
+            return false;
+        }
+
+        let ethertype = (frame[12] as u16) << 8 | frame[13] as u16;
+        match *self {
+            PcapFilter::All => true,
+            PcapFilter::EtherType(filter) => ethertype == filter,
+            PcapFilter::IpProto(filter) => match ethertype {
+                0x800 => frame.len() > 14 + 9 && frame[14 + 9] == filter,
+                0x86DD => frame.len() > 14 + 6 && frame[14 + 6] == filter,
+                _ => false,
+            },
+        }
+    }
+}
+
+/// A packet capture resource
+///
+/// Reads return a libpcap file: the global header first, then one record per frame.
+/// Frames that arrive while `CAPTURE_QUEUE_LEN` frames are waiting to be read are dropped,
+/// and the number dropped is printed to the debug console when the resource is closed.
+pub struct PcapResource {
+    /// Frames mirrored by the drivers, registered in `captures`
+    capture: Box<Capture>,
+    filter: PcapFilter,
+    /// Encoded bytes not yet read
+    data: Vec<u8>,
+    flags: usize,
+}
+
+impl PcapResource {
+    fn new(filter: PcapFilter, data: Vec<u8>, flags: usize) -> Box<Self> {
+        let mut ret = box PcapResource {
+            capture: box Capture::new(),
+            filter: filter,
+            data: data,
+            flags: flags,
+        };
+
+        unsafe {
+            let reenable = scheduler::start_no_ints();
+            if captures.is_null() {
+                captures = Box::into_raw(box Vec::new());
+            }
+            (*captures).push(&mut *ret.capture);
+            scheduler::end_no_ints(reenable);
+        }
+
+        ret
+    }
+
+    /// Append the next matching frame to `data`
+    fn record_frame(&mut self) -> bool {
+        loop {
+            let option = unsafe {
+                let reenable = scheduler::start_no_ints();
+                let option = self.capture.frames.pop();
+                scheduler::end_no_ints(reenable);
+                option
+            };
+
+            match option {
+                Some((time, frame)) => if self.filter.matches(&frame) {
+                    let len = cmp::min(frame.len(), PCAP_SNAPLEN as usize);
+                    push_u32(&mut self.data, time.secs as u32);
+                    push_u32(&mut self.data, (time.nanos / 1000) as u32);
+                    push_u32(&mut self.data, len as u32);
+                    push_u32(&mut self.data, frame.len() as u32);
+                    self.data.push_all(frame.get_slice(None, Some(len)));
+                    return true;
+                },
+                None => return false,
+            }
+        }
+    }
+}
+
+impl Resource for PcapResource {
+    fn dup(&self) -> Option<Box<Resource>> {
+        Some(PcapResource::new(self.filter, self.data.clone(), self.flags))
+    }
+
+    fn url(&self) -> Url {
+        Url::from_string(self.filter.to_string())
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> Option<usize> {
+        loop {
+            if !self.data.is_empty() {
+                let count = cmp::min(buf.len(), self.data.len());
+                for i in 0..count {
+                    buf[i] = self.data[i];
+                }
+                self.data = self.data.get_slice(Some(count), None).to_vec();
+                return Some(count);
+            }
+
+            if !self.record_frame() {
+                if self.flags & O_NONBLOCK == O_NONBLOCK {
+                    return Some(WOULD_BLOCK);
+                }
+
+                unsafe { context_switch(false) };
+            }
+        }
+    }
+
+    fn read_to_end(&mut self, vec: &mut Vec<u8>) -> Option<usize> {
+        while self.data.is_empty() && !self.record_frame() {
+            if self.flags & O_NONBLOCK == O_NONBLOCK {
+                return Some(WOULD_BLOCK);
+            }
+
+            unsafe { context_switch(false) };
+        }
+
+        while self.record_frame() {}
+
+        let count = self.data.len();
+        vec.push_all(&self.data);
+        self.data.clear();
+        Some(count)
+    }
+
+    fn sync(&mut self) -> bool {
+        true
+    }
+}
+
+impl Drop for PcapResource {
+    fn drop(&mut self) {
+        unsafe {
+            let reenable = scheduler::start_no_ints();
+            let capture: *mut Capture = &mut *self.capture;
+            (*captures).retain(|ptr| *ptr != capture);
+            scheduler::end_no_ints(reenable);
+        }
+
+        if self.capture.dropped > 0 {
+            debug::d("PCAP: Dropped ");
+            debug::dd(self.capture.dropped as usize);
+            debug::d(" frames\n");
+        }
+    }
+}
+
+/// A packet capture scheme
+///
+/// `pcap:` captures every frame, `pcap://ether/TYPE` and `pcap://ip/PROTO` filter by a
+/// hexadecimal EtherType or IP protocol.
+pub struct PcapScheme;
+
+impl KScheme for PcapScheme {
+    fn scheme(&self) -> &str {
+        "pcap"
+    }
+
+    fn open(&mut self, url: &Url, flags: usize) -> Option<Box<Resource>> {
+        let mut header = Vec::new();
+        push_u32(&mut header, 0xA1B2C3D4);
+        push_u16(&mut header, 2);
+        push_u16(&mut header, 4);
+        push_u32(&mut header, 0); // GMT
+        push_u32(&mut header, 0); // Timestamp accuracy
+        push_u32(&mut header, PCAP_SNAPLEN);
+        push_u32(&mut header, PCAP_LINKTYPE_ETHERNET);
+
+        Some(PcapResource::new(PcapFilter::from_url(url), header, flags))
+    }
+}
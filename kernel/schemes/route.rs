@@ -0,0 +1,281 @@
+use alloc::boxed::Box;
+
+use collections::string::{String, ToString};
+use collections::vec::Vec;
+
+use core::{cmp, str};
+
+use common::to_num::ToNum;
+
+use network::common::*;
+use network::scheme::{all_interfaces, configure_interface, find_interface, Interface};
+
+use scheduler;
+
+use schemes::arp;
+use schemes::{KScheme, Resource, ResourceSeek, Url};
+
+/// A route to a network
+#[derive(Clone)]
+pub struct Route {
+    pub network: Ipv4Addr,
+    pub netmask: Ipv4Addr,
+    /// None when the network is reachable without a router
+    pub gateway: Option<Ipv4Addr>,
+    /// The name of the interface to send through
+    pub interface: String,
+}
+
+impl Route {
+    pub fn to_string(&self) -> String {
+        let mut string = format!("{}/{}", self.network.to_string(), self.netmask.prefix_len());
+        if let Some(gateway) = self.gateway {
+            string = string + " via " + &gateway.to_string();
+        }
+        string + " dev " + &self.interface
+    }
+}
+
+/// The routes added through `route:`
+///
+/// The subnets of the interfaces are routed without being listed here.
+pub struct RoutingTable {
+    pub routes: Vec<Route>,
+}
+
+impl RoutingTable {
+    pub fn new() -> Self {
+        RoutingTable { routes: Vec::new() }
+    }
+
+    /// Add a route, replacing any route to the same network
+    pub fn add(&mut self, mut route: Route) {
+        for i in 0..4 {
+            route.network.bytes[i] &= route.netmask.bytes[i];
+        }
+        self.remove(route.network, route.netmask);
+        self.routes.push(route);
+    }
+
+    /// Remove the route to a network, returns false if there was none
+    pub fn remove(&mut self, network: Ipv4Addr, netmask: Ipv4Addr) -> bool {
+        let len = self.routes.len();
+        self.routes.retain(|route| {
+            !(route.network.in_network(network, netmask) &&
+              route.netmask.prefix_len() == netmask.prefix_len())
+        });
+        self.routes.len() != len
+    }
+
+    /// Find the interface and next hop for `dst`, by longest prefix
+    ///
+    /// The subnet of an interface wins over a route of the same length.
+    pub fn lookup(&self, dst: Ipv4Addr) -> Option<(Interface, Ipv4Addr)> {
+        let mut best: Option<(usize, Interface, Ipv4Addr)> = None;
+
+        for interface in all_interfaces().into_iter() {
+            if !interface.loopback && interface.contains(dst) {
+                let len = interface.netmask.prefix_len();
+                if best.as_ref().map_or(true, |&(best_len, _, _)| len > best_len) {
+                    best = Some((len, interface, dst));
+                }
+            }
+        }
+
+        for route in self.routes.iter() {
+            if dst.in_network(route.network, route.netmask) {
+                let len = route.netmask.prefix_len();
+                if best.as_ref().map_or(true, |&(best_len, _, _)| len > best_len) {
+                    if let Some(interface) = find_interface(&route.interface) {
+                        best = Some((len, interface, route.gateway.unwrap_or(dst)));
+                    }
+                }
+            }
+        }
+
+        best.map(|(_, interface, next_hop)| (interface, next_hop))
+    }
+
+    pub fn to_string(&self) -> String {
+        let mut string = String::new();
+
+        for interface in all_interfaces().iter() {
+            let mut network = interface.ip;
+            for i in 0..4 {
+                network.bytes[i] &= interface.netmask.bytes[i];
+            }
+            string = string + &format!("{}/{} dev {}\n", network.to_string(), interface.netmask.prefix_len(), interface.name);
+        }
+
+        for route in self.routes.iter() {
+            string = string + &route.to_string() + "\n";
+        }
+
+        string
+    }
+}
+
+/// Parse `ADDR/PREFIX`, a missing prefix is a single address
+fn parse_cidr(string: &str) -> (Ipv4Addr, Ipv4Addr) {
+    let mut parts = string.splitn(2, '/');
+    let addr = Ipv4Addr::from_string(&parts.next().unwrap_or("").to_string());
+    let len = match parts.next() {
+        Some(len) => len.to_string().to_num(),
+        None => 32,
+    };
+    (addr, Ipv4Addr::from_prefix_len(len))
+}
+
+/// A route resource
+///
+/// Reads list the routes, each write is one command:
+/// `add NET/PREFIX [via GATEWAY] dev IFACE`, `del NET/PREFIX` or `addr IFACE IP/PREFIX`.
+/// The commands in `file:///etc/network` are written here at startup.
+pub struct RouteResource {
+    table: *mut RoutingTable,
+    data: Vec<u8>,
+    seek: usize,
+}
+
+impl RouteResource {
+    fn command(&mut self, line: &str) -> bool {
+        let words: Vec<&str> = line.split(' ').filter(|word| !word.is_empty()).collect();
+
+        match words.get(0).map(|word| *word) {
+            Some("add") if words.len() >= 4 => {
+                let (network, netmask) = parse_cidr(words[1]);
+
+                let mut gateway = None;
+                let mut interface = None;
+                let mut i = 2;
+                while i + 1 < words.len() {
+                    match words[i] {
+                        "via" => gateway = Some(Ipv4Addr::from_string(&words[i + 1].to_string())),
+                        "dev" => interface = Some(words[i + 1].to_string()),
+                        _ => return false,
+                    }
+                    i += 2;
+                }
+
+                match interface {
+                    Some(interface) => {
+                        if find_interface(&interface).is_none() {
+                            return false;
+                        }
+
+                        unsafe {
+                            let reenable = scheduler::start_no_ints();
+                            (*self.table).add(Route {
+                                network: network,
+                                netmask: netmask,
+                                gateway: gateway,
+                                interface: interface,
+                            });
+                            scheduler::end_no_ints(reenable);
+                        }
+                        true
+                    }
+                    None => false,
+                }
+            }
+            Some("del") if words.len() == 2 => {
+                let (network, netmask) = parse_cidr(words[1]);
+                unsafe {
+                    let reenable = scheduler::start_no_ints();
+                    let removed = (*self.table).remove(network, netmask);
+                    scheduler::end_no_ints(reenable);
+                    removed
+                }
+            }
+            Some("addr") if words.len() == 3 => {
+                let (ip, netmask) = parse_cidr(words[2]);
+                if configure_interface(words[1], ip, netmask) {
+                    // Peers may still map the address to another card
+                    if let Some(interface) = find_interface(words[1]) {
+                        arp::announce(&interface);
+                    }
+                    true
+                } else {
+                    false
+                }
+            }
+            _ => false,
+        }
+    }
+}
+
+impl Resource for RouteResource {
+    fn dup(&self) -> Option<Box<Resource>> {
+        Some(box RouteResource {
+            table: self.table,
+            data: self.data.clone(),
+            seek: self.seek,
+        })
+    }
+
+    fn url(&self) -> Url {
+        Url::from_str("route:")
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> Option<usize> {
+        let count = cmp::min(buf.len(), self.data.len() - self.seek);
+        for i in 0..count {
+            buf[i] = self.data[self.seek + i];
+        }
+        self.seek += count;
+        Some(count)
+    }
+
+    fn write(&mut self, buf: &[u8]) -> Option<usize> {
+        let string = match str::from_utf8(buf) {
+            Ok(string) => string,
+            Err(_) => return None,
+        };
+
+        for line in string.lines() {
+            if !line.trim().is_empty() && !self.command(line.trim()) {
+                return None;
+            }
+        }
+
+        Some(buf.len())
+    }
+
+    fn seek(&mut self, pos: ResourceSeek) -> Option<usize> {
+        match pos {
+            ResourceSeek::Start(offset) => self.seek = cmp::min(self.data.len(), offset),
+            _ => return None,
+        }
+        Some(self.seek)
+    }
+
+    fn sync(&mut self) -> bool {
+        true
+    }
+}
+
+/// The routing scheme
+pub struct RouteScheme {
+    pub table: *mut RoutingTable,
+}
+
+impl KScheme for RouteScheme {
+    fn scheme(&self) -> &str {
+        "route"
+    }
+
+    fn open(&mut self, _: &Url, _: usize) -> Option<Box<Resource>> {
+        let string;
+        unsafe {
+            let reenable = scheduler::start_no_ints();
+            string = (*self.table).to_string();
+            scheduler::end_no_ints(reenable);
+        }
+
+        Some(box RouteResource {
+            table: self.table,
+            data: string.into_bytes(),
+            seek: 0,
+        })
+    }
+}
@@ -2,7 +2,7 @@ use alloc::boxed::Box;
 
 use collections::string::String;
 
-use core::{cmp, mem, ptr};
+use core::{cmp, mem, ptr, str};
 
 use common::event::Event;
 use common::to_num::ToNum;
@@ -13,9 +13,14 @@ use graphics::point::Point;
 use graphics::size::Size;
 use graphics::window::Window;
 
+use scheduler;
+
 use schemes::{KScheme, Resource, ResourceSeek, Url};
 
 /// A window scheme
+///
+/// `window:///X/Y/WIDTH/HEIGHT/TITLE` opens a new window, reads give its events and writes
+/// its pixels. `window://control/ID` takes commands for the window with that id.
 pub struct WindowScheme;
 
 /// A window resource
@@ -34,9 +39,10 @@ impl Resource for WindowResource {
         })
     }
 
-    /// Return the url of this resource
+    /// Return the url of this resource, `window://ID/X/Y/WIDTH/HEIGHT/TITLE`
     fn url(&self) -> Url {
-        Url::from_string(format!("window://{}/{}/{}/{}/{}",
+        Url::from_string(format!("window://{}/{}/{}/{}/{}/{}",
+                                 self.window.id,
                                  self.window.point.x,
                                  self.window.point.y,
                                  self.window.size.width,
@@ -65,7 +71,8 @@ impl Resource for WindowResource {
     fn write(&mut self, buf: &[u8]) -> Option<usize> {
         let content = &mut self.window.content;
 
-        let size = cmp::min(content.size - self.seek, buf.len());
+        // The content may have shrunk since the last seek
+        let size = cmp::min(content.size.saturating_sub(self.seek), buf.len());
         unsafe {
             Display::copy_run(buf.as_ptr() as usize,
                               content.offscreen + self.seek,
@@ -96,6 +103,88 @@ impl Resource for WindowResource {
     }
 }
 
+/// A control resource of a window, each write is one command:
+/// `grab`, `ungrab` or `size WIDTH HEIGHT`, reads give the size of the content as `WIDTH HEIGHT`
+pub struct WindowControlResource {
+    /// The id of the window
+    pub id: usize,
+}
+
+impl WindowControlResource {
+    /// Run `f` on the window, returns None if it was closed
+    fn with_window<T, F: FnOnce(&mut Window) -> T>(&self, f: F) -> Option<T> {
+        unsafe {
+            let reenable = scheduler::start_no_ints();
+            let mut window_ptr = None;
+            for window in (*::session_ptr).windows.iter() {
+                if (**window).id == self.id {
+                    window_ptr = Some(*window);
+                }
+            }
+            scheduler::end_no_ints(reenable);
+
+            match window_ptr {
+                Some(window) => Some(f(&mut *window)),
+                None => None,
+            }
+        }
+    }
+}
+
+impl Resource for WindowControlResource {
+    fn dup(&self) -> Option<Box<Resource>> {
+        Some(box WindowControlResource {
+            id: self.id,
+        })
+    }
+
+    fn url(&self) -> Url {
+        Url::from_string(format!("window://control/{}", self.id))
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> Option<usize> {
+        let size = match self.with_window(|window| window.size) {
+            Some(size) => format!("{} {}", size.width, size.height),
+            None => return None,
+        };
+
+        let count = cmp::min(buf.len(), size.len());
+        for (b, c) in buf.iter_mut().zip(size.bytes()) {
+            *b = c;
+        }
+        Some(count)
+    }
+
+    fn write(&mut self, buf: &[u8]) -> Option<usize> {
+        let command = match str::from_utf8(buf) {
+            Ok(command) => command.trim(),
+            Err(_) => return None,
+        };
+
+        let mut args = command.split(' ');
+        let ok = match args.next() {
+            Some("grab") => self.with_window(|window| window.grab = true).is_some(),
+            Some("ungrab") => self.with_window(|window| window.grab = false).is_some(),
+            Some("size") => {
+                let width = args.next().map_or(0, |arg| arg.to_num());
+                let height = args.next().map_or(0, |arg| arg.to_num());
+                self.with_window(|window| window.set_size(Size::new(width, height))).is_some()
+            }
+            _ => false,
+        };
+
+        if ok {
+            Some(buf.len())
+        } else {
+            None
+        }
+    }
+
+    fn sync(&mut self) -> bool {
+        true
+    }
+}
+
 impl KScheme for WindowScheme {
     fn scheme(&self) -> &str {
         "window"
@@ -104,6 +193,18 @@ impl KScheme for WindowScheme {
     fn open(&mut self, url: &Url, _: usize) -> Option<Box<Resource>> {
         //window://host/path/path/path is the path type we're working with.
         let url_path = parse_path(url.reference());
+
+        if url_path.len() == 2 && url_path[0] == "control" {
+            let id = url_path[1].to_num();
+            let control = box WindowControlResource {
+                id: id,
+            };
+            if control.with_window(|_| ()).is_some() {
+                return Some(control);
+            } else {
+                return None;
+            }
+        }
         let pointx = match url_path.get(0) {
             Some(x) => x.to_num_signed(),
             None => 0,
@@ -42,6 +42,12 @@ pub const SYS_UNLINK: usize = 10;
 pub const SYS_WRITE: usize = 4;
 pub const SYS_YIELD: usize = 158;
 
+//Errors, returned negated in place of a count
+/// Resource temporarily unavailable
+pub const EAGAIN: usize = 11;
+/// Returned by reads of a resource opened with O_NONBLOCK when they would block, -EAGAIN
+pub const WOULD_BLOCK: usize = !EAGAIN + 1;
+
 //Rust Memory
 pub const SYS_ALLOC: usize = 1000;
 pub const SYS_REALLOC: usize = 1001;
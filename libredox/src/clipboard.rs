@@ -0,0 +1,63 @@
+//! The clipboard shared between applications
+//!
+//! The clipboard holds a selection for each MIME type, through the `clipboard:` scheme.
+//! Copying text replaces everything on the clipboard, so that an older image is not pasted
+//! in its place.
+
+use fs::File;
+use io::{Read, Write};
+use string::{String, ToString};
+use vec::Vec;
+
+/// The MIME type of text
+pub const TEXT: &'static str = "text/plain";
+
+/// The types on the clipboard
+pub fn types() -> Vec<String> {
+    let mut string = String::new();
+    if let Some(mut file) = File::open("clipboard:") {
+        file.read_to_string(&mut string);
+    }
+    string.lines()
+          .filter_map(|line| line.split(' ').next())
+          .filter(|mime| !mime.is_empty())
+          .map(|mime| mime.to_string())
+          .collect()
+}
+
+/// The selection of a type, None if it is not on the clipboard
+pub fn get(mime: &str) -> Option<Vec<u8>> {
+    match File::open(&("clipboard:".to_string() + mime)) {
+        Some(mut file) => {
+            let mut data = Vec::new();
+            match file.read_to_end(&mut data) {
+                Some(_) => Some(data),
+                None => None,
+            }
+        }
+        None => None,
+    }
+}
+
+/// Replace the selection of a type, leaving the other types
+pub fn set(mime: &str, data: &[u8]) -> bool {
+    match File::create(&("clipboard:".to_string() + mime)) {
+        Some(mut file) => file.write(data) == Some(data.len()) && file.sync(),
+        None => false,
+    }
+}
+
+/// Remove every selection
+pub fn clear() -> bool {
+    File::create("clipboard:").is_some()
+}
+
+/// The text on the clipboard
+pub fn get_text() -> Option<String> {
+    get(TEXT).and_then(|data| String::from_utf8(data).ok())
+}
+
+/// Replace the clipboard with text
+pub fn set_text(text: &str) -> bool {
+    clear() && set(TEXT, text.as_bytes())
+}
@@ -367,6 +367,10 @@ pub use core_collections::{LinkedList, VecDeque};
 pub use core_collections::{binary_heap, btree_map, btree_set};
 pub use core_collections::{linked_list, vec_deque};
 
+// Code the kernel includes with #[path], like graphics::decode, finds String and Vec where the
+// kernel has them
+pub use core_collections::{string, vec};
+
 /*
 pub use self::hash_map::HashMap;
 pub use self::hash_set::HashSet;
@@ -3,6 +3,7 @@ use alloc::boxed::Box;
 use string::{String, ToString};
 use vec::Vec;
 
+use clipboard;
 use event::*;
 
 use orbital::*;
@@ -114,6 +115,8 @@ pub struct ConsoleWindow {
     pub scroll_y: isize,
     /// Wrap the text, if true
     pub wrap: bool,
+    /// Control is held, for copying and pasting
+    pub ctrl: bool,
 }
 
 impl ConsoleWindow {
@@ -128,6 +131,7 @@ impl ConsoleWindow {
             scroll_x: 0,
             scroll_y: 0,
             wrap: true,
+            ctrl: false,
         }
     }
 
@@ -160,6 +164,9 @@ impl ConsoleWindow {
     pub fn read(&mut self) -> Option<String> {
         while let Some(event) = self.poll() {
             if let EventOption::Key(key_event) = event.to_option() {
+                if key_event.scancode == K_CTRL {
+                    self.ctrl = key_event.pressed;
+                }
                 if key_event.pressed {
                     match key_event.scancode {
                         K_BKSP => if self.offset > 0 {
@@ -194,6 +201,10 @@ impl ConsoleWindow {
                         }
                         _ => match key_event.character {
                             '\x00' => (),
+                            'c' if self.ctrl => {
+                                clipboard::set_text(&self.history[self.history_i]);
+                            }
+                            'v' if self.ctrl => self.paste(),
                             '\n' => {
                                 let command = self.history[self.history_i].clone();
                                 self.offset = 0;
@@ -223,6 +234,19 @@ impl ConsoleWindow {
         return None;
     }
 
+    /// Insert the first line of the text on the clipboard at the cursor
+    ///
+    /// The cursor moves a byte at a time, like typed keys, so only ASCII is pasted
+    fn paste(&mut self) {
+        if let Some(text) = clipboard::get_text() {
+            let line: String = text.lines().next().unwrap_or("").chars().filter(|&c| (c as u32) < 0x80).collect();
+            self.history[self.history_i] = self.history[self.history_i][0 .. self.offset].to_string() +
+                                           &line +
+                                           &self.history[self.history_i][self.offset ..];
+            self.offset += line.len();
+        }
+    }
+
     /// Redraw the window
     pub fn sync(&mut self) {
         let scroll_x = self.scroll_x;
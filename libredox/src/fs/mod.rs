@@ -87,6 +87,7 @@ impl File {
 }
 
 impl Read for File {
+    /// Read into the buffer, None on errors and `WOULD_BLOCK` when a non-blocking read would block
     fn read(&mut self, buf: &mut [u8]) -> Option<usize> {
         unsafe {
             let count = sys_read(self.fd, buf.as_mut_ptr(), buf.len());
@@ -0,0 +1,590 @@
+use cmp;
+
+use vec::Vec;
+
+use super::color::Color;
+use super::font::{FontSet, Glyph};
+use super::raster::{ceil, floor, Point, Raster};
+
+/// How a shape is filled, with a color or a gradient
+pub trait Paint {
+    /// The color at a point, in pixels from the top left of the canvas
+    fn color(&self, x: f32, y: f32) -> Color;
+}
+
+impl Paint for Color {
+    #[allow(unused_variables)]
+    fn color(&self, x: f32, y: f32) -> Color {
+        *self
+    }
+}
+
+/// Mix two colors, from all of `a` at 0 to all of `b` at 1
+fn mix(a: Color, b: Color, t: f32) -> Color {
+    let channel = |shift: u32| -> u32 {
+        let a = ((a.data >> shift) & 0xFF) as f32;
+        let b = ((b.data >> shift) & 0xFF) as f32;
+        ((a + (b - a) * t + 0.5) as u32) << shift
+    };
+    Color { data: channel(24) | channel(16) | channel(8) | channel(0) }
+}
+
+#[derive(Copy, Clone)]
+enum GradientShape {
+    /// From a point along a direction, scaled so that the end of the gradient is at 1
+    Linear { x: f32, y: f32, dx: f32, dy: f32 },
+    /// Out from a center to a radius
+    Radial { x: f32, y: f32, radius: f32 },
+}
+
+/// A gradient between colors at stops from 0 to 1
+///
+/// Use a reference to it as the `Paint` of a shape.
+pub struct Gradient {
+    shape: GradientShape,
+    stops: Vec<(f32, Color)>,
+}
+
+impl Gradient {
+    /// A gradient from `start` at the first point to `end` at the second
+    pub fn linear(x1: isize, y1: isize, x2: isize, y2: isize, start: Color, end: Color) -> Self {
+        let dx = (x2 - x1) as f32;
+        let dy = (y2 - y1) as f32;
+        let length = dx * dx + dy * dy;
+        let (dx, dy) = if length > 0.0 {
+            (dx / length, dy / length)
+        } else {
+            (0.0, 0.0)
+        };
+
+        Gradient {
+            shape: GradientShape::Linear {
+                x: x1 as f32 + 0.5,
+                y: y1 as f32 + 0.5,
+                dx: dx,
+                dy: dy,
+            },
+            stops: vec![(0.0, start), (1.0, end)],
+        }
+    }
+
+    /// A gradient from `inner` at the center to `outer` at the radius and past it
+    pub fn radial(x: isize, y: isize, radius: usize, inner: Color, outer: Color) -> Self {
+        Gradient {
+            shape: GradientShape::Radial {
+                x: x as f32 + 0.5,
+                y: y as f32 + 0.5,
+                radius: radius as f32,
+            },
+            stops: vec![(0.0, inner), (1.0, outer)],
+        }
+    }
+
+    /// Add a color at a position from 0 to 1
+    pub fn add_stop(&mut self, position: f32, color: Color) {
+        let position = if position < 0.0 {
+            0.0
+        } else if position > 1.0 {
+            1.0
+        } else {
+            position
+        };
+        let i = match self.stops.iter().position(|stop| stop.0 > position) {
+            Some(i) => i,
+            None => self.stops.len(),
+        };
+        self.stops.insert(i, (position, color));
+    }
+
+    /// The color at a position along the gradient
+    fn at(&self, t: f32) -> Color {
+        let mut previous = self.stops[0];
+        for &stop in self.stops.iter() {
+            if t <= stop.0 {
+                if stop.0 > previous.0 && t > previous.0 {
+                    return mix(previous.1, stop.1, (t - previous.0) / (stop.0 - previous.0));
+                }
+                return stop.1;
+            }
+            previous = stop;
+        }
+        previous.1
+    }
+}
+
+impl<'a> Paint for &'a Gradient {
+    fn color(&self, x: f32, y: f32) -> Color {
+        let t = match self.shape {
+            GradientShape::Linear { x: x0, y: y0, dx, dy } => (x - x0) * dx + (y - y0) * dy,
+            GradientShape::Radial { x: x0, y: y0, radius } => {
+                if radius > 0.0 {
+                    ((x - x0) * (x - x0) + (y - y0) * (y - y0)).sqrt() / radius
+                } else {
+                    1.0
+                }
+            }
+        };
+        self.at(t)
+    }
+}
+
+/// Add a quarter of an ellipse to a contour, going clockwise from the axis at `quarter` right
+/// angles from the right
+fn arc(contour: &mut Vec<Point>, x: f32, y: f32, rx: f32, ry: f32, quarter: usize) {
+    let (mut cos, mut sin) = match quarter % 4 {
+        0 => (1.0, 0.0),
+        1 => (0.0, 1.0),
+        2 => (-1.0, 0.0),
+        _ => (0.0, -1.0),
+    };
+
+    let radius = if rx > ry {
+        rx
+    } else {
+        ry
+    };
+    if radius <= 0.0 {
+        contour.push(Point::new(x, y));
+        return;
+    }
+
+    // Enough segments to keep the error under a tenth of a pixel
+    let segments = 1 + ceil(3.5 * radius.sqrt()) as usize;
+    let angle = 1.5707964 / segments as f32;
+    let step_cos = 1.0 - angle * angle / 2.0 + angle * angle * angle * angle / 24.0;
+    let step_sin = angle - angle * angle * angle / 6.0;
+
+    for _ in 0..segments {
+        contour.push(Point::new(x + rx * cos, y + ry * sin));
+        let next_cos = cos * step_cos - sin * step_sin;
+        sin = cos * step_sin + sin * step_cos;
+        cos = next_cos;
+    }
+}
+
+/// The outline of a rectangle with rounded corners, clockwise
+fn rounded(left: f32, top: f32, right: f32, bottom: f32, radius: f32) -> Vec<Point> {
+    let half = if right - left < bottom - top {
+        (right - left) / 2.0
+    } else {
+        (bottom - top) / 2.0
+    };
+    let r = if radius > half {
+        half
+    } else if radius < 0.0 {
+        0.0
+    } else {
+        radius
+    };
+
+    let mut contour = Vec::new();
+    arc(&mut contour, right - r, top + r, r, r, 3);
+    arc(&mut contour, right - r, bottom - r, r, r, 0);
+    arc(&mut contour, left + r, bottom - r, r, r, 1);
+    arc(&mut contour, left + r, top + r, r, r, 2);
+    contour
+}
+
+/// The outline of a line with some width between the centers of two pixels
+fn stroke(x1: isize, y1: isize, x2: isize, y2: isize, width: f32) -> Vec<Point> {
+    let (x1, y1) = (x1 as f32 + 0.5, y1 as f32 + 0.5);
+    let (x2, y2) = (x2 as f32 + 0.5, y2 as f32 + 0.5);
+
+    let dx = x2 - x1;
+    let dy = y2 - y1;
+    let length = (dx * dx + dy * dy).sqrt();
+    // A line that goes nowhere is a square dot
+    let (ux, uy) = if length > 0.0 {
+        (dx / length, dy / length)
+    } else {
+        (1.0, 0.0)
+    };
+
+    // Half of the width across the line, and half a pixel past each end like a square cap
+    let (nx, ny) = (-uy * width / 2.0, ux * width / 2.0);
+    let (ex, ey) = (ux / 2.0, uy / 2.0);
+
+    vec![Point::new(x1 - ex + nx, y1 - ey + ny),
+         Point::new(x2 + ex + nx, y2 + ey + ny),
+         Point::new(x2 + ex - nx, y2 + ey - ny),
+         Point::new(x1 - ex - nx, y1 - ey - ny)]
+}
+
+/// Pixels to draw on, with a clip rectangle
+///
+/// Shapes are anti-aliased and blended by the alpha of their paint. `pixel`, `rect`, `set`,
+/// `image` and `blit` replace the pixels instead. Offscreen canvases start transparent and can
+/// be drawn onto another canvas with `blit_alpha`.
+pub struct Canvas {
+    width: usize,
+    height: usize,
+    data: Vec<u32>,
+    /// The left, top, right and bottom of the clip
+    clip: (isize, isize, isize, isize),
+}
+
+impl Canvas {
+    /// Create a transparent canvas
+    pub fn new(width: usize, height: usize) -> Self {
+        Canvas::from_data(width, height, vec![0; width * height])
+    }
+
+    /// Create a canvas from pixels as 0xAARRGGBB, row by row
+    pub fn from_data(width: usize, height: usize, mut data: Vec<u32>) -> Self {
+        data.resize(width * height, 0);
+        Canvas {
+            width: width,
+            height: height,
+            data: data,
+            clip: (0, 0, width as isize, height as isize),
+        }
+    }
+
+    /// The width of the canvas
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// The height of the canvas
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// The pixels as 0xAARRGGBB, row by row
+    pub fn data(&self) -> &[u32] {
+        &self.data
+    }
+
+    /// The pixels as 0xAARRGGBB, row by row
+    pub fn data_mut(&mut self) -> &mut [u32] {
+        &mut self.data
+    }
+
+    /// Only draw inside of a rectangle until the clip is reset
+    pub fn set_clip(&mut self, x: isize, y: isize, w: usize, h: usize) {
+        let clamp = |value: isize, max: usize| -> isize {
+            if value < 0 {
+                0
+            } else if value > max as isize {
+                max as isize
+            } else {
+                value
+            }
+        };
+
+        let left = clamp(x, self.width);
+        let top = clamp(y, self.height);
+        let right = clamp(x + w as isize, self.width);
+        let bottom = clamp(y + h as isize, self.height);
+        self.clip = (left, top, right, bottom);
+    }
+
+    /// Draw on the whole canvas again
+    pub fn reset_clip(&mut self) {
+        self.clip = (0, 0, self.width as isize, self.height as isize);
+    }
+
+    /// The x, y, width and height of the clip
+    pub fn clip(&self) -> (isize, isize, usize, usize) {
+        let (left, top, right, bottom) = self.clip;
+        (left, top, (right - left) as usize, (bottom - top) as usize)
+    }
+
+    fn inside(&self, x: isize, y: isize) -> bool {
+        let (left, top, right, bottom) = self.clip;
+        x >= left && y >= top && x < right && y < bottom
+    }
+
+    /// Get the color of a pixel
+    pub fn get(&self, x: isize, y: isize) -> Option<Color> {
+        if x >= 0 && y >= 0 && x < self.width as isize && y < self.height as isize {
+            Some(Color { data: self.data[y as usize * self.width + x as usize] })
+        } else {
+            None
+        }
+    }
+
+    /// Set a pixel
+    pub fn pixel(&mut self, x: isize, y: isize, color: Color) {
+        if self.inside(x, y) {
+            let offset = y as usize * self.width + x as usize;
+            self.data[offset] = color.data;
+        }
+    }
+
+    /// Blend a color over a pixel, with an extra alpha for coverage
+    pub fn blend(&mut self, x: isize, y: isize, color: Color, alpha: u8) {
+        if !self.inside(x, y) {
+            return;
+        }
+
+        let offset = y as usize * self.width + x as usize;
+        let alpha = (color.data >> 24) * alpha as u32 / 255;
+        if alpha >= 255 {
+            self.data[offset] = color.data | 0xFF000000;
+        } else if alpha > 0 {
+            let old = self.data[offset];
+            let old_alpha = (old >> 24) * (255 - alpha) / 255;
+            let new_alpha = alpha + old_alpha;
+            let mix = |shift: u32| -> u32 {
+                let new = (color.data >> shift) & 0xFF;
+                let old = (old >> shift) & 0xFF;
+                ((new * alpha + old * old_alpha) / new_alpha) << shift
+            };
+            self.data[offset] = new_alpha << 24 | mix(16) | mix(8) | mix(0);
+        }
+    }
+
+    /// Set the whole canvas, inside of the clip, to a color
+    pub fn set(&mut self, color: Color) {
+        let (x, y, w, h) = self.clip();
+        self.rect(x, y, w, h, color);
+    }
+
+    /// Set a rectangle to a color
+    pub fn rect(&mut self, x: isize, y: isize, w: usize, h: usize, color: Color) {
+        let (left, top, right, bottom) = self.clip;
+        let start_x = cmp::max(x, left);
+        let start_y = cmp::max(y, top);
+        let end_x = cmp::min(x + w as isize, right);
+        let end_y = cmp::min(y + h as isize, bottom);
+
+        for row in start_y..end_y {
+            let line = row as usize * self.width;
+            for col in start_x..end_x {
+                self.data[line + col as usize] = color.data;
+            }
+        }
+    }
+
+    /// Fill contours, where holes go the other way around from the shape they are in
+    fn fill_path<P: Paint>(&mut self, contours: &[Vec<Point>], paint: P) {
+        let (clip_left, clip_top, clip_right, clip_bottom) = self.clip;
+
+        let mut min = Point::new(0.0, 0.0);
+        let mut max = Point::new(0.0, 0.0);
+        let mut first = true;
+        for point in contours.iter().flat_map(|contour| contour.iter()) {
+            if first || point.x < min.x {
+                min.x = point.x;
+            }
+            if first || point.y < min.y {
+                min.y = point.y;
+            }
+            if first || point.x > max.x {
+                max.x = point.x;
+            }
+            if first || point.y > max.y {
+                max.y = point.y;
+            }
+            first = false;
+        }
+        if first {
+            return;
+        }
+
+        // The raster has a spare column on each side, as coverage must not run into the next
+        // row. Rows outside of the clip are left out.
+        let left = floor(min.x) as isize - 1;
+        let right = ceil(max.x) as isize + 1;
+        let top = cmp::max(floor(min.y) as isize, clip_top);
+        let bottom = cmp::min(ceil(max.y) as isize, clip_bottom);
+        if right <= clip_left || left >= clip_right || bottom <= top {
+            return;
+        }
+
+        let w = (right - left) as usize;
+        let h = (bottom - top) as usize;
+        let mut raster = Raster::new(w, h);
+        for contour in contours.iter() {
+            for i in 0..contour.len() {
+                let a = contour[i];
+                let b = contour[(i + 1) % contour.len()];
+                raster.draw_line(Point::new(a.x - left as f32, a.y - top as f32),
+                                 Point::new(b.x - left as f32, b.y - top as f32));
+            }
+        }
+
+        let coverage = raster.coverage();
+        let start_x = cmp::max(left, clip_left);
+        let end_x = cmp::min(right, clip_right);
+        for row in 0..h {
+            let y = top + row as isize;
+            for x in start_x..end_x {
+                let alpha = coverage[row * w + (x - left) as usize];
+                if alpha > 0 {
+                    let color = paint.color(x as f32 + 0.5, y as f32 + 0.5);
+                    self.blend(x, y, color, alpha);
+                }
+            }
+        }
+    }
+
+    /// Draw a line one pixel wide between the centers of two pixels
+    pub fn line<P: Paint>(&mut self, x1: isize, y1: isize, x2: isize, y2: isize, paint: P) {
+        self.wide_line(x1, y1, x2, y2, 1, paint);
+    }
+
+    /// Draw a line between the centers of two pixels
+    pub fn wide_line<P: Paint>(&mut self,
+                               x1: isize,
+                               y1: isize,
+                               x2: isize,
+                               y2: isize,
+                               width: usize,
+                               paint: P) {
+        self.fill_path(&[stroke(x1, y1, x2, y2, width as f32)], paint);
+    }
+
+    /// Draw lines joining points, and the last point to the first
+    pub fn polygon<P: Paint>(&mut self, points: &[(isize, isize)], width: usize, paint: P) {
+        let mut contours = Vec::with_capacity(points.len());
+        for i in 0..points.len() {
+            let (x1, y1) = points[i];
+            let (x2, y2) = points[(i + 1) % points.len()];
+            contours.push(stroke(x1, y1, x2, y2, width as f32));
+        }
+        self.fill_path(&contours, paint);
+    }
+
+    /// Fill a polygon through the centers of pixels
+    pub fn fill_polygon<P: Paint>(&mut self, points: &[(isize, isize)], paint: P) {
+        let contour = points.iter()
+                            .map(|&(x, y)| Point::new(x as f32 + 0.5, y as f32 + 0.5))
+                            .collect::<Vec<Point>>();
+        self.fill_path(&[contour], paint);
+    }
+
+    /// Draw a circle one pixel wide around the center of a pixel
+    pub fn circle<P: Paint>(&mut self, x: isize, y: isize, radius: usize, paint: P) {
+        let (cx, cy) = (x as f32 + 0.5, y as f32 + 0.5);
+        let outer = radius as f32 + 0.5;
+        let inner = radius as f32 - 0.5;
+
+        let outline = rounded(cx - outer, cy - outer, cx + outer, cy + outer, outer);
+        let contours = if inner > 0.0 {
+            let mut hole = rounded(cx - inner, cy - inner, cx + inner, cy + inner, inner);
+            hole.reverse();
+            vec![outline, hole]
+        } else {
+            vec![outline]
+        };
+        self.fill_path(&contours, paint);
+    }
+
+    /// Fill a circle around the center of a pixel
+    pub fn fill_circle<P: Paint>(&mut self, x: isize, y: isize, radius: usize, paint: P) {
+        let (cx, cy) = (x as f32 + 0.5, y as f32 + 0.5);
+        let r = radius as f32 + 0.5;
+        self.fill_path(&[rounded(cx - r, cy - r, cx + r, cy + r, r)], paint);
+    }
+
+    /// Draw the inside edge of a rectangle with rounded corners, one pixel wide
+    pub fn rounded_rect<P: Paint>(&mut self,
+                                  x: isize,
+                                  y: isize,
+                                  w: usize,
+                                  h: usize,
+                                  radius: usize,
+                                  paint: P) {
+        let (left, top) = (x as f32, y as f32);
+        let (right, bottom) = (left + w as f32, top + h as f32);
+        let r = radius as f32;
+
+        let outline = rounded(left, top, right, bottom, r);
+        let contours = if w > 2 && h > 2 {
+            let mut hole = rounded(left + 1.0, top + 1.0, right - 1.0, bottom - 1.0, r - 1.0);
+            hole.reverse();
+            vec![outline, hole]
+        } else {
+            vec![outline]
+        };
+        self.fill_path(&contours, paint);
+    }
+
+    /// Fill a rectangle with rounded corners
+    pub fn fill_rounded_rect<P: Paint>(&mut self,
+                                       x: isize,
+                                       y: isize,
+                                       w: usize,
+                                       h: usize,
+                                       radius: usize,
+                                       paint: P) {
+        let (left, top) = (x as f32, y as f32);
+        let outline = rounded(left, top, left + w as f32, top + h as f32, radius as f32);
+        self.fill_path(&[outline], paint);
+    }
+
+    /// Fill a rectangle, blending it if the paint is not opaque
+    pub fn fill_rect<P: Paint>(&mut self, x: isize, y: isize, w: usize, h: usize, paint: P) {
+        self.fill_rounded_rect(x, y, w, h, 0, paint);
+    }
+
+    /// Set a rectangle to an image
+    pub fn image(&mut self, x: isize, y: isize, w: usize, h: usize, data: &[Color]) {
+        for row in 0..h {
+            for col in 0..w {
+                if let Some(color) = data.get(row * w + col) {
+                    self.pixel(x + col as isize, y + row as isize, *color);
+                }
+            }
+        }
+    }
+
+    /// Blend an image by its alpha
+    pub fn image_alpha(&mut self, x: isize, y: isize, w: usize, h: usize, data: &[Color]) {
+        for row in 0..h {
+            for col in 0..w {
+                if let Some(color) = data.get(row * w + col) {
+                    self.blend(x + col as isize, y + row as isize, *color, 255);
+                }
+            }
+        }
+    }
+
+    /// Copy another canvas, with its top left at x and y
+    pub fn blit(&mut self, x: isize, y: isize, source: &Canvas) {
+        for row in 0..source.height {
+            for col in 0..source.width {
+                let data = source.data[row * source.width + col];
+                self.pixel(x + col as isize, y + row as isize, Color { data: data });
+            }
+        }
+    }
+
+    /// Blend another canvas by its alpha, with its top left at x and y
+    pub fn blit_alpha(&mut self, x: isize, y: isize, source: &Canvas) {
+        for row in 0..source.height {
+            for col in 0..source.width {
+                let data = source.data[row * source.width + col];
+                self.blend(x + col as isize, y + row as isize, Color { data: data }, 255);
+            }
+        }
+    }
+
+    /// Draw a glyph with its pen position at x and its baseline at y
+    pub fn glyph(&mut self, x: isize, y: isize, glyph: &Glyph, color: Color) {
+        let left = x + glyph.left;
+        let top = y - glyph.top;
+        for row in 0..glyph.height {
+            for col in 0..glyph.width {
+                let coverage = glyph.coverage[row * glyph.width + col];
+                self.blend(left + col as isize, top + row as isize, color, coverage);
+            }
+        }
+    }
+
+    /// Draw text from `fonts` at `size`, with the top left of the first line at x and y
+    pub fn text(&mut self,
+                x: isize,
+                y: isize,
+                text: &str,
+                size: usize,
+                color: Color,
+                fonts: &mut FontSet) {
+        let layout = fonts.layout(text, size, None);
+        for placed in layout.glyphs.iter() {
+            self.glyph(x + placed.x, y + placed.y, fonts.glyph(placed.c, size), color);
+        }
+    }
+}
@@ -0,0 +1,259 @@
+use collections::vec::Vec;
+
+/// The base lengths and extra bits of length codes 257 to 285
+const LENGTH_BASE: [u16; 29] = [3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43,
+                                51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258];
+const LENGTH_EXTRA: [u8; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4,
+                                4, 4, 5, 5, 5, 5, 0];
+
+/// The base distances and extra bits of distance codes 0 to 29
+const DIST_BASE: [u16; 30] = [1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385,
+                              513, 769, 1025, 1537, 2049, 3073, 4097, 6145, 8193, 12289, 16385,
+                              24577];
+const DIST_EXTRA: [u8; 30] = [0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10,
+                              10, 11, 11, 12, 12, 13, 13];
+
+/// The order code length code lengths are sent in
+const CLEN_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+/// A canonical Huffman code, as the number of codes of each length and the symbols in code order
+struct Huffman {
+    counts: [u16; 16],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Option<Self> {
+        let mut counts = [0; 16];
+        for &length in lengths.iter() {
+            counts[length as usize] += 1;
+        }
+        counts[0] = 0;
+
+        // Over-subscribed codes cannot be decoded, incomplete ones are allowed
+        let mut left = 1i32;
+        for length in 1..16 {
+            left <<= 1;
+            left -= counts[length] as i32;
+            if left < 0 {
+                return None;
+            }
+        }
+
+        let mut offsets = [0; 16];
+        for length in 1..15 {
+            offsets[length + 1] = offsets[length] + counts[length];
+        }
+
+        let mut symbols = vec![0; lengths.len()];
+        for (symbol, &length) in lengths.iter().enumerate() {
+            if length != 0 {
+                symbols[offsets[length as usize] as usize] = symbol as u16;
+                offsets[length as usize] += 1;
+            }
+        }
+
+        Some(Huffman {
+            counts: counts,
+            symbols: symbols,
+        })
+    }
+}
+
+/// Reads bits from the least significant end of each byte
+struct Bits<'a> {
+    data: &'a [u8],
+    offset: usize,
+    buffer: u32,
+    count: u32,
+}
+
+impl<'a> Bits<'a> {
+    fn bits(&mut self, need: u32) -> Option<u32> {
+        while self.count < need {
+            let byte = try_option!(self.data.get(self.offset));
+            self.offset += 1;
+            self.buffer |= (*byte as u32) << self.count;
+            self.count += 8;
+        }
+
+        let value = self.buffer & ((1 << need) - 1);
+        self.buffer >>= need;
+        self.count -= need;
+        Some(value)
+    }
+
+    fn decode(&mut self, huffman: &Huffman) -> Option<u16> {
+        // Codes are sent most significant bit first
+        let mut code = 0i32;
+        let mut first = 0i32;
+        let mut index = 0i32;
+        for length in 1..16 {
+            code |= try_option!(self.bits(1)) as i32;
+            let count = huffman.counts[length] as i32;
+            if code - count < first {
+                return huffman.symbols.get((index + code - first) as usize).map(|s| *s);
+            }
+            index += count;
+            first += count;
+            first <<= 1;
+            code <<= 1;
+        }
+        None
+    }
+
+    fn align(&mut self) {
+        self.buffer = 0;
+        self.count = 0;
+    }
+}
+
+fn inflate_block(bits: &mut Bits,
+                 output: &mut Vec<u8>,
+                 lengths: &Huffman,
+                 distances: &Huffman)
+                 -> Option<()> {
+    loop {
+        let symbol = try_option!(bits.decode(lengths)) as usize;
+        if symbol < 256 {
+            output.push(symbol as u8);
+        } else if symbol == 256 {
+            return Some(());
+        } else {
+            let symbol = symbol - 257;
+            if symbol >= 29 {
+                return None;
+            }
+            let length = LENGTH_BASE[symbol] as usize +
+                         try_option!(bits.bits(LENGTH_EXTRA[symbol] as u32)) as usize;
+
+            let symbol = try_option!(bits.decode(distances)) as usize;
+            if symbol >= 30 {
+                return None;
+            }
+            let distance = DIST_BASE[symbol] as usize +
+                           try_option!(bits.bits(DIST_EXTRA[symbol] as u32)) as usize;
+            if distance > output.len() {
+                return None;
+            }
+
+            // The copy may overlap what it is writing
+            let start = output.len() - distance;
+            for i in 0..length {
+                let byte = output[start + i];
+                output.push(byte);
+            }
+        }
+    }
+}
+
+fn fixed_codes() -> (Huffman, Huffman) {
+    let mut lengths = [0; 288];
+    for i in 0..144 {
+        lengths[i] = 8;
+    }
+    for i in 144..256 {
+        lengths[i] = 9;
+    }
+    for i in 256..280 {
+        lengths[i] = 7;
+    }
+    for i in 280..288 {
+        lengths[i] = 8;
+    }
+    (Huffman::new(&lengths).unwrap(), Huffman::new(&[5; 30]).unwrap())
+}
+
+fn dynamic_codes(bits: &mut Bits) -> Option<(Huffman, Huffman)> {
+    let literals = try_option!(bits.bits(5)) as usize + 257;
+    let distances = try_option!(bits.bits(5)) as usize + 1;
+    let clens = try_option!(bits.bits(4)) as usize + 4;
+
+    let mut clen_lengths = [0; 19];
+    for i in 0..clens {
+        clen_lengths[CLEN_ORDER[i]] = try_option!(bits.bits(3)) as u8;
+    }
+    let clen = try_option!(Huffman::new(&clen_lengths));
+
+    let mut lengths = Vec::with_capacity(literals + distances);
+    while lengths.len() < literals + distances {
+        let symbol = try_option!(bits.decode(&clen));
+        let (value, repeat) = match symbol {
+            0...15 => (symbol as u8, 1),
+            16 => {
+                let previous = *try_option!(lengths.last());
+                (previous, 3 + try_option!(bits.bits(2)))
+            }
+            17 => (0, 3 + try_option!(bits.bits(3))),
+            _ => (0, 11 + try_option!(bits.bits(7))),
+        };
+        for _ in 0..repeat {
+            lengths.push(value);
+        }
+    }
+    if lengths.len() != literals + distances || lengths[256] == 0 {
+        return None;
+    }
+
+    Some((try_option!(Huffman::new(&lengths[..literals])),
+          try_option!(Huffman::new(&lengths[literals..]))))
+}
+
+/// Decompress raw DEFLATE data
+pub fn inflate(data: &[u8]) -> Option<Vec<u8>> {
+    let mut bits = Bits {
+        data: data,
+        offset: 0,
+        buffer: 0,
+        count: 0,
+    };
+    let mut output = Vec::new();
+
+    loop {
+        let last = try_option!(bits.bits(1)) == 1;
+        match try_option!(bits.bits(2)) {
+            0 => {
+                bits.align();
+                let offset = bits.offset;
+                if offset + 4 > data.len() {
+                    return None;
+                }
+                let length = data[offset] as usize | (data[offset + 1] as usize) << 8;
+                let start = offset + 4;
+                if start + length > data.len() {
+                    return None;
+                }
+                output.push_all(&data[start..start + length]);
+                bits.offset = start + length;
+            }
+            1 => {
+                let (lengths, distances) = fixed_codes();
+                try_option!(inflate_block(&mut bits, &mut output, &lengths, &distances));
+            }
+            2 => {
+                let (lengths, distances) = try_option!(dynamic_codes(&mut bits));
+                try_option!(inflate_block(&mut bits, &mut output, &lengths, &distances));
+            }
+            _ => return None,
+        }
+
+        if last {
+            return Some(output);
+        }
+    }
+}
+
+/// Decompress a zlib stream, without checking the Adler-32 at the end
+pub fn zlib_decompress(data: &[u8]) -> Option<Vec<u8>> {
+    if data.len() < 2 {
+        return None;
+    }
+
+    let cmf = data[0];
+    let flags = data[1];
+    if cmf & 0x0F != 8 || (cmf as u16 * 256 + flags as u16) % 31 != 0 || flags & 0x20 != 0 {
+        return None;
+    }
+
+    inflate(&data[2..])
+}
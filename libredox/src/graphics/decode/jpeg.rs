@@ -0,0 +1,849 @@
+use collections::vec::Vec;
+
+/// The first bytes of every JPEG, the start of image marker and the next marker
+pub const SIGNATURE: [u8; 3] = [0xFF, 0xD8, 0xFF];
+
+/// The natural index of each coefficient in zigzag order, with extra entries for corrupt runs
+const ZIGZAG: [usize; 80] = [0, 1, 8, 16, 9, 2, 3, 10, 17, 24, 32, 25, 18, 11, 4, 5, 12, 19, 26,
+                             33, 40, 48, 41, 34, 27, 20, 13, 6, 7, 14, 21, 28, 35, 42, 49, 56, 57,
+                             50, 43, 36, 29, 22, 15, 23, 30, 37, 44, 51, 58, 59, 52, 45, 38, 31,
+                             39, 46, 53, 60, 61, 54, 47, 55, 62, 63, 63, 63, 63, 63, 63, 63, 63,
+                             63, 63, 63, 63, 63, 63, 63, 63, 63];
+
+/// Images larger than this are refused rather than risking running out of memory
+const MAX_PIXELS: usize = 1 << 26;
+
+/// A Huffman table, as the number of codes of each length and the symbols in code order
+struct Huffman {
+    counts: [u16; 17],
+    symbols: Vec<u8>,
+}
+
+/// Reads the bits of entropy coded data, most significant first, removing stuffed zeros
+struct Reader<'a> {
+    data: &'a [u8],
+    offset: usize,
+    buffer: u32,
+    count: u32,
+}
+
+impl<'a> Reader<'a> {
+    fn fill(&mut self) {
+        while self.count <= 24 {
+            let mut byte = 0;
+            if self.offset < self.data.len() {
+                byte = self.data[self.offset];
+                if byte == 0xFF {
+                    let next = match self.data.get(self.offset + 1) {
+                        Some(next) => *next,
+                        None => 0xD9,
+                    };
+                    if next == 0 {
+                        self.offset += 2;
+                    } else {
+                        // A marker, which ends the data, so pad with zeros until it is handled
+                        byte = 0;
+                    }
+                } else {
+                    self.offset += 1;
+                }
+            }
+            self.buffer |= (byte as u32) << (24 - self.count);
+            self.count += 8;
+        }
+    }
+
+    fn bits(&mut self, count: u32) -> u32 {
+        if count == 0 {
+            return 0;
+        }
+        self.fill();
+        let value = self.buffer >> (32 - count);
+        self.buffer <<= count;
+        self.count -= count;
+        value
+    }
+
+    fn bit(&mut self) -> bool {
+        self.bits(1) == 1
+    }
+
+    /// Read a value of `count` bits, with the sign in the first bit
+    fn receive_extend(&mut self, count: u32) -> i32 {
+        if count == 0 || count > 16 {
+            return 0;
+        }
+        let value = self.bits(count) as i32;
+        if value < 1 << (count - 1) {
+            value - (1 << count) + 1
+        } else {
+            value
+        }
+    }
+
+    fn decode(&mut self, huffman: &Huffman) -> Option<u8> {
+        let mut code = 0i32;
+        let mut first = 0i32;
+        let mut index = 0i32;
+        for length in 1..17 {
+            code |= self.bits(1) as i32;
+            let count = huffman.counts[length] as i32;
+            if code - count < first {
+                return huffman.symbols.get((index + code - first) as usize).map(|s| *s);
+            }
+            index += count;
+            first += count;
+            first <<= 1;
+            code <<= 1;
+        }
+        None
+    }
+
+    /// Drop the bits left before a restart marker, and skip it
+    fn restart(&mut self) {
+        self.buffer = 0;
+        self.count = 0;
+        while self.offset + 1 < self.data.len() {
+            if self.data[self.offset] == 0xFF && self.data[self.offset + 1] >= 0xD0 &&
+               self.data[self.offset + 1] <= 0xD7 {
+                self.offset += 2;
+                return;
+            }
+            self.offset += 1;
+        }
+    }
+}
+
+struct Component {
+    id: u8,
+    h: usize,
+    v: usize,
+    quant: usize,
+    /// The blocks in each row and column, covering whole MCUs
+    blocks_w: usize,
+    blocks_h: usize,
+    /// The coefficients of each block, in natural order
+    coefficients: Vec<i16>,
+    dc_table: usize,
+    ac_table: usize,
+    dc_pred: i32,
+}
+
+/// The parameters of a scan
+struct Scan {
+    components: Vec<usize>,
+    start: usize,
+    end: usize,
+    high: u32,
+    low: u32,
+}
+
+struct Decoder {
+    width: usize,
+    height: usize,
+    progressive: bool,
+    components: Vec<Component>,
+    quant: [[u16; 64]; 4],
+    dc_tables: [Option<Huffman>; 4],
+    ac_tables: [Option<Huffman>; 4],
+    restart_interval: usize,
+    /// The color transform from an Adobe marker
+    adobe: Option<u8>,
+    mcus_x: usize,
+    mcus_y: usize,
+    eob_run: u32,
+}
+
+fn decode_baseline(reader: &mut Reader,
+                   component: &mut Component,
+                   block: usize,
+                   dc: &Huffman,
+                   ac: &Huffman)
+                   -> Option<()> {
+    let coefficients = &mut component.coefficients[block * 64..block * 64 + 64];
+
+    let size = try_option!(reader.decode(dc)) as u32;
+    component.dc_pred += reader.receive_extend(size);
+    coefficients[0] = component.dc_pred as i16;
+
+    let mut k = 1;
+    while k < 64 {
+        let rs = try_option!(reader.decode(ac));
+        let run = (rs >> 4) as usize;
+        let size = (rs & 15) as u32;
+        if size == 0 {
+            if run != 15 {
+                break;
+            }
+            k += 16;
+        } else {
+            k += run;
+            coefficients[ZIGZAG[k]] = reader.receive_extend(size) as i16;
+            k += 1;
+        }
+    }
+    Some(())
+}
+
+fn decode_dc_first(reader: &mut Reader,
+                   component: &mut Component,
+                   block: usize,
+                   dc: &Huffman,
+                   low: u32)
+                   -> Option<()> {
+    let size = try_option!(reader.decode(dc)) as u32;
+    component.dc_pred += reader.receive_extend(size);
+    component.coefficients[block * 64] = (component.dc_pred << low) as i16;
+    Some(())
+}
+
+fn decode_dc_refine(reader: &mut Reader, component: &mut Component, block: usize, low: u32) {
+    if reader.bit() {
+        component.coefficients[block * 64] |= 1 << low;
+    }
+}
+
+fn decode_ac_first(reader: &mut Reader,
+                   coefficients: &mut [i16],
+                   ac: &Huffman,
+                   scan: &Scan,
+                   eob_run: &mut u32)
+                   -> Option<()> {
+    if *eob_run > 0 {
+        *eob_run -= 1;
+        return Some(());
+    }
+
+    let mut k = scan.start;
+    while k <= scan.end {
+        let rs = try_option!(reader.decode(ac));
+        let run = (rs >> 4) as u32;
+        let size = (rs & 15) as u32;
+        if size == 0 {
+            if run < 15 {
+                *eob_run = (1 << run) - 1 + reader.bits(run);
+                break;
+            }
+            k += 16;
+        } else {
+            k += run as usize;
+            if k > 63 {
+                return None;
+            }
+            coefficients[ZIGZAG[k]] = (reader.receive_extend(size) << scan.low) as i16;
+            k += 1;
+        }
+    }
+    Some(())
+}
+
+/// Nonzero coefficients get a correction bit each, as they are passed
+fn refine(reader: &mut Reader, coefficient: &mut i16, p1: i16, m1: i16) {
+    if *coefficient != 0 && reader.bit() && *coefficient & p1 == 0 {
+        if *coefficient >= 0 {
+            *coefficient += p1;
+        } else {
+            *coefficient += m1;
+        }
+    }
+}
+
+fn decode_ac_refine(reader: &mut Reader,
+                    coefficients: &mut [i16],
+                    ac: &Huffman,
+                    scan: &Scan,
+                    eob_run: &mut u32)
+                    -> Option<()> {
+    let p1 = 1i16 << scan.low;
+    let m1 = -1i16 << scan.low;
+
+    let mut k = scan.start;
+    if *eob_run == 0 {
+        while k <= scan.end {
+            let rs = try_option!(reader.decode(ac));
+            let mut run = (rs >> 4) as i32;
+            let size = rs & 15;
+            let mut value = 0;
+            if size != 0 {
+                value = if reader.bit() {
+                    p1
+                } else {
+                    m1
+                };
+            } else if run != 15 {
+                *eob_run = (1 << run) + reader.bits(run as u32);
+                break;
+            }
+
+            // Skip `run` zero coefficients, then place the new one on the next zero
+            while k <= scan.end {
+                let z = ZIGZAG[k];
+                if coefficients[z] != 0 {
+                    refine(reader, &mut coefficients[z], p1, m1);
+                } else {
+                    if run == 0 {
+                        break;
+                    }
+                    run -= 1;
+                }
+                k += 1;
+            }
+
+            if value != 0 && k <= scan.end {
+                coefficients[ZIGZAG[k]] = value;
+            }
+            k += 1;
+        }
+    }
+
+    if *eob_run > 0 {
+        while k <= scan.end {
+            refine(reader, &mut coefficients[ZIGZAG[k]], p1, m1);
+            k += 1;
+        }
+        *eob_run -= 1;
+    }
+    Some(())
+}
+
+fn decode_block(reader: &mut Reader,
+                component: &mut Component,
+                block: usize,
+                scan: &Scan,
+                progressive: bool,
+                dc_tables: &[Option<Huffman>; 4],
+                ac_tables: &[Option<Huffman>; 4],
+                eob_run: &mut u32)
+                -> Option<()> {
+    if !progressive {
+        let dc = try_option!(dc_tables[component.dc_table].as_ref());
+        let ac = try_option!(ac_tables[component.ac_table].as_ref());
+        decode_baseline(reader, component, block, dc, ac)
+    } else if scan.start == 0 {
+        if scan.high == 0 {
+            let dc = try_option!(dc_tables[component.dc_table].as_ref());
+            decode_dc_first(reader, component, block, dc, scan.low)
+        } else {
+            decode_dc_refine(reader, component, block, scan.low);
+            Some(())
+        }
+    } else {
+        let ac = try_option!(ac_tables[component.ac_table].as_ref());
+        let coefficients = &mut component.coefficients[block * 64..block * 64 + 64];
+        if scan.high == 0 {
+            decode_ac_first(reader, coefficients, ac, scan, eob_run)
+        } else {
+            decode_ac_refine(reader, coefficients, ac, scan, eob_run)
+        }
+    }
+}
+
+/// An inverse DCT in fixed point, from dequantized coefficients to samples
+fn idct(input: &[i32], output: &mut [u8], stride: usize) {
+    macro_rules! idct_1d {
+        ($s0:expr, $s1:expr, $s2:expr, $s3:expr, $s4:expr, $s5:expr, $s6:expr, $s7:expr) => ({
+            let p1 = ($s2 + $s6) * 2217;
+            let t2 = p1 + $s6 * -7567;
+            let t3 = p1 + $s2 * 3135;
+            let t0 = ($s0 + $s4) * 4096;
+            let t1 = ($s0 - $s4) * 4096;
+            let x0 = t0 + t3;
+            let x3 = t0 - t3;
+            let x1 = t1 + t2;
+            let x2 = t1 - t2;
+
+            let (t0, t1, t2, t3) = ($s7, $s5, $s3, $s1);
+            let p3 = t0 + t2;
+            let p4 = t1 + t3;
+            let p1 = t0 + t3;
+            let p2 = t1 + t2;
+            let p5 = (p3 + p4) * 4816;
+            let t0 = t0 * 1223;
+            let t1 = t1 * 8410;
+            let t2 = t2 * 12586;
+            let t3 = t3 * 6149;
+            let p1 = p5 + p1 * -3685;
+            let p2 = p5 + p2 * -10497;
+            let p3 = p3 * -8034;
+            let p4 = p4 * -1597;
+            (x0, x1, x2, x3, t0 + p1 + p3, t1 + p2 + p4, t2 + p2 + p3, t3 + p1 + p4)
+        })
+    }
+
+    let mut columns = [0i32; 64];
+    for i in 0..8 {
+        let d = |row: usize| input[row * 8 + i];
+        if d(1) == 0 && d(2) == 0 && d(3) == 0 && d(4) == 0 && d(5) == 0 && d(6) == 0 &&
+           d(7) == 0 {
+            for row in 0..8 {
+                columns[row * 8 + i] = d(0) * 4;
+            }
+        } else {
+            let (x0, x1, x2, x3, t0, t1, t2, t3) =
+                idct_1d!(d(0), d(1), d(2), d(3), d(4), d(5), d(6), d(7));
+            // Keep two extra bits of precision for the rows
+            let (x0, x1, x2, x3) = (x0 + 512, x1 + 512, x2 + 512, x3 + 512);
+            columns[i] = (x0 + t3) >> 10;
+            columns[56 + i] = (x0 - t3) >> 10;
+            columns[8 + i] = (x1 + t2) >> 10;
+            columns[48 + i] = (x1 - t2) >> 10;
+            columns[16 + i] = (x2 + t1) >> 10;
+            columns[40 + i] = (x2 - t1) >> 10;
+            columns[24 + i] = (x3 + t0) >> 10;
+            columns[32 + i] = (x3 - t0) >> 10;
+        }
+    }
+
+    let clamp = |value: i32| -> u8 {
+        if value < 0 {
+            0
+        } else if value > 255 {
+            255
+        } else {
+            value as u8
+        }
+    };
+
+    for row in 0..8 {
+        let v = &columns[row * 8..row * 8 + 8];
+        let (x0, x1, x2, x3, t0, t1, t2, t3) =
+            idct_1d!(v[0], v[1], v[2], v[3], v[4], v[5], v[6], v[7]);
+        // Round, and move from -128..127 to 0..255
+        let bias = 65536 + (128 << 17);
+        let (x0, x1, x2, x3) = (x0 + bias, x1 + bias, x2 + bias, x3 + bias);
+        let out = &mut output[row * stride..row * stride + 8];
+        out[0] = clamp((x0 + t3) >> 17);
+        out[7] = clamp((x0 - t3) >> 17);
+        out[1] = clamp((x1 + t2) >> 17);
+        out[6] = clamp((x1 - t2) >> 17);
+        out[2] = clamp((x2 + t1) >> 17);
+        out[5] = clamp((x2 - t1) >> 17);
+        out[3] = clamp((x3 + t0) >> 17);
+        out[4] = clamp((x3 - t0) >> 17);
+    }
+}
+
+impl Decoder {
+    fn frame(&mut self, segment: &[u8], progressive: bool) -> Option<()> {
+        if segment.len() < 6 || segment[0] != 8 || !self.components.is_empty() {
+            return None;
+        }
+
+        self.progressive = progressive;
+        self.height = (segment[1] as usize) << 8 | segment[2] as usize;
+        self.width = (segment[3] as usize) << 8 | segment[4] as usize;
+        let count = segment[5] as usize;
+        if self.width == 0 || self.height == 0 || self.width > MAX_PIXELS / self.height ||
+           count == 0 || count > 4 || segment.len() < 6 + count * 3 {
+            return None;
+        }
+
+        for i in 0..count {
+            let info = &segment[6 + i * 3..9 + i * 3];
+            let (h, v) = ((info[1] >> 4) as usize, (info[1] & 15) as usize);
+            if h == 0 || h > 4 || v == 0 || v > 4 || info[2] > 3 {
+                return None;
+            }
+            self.components.push(Component {
+                id: info[0],
+                h: h,
+                v: v,
+                quant: info[2] as usize,
+                blocks_w: 0,
+                blocks_h: 0,
+                coefficients: Vec::new(),
+                dc_table: 0,
+                ac_table: 0,
+                dc_pred: 0,
+            });
+        }
+
+        let h_max = self.components.iter().map(|c| c.h).max().unwrap_or(1);
+        let v_max = self.components.iter().map(|c| c.v).max().unwrap_or(1);
+        self.mcus_x = (self.width + 8 * h_max - 1) / (8 * h_max);
+        self.mcus_y = (self.height + 8 * v_max - 1) / (8 * v_max);
+        for component in self.components.iter_mut() {
+            component.blocks_w = self.mcus_x * component.h;
+            component.blocks_h = self.mcus_y * component.v;
+            component.coefficients = vec![0; component.blocks_w * component.blocks_h * 64];
+        }
+        Some(())
+    }
+
+    fn quant_tables(&mut self, segment: &[u8]) -> Option<()> {
+        let mut offset = 0;
+        while offset < segment.len() {
+            let precision = segment[offset] >> 4;
+            let id = (segment[offset] & 15) as usize;
+            offset += 1;
+            let size = if precision == 0 { 64 } else { 128 };
+            if id > 3 || offset + size > segment.len() {
+                return None;
+            }
+
+            for k in 0..64 {
+                self.quant[id][ZIGZAG[k]] = if precision == 0 {
+                    segment[offset + k] as u16
+                } else {
+                    (segment[offset + k * 2] as u16) << 8 | segment[offset + k * 2 + 1] as u16
+                };
+            }
+            offset += size;
+        }
+        Some(())
+    }
+
+    fn huffman_tables(&mut self, segment: &[u8]) -> Option<()> {
+        let mut offset = 0;
+        while offset + 17 <= segment.len() {
+            let class = segment[offset] >> 4;
+            let id = (segment[offset] & 15) as usize;
+            if id > 3 {
+                return None;
+            }
+
+            let mut counts = [0; 17];
+            let mut total = 0;
+            for length in 1..17 {
+                counts[length] = segment[offset + length] as u16;
+                total += counts[length] as usize;
+            }
+            offset += 17;
+            if offset + total > segment.len() {
+                return None;
+            }
+
+            let huffman = Huffman {
+                counts: counts,
+                symbols: segment[offset..offset + total].to_vec(),
+            };
+            offset += total;
+
+            if class == 0 {
+                self.dc_tables[id] = Some(huffman);
+            } else {
+                self.ac_tables[id] = Some(huffman);
+            }
+        }
+        Some(())
+    }
+
+    /// Read a scan header and decode the scan, returning where the data after it starts
+    fn scan(&mut self, data: &[u8], segment: &[u8], start: usize) -> Option<usize> {
+        let count = *try_option!(segment.get(0)) as usize;
+        if count == 0 || segment.len() < 4 + count * 2 {
+            return None;
+        }
+
+        let mut scan = Scan {
+            components: Vec::new(),
+            start: segment[1 + count * 2] as usize,
+            end: segment[2 + count * 2] as usize,
+            high: (segment[3 + count * 2] >> 4) as u32,
+            low: (segment[3 + count * 2] & 15) as u32,
+        };
+        for i in 0..count {
+            let id = segment[1 + i * 2];
+            let tables = segment[2 + i * 2];
+            let index = try_option!(self.components.iter().position(|c| c.id == id));
+            self.components[index].dc_table = (tables >> 4) as usize & 3;
+            self.components[index].ac_table = (tables & 15) as usize & 3;
+            scan.components.push(index);
+        }
+        if scan.end > 63 || scan.start > scan.end || scan.low > 13 {
+            return None;
+        }
+        if self.progressive && scan.start > 0 && count != 1 {
+            return None;
+        }
+
+        for component in self.components.iter_mut() {
+            component.dc_pred = 0;
+        }
+        self.eob_run = 0;
+
+        let mut reader = Reader {
+            data: data,
+            offset: start,
+            buffer: 0,
+            count: 0,
+        };
+
+        let h_max = self.components.iter().map(|c| c.h).max().unwrap_or(1);
+        let v_max = self.components.iter().map(|c| c.v).max().unwrap_or(1);
+
+        // A scan of one component covers only its blocks that are in the image, one per MCU
+        let (mcus_x, mcus_y) = if count == 1 {
+            let component = &self.components[scan.components[0]];
+            let width = (self.width * component.h + h_max - 1) / h_max;
+            let height = (self.height * component.v + v_max - 1) / v_max;
+            ((width + 7) / 8, (height + 7) / 8)
+        } else {
+            (self.mcus_x, self.mcus_y)
+        };
+
+        let progressive = self.progressive;
+        let mut mcu = 0;
+        for mcu_y in 0..mcus_y {
+            for mcu_x in 0..mcus_x {
+                if self.restart_interval > 0 && mcu > 0 && mcu % self.restart_interval == 0 {
+                    reader.restart();
+                    for component in self.components.iter_mut() {
+                        component.dc_pred = 0;
+                    }
+                    self.eob_run = 0;
+                }
+                mcu += 1;
+
+                for &index in scan.components.iter() {
+                    let component = &mut self.components[index];
+                    let (blocks_x, blocks_y) = if count == 1 {
+                        (1, 1)
+                    } else {
+                        (component.h, component.v)
+                    };
+                    for y in 0..blocks_y {
+                        for x in 0..blocks_x {
+                            let row = mcu_y * blocks_y + y;
+                            let col = mcu_x * blocks_x + x;
+                            let block = row * component.blocks_w + col;
+                            try_option!(decode_block(&mut reader,
+                                                     component,
+                                                     block,
+                                                     &scan,
+                                                     progressive,
+                                                     &self.dc_tables,
+                                                     &self.ac_tables,
+                                                     &mut self.eob_run));
+                        }
+                    }
+                }
+            }
+        }
+
+        Some(reader.offset)
+    }
+
+    /// Transform the coefficients of a component into its samples
+    fn samples(&self, component: &Component) -> Vec<u8> {
+        let stride = component.blocks_w * 8;
+        let mut samples = vec![0; stride * component.blocks_h * 8];
+        let quant = &self.quant[component.quant];
+        let mut input = [0i32; 64];
+        for row in 0..component.blocks_h {
+            for col in 0..component.blocks_w {
+                let block = (row * component.blocks_w + col) * 64;
+                for i in 0..64 {
+                    input[i] = component.coefficients[block + i] as i32 * quant[i] as i32;
+                }
+                let start = row * 8 * stride + col * 8;
+                idct(&input, &mut samples[start..], stride);
+            }
+        }
+        samples
+    }
+
+    fn pixels(&self) -> Vec<u32> {
+        let h_max = self.components.iter().map(|c| c.h).max().unwrap_or(1);
+        let v_max = self.components.iter().map(|c| c.v).max().unwrap_or(1);
+
+        let planes: Vec<Vec<u8>> = self.components.iter().map(|c| self.samples(c)).collect();
+        let sample = |index: usize, x: usize, y: usize| -> i32 {
+            let component = &self.components[index];
+            let x = x * component.h / h_max;
+            let y = y * component.v / v_max;
+            planes[index][y * component.blocks_w * 8 + x] as i32
+        };
+
+        let clamp = |value: i32| -> u32 {
+            if value < 0 {
+                0
+            } else if value > 255 {
+                255
+            } else {
+                value as u32
+            }
+        };
+
+        // Y, Cb and Cr to red, green and blue, in 16 bit fixed point
+        let ycc = |y: i32, cb: i32, cr: i32| -> (u32, u32, u32) {
+            let y = y << 16;
+            let (cb, cr) = (cb - 128, cr - 128);
+            (clamp((y + 91881 * cr + 32768) >> 16),
+             clamp((y - 22554 * cb - 46802 * cr + 32768) >> 16),
+             clamp((y + 116130 * cb + 32768) >> 16))
+        };
+
+        // Without an Adobe marker, three components are YCbCr unless they are named R, G and B
+        let rgb = match self.adobe {
+            Some(transform) => transform == 0,
+            None => {
+                self.components.len() == 3 && self.components[0].id == b'R' &&
+                self.components[1].id == b'G' && self.components[2].id == b'B'
+            }
+        };
+
+        let mut pixels = Vec::with_capacity(self.width * self.height);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let (r, g, b) = match self.components.len() {
+                    1 | 2 => {
+                        let gray = sample(0, x, y) as u32;
+                        (gray, gray, gray)
+                    }
+                    3 if rgb => {
+                        (sample(0, x, y) as u32, sample(1, x, y) as u32, sample(2, x, y) as u32)
+                    }
+                    3 => ycc(sample(0, x, y), sample(1, x, y), sample(2, x, y)),
+                    _ => {
+                        // Adobe stores CMYK inverted, and YCCK converts to inverted CMY
+                        let (c, m, ye) = if self.adobe == Some(2) {
+                            ycc(sample(0, x, y), sample(1, x, y), sample(2, x, y))
+                        } else {
+                            (sample(0, x, y) as u32,
+                             sample(1, x, y) as u32,
+                             sample(2, x, y) as u32)
+                        };
+                        let k = sample(3, x, y) as u32;
+                        (c * k / 255, m * k / 255, ye * k / 255)
+                    }
+                };
+                pixels.push(0xFF000000 | r << 16 | g << 8 | b);
+            }
+        }
+        pixels
+    }
+}
+
+/// Decode a baseline or progressive JPEG with Huffman coding
+///
+/// Returns the width, the height and the pixels as 0xAARRGGBB, row by row.
+pub fn decode(data: &[u8]) -> Option<(usize, usize, Vec<u32>)> {
+    if data.len() < 3 || &data[0..3] != &SIGNATURE[..] {
+        return None;
+    }
+
+    let mut decoder = Decoder {
+        width: 0,
+        height: 0,
+        progressive: false,
+        components: Vec::new(),
+        quant: [[1; 64]; 4],
+        dc_tables: [None, None, None, None],
+        ac_tables: [None, None, None, None],
+        restart_interval: 0,
+        adobe: None,
+        mcus_x: 0,
+        mcus_y: 0,
+        eob_run: 0,
+    };
+
+    let mut offset = 2;
+    loop {
+        // Find the next marker, skipping fill bytes and anything left after a scan
+        while offset + 1 < data.len() && (data[offset] != 0xFF || data[offset + 1] == 0xFF ||
+                                          data[offset + 1] == 0) {
+            offset += 1;
+        }
+        if offset + 1 >= data.len() {
+            break;
+        }
+        let marker = data[offset + 1];
+        offset += 2;
+
+        match marker {
+            0xD9 => break,
+            0xD0...0xD7 | 0x01 => continue,
+            _ => (),
+        }
+
+        if offset + 2 > data.len() {
+            return None;
+        }
+        let length = (data[offset] as usize) << 8 | data[offset + 1] as usize;
+        if length < 2 || offset + length > data.len() {
+            return None;
+        }
+        let segment = &data[offset + 2..offset + length];
+        offset += length;
+
+        match marker {
+            0xC0 | 0xC1 => try_option!(decoder.frame(segment, false)),
+            0xC2 => try_option!(decoder.frame(segment, true)),
+            // Lossless, hierarchical and arithmetic coded frames are not supported
+            0xC3 | 0xC5...0xC7 | 0xC9...0xCB | 0xCD...0xCF => return None,
+            0xC4 => try_option!(decoder.huffman_tables(segment)),
+            0xDB => try_option!(decoder.quant_tables(segment)),
+            0xDD => {
+                if segment.len() >= 2 {
+                    decoder.restart_interval = (segment[0] as usize) << 8 | segment[1] as usize;
+                }
+            }
+            0xDA => {
+                if decoder.components.is_empty() {
+                    return None;
+                }
+                offset = try_option!(decoder.scan(data, segment, offset));
+            }
+            0xEE => {
+                if segment.len() >= 12 && &segment[0..5] == b"Adobe" {
+                    decoder.adobe = Some(segment[11]);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    if decoder.components.is_empty() {
+        return None;
+    }
+
+    Some((decoder.width, decoder.height, decoder.pixels()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::decode;
+
+    // The fixtures have a quantizer of 8 and only DC coefficients, so each block is one color
+
+    /// Check a decoded 16x16 gray image against the value of each 8x8 block
+    fn check_blocks(data: &[u8], blocks: [u32; 4]) {
+        let (width, height, pixels) = decode(data).unwrap();
+        assert_eq!((width, height), (16, 16));
+
+        for y in 0..16 {
+            for x in 0..16 {
+                let value = blocks[y / 8 * 2 + x / 8];
+                assert_eq!(pixels[y * 16 + x], 0xFF000000 | value << 16 | value << 8 | value);
+            }
+        }
+    }
+
+    #[test]
+    fn test_baseline_gray() {
+        check_blocks(include_bytes!("test/gray.jpg"), [64, 201, 65, 255]);
+    }
+
+    #[test]
+    fn test_baseline_ycbcr() {
+        // Y 150, Cb 90 and Cr 200
+        let (width, height, pixels) = decode(include_bytes!("test/ycbcr.jpg")).unwrap();
+        assert_eq!((width, height), (8, 8));
+        assert!(pixels.iter().all(|pixel| *pixel == 0xFFFB7053));
+    }
+
+    #[test]
+    fn test_progressive() {
+        // The DC coefficients come in two scans, the low bit last, and the AC scans are empty
+        check_blocks(include_bytes!("test/progressive.jpg"), [64, 201, 65, 0]);
+    }
+
+    #[test]
+    fn test_invalid() {
+        let data = include_bytes!("test/gray.jpg");
+        assert!(decode(&data[.. 3]).is_none());
+        assert!(decode(&data[.. 40]).is_none());
+        assert!(decode(b"not a jpeg").is_none());
+    }
+}
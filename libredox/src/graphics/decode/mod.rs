@@ -0,0 +1,43 @@
+//! Image decoders, which the kernel shares for the session's images
+//!
+//! The kernel includes these files with `#[path]`, the way libredox includes
+//! `kernel/syscall/common.rs` and `kernel/externs.rs`; that is how the two crates share code.
+//! So this only uses `core`, `alloc` and `collections`, through paths both crates have:
+//! libredox re-exports `collections::vec`, and the kernel includes `try_option.rs` of libredox
+//! before this. The tests and their fixtures in `test/` run with libredox.
+
+use collections::vec::Vec;
+
+pub mod inflate;
+pub mod jpeg;
+pub mod png;
+
+/// An image format, known by the first bytes of a file
+#[derive(Copy, Clone, PartialEq)]
+pub enum Format {
+    Bmp,
+    Png,
+    Jpeg,
+}
+
+/// Find the format of an image from its signature
+pub fn format(data: &[u8]) -> Option<Format> {
+    if data.len() >= 2 && &data[0..2] == b"BM" {
+        Some(Format::Bmp)
+    } else if data.len() >= 8 && &data[0..8] == &png::SIGNATURE[..] {
+        Some(Format::Png)
+    } else if data.len() >= 3 && &data[0..3] == &jpeg::SIGNATURE[..] {
+        Some(Format::Jpeg)
+    } else {
+        None
+    }
+}
+
+/// Decode a PNG or JPEG to its width, height, and pixels as 0xAARRGGBB
+pub fn decode(data: &[u8]) -> Option<(usize, usize, Vec<u32>)> {
+    match format(data) {
+        Some(Format::Png) => png::decode(data),
+        Some(Format::Jpeg) => jpeg::decode(data),
+        _ => None,
+    }
+}
@@ -0,0 +1,381 @@
+use collections::vec::Vec;
+
+use super::inflate::zlib_decompress;
+
+/// The first bytes of every PNG
+pub const SIGNATURE: [u8; 8] = [137, 80, 78, 71, 13, 10, 26, 10];
+
+/// The first column, first row, and spacing of each pass of Adam7 interlacing
+const ADAM7: [(usize, usize, usize, usize); 7] = [(0, 0, 8, 8),
+                                                  (4, 0, 8, 8),
+                                                  (0, 4, 4, 8),
+                                                  (2, 0, 4, 4),
+                                                  (0, 2, 2, 4),
+                                                  (1, 0, 2, 2),
+                                                  (0, 1, 1, 2)];
+
+/// Images larger than this are refused rather than risking running out of memory
+const MAX_PIXELS: usize = 1 << 26;
+
+fn be16(data: &[u8], offset: usize) -> u16 {
+    (data[offset] as u16) << 8 | data[offset + 1] as u16
+}
+
+fn be32(data: &[u8], offset: usize) -> u32 {
+    (be16(data, offset) as u32) << 16 | be16(data, offset + 2) as u32
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let pa = (p - a as i16).abs();
+    let pb = (p - b as i16).abs();
+    let pc = (p - c as i16).abs();
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+/// Undo the filter of a row, given the row above, which is zeros for the first row of a pass
+fn unfilter(filter: u8, row: &mut [u8], previous: &[u8], bpp: usize) -> Option<()> {
+    match filter {
+        0 => (),
+        1 => for i in bpp..row.len() {
+            row[i] = row[i].wrapping_add(row[i - bpp]);
+        },
+        2 => for i in 0..row.len() {
+            row[i] = row[i].wrapping_add(previous[i]);
+        },
+        3 => for i in 0..row.len() {
+            let left = if i >= bpp { row[i - bpp] as u16 } else { 0 };
+            row[i] = row[i].wrapping_add(((left + previous[i] as u16) / 2) as u8);
+        },
+        4 => for i in 0..row.len() {
+            let (left, corner) = if i >= bpp {
+                (row[i - bpp], previous[i - bpp])
+            } else {
+                (0, 0)
+            };
+            row[i] = row[i].wrapping_add(paeth(left, previous[i], corner));
+        },
+        _ => return None,
+    }
+    Some(())
+}
+
+struct Header {
+    width: usize,
+    height: usize,
+    depth: usize,
+    color_type: u8,
+    palette: Vec<u32>,
+    /// The gray or RGB sample that is transparent, at the image bit depth
+    transparent: Option<[u16; 3]>,
+}
+
+impl Header {
+    fn channels(&self) -> usize {
+        match self.color_type {
+            2 => 3,
+            4 => 2,
+            6 => 4,
+            _ => 1,
+        }
+    }
+
+    /// Read sample `index` of a row
+    fn sample(&self, row: &[u8], index: usize) -> u16 {
+        match self.depth {
+            8 => row[index] as u16,
+            16 => be16(row, index * 2),
+            depth => {
+                let bit = index * depth;
+                (row[bit / 8] as u16 >> (8 - depth - bit % 8)) & ((1 << depth) - 1)
+            }
+        }
+    }
+
+    /// Scale a sample to 8 bits
+    fn scale(&self, sample: u16) -> u32 {
+        (match self.depth {
+            1 => sample * 255,
+            2 => sample * 85,
+            4 => sample * 17,
+            16 => sample >> 8,
+            _ => sample,
+        }) as u32
+    }
+
+    fn pixel(&self, row: &[u8], x: usize) -> u32 {
+        let channels = self.channels();
+        let sample = |channel: usize| self.sample(row, x * channels + channel);
+
+        match self.color_type {
+            0 => {
+                let gray = sample(0);
+                let alpha = match self.transparent {
+                    Some(transparent) if transparent[0] == gray => 0,
+                    _ => 0xFF,
+                };
+                let gray = self.scale(gray);
+                alpha << 24 | gray << 16 | gray << 8 | gray
+            }
+            2 => {
+                let (r, g, b) = (sample(0), sample(1), sample(2));
+                let alpha = match self.transparent {
+                    Some(transparent) if transparent == [r, g, b] => 0,
+                    _ => 0xFF,
+                };
+                alpha << 24 | self.scale(r) << 16 | self.scale(g) << 8 | self.scale(b)
+            }
+            3 => {
+                match self.palette.get(sample(0) as usize) {
+                    Some(color) => *color,
+                    None => 0xFF000000,
+                }
+            }
+            4 => {
+                let gray = self.scale(sample(0));
+                self.scale(sample(1)) << 24 | gray << 16 | gray << 8 | gray
+            }
+            _ => {
+                self.scale(sample(3)) << 24 | self.scale(sample(0)) << 16 |
+                self.scale(sample(1)) << 8 | self.scale(sample(2))
+            }
+        }
+    }
+}
+
+/// Decode a PNG of any color type and bit depth, interlaced or not
+///
+/// Returns the width, the height and the pixels as 0xAARRGGBB, row by row.
+pub fn decode(data: &[u8]) -> Option<(usize, usize, Vec<u32>)> {
+    if data.len() < 8 || &data[0..8] != &SIGNATURE[..] {
+        return None;
+    }
+
+    let mut header = Header {
+        width: 0,
+        height: 0,
+        depth: 0,
+        color_type: 0,
+        palette: Vec::new(),
+        transparent: None,
+    };
+    let mut interlaced = false;
+    let mut compressed = Vec::new();
+
+    let mut offset = 8;
+    while offset + 12 <= data.len() {
+        let length = be32(data, offset) as usize;
+        let kind = &data[offset + 4..offset + 8];
+        let start = offset + 8;
+        if start + length > data.len() {
+            return None;
+        }
+        let chunk = &data[start..start + length];
+        offset = start + length + 4;
+
+        if kind == b"IHDR" {
+            if length < 13 {
+                return None;
+            }
+            header.width = be32(chunk, 0) as usize;
+            header.height = be32(chunk, 4) as usize;
+            header.depth = chunk[8] as usize;
+            header.color_type = chunk[9];
+            interlaced = chunk[12] == 1;
+
+            let valid = match (header.color_type, header.depth) {
+                (0, 1) | (0, 2) | (0, 4) | (0, 8) | (0, 16) => true,
+                (3, 1) | (3, 2) | (3, 4) | (3, 8) => true,
+                (2, 8) | (2, 16) | (4, 8) | (4, 16) | (6, 8) | (6, 16) => true,
+                _ => false,
+            };
+            if !valid || chunk[10] != 0 || chunk[11] != 0 || chunk[12] > 1 ||
+               header.width == 0 || header.height == 0 ||
+               header.width > MAX_PIXELS / header.height {
+                return None;
+            }
+        } else if kind == b"PLTE" {
+            for entry in chunk.chunks(3) {
+                if entry.len() == 3 {
+                    header.palette.push(0xFF000000 | (entry[0] as u32) << 16 |
+                                        (entry[1] as u32) << 8 |
+                                        entry[2] as u32);
+                }
+            }
+        } else if kind == b"tRNS" {
+            match header.color_type {
+                0 if length >= 2 => {
+                    header.transparent = Some([be16(chunk, 0), 0, 0]);
+                }
+                2 if length >= 6 => {
+                    header.transparent = Some([be16(chunk, 0), be16(chunk, 2), be16(chunk, 4)]);
+                }
+                3 => {
+                    for (i, alpha) in chunk.iter().enumerate() {
+                        if let Some(color) = header.palette.get_mut(i) {
+                            *color = (*color & 0xFFFFFF) | (*alpha as u32) << 24;
+                        }
+                    }
+                }
+                _ => (),
+            }
+        } else if kind == b"IDAT" {
+            compressed.push_all(chunk);
+        } else if kind == b"IEND" {
+            break;
+        } else if kind[0] & 0x20 == 0 {
+            // An unknown chunk that is critical for the image
+            return None;
+        }
+    }
+
+    if header.width == 0 || (header.color_type == 3 && header.palette.is_empty()) {
+        return None;
+    }
+
+    let raw = try_option!(zlib_decompress(&compressed));
+
+    let width = header.width;
+    let height = header.height;
+    let bits = header.channels() * header.depth;
+    let bpp = (bits + 7) / 8;
+
+    let whole = [(0, 0, 1, 1)];
+    let passes: &[(usize, usize, usize, usize)] = if interlaced {
+        &ADAM7
+    } else {
+        &whole
+    };
+
+    let mut pixels = vec![0; width * height];
+    let mut offset = 0;
+    for &(x0, y0, dx, dy) in passes.iter() {
+        if x0 >= width || y0 >= height {
+            continue;
+        }
+        let pass_width = (width - x0 + dx - 1) / dx;
+        let pass_height = (height - y0 + dy - 1) / dy;
+        let row_size = (pass_width * bits + 7) / 8;
+
+        let mut previous = vec![0; row_size];
+        let mut row = vec![0; row_size];
+        for y in 0..pass_height {
+            if offset + 1 + row_size > raw.len() {
+                return None;
+            }
+            let filter = raw[offset];
+            for i in 0..row_size {
+                row[i] = raw[offset + 1 + i];
+            }
+            offset += 1 + row_size;
+
+            try_option!(unfilter(filter, &mut row, &previous, bpp));
+
+            let line = (y0 + y * dy) * width;
+            for x in 0..pass_width {
+                pixels[line + x0 + x * dx] = header.pixel(&row, x);
+            }
+
+            let swap = previous;
+            previous = row;
+            row = swap;
+        }
+    }
+
+    Some((width, height, pixels))
+}
+
+#[cfg(test)]
+mod tests {
+    use collections::vec::Vec;
+
+    use super::decode;
+
+    /// Check a decoded image against the color of each pixel
+    fn check<F: Fn(u32, u32) -> u32>(data: &[u8], width: usize, height: usize, color: F) {
+        let (w, h, pixels) = decode(data).unwrap();
+        assert_eq!((w, h), (width, height));
+
+        let mut expected = Vec::new();
+        for y in 0..height as u32 {
+            for x in 0..width as u32 {
+                expected.push(color(x, y));
+            }
+        }
+        assert_eq!(pixels, expected);
+    }
+
+    fn gray(value: u32) -> u32 {
+        value << 16 | value << 8 | value
+    }
+
+    // The fixtures use a different filter on each row, and are 5x4 unless interlaced
+
+    #[test]
+    fn test_gray_1_bit() {
+        check(include_bytes!("test/gray1.png"), 5, 4, |x, y| {
+            0xFF000000 | gray((x + y) % 2 * 255)
+        });
+    }
+
+    #[test]
+    fn test_gray_transparent() {
+        // tRNS makes gray 50 transparent
+        check(include_bytes!("test/gray8.png"), 5, 4, |x, y| {
+            let value = x * 40 + y * 10;
+            let alpha = if value == 50 { 0 } else { 0xFF };
+            alpha << 24 | gray(value)
+        });
+    }
+
+    #[test]
+    fn test_rgb() {
+        let color = |x: u32, y: u32| 0xFF000000 | (x * 50) << 16 | (y * 60) << 8 | (x + y) * 20;
+        check(include_bytes!("test/rgb8.png"), 5, 4, &color);
+        // 16 bit samples keep their high byte
+        check(include_bytes!("test/rgb16.png"), 5, 4, &color);
+    }
+
+    #[test]
+    fn test_palette() {
+        // 4 bit indices into red, green, blue and white, with green half transparent
+        let palette = [0xFFFF0000, 0x8000FF00, 0xFF0000FF, 0xFFFFFFFF];
+        check(include_bytes!("test/palette4.png"), 5, 4, |x, y| palette[((x + y) % 4) as usize]);
+    }
+
+    #[test]
+    fn test_gray_alpha() {
+        check(include_bytes!("test/gray_alpha8.png"), 5, 4, |x, y| {
+            (255 - x * 30) << 24 | gray(x * 40 + y * 10)
+        });
+    }
+
+    #[test]
+    fn test_rgba() {
+        check(include_bytes!("test/rgba8.png"), 5, 4, |x, y| {
+            (255 - x * 30) << 24 | (x * 50) << 16 | (y * 60) << 8 | (x + y) * 20
+        });
+    }
+
+    #[test]
+    fn test_interlaced() {
+        // 9x9 has pixels in every Adam7 pass, and passes narrower than a whole pattern
+        check(include_bytes!("test/rgba8_interlaced.png"), 9, 9, |x, y| {
+            (255 - x * 10) << 24 | (x * 25) << 16 | (y * 25) << 8 | x * y
+        });
+    }
+
+    #[test]
+    fn test_invalid() {
+        let data = include_bytes!("test/rgb8.png");
+        assert!(decode(&data[.. 8]).is_none());
+        assert!(decode(&data[.. data.len() - 20]).is_none());
+        assert!(decode(b"not a png").is_none());
+    }
+}
@@ -0,0 +1,381 @@
+use boxed::Box;
+use collections::BTreeMap;
+use fs::{File, read_dir};
+use io::Read;
+use string::{String, ToString};
+use vec::Vec;
+
+pub use self::psf::PsfFont;
+pub use self::truetype::TrueTypeFont;
+
+pub mod psf;
+pub mod truetype;
+
+/// The fonts loaded by `FontSet::load`, PSF fonts with the extension .psf or .psfu and
+/// TrueType fonts with the extension .ttf
+pub const FONT_DIR: &'static str = "file:///ui/fonts/";
+
+/// The boot font, used when no other font has a character
+pub const BOOT_FONT: &'static str = "file:///ui/unifont.font";
+
+/// Glyphs are kept until there are this many
+const CACHE_SIZE: usize = 1024;
+
+/// A rendered character
+#[derive(Clone)]
+pub struct Glyph {
+    pub width: usize,
+    pub height: usize,
+    /// The distance from the pen to the left of the bitmap
+    pub left: isize,
+    /// The distance from the baseline up to the top of the bitmap
+    pub top: isize,
+    /// The distance to move the pen for the next character
+    pub advance: usize,
+    /// The coverage of each pixel, from 0 for none to 255 for all
+    pub coverage: Vec<u8>,
+}
+
+impl Glyph {
+    /// A glyph that only moves the pen, like a space
+    pub fn empty(advance: usize) -> Self {
+        Glyph {
+            width: 0,
+            height: 0,
+            left: 0,
+            top: 0,
+            advance: advance,
+            coverage: Vec::new(),
+        }
+    }
+
+    /// An outlined box, for characters that no font has
+    pub fn missing(size: usize, ascent: usize) -> Self {
+        let width = if size / 2 > 3 {
+            size / 2
+        } else {
+            3
+        };
+        let height = if ascent > 3 {
+            ascent
+        } else {
+            3
+        };
+
+        let mut coverage = Vec::with_capacity((width - 2) * height);
+        for y in 0..height {
+            for x in 0..width - 2 {
+                if x == 0 || x == width - 3 || y == 0 || y == height - 1 {
+                    coverage.push(255);
+                } else {
+                    coverage.push(0);
+                }
+            }
+        }
+
+        Glyph {
+            width: width - 2,
+            height: height,
+            left: 1,
+            top: height as isize,
+            advance: width,
+            coverage: coverage,
+        }
+    }
+}
+
+/// A font
+///
+/// `size` is the height of the em square in pixels, which is the height of the glyphs of a
+/// bitmap font. Bitmap fonts are only scaled by whole multiples.
+pub trait Font {
+    /// The name of the font, the file name without the extension
+    fn name(&self) -> &str;
+
+    /// Does the font have a glyph for the character?
+    fn has_glyph(&self, c: char) -> bool;
+
+    /// Render the glyph for a character, if the font has it
+    fn glyph(&self, c: char, size: usize) -> Option<Glyph>;
+
+    /// The distance between baselines
+    fn line_height(&self, size: usize) -> usize;
+
+    /// The distance from the top of a line to the baseline
+    fn ascent(&self, size: usize) -> usize;
+
+    /// The adjustment of the distance between two characters
+    #[allow(unused_variables)]
+    fn kerning(&self, left: char, right: char, size: usize) -> isize {
+        0
+    }
+}
+
+/// Load a font by its signature, None if it is not a font
+pub fn load_font(path: &str) -> Option<Box<Font>> {
+    let mut file = try_option!(File::open(path));
+    let mut data = Vec::new();
+    file.read_to_end(&mut data);
+
+    let file_name = match path.rfind('/') {
+        Some(i) => &path[i + 1..],
+        None => path,
+    };
+    let name = match file_name.find('.') {
+        Some(i) => &file_name[..i],
+        None => file_name,
+    };
+
+    if let Some(font) = PsfFont::from_data(name, &data) {
+        Some(box font)
+    } else if path.ends_with(".font") && !data.is_empty() && data.len() % 16 == 0 {
+        Some(box PsfFont::from_raw(name, &data))
+    } else if let Some(font) = TrueTypeFont::from_data(name, data) {
+        Some(box font)
+    } else {
+        None
+    }
+}
+
+/// A glyph placed by a layout
+#[derive(Copy, Clone)]
+pub struct PlacedGlyph {
+    pub c: char,
+    /// The pen position, from the left of the text
+    pub x: isize,
+    /// The baseline, from the top of the text
+    pub y: isize,
+    pub advance: usize,
+}
+
+/// Text broken into lines, with the place of each character
+pub struct TextLayout {
+    pub glyphs: Vec<PlacedGlyph>,
+    pub width: usize,
+    pub height: usize,
+    pub lines: usize,
+}
+
+/// Fonts in the order they are tried for each character, with a cache of rendered glyphs
+pub struct FontSet {
+    fonts: Vec<Box<Font>>,
+    /// Glyphs and the font that had them, by character and size
+    cache: BTreeMap<(char, usize), (Option<usize>, Glyph)>,
+}
+
+impl FontSet {
+    /// Create an empty set, where every character is a missing box
+    pub fn new() -> Self {
+        FontSet {
+            fonts: Vec::new(),
+            cache: BTreeMap::new(),
+        }
+    }
+
+    /// Load the fonts in `FONT_DIR`, then the boot font
+    pub fn load() -> Self {
+        let mut set = FontSet::new();
+
+        if let Some(entries) = read_dir(FONT_DIR) {
+            let mut paths = Vec::new();
+            for entry in entries {
+                let name = entry.path();
+                if name.ends_with(".ttf") || name.ends_with(".psf") || name.ends_with(".psfu") {
+                    paths.push(FONT_DIR.to_string() + name);
+                }
+            }
+            // Regular faces come before those with a style after a '-'
+            paths.sort_by(|a, b| (a.contains('-'), a).cmp(&(b.contains('-'), b)));
+
+            for path in paths.iter() {
+                if let Some(font) = load_font(path) {
+                    set.add(font);
+                }
+            }
+        }
+
+        if let Some(font) = load_font(BOOT_FONT) {
+            set.add(font);
+        }
+
+        set
+    }
+
+    /// Add a font, tried after those already added
+    pub fn add(&mut self, font: Box<Font>) {
+        self.fonts.push(font);
+        self.cache.clear();
+    }
+
+    /// Try a font first, returning false if there is no font with that name
+    pub fn prefer(&mut self, name: &str) -> bool {
+        match self.fonts.iter().position(|font| font.name() == name) {
+            Some(i) => {
+                let font = self.fonts.remove(i);
+                self.fonts.insert(0, font);
+                self.cache.clear();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The names of the fonts, in the order they are tried
+    pub fn names(&self) -> Vec<String> {
+        self.fonts.iter().map(|font| font.name().to_string()).collect()
+    }
+
+    /// The distance between baselines, taken from the first font
+    pub fn line_height(&self, size: usize) -> usize {
+        match self.fonts.get(0) {
+            Some(font) => font.line_height(size),
+            None => size,
+        }
+    }
+
+    /// The distance from the top of a line to the baseline, taken from the first font
+    pub fn ascent(&self, size: usize) -> usize {
+        match self.fonts.get(0) {
+            Some(font) => font.ascent(size),
+            None => size - size / 4,
+        }
+    }
+
+    fn cached(&mut self, c: char, size: usize) -> &(Option<usize>, Glyph) {
+        let key = (c, size);
+        if !self.cache.contains_key(&key) {
+            if self.cache.len() >= CACHE_SIZE {
+                self.cache.clear();
+            }
+
+            let mut found = None;
+            for i in 0..self.fonts.len() {
+                if self.fonts[i].has_glyph(c) {
+                    if let Some(glyph) = self.fonts[i].glyph(c, size) {
+                        found = Some((Some(i), glyph));
+                        break;
+                    }
+                }
+            }
+
+            let entry = match found {
+                Some(entry) => entry,
+                None => {
+                    let ascent = self.ascent(size);
+                    (None, Glyph::missing(size, ascent))
+                }
+            };
+            self.cache.insert(key, entry);
+        }
+        self.cache.get(&key).unwrap()
+    }
+
+    /// The glyph for a character from the first font that has it
+    pub fn glyph(&mut self, c: char, size: usize) -> &Glyph {
+        &self.cached(c, size).1
+    }
+
+    /// The kerning between two characters, if they are from the same font
+    pub fn kerning(&mut self, left: char, right: char, size: usize) -> isize {
+        let left_font = self.cached(left, size).0;
+        let right_font = self.cached(right, size).0;
+        match (left_font, right_font) {
+            (Some(a), Some(b)) if a == b => self.fonts[a].kerning(left, right, size),
+            _ => 0,
+        }
+    }
+
+    /// Place the characters of some text, breaking lines at '\n', and at spaces to fit in
+    /// `max_width` if it is given
+    pub fn layout(&mut self, text: &str, size: usize, max_width: Option<usize>) -> TextLayout {
+        let line_height = self.line_height(size) as isize;
+        let ascent = self.ascent(size) as isize;
+
+        let mut glyphs: Vec<PlacedGlyph> = Vec::new();
+        let mut lines = 0;
+        for line in text.split('\n') {
+            let mut y = lines as isize * line_height + ascent;
+            let mut x = 0;
+            let mut line_start = glyphs.len();
+            // Where the line can be broken, after the last space
+            let mut break_at = None;
+            let mut previous = None;
+            lines += 1;
+
+            for c in line.chars() {
+                if c == '\r' {
+                    continue;
+                }
+
+                let advance = self.glyph(c, size).advance;
+                if let Some(previous) = previous {
+                    x += self.kerning(previous, c, size);
+                }
+
+                let space = c == ' ' || c == '\t';
+                if let Some(max_width) = max_width {
+                    let overflow = x + advance as isize > max_width as isize;
+                    if overflow && !space && glyphs.len() > line_start {
+                        // Move the last word to a new line, or break it if it fills the line
+                        let start = match break_at {
+                            Some(i) if i > line_start && i < glyphs.len() => i,
+                            _ => glyphs.len(),
+                        };
+                        let shift = if start < glyphs.len() {
+                            glyphs[start].x
+                        } else {
+                            x
+                        };
+
+                        y += line_height;
+                        for glyph in glyphs[start..].iter_mut() {
+                            glyph.x -= shift;
+                            glyph.y = y;
+                        }
+                        x -= shift;
+                        line_start = start;
+                        break_at = None;
+                        lines += 1;
+                    }
+                }
+
+                glyphs.push(PlacedGlyph {
+                    c: c,
+                    x: x,
+                    y: y,
+                    advance: advance,
+                });
+                x += advance as isize;
+
+                if space {
+                    break_at = Some(glyphs.len());
+                }
+                previous = Some(c);
+            }
+        }
+
+        // Spaces left at the end of a line do not make it wider
+        let mut width = 0;
+        for glyph in glyphs.iter() {
+            if glyph.c != ' ' && glyph.c != '\t' {
+                let right = glyph.x + glyph.advance as isize;
+                if right > width {
+                    width = right;
+                }
+            }
+        }
+
+        TextLayout {
+            glyphs: glyphs,
+            width: width as usize,
+            height: lines * line_height as usize,
+            lines: lines,
+        }
+    }
+
+    /// The width and height of some text, without breaking lines to fit
+    pub fn measure(&mut self, text: &str, size: usize) -> (usize, usize) {
+        let layout = self.layout(text, size, None);
+        (layout.width, layout.height)
+    }
+}
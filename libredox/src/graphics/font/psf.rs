@@ -0,0 +1,228 @@
+use collections::BTreeMap;
+use str;
+use string::{String, ToString};
+use vec::Vec;
+
+use super::{Font, Glyph};
+
+const PSF1_MAGIC: [u8; 2] = [0x36, 0x04];
+const PSF2_MAGIC: [u8; 4] = [0x72, 0xB5, 0x4A, 0x86];
+
+/// A bitmap font, in the PC Screen Font format or as raw 8x16 glyphs like the boot font
+pub struct PsfFont {
+    name: String,
+    width: usize,
+    height: usize,
+    /// The bytes of one glyph, rows padded to whole bytes
+    glyph_size: usize,
+    glyphs: Vec<u8>,
+    /// Which glyph draws a character, or None if the glyphs are in character order
+    map: Option<BTreeMap<char, usize>>,
+}
+
+impl PsfFont {
+    /// Parse a PSF1 or PSF2 font
+    pub fn from_data(name: &str, data: &[u8]) -> Option<Self> {
+        let get = |i: usize| -> usize {
+            match data.get(i) {
+                Some(byte) => *byte as usize,
+                None => 0,
+            }
+        };
+        let getd = |i: usize| -> usize {
+            get(i) | get(i + 1) << 8 | get(i + 2) << 16 | get(i + 3) << 24
+        };
+
+        if data.len() >= 4 && &data[0..4] == &PSF2_MAGIC[..] {
+            let header_size = getd(8);
+            let flags = getd(12);
+            let count = getd(16);
+            let glyph_size = getd(20);
+            let height = getd(24);
+            let width = getd(28);
+            if width == 0 || height == 0 || glyph_size < (width + 7) / 8 * height {
+                return None;
+            }
+
+            let table = header_size + count * glyph_size;
+            if table > data.len() {
+                return None;
+            }
+
+            // Each glyph has UTF-8 characters, then sequences each after 0xFE, ending with 0xFF
+            let map = if flags & 1 == 1 {
+                let mut map = BTreeMap::new();
+                let mut glyph = 0;
+                let mut start = table;
+                let mut sequence = false;
+                for i in table..data.len() {
+                    let byte = data[i];
+                    if byte == 0xFE || byte == 0xFF {
+                        if !sequence {
+                            if let Ok(chars) = str::from_utf8(&data[start..i]) {
+                                for c in chars.chars() {
+                                    map.insert(c, glyph);
+                                }
+                            }
+                        }
+                        if byte == 0xFF {
+                            glyph += 1;
+                            sequence = false;
+                        } else {
+                            sequence = true;
+                        }
+                        start = i + 1;
+                    }
+                }
+                Some(map)
+            } else {
+                None
+            };
+
+            Some(PsfFont {
+                name: name.to_string(),
+                width: width,
+                height: height,
+                glyph_size: glyph_size,
+                glyphs: data[header_size..table].to_vec(),
+                map: map,
+            })
+        } else if data.len() >= 4 && &data[0..2] == &PSF1_MAGIC[..] {
+            let mode = get(2);
+            let height = get(3);
+            let count = if mode & 1 == 1 {
+                512
+            } else {
+                256
+            };
+            if height == 0 {
+                return None;
+            }
+
+            let table = 4 + count * height;
+            if table > data.len() {
+                return None;
+            }
+
+            // Each glyph has UCS-2 characters, then sequences each after 0xFFFE, ending with 0xFFFF
+            let map = if mode & 2 == 2 {
+                let mut map = BTreeMap::new();
+                let mut glyph = 0;
+                let mut sequence = false;
+                let mut i = table;
+                while i + 1 < data.len() {
+                    let value = get(i) | get(i + 1) << 8;
+                    if value == 0xFFFF {
+                        glyph += 1;
+                        sequence = false;
+                    } else if value == 0xFFFE {
+                        sequence = true;
+                    } else if !sequence {
+                        if let Some(c) = ::char::from_u32(value as u32) {
+                            map.insert(c, glyph);
+                        }
+                    }
+                    i += 2;
+                }
+                Some(map)
+            } else {
+                None
+            };
+
+            Some(PsfFont {
+                name: name.to_string(),
+                width: 8,
+                height: height,
+                glyph_size: height,
+                glyphs: data[4..table].to_vec(),
+                map: map,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Use raw 8x16 glyphs in character order, like `unifont.font`
+    pub fn from_raw(name: &str, data: &[u8]) -> Self {
+        PsfFont {
+            name: name.to_string(),
+            width: 8,
+            height: 16,
+            glyph_size: 16,
+            glyphs: data.to_vec(),
+            map: None,
+        }
+    }
+
+    fn glyph_index(&self, c: char) -> Option<usize> {
+        let index = match self.map {
+            Some(ref map) => try_option!(map.get(&c).map(|index| *index)),
+            None => c as usize,
+        };
+
+        if (index + 1) * self.glyph_size <= self.glyphs.len() {
+            Some(index)
+        } else {
+            None
+        }
+    }
+
+    /// Bitmaps only look right at whole multiples of their size
+    fn scale(&self, size: usize) -> usize {
+        if size > self.height {
+            (size + self.height / 2) / self.height
+        } else {
+            1
+        }
+    }
+}
+
+impl Font for PsfFont {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn has_glyph(&self, c: char) -> bool {
+        self.glyph_index(c).is_some()
+    }
+
+    fn glyph(&self, c: char, size: usize) -> Option<Glyph> {
+        let index = try_option!(self.glyph_index(c));
+        let scale = self.scale(size);
+        let row_size = (self.width + 7) / 8;
+        let bitmap = &self.glyphs[index * self.glyph_size..(index + 1) * self.glyph_size];
+
+        let width = self.width * scale;
+        let height = self.height * scale;
+        let mut coverage = Vec::with_capacity(width * height);
+        for y in 0..height {
+            let row = &bitmap[(y / scale) * row_size..(y / scale + 1) * row_size];
+            for x in 0..width {
+                let col = x / scale;
+                if row[col / 8] & (0x80 >> (col % 8)) != 0 {
+                    coverage.push(255);
+                } else {
+                    coverage.push(0);
+                }
+            }
+        }
+
+        Some(Glyph {
+            width: width,
+            height: height,
+            left: 0,
+            top: self.ascent(size) as isize,
+            advance: width,
+            coverage: coverage,
+        })
+    }
+
+    fn line_height(&self, size: usize) -> usize {
+        self.height * self.scale(size)
+    }
+
+    fn ascent(&self, size: usize) -> usize {
+        // The baseline of VGA style fonts is a quarter of the way up
+        (self.height - self.height / 4) * self.scale(size)
+    }
+}
@@ -0,0 +1,555 @@
+use graphics::raster::{ceil, floor, Point, Raster};
+use string::{String, ToString};
+use vec::Vec;
+
+use super::{Font, Glyph};
+
+/// Composite glyphs may nest, but not forever
+const MAX_DEPTH: usize = 8;
+
+/// A piece of an outline, in font units with y pointing up
+enum Curve {
+    Line(Point, Point),
+    Quad(Point, Point, Point),
+}
+
+/// A transform of component glyphs, `[xx, yx, xy, yy, dx, dy]`
+type Affine = [f32; 6];
+
+const IDENTITY: Affine = [1.0, 0.0, 0.0, 1.0, 0.0, 0.0];
+
+fn transform(affine: &Affine, x: f32, y: f32) -> Point {
+    Point::new(affine[0] * x + affine[2] * y + affine[4],
+               affine[1] * x + affine[3] * y + affine[5])
+}
+
+fn midpoint(a: Point, b: Point) -> Point {
+    Point::new((a.x + b.x) / 2.0, (a.y + b.y) / 2.0)
+}
+
+/// A TrueType font, with quadratic outlines in a `glyf` table
+pub struct TrueTypeFont {
+    name: String,
+    data: Vec<u8>,
+    units_per_em: f32,
+    long_loca: bool,
+    num_glyphs: usize,
+    loca: usize,
+    glyf: usize,
+    hmtx: usize,
+    num_h_metrics: usize,
+    ascender: i16,
+    descender: i16,
+    line_gap: i16,
+    /// The offset and format of the Unicode subtable of the character map
+    cmap: usize,
+    cmap_format: u16,
+    /// The offset of the horizontal pair table of `kern`, if there is one
+    kern: Option<usize>,
+}
+
+impl TrueTypeFont {
+    /// Parse a font, returning None if it is not a TrueType font we can use
+    pub fn from_data(name: &str, data: Vec<u8>) -> Option<Self> {
+        let mut font = TrueTypeFont {
+            name: name.to_string(),
+            data: data,
+            units_per_em: 0.0,
+            long_loca: false,
+            num_glyphs: 0,
+            loca: 0,
+            glyf: 0,
+            hmtx: 0,
+            num_h_metrics: 0,
+            ascender: 0,
+            descender: 0,
+            line_gap: 0,
+            cmap: 0,
+            cmap_format: 0,
+            kern: None,
+        };
+
+        let version = font.u32(0);
+        if version != 0x00010000 && version != 0x74727565 {
+            return None;
+        }
+
+        let head = try_option!(font.table(b"head"));
+        let maxp = try_option!(font.table(b"maxp"));
+        let hhea = try_option!(font.table(b"hhea"));
+        font.loca = try_option!(font.table(b"loca"));
+        font.glyf = try_option!(font.table(b"glyf"));
+        font.hmtx = try_option!(font.table(b"hmtx"));
+        let cmap = try_option!(font.table(b"cmap"));
+
+        font.units_per_em = font.u16(head + 18) as f32;
+        font.long_loca = font.u16(head + 50) != 0;
+        font.num_glyphs = font.u16(maxp + 4) as usize;
+        font.ascender = font.i16(hhea + 4);
+        font.descender = font.i16(hhea + 6);
+        font.line_gap = font.i16(hhea + 8);
+        font.num_h_metrics = font.u16(hhea + 34) as usize;
+        if font.units_per_em == 0.0 || font.num_h_metrics == 0 {
+            return None;
+        }
+
+        // Prefer the full Unicode map, then the Basic Multilingual Plane
+        let mut best = 0;
+        for i in 0..font.u16(cmap + 2) as usize {
+            let record = cmap + 4 + i * 8;
+            let platform = font.u16(record);
+            let encoding = font.u16(record + 2);
+            let subtable = cmap + font.u32(record + 4) as usize;
+            let format = font.u16(subtable);
+
+            let rank = match (platform, encoding, format) {
+                (3, 10, 12) | (0, 4, 12) | (0, 6, 12) => 3,
+                (3, 1, 4) | (0, _, 4) => 2,
+                _ => 0,
+            };
+            if rank > best {
+                best = rank;
+                font.cmap = subtable;
+                font.cmap_format = format;
+            }
+        }
+        if best == 0 {
+            return None;
+        }
+
+        if let Some(kern) = font.table(b"kern") {
+            // Only the first subtable, if it is horizontal format 0
+            if font.u16(kern) == 0 && font.u16(kern + 2) > 0 {
+                let coverage = font.u16(kern + 8);
+                if coverage & 0xFF07 == 0x0001 {
+                    font.kern = Some(kern + 4);
+                }
+            }
+        }
+
+        Some(font)
+    }
+
+    fn u8(&self, offset: usize) -> u8 {
+        match self.data.get(offset) {
+            Some(byte) => *byte,
+            None => 0,
+        }
+    }
+
+    fn u16(&self, offset: usize) -> u16 {
+        (self.u8(offset) as u16) << 8 | self.u8(offset + 1) as u16
+    }
+
+    fn i16(&self, offset: usize) -> i16 {
+        self.u16(offset) as i16
+    }
+
+    fn u32(&self, offset: usize) -> u32 {
+        (self.u16(offset) as u32) << 16 | self.u16(offset + 2) as u32
+    }
+
+    /// Find a table in the directory
+    fn table(&self, tag: &[u8]) -> Option<usize> {
+        for i in 0..self.u16(4) as usize {
+            let record = 12 + i * 16;
+            if record + 4 <= self.data.len() && &self.data[record..record + 4] == tag {
+                let offset = self.u32(record + 8) as usize;
+                let length = self.u32(record + 12) as usize;
+                if offset + length <= self.data.len() {
+                    return Some(offset);
+                } else {
+                    return None;
+                }
+            }
+        }
+        None
+    }
+
+    /// Find the glyph for a character, zero being the missing glyph
+    fn glyph_index(&self, c: char) -> u16 {
+        let c = c as u32;
+        let cmap = self.cmap;
+
+        if self.cmap_format == 12 {
+            let groups = self.u32(cmap + 12) as usize;
+            let (mut low, mut high) = (0, groups);
+            while low < high {
+                let middle = (low + high) / 2;
+                let group = cmap + 16 + middle * 12;
+                if c < self.u32(group) {
+                    high = middle;
+                } else if c > self.u32(group + 4) {
+                    low = middle + 1;
+                } else {
+                    return (self.u32(group + 8) + c - self.u32(group)) as u16;
+                }
+            }
+            0
+        } else {
+            if c > 0xFFFF {
+                return 0;
+            }
+            let c = c as u16;
+
+            let seg_x2 = self.u16(cmap + 6) as usize;
+            let ends = cmap + 14;
+            let starts = ends + seg_x2 + 2;
+            let deltas = starts + seg_x2;
+            let range_offsets = deltas + seg_x2;
+
+            for i in 0..seg_x2 / 2 {
+                if self.u16(ends + i * 2) >= c {
+                    let start = self.u16(starts + i * 2);
+                    if start > c {
+                        return 0;
+                    }
+
+                    let delta = self.u16(deltas + i * 2);
+                    let range_offset = self.u16(range_offsets + i * 2) as usize;
+                    if range_offset == 0 {
+                        return c.wrapping_add(delta);
+                    }
+
+                    let address = range_offsets + i * 2 + range_offset + (c - start) as usize * 2;
+                    let index = self.u16(address);
+                    if index == 0 {
+                        return 0;
+                    }
+                    return index.wrapping_add(delta);
+                }
+            }
+            0
+        }
+    }
+
+    /// The bytes of a glyph in `glyf`, which are empty for glyphs without an outline
+    fn glyph_range(&self, index: u16) -> (usize, usize) {
+        let index = index as usize;
+        if index >= self.num_glyphs {
+            return (0, 0);
+        }
+
+        let (start, end) = if self.long_loca {
+            (self.u32(self.loca + index * 4) as usize,
+             self.u32(self.loca + index * 4 + 4) as usize)
+        } else {
+            (self.u16(self.loca + index * 2) as usize * 2,
+             self.u16(self.loca + index * 2 + 2) as usize * 2)
+        };
+
+        if start < end && self.glyf + end <= self.data.len() {
+            (self.glyf + start, self.glyf + end)
+        } else {
+            (0, 0)
+        }
+    }
+
+    fn advance_width(&self, index: u16) -> u16 {
+        let metric = if (index as usize) < self.num_h_metrics {
+            index as usize
+        } else {
+            self.num_h_metrics - 1
+        };
+        self.u16(self.hmtx + metric * 4)
+    }
+
+    /// Add the outline of a glyph to `curves`
+    fn outline(&self, index: u16, affine: &Affine, depth: usize, curves: &mut Vec<Curve>) {
+        let (start, end) = self.glyph_range(index);
+        if start == end || depth > MAX_DEPTH {
+            return;
+        }
+
+        let contours = self.i16(start);
+        if contours >= 0 {
+            self.simple_outline(start, end, contours as usize, affine, curves);
+        } else {
+            self.composite_outline(start, affine, depth, curves);
+        }
+    }
+
+    fn simple_outline(&self,
+                      start: usize,
+                      end: usize,
+                      contours: usize,
+                      affine: &Affine,
+                      curves: &mut Vec<Curve>) {
+        let mut ends = Vec::with_capacity(contours);
+        for i in 0..contours {
+            ends.push(self.u16(start + 10 + i * 2) as usize);
+        }
+        let points = match ends.last() {
+            Some(last) => last + 1,
+            None => return,
+        };
+
+        let instructions = start + 10 + contours * 2;
+        let mut offset = instructions + 2 + self.u16(instructions) as usize;
+
+        // Flags, with runs of repeats
+        let mut flags = Vec::with_capacity(points);
+        while flags.len() < points && offset < end {
+            let flag = self.u8(offset);
+            offset += 1;
+            flags.push(flag);
+            if flag & 8 != 0 {
+                let repeat = self.u8(offset);
+                offset += 1;
+                for _ in 0..repeat {
+                    flags.push(flag);
+                }
+            }
+        }
+        if flags.len() < points {
+            return;
+        }
+
+        // Coordinates, as deltas that are either a byte and a sign, the same, or a word
+        let mut xs = Vec::with_capacity(points);
+        let mut x = 0i32;
+        for i in 0..points {
+            let flag = flags[i];
+            if flag & 2 != 0 {
+                let dx = self.u8(offset) as i32;
+                offset += 1;
+                x += if flag & 16 != 0 { dx } else { -dx };
+            } else if flag & 16 == 0 {
+                x += self.i16(offset) as i32;
+                offset += 2;
+            }
+            xs.push(x);
+        }
+
+        let mut ys = Vec::with_capacity(points);
+        let mut y = 0i32;
+        for i in 0..points {
+            let flag = flags[i];
+            if flag & 4 != 0 {
+                let dy = self.u8(offset) as i32;
+                offset += 1;
+                y += if flag & 32 != 0 { dy } else { -dy };
+            } else if flag & 32 == 0 {
+                y += self.i16(offset) as i32;
+                offset += 2;
+            }
+            ys.push(y);
+        }
+
+        let mut first = 0;
+        for &last in ends.iter() {
+            if last < first || last >= points {
+                return;
+            }
+
+            let point = |i: usize| transform(affine, xs[i] as f32, ys[i] as f32);
+            let on_curve = |i: usize| flags[i] & 1 != 0;
+
+            // Start on a point on the curve, or between two that are off it
+            let count = last - first + 1;
+            let mut begin = first;
+            while begin <= last && !on_curve(begin) {
+                begin += 1;
+            }
+            let start_point = if begin <= last {
+                point(begin)
+            } else {
+                begin = first;
+                midpoint(point(first), point(last))
+            };
+
+            let mut current = start_point;
+            let mut control: Option<Point> = None;
+            for step in 1..count + 1 {
+                let i = first + (begin - first + step) % count;
+                let p = point(i);
+                if on_curve(i) {
+                    match control {
+                        Some(c) => curves.push(Curve::Quad(current, c, p)),
+                        None => curves.push(Curve::Line(current, p)),
+                    }
+                    current = p;
+                    control = None;
+                } else {
+                    if let Some(c) = control {
+                        let middle = midpoint(c, p);
+                        curves.push(Curve::Quad(current, c, middle));
+                        current = middle;
+                    }
+                    control = Some(p);
+                }
+            }
+            match control {
+                Some(c) => curves.push(Curve::Quad(current, c, start_point)),
+                None => curves.push(Curve::Line(current, start_point)),
+            }
+
+            first = last + 1;
+        }
+    }
+
+    fn composite_outline(&self,
+                         start: usize,
+                         affine: &Affine,
+                         depth: usize,
+                         curves: &mut Vec<Curve>) {
+        let mut offset = start + 10;
+        loop {
+            let flags = self.u16(offset);
+            let index = self.u16(offset + 2);
+            offset += 4;
+
+            let (dx, dy) = if flags & 1 != 0 {
+                offset += 4;
+                (self.i16(offset - 4) as f32, self.i16(offset - 2) as f32)
+            } else {
+                offset += 2;
+                (self.u8(offset - 2) as i8 as f32, self.u8(offset - 1) as i8 as f32)
+            };
+            // Components placed by matching points are not supported, they are left in place
+            let (dx, dy) = if flags & 2 != 0 {
+                (dx, dy)
+            } else {
+                (0.0, 0.0)
+            };
+
+            let f2dot14 = |offset: usize| self.i16(offset) as f32 / 16384.0;
+            let (xx, yx, xy, yy) = if flags & 8 != 0 {
+                offset += 2;
+                let scale = f2dot14(offset - 2);
+                (scale, 0.0, 0.0, scale)
+            } else if flags & 0x40 != 0 {
+                offset += 4;
+                (f2dot14(offset - 4), 0.0, 0.0, f2dot14(offset - 2))
+            } else if flags & 0x80 != 0 {
+                offset += 8;
+                (f2dot14(offset - 8), f2dot14(offset - 6), f2dot14(offset - 4), f2dot14(offset - 2))
+            } else {
+                (1.0, 0.0, 0.0, 1.0)
+            };
+
+            // The component transform, then ours
+            let component = [affine[0] * xx + affine[2] * yx,
+                             affine[1] * xx + affine[3] * yx,
+                             affine[0] * xy + affine[2] * yy,
+                             affine[1] * xy + affine[3] * yy,
+                             affine[0] * dx + affine[2] * dy + affine[4],
+                             affine[1] * dx + affine[3] * dy + affine[5]];
+            self.outline(index, &component, depth + 1, curves);
+
+            if flags & 0x20 == 0 {
+                break;
+            }
+        }
+    }
+
+    fn scale(&self, size: usize) -> f32 {
+        size as f32 / self.units_per_em
+    }
+}
+
+impl Font for TrueTypeFont {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn has_glyph(&self, c: char) -> bool {
+        self.glyph_index(c) != 0
+    }
+
+    fn glyph(&self, c: char, size: usize) -> Option<Glyph> {
+        let index = self.glyph_index(c);
+        if index == 0 {
+            return None;
+        }
+
+        let scale = self.scale(size);
+        let advance = (self.advance_width(index) as f32 * scale + 0.5) as usize;
+
+        let mut curves = Vec::new();
+        self.outline(index, &IDENTITY, 0, &mut curves);
+        if curves.is_empty() {
+            return Some(Glyph::empty(advance));
+        }
+
+        // The bounds of the outline, in pixels
+        let mut min = Point::new(0.0, 0.0);
+        let mut max = Point::new(0.0, 0.0);
+        let mut first = true;
+        for curve in curves.iter() {
+            let points = match *curve {
+                Curve::Line(a, b) => [a, b, b],
+                Curve::Quad(a, b, c) => [a, b, c],
+            };
+            for p in points.iter() {
+                if first || p.x < min.x { min.x = p.x; }
+                if first || p.y < min.y { min.y = p.y; }
+                if first || p.x > max.x { max.x = p.x; }
+                if first || p.y > max.y { max.y = p.y; }
+                first = false;
+            }
+        }
+        let left = floor(min.x * scale);
+        let top = ceil(max.y * scale);
+        let width = (ceil(max.x * scale) - left) as usize + 1;
+        let height = (top - floor(min.y * scale)) as usize;
+
+        let to_pixels = |p: Point| Point::new(p.x * scale - left, top - p.y * scale);
+
+        let mut raster = Raster::new(width, height);
+        for curve in curves.iter() {
+            match *curve {
+                Curve::Line(a, b) => raster.draw_line(to_pixels(a), to_pixels(b)),
+                Curve::Quad(a, b, c) => raster.draw_quad(to_pixels(a), to_pixels(b), to_pixels(c)),
+            }
+        }
+
+        Some(Glyph {
+            width: width,
+            height: height,
+            left: left as isize,
+            top: top as isize,
+            advance: advance,
+            coverage: raster.coverage(),
+        })
+    }
+
+    fn line_height(&self, size: usize) -> usize {
+        let units = self.ascender as i32 - self.descender as i32 + self.line_gap as i32;
+        (units as f32 * self.scale(size) + 0.5) as usize
+    }
+
+    fn ascent(&self, size: usize) -> usize {
+        let gap = self.line_gap as i32 / 2;
+        ((self.ascender as i32 + gap) as f32 * self.scale(size) + 0.5) as usize
+    }
+
+    fn kerning(&self, left: char, right: char, size: usize) -> isize {
+        let kern = match self.kern {
+            Some(kern) => kern,
+            None => return 0,
+        };
+
+        let pair = (self.glyph_index(left) as u32) << 16 | self.glyph_index(right) as u32;
+        let pairs = self.u16(kern + 6) as usize;
+        let (mut low, mut high) = (0, pairs);
+        while low < high {
+            let middle = (low + high) / 2;
+            let entry = kern + 14 + middle * 6;
+            let key = self.u32(entry);
+            if pair < key {
+                high = middle;
+            } else if pair > key {
+                low = middle + 1;
+            } else {
+                let value = self.i16(entry + 4) as f32 * self.scale(size);
+                return if value < 0.0 {
+                    (value - 0.5) as isize
+                } else {
+                    (value + 0.5) as isize
+                };
+            }
+        }
+        0
+    }
+}
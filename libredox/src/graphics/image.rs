@@ -0,0 +1,41 @@
+use boxed::Box;
+
+use super::bmp::BmpFile;
+use super::color::Color;
+use super::decode::{self, Format};
+use super::jpeg::JpegFile;
+use super::png::PngFile;
+
+/// A decoded image
+pub trait Image {
+    /// The image width
+    fn width(&self) -> usize;
+    /// The image height
+    fn height(&self) -> usize;
+    /// The colors of the pixels, row by row
+    fn as_slice(&self) -> &[Color];
+}
+
+impl Image for BmpFile {
+    fn width(&self) -> usize {
+        BmpFile::width(self)
+    }
+
+    fn height(&self) -> usize {
+        BmpFile::height(self)
+    }
+
+    fn as_slice(&self) -> &[Color] {
+        BmpFile::as_slice(self)
+    }
+}
+
+/// Decode a BMP, PNG or JPEG, picking the decoder by the signature of the data
+pub fn load_image(file_data: &[u8]) -> Option<Box<Image>> {
+    match decode::format(file_data) {
+        Some(Format::Bmp) => Some(box BmpFile::from_data(file_data)),
+        Some(Format::Png) => Some(box PngFile::from_data(file_data)),
+        Some(Format::Jpeg) => Some(box JpegFile::from_data(file_data)),
+        None => None,
+    }
+}
@@ -0,0 +1,47 @@
+use super::color::Color;
+use super::decode::jpeg;
+use super::image::Image;
+
+use vec::Vec;
+
+/// A JPEG image
+pub struct JpegFile {
+    /// The image width
+    w: usize,
+    /// The image height
+    h: usize,
+    /// The data of the image
+    data: Vec<Color>,
+}
+
+impl JpegFile {
+    /// Decode a JPEG, which is empty if it cannot be decoded
+    pub fn from_data(file_data: &[u8]) -> Self {
+        match jpeg::decode(file_data) {
+            Some((width, height, pixels)) => JpegFile {
+                w: width,
+                h: height,
+                data: pixels.into_iter().map(|pixel| Color { data: pixel }).collect(),
+            },
+            None => JpegFile {
+                w: 0,
+                h: 0,
+                data: Vec::new(),
+            },
+        }
+    }
+}
+
+impl Image for JpegFile {
+    fn width(&self) -> usize {
+        self.w
+    }
+
+    fn height(&self) -> usize {
+        self.h
+    }
+
+    fn as_slice(&self) -> &[Color] {
+        &self.data
+    }
+}
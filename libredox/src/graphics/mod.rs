@@ -1,4 +1,11 @@
 pub mod color;
 pub mod point;
 pub mod bmp;
+pub mod canvas;
+pub mod decode;
+pub mod font;
+pub mod image;
+pub mod jpeg;
+pub mod png;
+mod raster;
 pub mod size;
@@ -0,0 +1,47 @@
+use super::color::Color;
+use super::decode::png;
+use super::image::Image;
+
+use vec::Vec;
+
+/// A PNG image
+pub struct PngFile {
+    /// The image width
+    w: usize,
+    /// The image height
+    h: usize,
+    /// The data of the image
+    data: Vec<Color>,
+}
+
+impl PngFile {
+    /// Decode a PNG, which is empty if it cannot be decoded
+    pub fn from_data(file_data: &[u8]) -> Self {
+        match png::decode(file_data) {
+            Some((width, height, pixels)) => PngFile {
+                w: width,
+                h: height,
+                data: pixels.into_iter().map(|pixel| Color { data: pixel }).collect(),
+            },
+            None => PngFile {
+                w: 0,
+                h: 0,
+                data: Vec::new(),
+            },
+        }
+    }
+}
+
+impl Image for PngFile {
+    fn width(&self) -> usize {
+        self.w
+    }
+
+    fn height(&self) -> usize {
+        self.h
+    }
+
+    fn as_slice(&self) -> &[Color] {
+        &self.data
+    }
+}
@@ -0,0 +1,188 @@
+use vec::Vec;
+
+/// A point in pixels, y pointing down
+#[derive(Copy, Clone)]
+pub struct Point {
+    pub x: f32,
+    pub y: f32,
+}
+
+impl Point {
+    pub fn new(x: f32, y: f32) -> Self {
+        Point { x: x, y: y }
+    }
+
+    fn lerp(t: f32, a: Point, b: Point) -> Point {
+        Point::new(a.x + t * (b.x - a.x), a.y + t * (b.y - a.y))
+    }
+}
+
+// The float intrinsics may lower to calls into a libm we do not have
+pub fn floor(x: f32) -> f32 {
+    let i = x as i32 as f32;
+    if i > x {
+        i - 1.0
+    } else {
+        i
+    }
+}
+
+pub fn ceil(x: f32) -> f32 {
+    let i = x as i32 as f32;
+    if i < x {
+        i + 1.0
+    } else {
+        i
+    }
+}
+
+/// An anti-aliasing rasterizer
+///
+/// Every edge adds the signed area it covers to the cells of an accumulation buffer, which are
+/// then summed along each row to get the coverage. This is exact for straight edges and needs no
+/// sorting or scanline lists.
+pub struct Raster {
+    w: usize,
+    h: usize,
+    a: Vec<f32>,
+}
+
+impl Raster {
+    pub fn new(w: usize, h: usize) -> Self {
+        Raster {
+            w: w,
+            h: h,
+            a: vec![0.0; w * h + 4],
+        }
+    }
+
+    fn add(&mut self, i: isize, value: f32) {
+        if i >= 0 && (i as usize) < self.a.len() {
+            self.a[i as usize] += value;
+        }
+    }
+
+    pub fn draw_line(&mut self, p0: Point, p1: Point) {
+        if p0.y == p1.y {
+            return;
+        }
+
+        let (dir, p0, p1) = if p0.y < p1.y {
+            (1.0, p0, p1)
+        } else {
+            (-1.0, p1, p0)
+        };
+
+        let dxdy = (p1.x - p0.x) / (p1.y - p0.y);
+        let mut x = p0.x;
+        let y0 = if p0.y < 0.0 {
+            x -= p0.y * dxdy;
+            0
+        } else {
+            p0.y as usize
+        };
+        let y1 = ceil(p1.y);
+        let y1 = if y1 < self.h as f32 {
+            y1 as usize
+        } else {
+            self.h
+        };
+
+        for y in y0..y1 {
+            let line = (y * self.w) as isize;
+            let top = if (y as f32) > p0.y {
+                y as f32
+            } else {
+                p0.y
+            };
+            let bottom = if ((y + 1) as f32) < p1.y {
+                (y + 1) as f32
+            } else {
+                p1.y
+            };
+            let dy = bottom - top;
+            let xnext = x + dxdy * dy;
+            let d = dy * dir;
+
+            let (x0, x1) = if x < xnext {
+                (x, xnext)
+            } else {
+                (xnext, x)
+            };
+            let x0floor = floor(x0);
+            let x0i = x0floor as isize;
+            let x1ceil = ceil(x1);
+            let x1i = x1ceil as isize;
+
+            if x1i <= x0i + 1 {
+                // The edge stays within one cell on this row
+                let xmf = 0.5 * (x + xnext) - x0floor;
+                self.add(line + x0i, d - d * xmf);
+                self.add(line + x0i + 1, d * xmf);
+            } else {
+                let s = 1.0 / (x1 - x0);
+                let x0f = x0 - x0floor;
+                let a0 = 0.5 * s * (1.0 - x0f) * (1.0 - x0f);
+                let x1f = x1 - x1ceil + 1.0;
+                let am = 0.5 * s * x1f * x1f;
+
+                self.add(line + x0i, d * a0);
+                if x1i == x0i + 2 {
+                    self.add(line + x0i + 1, d * (1.0 - a0 - am));
+                } else {
+                    let a1 = s * (1.5 - x0f);
+                    self.add(line + x0i + 1, d * (a1 - a0));
+                    for xi in x0i + 2..x1i - 1 {
+                        self.add(line + xi, d * s);
+                    }
+                    let a2 = a1 + (x1i - x0i - 3) as f32 * s;
+                    self.add(line + x1i - 1, d * (1.0 - a2 - am));
+                }
+                self.add(line + x1i, d * am);
+            }
+
+            x = xnext;
+        }
+    }
+
+    /// Draw a quadratic curve by flattening it into lines
+    pub fn draw_quad(&mut self, p0: Point, p1: Point, p2: Point) {
+        let devx = p0.x - 2.0 * p1.x + p2.x;
+        let devy = p0.y - 2.0 * p1.y + p2.y;
+        let devsq = devx * devx + devy * devy;
+        if devsq < 0.333 {
+            self.draw_line(p0, p2);
+            return;
+        }
+
+        // Enough segments to keep the error under a tenth of a pixel
+        let n = 1 + floor((3.0 * devsq).sqrt().sqrt()) as usize;
+        let step = 1.0 / n as f32;
+        let mut t = 0.0;
+        let mut p = p0;
+        for _ in 0..n - 1 {
+            t += step;
+            let next = Point::lerp(t, Point::lerp(t, p0, p1), Point::lerp(t, p1, p2));
+            self.draw_line(p, next);
+            p = next;
+        }
+        self.draw_line(p, p2);
+    }
+
+    /// Sum the rows into coverage from 0 to 255
+    pub fn coverage(&self) -> Vec<u8> {
+        let mut coverage = Vec::with_capacity(self.w * self.h);
+        let mut acc = 0.0;
+        for i in 0..self.w * self.h {
+            acc += self.a[i];
+            let value = acc.abs();
+            let value = if value < 1.0 {
+                value
+            } else {
+                1.0
+            };
+            coverage.push((255.0 * value + 0.5) as u8);
+        }
+        coverage
+    }
+}
@@ -0,0 +1,555 @@
+//! HTTP/1.1
+//!
+//! A client with keep-alive and redirects, and the message parsing shared with servers.
+//! Hosts are IP addresses, as there is no name resolution yet.
+
+use io::{Read, Write};
+use net::TcpStream;
+use string::{String, ToString};
+use to_num::ToNum;
+use url::Url;
+use vec::Vec;
+
+/// The most redirects followed for one request
+pub const MAX_REDIRECTS: usize = 5;
+
+/// Message headers, in the order they were given
+pub struct Headers {
+    pub list: Vec<(String, String)>,
+}
+
+impl Headers {
+    pub fn new() -> Self {
+        Headers { list: Vec::new() }
+    }
+
+    /// The value of the header `name`, which is matched without case
+    pub fn get(&self, name: &str) -> Option<&str> {
+        let name = name.to_lowercase();
+        for &(ref key, ref value) in self.list.iter() {
+            if key.to_lowercase() == name {
+                return Some(&**value);
+            }
+        }
+        None
+    }
+
+    /// Set the header `name`, replacing any previous value
+    pub fn set(&mut self, name: &str, value: &str) {
+        self.remove(name);
+        self.list.push((name.to_string(), value.to_string()));
+    }
+
+    pub fn remove(&mut self, name: &str) {
+        let name = name.to_lowercase();
+        self.list.retain(|&(ref key, _)| key.to_lowercase() != name);
+    }
+
+    /// Does the comma separated header `name` contain `token`
+    pub fn has_token(&self, name: &str, token: &str) -> bool {
+        match self.get(name) {
+            Some(value) => value.split(',').any(|part| part.trim().to_lowercase() == token),
+            None => false,
+        }
+    }
+
+    fn push_bytes(&self, bytes: &mut Vec<u8>) {
+        for &(ref key, ref value) in self.list.iter() {
+            bytes.push_all(key.as_bytes());
+            bytes.push_all(b": ");
+            bytes.push_all(value.as_bytes());
+            bytes.push_all(b"\r\n");
+        }
+        bytes.push_all(b"\r\n");
+    }
+}
+
+/// A buffered HTTP connection, bytes read past the end of one message are kept for the next
+pub struct Connection<S: Read + Write> {
+    pub stream: S,
+    buf: Vec<u8>,
+}
+
+impl<S: Read + Write> Connection<S> {
+    pub fn new(stream: S) -> Self {
+        Connection {
+            stream: stream,
+            buf: Vec::new(),
+        }
+    }
+
+    /// Read more of the stream into the buffer, returns false when it has ended
+    fn fill(&mut self) -> bool {
+        let mut bytes = [0; 4096];
+        match self.stream.read(&mut bytes) {
+            Some(0) | None => false,
+            Some(count) => {
+                self.buf.push_all(&bytes[.. count]);
+                true
+            }
+        }
+    }
+
+    /// Read a line, without the line ending
+    pub fn read_line(&mut self) -> Option<String> {
+        loop {
+            if let Some(i) = self.buf.iter().position(|&b| b == b'\n') {
+                let mut line = self.buf[.. i].to_vec();
+                self.buf = self.buf[i + 1 ..].to_vec();
+                if line.last() == Some(&b'\r') {
+                    line.pop();
+                }
+                return String::from_utf8(line).ok();
+            }
+
+            if !self.fill() {
+                return None;
+            }
+        }
+    }
+
+    /// Read exactly `len` bytes
+    pub fn read_exact(&mut self, len: usize) -> Option<Vec<u8>> {
+        while self.buf.len() < len {
+            if !self.fill() {
+                return None;
+            }
+        }
+
+        let bytes = self.buf[.. len].to_vec();
+        self.buf = self.buf[len ..].to_vec();
+        Some(bytes)
+    }
+
+    /// Read until the peer closes the stream
+    pub fn read_to_close(&mut self) -> Vec<u8> {
+        while self.fill() {}
+
+        let mut bytes = Vec::new();
+        bytes.push_all(&self.buf);
+        self.buf.clear();
+        bytes
+    }
+
+    /// Read the start line and the headers of a message, skipping empty lines in front
+    pub fn read_head(&mut self) -> Option<(String, Headers)> {
+        let mut start = String::new();
+        while start.is_empty() {
+            match self.read_line() {
+                Some(line) => start = line,
+                None => return None,
+            }
+        }
+
+        let mut headers = Headers::new();
+        loop {
+            match self.read_line() {
+                Some(line) => {
+                    if line.is_empty() {
+                        break;
+                    }
+
+                    let mut parts = line.splitn(2, ':');
+                    if let (Some(key), Some(value)) = (parts.next(), parts.next()) {
+                        headers.list.push((key.trim().to_string(), value.trim().to_string()));
+                    }
+                }
+                None => return None,
+            }
+        }
+
+        Some((start, headers))
+    }
+
+    /// Read a body framed by `headers`, either chunked or with a Content-Length
+    ///
+    /// Without either, the body runs until the stream is closed if `until_close` is set and is
+    /// empty otherwise.
+    pub fn read_body(&mut self, headers: &Headers, until_close: bool) -> Option<Vec<u8>> {
+        if headers.has_token("Transfer-Encoding", "chunked") {
+            let mut body = Vec::new();
+            loop {
+                let size = match self.read_line() {
+                    Some(line) => line.trim().to_string().to_num_radix(16),
+                    None => return None,
+                };
+
+                if size == 0 {
+                    // Skip the trailer
+                    loop {
+                        match self.read_line() {
+                            Some(line) => if line.is_empty() {
+                                return Some(body);
+                            },
+                            None => return None,
+                        }
+                    }
+                }
+
+                match self.read_exact(size) {
+                    Some(chunk) => body.push_all(&chunk),
+                    None => return None,
+                }
+
+                if self.read_line().is_none() {
+                    return None;
+                }
+            }
+        } else if let Some(length) = headers.get("Content-Length") {
+            self.read_exact(length.trim().to_string().to_num())
+        } else if until_close {
+            Some(self.read_to_close())
+        } else {
+            Some(Vec::new())
+        }
+    }
+
+    pub fn write(&mut self, bytes: &[u8]) -> bool {
+        self.stream.write(bytes).is_some()
+    }
+}
+
+/// An HTTP request
+pub struct Request {
+    pub method: String,
+    /// The path and query
+    pub path: String,
+    pub version: String,
+    pub headers: Headers,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    pub fn new(method: &str, path: &str) -> Self {
+        Request {
+            method: method.to_string(),
+            path: path.to_string(),
+            version: "HTTP/1.1".to_string(),
+            headers: Headers::new(),
+            body: Vec::new(),
+        }
+    }
+
+    /// Read a request, returns None when the connection was closed
+    pub fn read<S: Read + Write>(connection: &mut Connection<S>) -> Option<Self> {
+        let (start, headers) = match connection.read_head() {
+            Some(head) => head,
+            None => return None,
+        };
+
+        let mut parts = start.split(' ');
+        let mut request = match (parts.next(), parts.next()) {
+            (Some(method), Some(path)) => Request::new(method, path),
+            _ => return None,
+        };
+        request.version = parts.next().unwrap_or("HTTP/1.0").to_string();
+
+        request.body = match connection.read_body(&headers, false) {
+            Some(body) => body,
+            None => return None,
+        };
+        request.headers = headers;
+
+        Some(request)
+    }
+
+    /// Will the client send another request on this connection
+    pub fn keep_alive(&self) -> bool {
+        if self.version == "HTTP/1.0" {
+            self.headers.has_token("Connection", "keep-alive")
+        } else {
+            !self.headers.has_token("Connection", "close")
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.push_all(format!("{} {} {}\r\n", self.method, self.path, self.version).as_bytes());
+        if !self.body.is_empty() && self.headers.get("Content-Length").is_none() {
+            bytes.push_all(format!("Content-Length: {}\r\n", self.body.len()).as_bytes());
+        }
+        self.headers.push_bytes(&mut bytes);
+        bytes.push_all(&self.body);
+        bytes
+    }
+}
+
+/// An HTTP response
+pub struct Response {
+    pub version: String,
+    pub status: u16,
+    pub reason: String,
+    pub headers: Headers,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    pub fn new(status: u16, reason: &str) -> Self {
+        Response {
+            version: "HTTP/1.1".to_string(),
+            status: status,
+            reason: reason.to_string(),
+            headers: Headers::new(),
+            body: Vec::new(),
+        }
+    }
+
+    /// Read the response to a request, skipping interim responses
+    ///
+    /// Responses to HEAD have no body, whatever their headers say.
+    pub fn read<S: Read + Write>(connection: &mut Connection<S>, head: bool) -> Option<Self> {
+        loop {
+            let (start, headers) = match connection.read_head() {
+                Some(head) => head,
+                None => return None,
+            };
+
+            let mut parts = start.splitn(3, ' ');
+            let mut response = match (parts.next(), parts.next()) {
+                (Some(version), Some(status)) => {
+                    let mut response = Response::new(status.to_string().to_num() as u16,
+                                                     parts.next().unwrap_or(""));
+                    response.version = version.to_string();
+                    response
+                }
+                _ => return None,
+            };
+
+            if response.status >= 100 && response.status < 200 {
+                continue;
+            }
+
+            if !head && response.status != 204 && response.status != 304 {
+                response.body = match connection.read_body(&headers, true) {
+                    Some(body) => body,
+                    None => return None,
+                };
+            }
+            response.headers = headers;
+
+            return Some(response);
+        }
+    }
+
+    /// Will the server take another request on this connection
+    pub fn keep_alive(&self) -> bool {
+        if self.version == "HTTP/1.0" {
+            self.headers.has_token("Connection", "keep-alive")
+        } else {
+            !self.headers.has_token("Connection", "close")
+        }
+    }
+
+    /// Serialize the response, adding a Content-Length for the body if there is none
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.push_all(format!("{} {} {}\r\n", self.version, self.status, self.reason).as_bytes());
+        if self.headers.get("Content-Length").is_none() {
+            bytes.push_all(format!("Content-Length: {}\r\n", self.body.len()).as_bytes());
+        }
+        self.headers.push_bytes(&mut bytes);
+        bytes.push_all(&self.body);
+        bytes
+    }
+}
+
+/// An `http://HOST[:PORT]/PATH` URL
+#[derive(Clone)]
+pub struct HttpUrl {
+    pub host: String,
+    pub port: u16,
+    /// The path and query, starting with `/`
+    pub path: String,
+}
+
+impl HttpUrl {
+    pub fn from_string(string: &str) -> Option<Self> {
+        let url = Url::from_str(string);
+        if url.scheme() != "http" || url.host().is_empty() {
+            return None;
+        }
+
+        let port = match url.port().to_num() {
+            0 => 80,
+            port if port < 65536 => port as u16,
+            _ => return None,
+        };
+
+        Some(HttpUrl {
+            host: url.host(),
+            port: port,
+            path: "/".to_string() + &url.path(),
+        })
+    }
+
+    /// The `HOST:PORT` to connect to
+    pub fn authority(&self) -> String {
+        if self.host.contains(':') {
+            format!("[{}]:{}", self.host, self.port)
+        } else {
+            format!("{}:{}", self.host, self.port)
+        }
+    }
+
+    /// The value of the Host header
+    pub fn host_header(&self) -> String {
+        if self.port == 80 && !self.host.contains(':') {
+            self.host.clone()
+        } else {
+            self.authority()
+        }
+    }
+
+    /// Resolve a Location against this URL
+    pub fn join(&self, location: &str) -> Option<Self> {
+        if location.starts_with("http://") {
+            HttpUrl::from_string(location)
+        } else if location.contains("://") {
+            None
+        } else {
+            let mut url = self.clone();
+            if location.starts_with('/') {
+                url.path = location.to_string();
+            } else {
+                let dir = match url.path.split('?').next() {
+                    Some(path) => match path.rfind('/') {
+                        Some(i) => path[.. i + 1].to_string(),
+                        None => "/".to_string(),
+                    },
+                    None => "/".to_string(),
+                };
+                url.path = dir + location;
+            }
+            Some(url)
+        }
+    }
+
+    pub fn to_string(&self) -> String {
+        format!("http://{}{}", self.host_header(), self.path)
+    }
+}
+
+/// An HTTP client, keeping the connection to the last server open
+pub struct Client {
+    /// The kept-alive connection and the authority it goes to
+    connection: Option<(String, Connection<TcpStream>)>,
+    pub max_redirects: usize,
+    pub user_agent: String,
+}
+
+impl Client {
+    pub fn new() -> Self {
+        Client {
+            connection: None,
+            max_redirects: MAX_REDIRECTS,
+            user_agent: "redox".to_string(),
+        }
+    }
+
+    pub fn get(&mut self, url: &str) -> Option<Response> {
+        self.request(url, Request::new("GET", "/"))
+    }
+
+    pub fn head(&mut self, url: &str) -> Option<Response> {
+        self.request(url, Request::new("HEAD", "/"))
+    }
+
+    /// Send `request` to `url`, following redirects
+    ///
+    /// The path of the request is taken from the URL.
+    pub fn request(&mut self, url: &str, mut request: Request) -> Option<Response> {
+        let mut url = match HttpUrl::from_string(url) {
+            Some(url) => url,
+            None => return None,
+        };
+
+        let mut redirects = 0;
+        loop {
+            request.path = url.path.clone();
+            request.headers.set("Host", &url.host_header());
+            if request.headers.get("User-Agent").is_none() {
+                request.headers.set("User-Agent", &self.user_agent);
+            }
+
+            let response = match self.send(&url, &request) {
+                Some(response) => response,
+                None => return None,
+            };
+
+            let redirect = match response.status {
+                301 | 302 | 303 | 307 | 308 => redirects < self.max_redirects,
+                _ => false,
+            };
+
+            if redirect {
+                let next = match response.headers.get("Location") {
+                    Some(location) => url.join(location),
+                    None => None,
+                };
+
+                if let Some(next) = next {
+                    // Only 307 and 308 repeat the method and body
+                    if response.status == 303 ||
+                       (response.status < 303 && request.method == "POST") {
+                        request.method = "GET".to_string();
+                        request.body.clear();
+                        request.headers.remove("Content-Length");
+                        request.headers.remove("Content-Type");
+                    }
+
+                    url = next;
+                    redirects += 1;
+                    continue;
+                }
+            }
+
+            return Some(response);
+        }
+    }
+
+    /// Send over the kept-alive connection if it goes to the same server, opening a new one if
+    /// there is none or the server has closed it
+    fn send(&mut self, url: &HttpUrl, request: &Request) -> Option<Response> {
+        let authority = url.authority();
+
+        let reuse = match self.connection {
+            Some((ref connected, _)) => *connected == authority,
+            None => false,
+        };
+
+        if reuse {
+            if let Some(response) = self.exchange(request) {
+                return Some(response);
+            }
+        }
+
+        self.connection = match TcpStream::connect(&authority) {
+            Some(stream) => Some((authority, Connection::new(stream))),
+            None => return None,
+        };
+
+        self.exchange(request)
+    }
+
+    fn exchange(&mut self, request: &Request) -> Option<Response> {
+        let response = match self.connection {
+            Some((_, ref mut connection)) => if connection.write(&request.to_bytes()) {
+                Response::read(connection, request.method == "HEAD")
+            } else {
+                None
+            },
+            None => None,
+        };
+
+        let keep_alive = match response {
+            Some(ref response) => response.keep_alive(),
+            None => false,
+        };
+
+        if !keep_alive {
+            self.connection = None;
+        }
+
+        response
+    }
+}
@@ -6,12 +6,16 @@ use string::String;
 use vec::{IntoIter, Vec};
 use syscall::{sys_read, sys_write};
 
+/// Returned by reads of a file opened with O_NONBLOCK when nothing is ready, -EAGAIN
+pub use syscall::common::WOULD_BLOCK;
+
 pub struct Error;
 
 /// Types you can read
 pub trait Read {
 
-    /// Read a file to a buffer
+    /// Read a file to a buffer, returning `WOULD_BLOCK` if it was opened with O_NONBLOCK and
+    /// nothing is ready
     fn read(&mut self, buf: &mut [u8]) -> Option<usize>;
 
     /// Read the file to the end
@@ -20,7 +24,7 @@ pub trait Read {
         loop {
             let mut bytes = [0; 4096];
             match self.read(&mut bytes) {
-                Some(0) => return Some(read),
+                Some(0) | Some(WOULD_BLOCK) => return Some(read),
                 None => return None,
                 Some(count) => {
                     vec.push_all(&bytes[0..count]);
@@ -36,7 +40,7 @@ pub trait Read {
         loop {
             let mut bytes = [0; 4096];
             match self.read(&mut bytes) {
-                Some(0) => return Some(read),
+                Some(0) | Some(WOULD_BLOCK) => return Some(read),
                 None => return None,
                 Some(count) => {
                     string.push_str(unsafe { &str::from_utf8_unchecked(&bytes[0..count]) });
@@ -141,6 +141,7 @@
     pub mod env;
     // TODO pub mod ffi;
     pub mod fs;
+    pub mod http;
     pub mod io;
     pub mod net;
     // TODO pub mod os;
@@ -178,6 +179,10 @@
 /* } STD COPY */
 
 /* Additional Stuff { */
+    /// The `try_option!` macro
+    #[macro_use]
+    mod try_option;
+
     pub use boxed::Box;
     pub use env::*;
     pub use fs::*;
@@ -190,7 +195,12 @@
     #[cfg(not(std))]
     pub use console::*;
     pub use graphics::bmp::*;
+    pub use graphics::canvas::*;
     pub use graphics::color::*;
+    pub use graphics::font::*;
+    pub use graphics::image::*;
+    pub use graphics::jpeg::*;
+    pub use graphics::png::*;
     pub use orbital::*;
     pub use orbital::event::*;
     pub use url::*;
@@ -212,6 +222,9 @@
         pub mod wav;
     }
 
+    /// A module for the clipboard, shared with other applications
+    pub mod clipboard;
+
     /// A module for console functionality
     #[cfg(not(std))]
     #[macro_use]
@@ -219,7 +232,14 @@
     /// Graphics support
     mod graphics {
         pub mod bmp;
+        pub mod canvas;
         pub mod color;
+        pub mod decode;
+        pub mod font;
+        pub mod image;
+        pub mod jpeg;
+        pub mod png;
+        mod raster;
     }
     /// A module for window support
     pub mod orbital;
@@ -1,9 +1,18 @@
 //! A module dealing with network connections
 
+use core::mem;
+
+use fs::File;
+use io::{Read, Write};
 use string::{String, ToString};
 use to_num::ToNum;
+use url::Url;
 use vec::Vec;
 
+/// The text form of IPv6 addresses, shared with the kernel
+#[path = "net/ipv6_addr.rs"]
+mod ipv6_addr;
+
 pub trait FromBytes {
     fn from_bytes(bytes: Vec<u8>) -> Option<Self> where Self: Sized;
 }
@@ -100,8 +109,6 @@ impl MACAddr {
 
 pub static BROADCAST_MAC_ADDR: MACAddr = MACAddr { bytes: [0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF] };
 
-pub static mut MAC_ADDR: MACAddr = MACAddr { bytes: [0x00, 0x00, 0x00, 0x00, 0x00, 0x00] };
-
 #[derive(Copy, Clone)]
 pub struct IPv4Addr {
     pub bytes: [u8; 4],
@@ -146,9 +153,129 @@ pub struct IPv6Addr {
     pub bytes: [u8; 16],
 }
 
-pub static BROADCAST_IP_ADDR: IPv4Addr = IPv4Addr { bytes: [10, 85, 85, 255] };
+impl IPv6Addr {
+    pub fn equals(&self, other: Self) -> bool {
+        for i in 0..16 {
+            if self.bytes[i] != other.bytes[i] {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Parse an address like `fe80::5054:ff:fe12:3456`
+    pub fn from_string(string: &String) -> Self {
+        IPv6Addr { bytes: ipv6_addr::parse(string) }
+    }
+
+    /// Format as eight hexadecimal groups, compressing the longest run of zeros
+    pub fn to_string(&self) -> String {
+        ipv6_addr::to_string(&self.bytes)
+    }
+}
+
+/// An IPv4 or IPv6 address
+#[derive(Copy, Clone)]
+pub enum IpAddr {
+    V4(IPv4Addr),
+    V6(IPv6Addr),
+}
+
+impl IpAddr {
+    /// Parse an address, addresses containing ':' are IPv6
+    pub fn from_string(string: &String) -> Self {
+        if string.contains(':') {
+            IpAddr::V6(IPv6Addr::from_string(string))
+        } else {
+            IpAddr::V4(IPv4Addr::from_string(string))
+        }
+    }
+
+    /// Our own address on the `ip://` or `ip6://` resource `ip`
+    pub fn local(ip: &File) -> Option<Self> {
+        match ip.path() {
+            Some(path) => if path.starts_with("ip6:") {
+                // ip6://PEER/PROTO/LOCAL
+                match path.split('/').nth(4) {
+                    Some(local) => Some(IpAddr::V6(IPv6Addr::from_string(&local.to_string()))),
+                    None => None,
+                }
+            } else {
+                // ip://PEER/PROTO/LOCAL
+                match path.split('/').nth(4) {
+                    Some(local) => Some(IpAddr::V4(IPv4Addr::from_string(&local.to_string()))),
+                    None => None,
+                }
+            },
+            None => None,
+        }
+    }
+
+    pub fn equals(&self, other: Self) -> bool {
+        match (*self, other) {
+            (IpAddr::V4(a), IpAddr::V4(b)) => a.equals(b),
+            (IpAddr::V6(a), IpAddr::V6(b)) => a.equals(b),
+            _ => false,
+        }
+    }
+
+    pub fn to_string(&self) -> String {
+        match *self {
+            IpAddr::V4(ref addr) => addr.to_string(),
+            IpAddr::V6(ref addr) => addr.to_string(),
+        }
+    }
+
+    /// Format for the host part of an URL, with IPv6 in brackets
+    pub fn to_url_host(&self) -> String {
+        match *self {
+            IpAddr::V4(ref addr) => addr.to_string(),
+            IpAddr::V6(ref addr) => format!("[{}]", addr.to_string()),
+        }
+    }
+
+    /// The path of the `ip://` or `ip6://` resource reaching this address with protocol `proto`
+    pub fn ip_path(&self, proto: u8) -> String {
+        match *self {
+            IpAddr::V4(ref addr) => format!("ip://{}/{:X}", addr.to_string(), proto),
+            IpAddr::V6(ref addr) => format!("ip6://{}/{:X}", addr.to_string(), proto),
+        }
+    }
+
+    /// Checksum sum of the address, for pseudo headers
+    pub unsafe fn sum(&self) -> usize {
+        match *self {
+            IpAddr::V4(ref addr) => Checksum::sum((addr as *const IPv4Addr) as usize, mem::size_of::<IPv4Addr>()),
+            IpAddr::V6(ref addr) => Checksum::sum((addr as *const IPv6Addr) as usize, mem::size_of::<IPv6Addr>()),
+        }
+    }
+}
+
+/// A connection listed by `netstat://connections` while this is open, for the tcp and udp schemes
+pub struct NetstatEntry {
+    file: Option<File>,
+}
 
-pub static IP_ADDR: IPv4Addr = IPv4Addr { bytes: [10, 85, 85, 2] };
+impl NetstatEntry {
+    pub fn new() -> Self {
+        NetstatEntry { file: File::open("netstat://connection") }
+    }
+
+    /// List the connection as `PROTO LOCAL:PORT PEER:PORT STATE`, replacing what was listed
+    pub fn report(&mut self, proto: &str, host_addr: IpAddr, host_port: u16, peer_addr: IpAddr, peer_port: u16,
+                  state: &str) {
+        if let Some(ref mut file) = self.file {
+            file.write(format!("{} {}:{} {}:{} {}",
+                               proto,
+                               host_addr.to_url_host(),
+                               host_port,
+                               peer_addr.to_url_host(),
+                               peer_port,
+                               state)
+                           .as_bytes());
+        }
+    }
+}
 
 #[derive(Copy, Clone)]
 pub struct Checksum {
@@ -207,3 +334,271 @@ impl Checksum {
         0xFFFF - (sum as u16)
     }
 }
+
+/// An IP address and a port
+#[derive(Copy, Clone)]
+pub struct SocketAddr {
+    pub ip: IpAddr,
+    pub port: u16,
+}
+
+impl SocketAddr {
+    pub fn new(ip: IpAddr, port: u16) -> Self {
+        SocketAddr {
+            ip: ip,
+            port: port,
+        }
+    }
+
+    /// Parse `HOST:PORT`, with IPv6 hosts in brackets like `[fe80::1]:80`
+    pub fn from_string(string: &String) -> Option<Self> {
+        let url = Url::from_string("socket://".to_string() + string);
+
+        let host = url.host();
+        let port = url.port().to_num();
+        if !host.is_empty() && port > 0 && port < 65536 {
+            Some(SocketAddr::new(IpAddr::from_string(&host), port as u16))
+        } else {
+            None
+        }
+    }
+
+    /// Parse the peer of a `tcp://` or `udp://` path, `SCHEME://HOST:PORT/LOCAL_PORT`
+    fn from_path(path: &String) -> Option<Self> {
+        match path.split('/').nth(2) {
+            Some(host_port) => SocketAddr::from_string(&host_port.to_string()),
+            None => None,
+        }
+    }
+
+    /// The local port of a `tcp://` or `udp://` path
+    fn local_port(path: &String) -> u16 {
+        match path.split('/').nth(3) {
+            Some(port) => port.to_num() as u16,
+            None => 0,
+        }
+    }
+
+    pub fn equals(&self, other: Self) -> bool {
+        self.ip.equals(other.ip) && self.port == other.port
+    }
+
+    pub fn to_string(&self) -> String {
+        format!("{}:{}", self.ip.to_url_host(), self.port)
+    }
+}
+
+/// Anything that names a socket address, like `SocketAddr` or `"10.85.85.1:80"`
+pub trait ToSocketAddr {
+    fn to_socket_addr(&self) -> Option<SocketAddr>;
+}
+
+impl ToSocketAddr for SocketAddr {
+    fn to_socket_addr(&self) -> Option<SocketAddr> {
+        Some(*self)
+    }
+}
+
+impl ToSocketAddr for str {
+    fn to_socket_addr(&self) -> Option<SocketAddr> {
+        SocketAddr::from_string(&self.to_string())
+    }
+}
+
+impl ToSocketAddr for String {
+    fn to_socket_addr(&self) -> Option<SocketAddr> {
+        SocketAddr::from_string(self)
+    }
+}
+
+/// A TCP connection
+pub struct TcpStream {
+    file: File,
+}
+
+impl TcpStream {
+    /// Connect to a remote host
+    pub fn connect<A: ToSocketAddr + ?Sized>(addr: &A) -> Option<TcpStream> {
+        match addr.to_socket_addr() {
+            Some(addr) => match File::open(&format!("tcp://{}", addr.to_string())) {
+                Some(file) => Some(TcpStream { file: file }),
+                None => None,
+            },
+            None => None,
+        }
+    }
+
+    /// The remote address of this connection
+    pub fn peer_addr(&self) -> Option<SocketAddr> {
+        match self.file.path() {
+            Some(path) => SocketAddr::from_path(&path),
+            None => None,
+        }
+    }
+
+    /// The local port of this connection
+    pub fn local_port(&self) -> u16 {
+        match self.file.path() {
+            Some(path) => SocketAddr::local_port(&path),
+            None => 0,
+        }
+    }
+
+    pub fn try_clone(&self) -> Option<TcpStream> {
+        match self.file.dup() {
+            Some(file) => Some(TcpStream { file: file }),
+            None => None,
+        }
+    }
+}
+
+impl Read for TcpStream {
+    fn read(&mut self, buf: &mut [u8]) -> Option<usize> {
+        self.file.read(buf)
+    }
+}
+
+impl Write for TcpStream {
+    fn write(&mut self, buf: &[u8]) -> Option<usize> {
+        self.file.write(buf)
+    }
+}
+
+/// A TCP server, accepting connections on a local port
+pub struct TcpListener {
+    port: u16,
+    /// The listening `tcp:///PORT` resource, the tcp scheme keeps the connections waiting to be
+    /// accepted and each dup accepts one
+    file: File,
+}
+
+impl TcpListener {
+    /// Listen on the port of `addr`
+    pub fn bind<A: ToSocketAddr + ?Sized>(addr: &A) -> Option<TcpListener> {
+        match addr.to_socket_addr() {
+            Some(addr) => match File::open(&format!("tcp:///{}", addr.port)) {
+                Some(file) => Some(TcpListener {
+                    port: addr.port,
+                    file: file,
+                }),
+                None => None,
+            },
+            None => None,
+        }
+    }
+
+    /// Wait for the next connection
+    pub fn accept(&self) -> Option<(TcpStream, SocketAddr)> {
+        match self.file.dup() {
+            Some(file) => {
+                let stream = TcpStream { file: file };
+                match stream.peer_addr() {
+                    Some(addr) => Some((stream, addr)),
+                    None => None,
+                }
+            }
+            None => None,
+        }
+    }
+
+    pub fn local_port(&self) -> u16 {
+        self.port
+    }
+}
+
+/// A UDP socket bound to a local port
+pub struct UdpSocket {
+    port: u16,
+    /// The bound `udp:///PORT` resource, receiving the datagrams sent to our port
+    file: File,
+    /// The `udp://PEER/PORT` resource of the last destination, kept open for the next datagrams to it
+    peer: Option<(String, File)>,
+    /// Memberships of multicast groups, each an open `igmp://GROUP`
+    groups: Vec<(IPv4Addr, File)>,
+}
+
+impl UdpSocket {
+    /// Bind to the port of `addr`, datagrams sent to it are queued from now on
+    pub fn bind<A: ToSocketAddr + ?Sized>(addr: &A) -> Option<UdpSocket> {
+        match addr.to_socket_addr() {
+            Some(addr) => match File::open(&format!("udp:///{}", addr.port)) {
+                Some(file) => Some(UdpSocket {
+                    port: addr.port,
+                    file: file,
+                    peer: None,
+                    groups: Vec::new(),
+                }),
+                None => None,
+            },
+            None => None,
+        }
+    }
+
+    /// Send a datagram from our port to `addr`, which may be a broadcast or multicast address
+    pub fn send_to<A: ToSocketAddr + ?Sized>(&mut self, buf: &[u8], addr: &A) -> Option<usize> {
+        let addr = match addr.to_socket_addr() {
+            Some(addr) => addr.to_string(),
+            None => return None,
+        };
+
+        let reuse = match self.peer {
+            Some((ref peer, _)) => *peer == addr,
+            None => false,
+        };
+        if !reuse {
+            self.peer = match File::open(&format!("udp://{}/{}", addr, self.port)) {
+                Some(file) => Some((addr, file)),
+                None => return None,
+            };
+        }
+
+        match self.peer {
+            Some((_, ref mut file)) => file.write(buf),
+            None => None,
+        }
+    }
+
+    /// Wait for a datagram sent to our port, returning its length and sender
+    ///
+    /// The part of the datagram that does not fit in `buf` is discarded.
+    pub fn recv_from(&mut self, buf: &mut [u8]) -> Option<(usize, SocketAddr)> {
+        match self.file.read(buf) {
+            Some(count) => match self.file.path() {
+                Some(path) => match SocketAddr::from_path(&path) {
+                    Some(addr) => Some((count, addr)),
+                    None => None,
+                },
+                None => None,
+            },
+            None => None,
+        }
+    }
+
+    /// Receive datagrams sent to the multicast `group`, until it is left or the socket is dropped
+    pub fn join_multicast(&mut self, group: &IPv4Addr) -> bool {
+        for &(ref addr, _) in self.groups.iter() {
+            if addr.equals(*group) {
+                return true;
+            }
+        }
+
+        match File::open(&format!("igmp://{}", group.to_string())) {
+            Some(file) => {
+                self.groups.push((*group, file));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Stop receiving datagrams sent to the multicast `group`
+    pub fn leave_multicast(&mut self, group: &IPv4Addr) -> bool {
+        let len = self.groups.len();
+        self.groups.retain(|&(ref addr, _)| !addr.equals(*group));
+        self.groups.len() < len
+    }
+
+    pub fn local_port(&self) -> u16 {
+        self.port
+    }
+}
@@ -0,0 +1,110 @@
+//! The text form of IPv6 addresses
+//!
+//! The kernel includes this file with `#[path]`, like the image decoders, so it only uses
+//! `core` and `collections`.
+
+use collections::string::String;
+use collections::vec::Vec;
+
+/// Parse a group of hexadecimal digits, up to the first other character
+fn parse_group(part: &str) -> u16 {
+    let mut group = 0;
+    for c in part.bytes() {
+        let digit = match c {
+            b'0'...b'9' => c - b'0',
+            b'a'...b'f' => c - b'a' + 10,
+            b'A'...b'F' => c - b'A' + 10,
+            _ => break,
+        };
+        group = group * 16 + digit as usize;
+    }
+    group as u16
+}
+
+/// Parse an address like `fe80::5054:ff:fe12:3456`
+pub fn parse(string: &str) -> [u8; 16] {
+    let mut bytes = [0; 16];
+
+    let mut head: Vec<u16> = Vec::new();
+    let mut tail: Vec<u16> = Vec::new();
+    let mut compressed = false;
+    for (i, half) in string.split("::").enumerate() {
+        match i {
+            0 => for part in half.split(':') {
+                if !part.is_empty() {
+                    head.push(parse_group(part));
+                }
+            },
+            1 => {
+                compressed = true;
+                for part in half.split(':') {
+                    if !part.is_empty() {
+                        tail.push(parse_group(part));
+                    }
+                }
+            },
+            _ => break,
+        }
+    }
+
+    let mut groups = head;
+    if compressed {
+        while groups.len() + tail.len() < 8 {
+            groups.push(0);
+        }
+    }
+    groups.push_all(&tail);
+
+    for i in 0..8 {
+        if let Some(group) = groups.get(i) {
+            bytes[i * 2] = (*group >> 8) as u8;
+            bytes[i * 2 + 1] = *group as u8;
+        }
+    }
+
+    bytes
+}
+
+/// Format as eight hexadecimal groups, compressing the longest run of zeros
+pub fn to_string(bytes: &[u8; 16]) -> String {
+    let mut groups = [0u16; 8];
+    for i in 0..8 {
+        groups[i] = ((bytes[i * 2] as u16) << 8) | (bytes[i * 2 + 1] as u16);
+    }
+
+    let mut zeros_start = 8;
+    let mut zeros_len = 0;
+    let mut i = 0;
+    while i < 8 {
+        if groups[i] == 0 {
+            let start = i;
+            while i < 8 && groups[i] == 0 {
+                i += 1;
+            }
+            if i - start > zeros_len && i - start > 1 {
+                zeros_start = start;
+                zeros_len = i - start;
+            }
+        } else {
+            i += 1;
+        }
+    }
+
+    let mut string = String::new();
+
+    for i in 0..8 {
+        if i >= zeros_start && i < zeros_start + zeros_len {
+            if i == zeros_start {
+                string = string + "::";
+            }
+            continue;
+        }
+
+        if i > 0 && !string.ends_with(':') {
+            string = string + ":";
+        }
+        string = string + &format!("{:x}", groups[i]);
+    }
+
+    string
+}
@@ -0,0 +1,74 @@
+//! The modes of the display
+//!
+//! Switching the mode lays the windows out on the screen again, and windows that no longer fit
+//! get a resize event.
+
+use fs::File;
+use io::{Read, Write};
+use string::{String, ToString};
+use to_num::ToNum;
+use vec::Vec;
+
+/// A resolution of the display
+#[derive(Copy, Clone, PartialEq)]
+pub struct DisplayMode {
+    pub width: usize,
+    pub height: usize,
+}
+
+impl DisplayMode {
+    pub fn new(width: usize, height: usize) -> Self {
+        DisplayMode {
+            width: width,
+            height: height,
+        }
+    }
+
+    /// Parse `WIDTHxHEIGHT`
+    pub fn from_str(string: &str) -> Option<Self> {
+        let mut parts = string.trim().splitn(2, 'x');
+        let width = parts.next().map_or(0, |part| part.to_string().to_num());
+        let height = parts.next().map_or(0, |part| part.to_string().to_num());
+        if width > 0 && height > 0 {
+            Some(DisplayMode::new(width, height))
+        } else {
+            None
+        }
+    }
+
+    pub fn to_string(&self) -> String {
+        format!("{}x{}", self.width, self.height)
+    }
+}
+
+/// The modes the display can be switched to
+pub fn modes() -> Vec<DisplayMode> {
+    let mut string = String::new();
+    if let Some(mut file) = File::open("display:modes") {
+        file.read_to_string(&mut string);
+    }
+    string.lines().filter_map(|line| DisplayMode::from_str(line)).collect()
+}
+
+/// The current mode
+pub fn mode() -> Option<DisplayMode> {
+    let mut string = String::new();
+    match File::open("display:mode") {
+        Some(mut file) => {
+            file.read_to_string(&mut string);
+            DisplayMode::from_str(&string)
+        }
+        None => None,
+    }
+}
+
+/// Switch to a mode, which is one of `modes()`
+pub fn set_mode(mode: DisplayMode) -> bool {
+    match File::open("display:mode") {
+        Some(mut file) => {
+            let string = mode.to_string();
+            file.write(string.as_bytes()) == Some(string.len())
+        }
+        None => false,
+    }
+}
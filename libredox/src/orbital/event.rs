@@ -1,4 +1,4 @@
-use core::char;
+use core::{char, cmp};
 
 /// An optional event
 #[derive(Copy, Clone)]
@@ -9,6 +9,14 @@ pub enum EventOption {
     Key(KeyEvent),
     /// A quit request event
     Quit(QuitEvent),
+    /// A focus in or out event
+    Focus(FocusEvent),
+    /// A resize event
+    Resize(ResizeEvent),
+    /// A scroll wheel event
+    Scroll(ScrollEvent),
+    /// A pointer enter or leave event
+    Hover(HoverEvent),
     /// An unknown event
     Unknown(Event),
     /// No event
@@ -43,6 +51,10 @@ impl Event {
             'm' => EventOption::Mouse(MouseEvent::from_event(self)),
             'k' => EventOption::Key(KeyEvent::from_event(self)),
             'q' => EventOption::Quit(QuitEvent::from_event(self)),
+            'f' => EventOption::Focus(FocusEvent::from_event(self)),
+            'r' => EventOption::Resize(ResizeEvent::from_event(self)),
+            's' => EventOption::Scroll(ScrollEvent::from_event(self)),
+            'h' => EventOption::Hover(HoverEvent::from_event(self)),
             '\0' => EventOption::None,
             _ => EventOption::Unknown(self),
         }
@@ -193,3 +205,105 @@ impl QuitEvent {
         QuitEvent
     }
 }
+
+/// The window gained or lost the keyboard focus
+#[derive(Copy, Clone)]
+pub struct FocusEvent {
+    /// Does the window have the focus now?
+    pub focused: bool,
+}
+
+impl FocusEvent {
+    pub fn to_event(&self) -> Event {
+        Event {
+            code: 'f',
+            a: self.focused as isize,
+            b: 0,
+            c: 0,
+        }
+    }
+
+    pub fn from_event(event: Event) -> FocusEvent {
+        FocusEvent {
+            focused: event.a > 0,
+        }
+    }
+}
+
+/// The content of the window changed size
+#[derive(Copy, Clone)]
+pub struct ResizeEvent {
+    /// The new width
+    pub width: usize,
+    /// The new height
+    pub height: usize,
+}
+
+impl ResizeEvent {
+    pub fn to_event(&self) -> Event {
+        Event {
+            code: 'r',
+            a: self.width as isize,
+            b: self.height as isize,
+            c: 0,
+        }
+    }
+
+    pub fn from_event(event: Event) -> ResizeEvent {
+        ResizeEvent {
+            width: cmp::max(0, event.a) as usize,
+            height: cmp::max(0, event.b) as usize,
+        }
+    }
+}
+
+/// The scroll wheel moved
+#[derive(Copy, Clone)]
+pub struct ScrollEvent {
+    /// The horizontal movement, positive to the right
+    pub x: isize,
+    /// The vertical movement, positive upwards
+    pub y: isize,
+}
+
+impl ScrollEvent {
+    pub fn to_event(&self) -> Event {
+        Event {
+            code: 's',
+            a: self.x,
+            b: self.y,
+            c: 0,
+        }
+    }
+
+    pub fn from_event(event: Event) -> ScrollEvent {
+        ScrollEvent {
+            x: event.a,
+            y: event.b,
+        }
+    }
+}
+
+/// The pointer entered or left the content of the window
+#[derive(Copy, Clone)]
+pub struct HoverEvent {
+    /// Is the pointer over the window now?
+    pub entered: bool,
+}
+
+impl HoverEvent {
+    pub fn to_event(&self) -> Event {
+        Event {
+            code: 'h',
+            a: self.entered as isize,
+            b: 0,
+            c: 0,
+        }
+    }
+
+    pub fn from_event(event: Event) -> HoverEvent {
+        HoverEvent {
+            entered: event.a > 0,
+        }
+    }
+}
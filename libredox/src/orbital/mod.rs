@@ -1,22 +1,27 @@
 use alloc::boxed::Box;
 
 use core::mem;
-use core::ops::DerefMut;
+use core::ops::{Deref, DerefMut};
 use core::slice;
 
 use string::{String, ToString};
 use vec::Vec;
 
 use event::*;
+use graphics::canvas::Canvas;
 use graphics::color::Color;
 use fs::File;
 use io::*;
 
 use syscall::sys_yield;
 
+use to_num::ToNum;
+
+pub mod display;
 pub mod event;
+pub mod widget;
 
-/// A window
+/// A window, which derefs to the `Canvas` of its content
 pub struct Window {
     /// The x coordinate of the window
     x: isize,
@@ -30,10 +35,14 @@ pub struct Window {
     t: String,
     /// The input scheme
     file: File,
+    /// Takes the commands for the window, `window://control/ID`
+    control: File,
     /// Font file
     font: Vec<u8>,
-    /// Window data
-    data: Vec<u32>,
+    /// Window content
+    canvas: Canvas,
+    /// Called with the new width and height when the window is resized
+    on_resize: Option<Box<FnMut(usize, usize)>>,
 }
 
 impl Window {
@@ -44,16 +53,29 @@ impl Window {
             font_file.read_to_end(&mut font);
         }
 
-        match File::open(&format!("window:///{}/{}/{}/{}/{}", x, y, w, h, title)) {
-            Some(file) => Some(box Window {
+        let file = match File::open(&format!("window:///{}/{}/{}/{}/{}", x, y, w, h, title)) {
+            Some(file) => file,
+            None => return None,
+        };
+
+        // The path is window://ID/X/Y/WIDTH/HEIGHT/TITLE
+        let id = match file.path() {
+            Some(path) => path.split('/').nth(2).unwrap_or("").to_string(),
+            None => return None,
+        };
+
+        match File::open(&("window://control/".to_string() + &id)) {
+            Some(control) => Some(box Window {
                 x: x,
                 y: y,
                 w: w,
                 h: h,
                 t: title.to_string(),
                 file: file,
+                control: control,
                 font: font,
-                data: vec![0; w * h * 4],
+                canvas: Canvas::new(w, h),
+                on_resize: None,
             }),
             None => None
         }
@@ -81,6 +103,46 @@ impl Window {
         self.h
     }
 
+    /// Set the size of the content, which is cleared
+    ///
+    /// The window may get another size than asked for, like when it would be too small,
+    /// `width()` and `height()` give the size it got.
+    pub fn set_size(&mut self, width: usize, height: usize) -> bool {
+        if self.control.write(format!("size {} {}", width, height).as_bytes()).is_none() {
+            return false;
+        }
+
+        let mut buf = [0; 64];
+        match self.control.read(&mut buf) {
+            Some(count) => {
+                let size = String::from_utf8_lossy(&buf[.. count]).to_string();
+                let mut parts = size.split(' ');
+                let width = parts.next().unwrap_or("").to_string().to_num();
+                let height = parts.next().unwrap_or("").to_string().to_num();
+                self.resize(width, height);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Call `callback` with the new width and height whenever the window is resized
+    pub fn on_resize(&mut self, callback: Box<FnMut(usize, usize)>) {
+        self.on_resize = Some(callback);
+    }
+
+    fn resize(&mut self, width: usize, height: usize) {
+        if width != self.w || height != self.h {
+            self.w = width;
+            self.h = height;
+            self.canvas = Canvas::new(width, height);
+
+            if let Some(ref mut callback) = self.on_resize {
+                (**callback)(width, height);
+            }
+        }
+    }
+
     /// Get title
     pub fn title(&self) -> String {
         self.t.clone()
@@ -91,14 +153,6 @@ impl Window {
         //TODO
     }
 
-    /// Draw a pixel
-    pub fn pixel(&mut self, x: isize, y: isize, color: Color) {
-        if x >= 0 && y >= 0 && x < self.w as isize && y < self.h as isize {
-            let offset = y as usize * self.w + x as usize;
-            self.data[offset] = color.data;
-        }
-    }
-
     /// Draw a character, using the loaded font
     pub fn char(&mut self, x: isize, y: isize, c: char, color: Color) {
         let mut offset = (c as usize) * 16;
@@ -120,41 +174,7 @@ impl Window {
         }
     }
 
-    //TODO move, resize, set_title
-
-    /// Set entire window to a color
-    // TODO: Improve speed
-    #[allow(unused_variables)]
-    pub fn set(&mut self, color: Color) {
-        let w = self.w;
-        let h = self.h;
-        self.rect(0, 0, w, h, color);
-    }
-
-    /// Draw rectangle
-    // TODO: Improve speed
-    #[allow(unused_variables)]
-    pub fn rect(&mut self, start_x: isize, start_y: isize, w: usize, h: usize, color: Color) {
-        for y in start_y..start_y + h as isize {
-            for x in start_x..start_x + w as isize {
-                self.pixel(x, y, color);
-            }
-        }
-    }
-
-    /// Display an image
-    //TODO: Improve speed
-    pub fn image(&mut self, start_x: isize, start_y: isize, w: usize, h: usize, data: &[Color]) {
-        let mut i = 0;
-        for y in start_y..start_y + h as isize {
-            for x in start_x..start_x + w as isize {
-                if i < data.len() {
-                    self.pixel(x, y, data[i])
-                }
-                i += 1;
-            }
-        }
-    }
+    //TODO move, set_title
 
     /// Poll for an event
     //TODO: clean this up
@@ -166,7 +186,12 @@ impl Window {
                 slice::from_raw_parts_mut(event_ptr as *mut u8, mem::size_of::<Event>())
             }) {
                 Some(0) => unsafe { sys_yield() },
-                Some(_) => return Some(*event),
+                Some(_) => {
+                    if let EventOption::Resize(resize_event) = (*event).to_option() {
+                        self.resize(resize_event.width, resize_event.height);
+                    }
+                    return Some(*event);
+                }
                 None => return None,
             }
         }
@@ -175,11 +200,22 @@ impl Window {
     /// Flip the window buffer
     pub fn sync(&mut self) -> bool {
         self.file.seek(SeekFrom::Start(0));
-        let to_write: &[u8] = unsafe{ mem::transmute::<&[u32],&[u8]>(&self.data) };
+        let data = self.canvas.data();
+        let to_write: &[u8] = unsafe { slice::from_raw_parts(data.as_ptr() as *const u8, data.len() * 4) };
         self.file.write(to_write);
         return self.file.sync();
     }
 
+    /// Get the pointer even when it is outside of the window, or stop getting it
+    pub fn set_grab(&mut self, grab: bool) -> bool {
+        let command: &[u8] = if grab {
+            b"grab"
+        } else {
+            b"ungrab"
+        };
+        self.control.write(command).is_some()
+    }
+
     /// Return a iterator over events
     pub fn event_iter<'a>(&'a mut self) -> EventIter<'a> {
         EventIter {
@@ -188,6 +224,20 @@ impl Window {
     }
 }
 
+impl Deref for Window {
+    type Target = Canvas;
+
+    fn deref(&self) -> &Canvas {
+        &self.canvas
+    }
+}
+
+impl DerefMut for Window {
+    fn deref_mut(&mut self) -> &mut Canvas {
+        &mut self.canvas
+    }
+}
+
 /// Event iterator
 pub struct EventIter<'a> {
     window: &'a mut Window,
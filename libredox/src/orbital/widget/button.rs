@@ -0,0 +1,122 @@
+use core::any::Any;
+
+use string::{String, ToString};
+
+use graphics::canvas::Canvas;
+
+use super::{Action, Input, Rect, Reply, Theme, Widget};
+
+/// A button with text, which is clicked with the pointer, Enter or space
+pub struct Button {
+    rect: Rect,
+    text: String,
+    /// Is the left button held on the button?
+    pressed: bool,
+}
+
+impl Button {
+    /// Create a button
+    pub fn new(text: &str) -> Self {
+        Button {
+            rect: Rect::default(),
+            text: text.to_string(),
+            pressed: false,
+        }
+    }
+
+    /// The text
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Change the text
+    pub fn set_text(&mut self, text: &str) {
+        self.text = text.to_string();
+    }
+}
+
+impl Widget for Button {
+    fn rect(&self) -> Rect {
+        self.rect
+    }
+
+    fn set_rect(&mut self, rect: Rect) {
+        self.rect = rect;
+    }
+
+    #[allow(unused_variables)]
+    fn size_hint(&self, theme: &mut Theme, children: &[(usize, usize)]) -> (usize, usize) {
+        let (width, height) = theme.measure(&self.text);
+        (width + 24, height + 12)
+    }
+
+    fn draw(&self, canvas: &mut Canvas, theme: &mut Theme, focused: bool) {
+        let rect = self.rect;
+        let background = if self.pressed {
+            theme.button_pressed
+        } else {
+            theme.button
+        };
+        let border = if focused {
+            theme.highlight
+        } else {
+            theme.border
+        };
+        canvas.fill_rounded_rect(rect.x, rect.y, rect.w, rect.h, 4, background);
+        canvas.rounded_rect(rect.x, rect.y, rect.w, rect.h, 4, border);
+
+        let (width, height) = theme.measure(&self.text);
+        let x = rect.x + (rect.w as isize - width as isize) / 2;
+        let y = rect.y + (rect.h as isize - height as isize) / 2;
+        let color = theme.text;
+        theme.text(canvas, x, y, &self.text, color);
+    }
+
+    #[allow(unused_variables)]
+    fn event(&mut self, input: Input, theme: &mut Theme) -> Reply {
+        match input {
+            Input::Press(_, _) => {
+                self.pressed = true;
+                Reply::Redraw
+            }
+            Input::Drag(x, y) => {
+                let pressed = self.rect.contains(x, y);
+                if pressed != self.pressed {
+                    self.pressed = pressed;
+                    Reply::Redraw
+                } else {
+                    Reply::Ignored
+                }
+            }
+            Input::Release(x, y) => {
+                self.pressed = false;
+                if self.rect.contains(x, y) {
+                    Reply::Action(Action::Clicked)
+                } else {
+                    Reply::Redraw
+                }
+            }
+            Input::Key(key_event) => {
+                if key_event.pressed &&
+                   (key_event.character == '\n' || key_event.character == ' ') {
+                    Reply::Action(Action::Clicked)
+                } else {
+                    Reply::Ignored
+                }
+            }
+            Input::Scroll(_, _) => Reply::Ignored,
+        }
+    }
+
+    fn focusable(&self) -> bool {
+        true
+    }
+
+    fn as_any(&self) -> &Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut Any {
+        self
+    }
+}
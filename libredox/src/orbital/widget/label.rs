@@ -0,0 +1,80 @@
+use core::any::Any;
+
+use string::{String, ToString};
+
+use graphics::canvas::Canvas;
+use graphics::color::Color;
+
+use super::{Input, Rect, Reply, Theme, Widget};
+
+/// Text that does not change by itself, which can have several lines
+pub struct Label {
+    rect: Rect,
+    text: String,
+    /// The color of the text, or that of the theme
+    color: Option<Color>,
+}
+
+impl Label {
+    /// Create a label
+    pub fn new(text: &str) -> Self {
+        Label {
+            rect: Rect::default(),
+            text: text.to_string(),
+            color: None,
+        }
+    }
+
+    /// The text
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Change the text
+    pub fn set_text(&mut self, text: &str) {
+        self.text = text.to_string();
+    }
+
+    /// Draw the text in a color, rather than that of the theme
+    pub fn set_color(&mut self, color: Color) {
+        self.color = Some(color);
+    }
+}
+
+impl Widget for Label {
+    fn rect(&self) -> Rect {
+        self.rect
+    }
+
+    fn set_rect(&mut self, rect: Rect) {
+        self.rect = rect;
+    }
+
+    #[allow(unused_variables)]
+    fn size_hint(&self, theme: &mut Theme, children: &[(usize, usize)]) -> (usize, usize) {
+        let (width, height) = theme.measure(&self.text);
+        (width + 4, height + 4)
+    }
+
+    #[allow(unused_variables)]
+    fn draw(&self, canvas: &mut Canvas, theme: &mut Theme, focused: bool) {
+        let color = match self.color {
+            Some(color) => color,
+            None => theme.text,
+        };
+        theme.text(canvas, self.rect.x + 2, self.rect.y + 2, &self.text, color);
+    }
+
+    #[allow(unused_variables)]
+    fn event(&mut self, input: Input, theme: &mut Theme) -> Reply {
+        Reply::Ignored
+    }
+
+    fn as_any(&self) -> &Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut Any {
+        self
+    }
+}
@@ -0,0 +1,177 @@
+use core::any::Any;
+use core::cmp;
+
+use vec::Vec;
+
+use graphics::canvas::Canvas;
+
+use super::{Input, Rect, Reply, Theme, Widget, WidgetId};
+
+/// Places its children in a row or a column
+///
+/// Each child is as long as its size hint along the layout, and as wide as the layout across
+/// it. The space that is left over goes to the children added with `push_stretch`, or stays
+/// empty at the end.
+pub struct Layout {
+    rect: Rect,
+    vertical: bool,
+    children: Vec<WidgetId>,
+    /// Does each child get some of the space left over?
+    stretch: Vec<bool>,
+    /// The space between children
+    pub spacing: usize,
+    /// The space around the children
+    pub padding: usize,
+}
+
+impl Layout {
+    /// Place the children from left to right
+    pub fn row() -> Self {
+        Layout::new(false)
+    }
+
+    /// Place the children from top to bottom
+    pub fn column() -> Self {
+        Layout::new(true)
+    }
+
+    fn new(vertical: bool) -> Self {
+        Layout {
+            rect: Rect::default(),
+            vertical: vertical,
+            children: Vec::new(),
+            stretch: Vec::new(),
+            spacing: 0,
+            padding: 0,
+        }
+    }
+
+    /// Add a child after the others
+    pub fn push(&mut self, id: WidgetId) {
+        self.children.push(id);
+        self.stretch.push(false);
+    }
+
+    /// Add a child after the others, which gets the space left over
+    pub fn push_stretch(&mut self, id: WidgetId) {
+        self.children.push(id);
+        self.stretch.push(true);
+    }
+
+    /// Remove every child
+    pub fn clear(&mut self) {
+        self.children.clear();
+        self.stretch.clear();
+    }
+
+    /// The length along the layout and across it of a size
+    fn split(&self, size: (usize, usize)) -> (usize, usize) {
+        if self.vertical {
+            (size.1, size.0)
+        } else {
+            size
+        }
+    }
+}
+
+impl Widget for Layout {
+    fn rect(&self) -> Rect {
+        self.rect
+    }
+
+    fn set_rect(&mut self, rect: Rect) {
+        self.rect = rect;
+    }
+
+    #[allow(unused_variables)]
+    fn size_hint(&self, theme: &mut Theme, children: &[(usize, usize)]) -> (usize, usize) {
+        let mut along = 0;
+        let mut across = 0;
+        for &hint in children.iter() {
+            let (child_along, child_across) = self.split(hint);
+            along += child_along;
+            across = cmp::max(across, child_across);
+        }
+        if children.len() > 1 {
+            along += self.spacing * (children.len() - 1);
+        }
+        self.split((along + self.padding * 2, across + self.padding * 2))
+    }
+
+    fn children(&self) -> Vec<WidgetId> {
+        self.children.clone()
+    }
+
+    fn arrange(&mut self, children: &[(usize, usize)]) -> Vec<Rect> {
+        let (length, width) = self.split((self.rect.w, self.rect.h));
+        let length = length.saturating_sub(self.padding * 2);
+        let width = width.saturating_sub(self.padding * 2);
+
+        let mut wanted = 0;
+        let mut stretched = 0;
+        for (i, &hint) in children.iter().enumerate() {
+            wanted += self.split(hint).0;
+            if self.stretch[i] {
+                stretched += 1;
+            }
+        }
+        if children.len() > 1 {
+            wanted += self.spacing * (children.len() - 1);
+        }
+
+        // Stretched children share what is left over, or give up what is missing
+        let extra = length as isize - wanted as isize;
+        let share = if stretched > 0 {
+            extra / stretched
+        } else {
+            0
+        };
+        // Added to the next stretched child
+        let mut carry = if stretched > 0 {
+            extra % stretched
+        } else {
+            0
+        };
+
+        let mut rects = Vec::with_capacity(children.len());
+        let mut offset = self.padding as isize;
+        for (i, &hint) in children.iter().enumerate() {
+            let mut child_length = self.split(hint).0 as isize;
+            if self.stretch[i] {
+                child_length += share + carry;
+                carry = 0;
+                if child_length < 0 {
+                    carry = child_length;
+                    child_length = 0;
+                }
+            }
+
+            let (w, h) = self.split((child_length as usize, width));
+            let (x, y) = if self.vertical {
+                (self.padding as isize, offset)
+            } else {
+                (offset, self.padding as isize)
+            };
+            rects.push(Rect::new(self.rect.x + x, self.rect.y + y, w, h));
+
+            offset += child_length + self.spacing as isize;
+        }
+        rects
+    }
+
+    #[allow(unused_variables)]
+    fn draw(&self, canvas: &mut Canvas, theme: &mut Theme, focused: bool) {}
+
+    #[allow(unused_variables)]
+    fn event(&mut self, input: Input, theme: &mut Theme) -> Reply {
+        Reply::Ignored
+    }
+
+    fn as_any(&self) -> &Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut Any {
+        self
+    }
+}
@@ -0,0 +1,341 @@
+use core::any::Any;
+use core::cmp;
+
+use boxed::Box;
+use string::String;
+use vec::Vec;
+
+use event;
+use graphics::canvas::Canvas;
+use graphics::image::Image;
+use time::{self, Duration};
+
+use super::{Action, Input, Rect, Reply, Theme, Widget};
+
+/// The space around the content of a row, and between columns
+const PADDING: usize = 4;
+
+/// How many rows Page Up and Page Down move
+const PAGE: usize = 10;
+
+/// A row of a list
+pub struct ListItem {
+    /// The index of an icon added to the list with `add_icon`
+    pub icon: Option<usize>,
+    /// The text of each column
+    pub columns: Vec<String>,
+}
+
+impl ListItem {
+    /// Create a row
+    pub fn new(icon: Option<usize>, columns: Vec<String>) -> Self {
+        ListItem {
+            icon: icon,
+            columns: columns,
+        }
+    }
+}
+
+/// Rows of text in columns, with an icon at the start, one of which can be selected
+///
+/// Clicking a row selects it, and double clicking it or pressing Enter activates it. Typing a
+/// character selects the next row starting with it.
+pub struct List {
+    rect: Rect,
+    icons: Vec<Box<Image>>,
+    items: Vec<ListItem>,
+    selected: Option<usize>,
+    /// When the last row was clicked, to find double clicks
+    last_click: (usize, Duration),
+    /// The height of a row, kept for finding rows without the theme
+    row_height: usize,
+}
+
+impl List {
+    /// Create an empty list
+    pub fn new() -> Self {
+        List {
+            rect: Rect::default(),
+            icons: Vec::new(),
+            items: Vec::new(),
+            selected: None,
+            last_click: (0, Duration::new(0, 0)),
+            row_height: 0,
+        }
+    }
+
+    /// Add an icon for rows to use, returning its index
+    pub fn add_icon(&mut self, icon: Box<Image>) -> usize {
+        self.icons.push(icon);
+        self.icons.len() - 1
+    }
+
+    /// Add a row at the end
+    pub fn push(&mut self, item: ListItem) {
+        self.items.push(item);
+    }
+
+    /// Remove every row
+    pub fn clear(&mut self) {
+        self.items.clear();
+        self.selected = None;
+    }
+
+    /// The rows
+    pub fn items(&self) -> &[ListItem] {
+        &self.items
+    }
+
+    /// The selected row
+    pub fn selected(&self) -> Option<usize> {
+        self.selected
+    }
+
+    /// Select a row, or none
+    pub fn set_selected(&mut self, selected: Option<usize>) {
+        self.selected = match selected {
+            Some(i) if i < self.items.len() => Some(i),
+            _ => None,
+        };
+    }
+
+    /// The width of the icons
+    fn icon_width(&self) -> usize {
+        self.icons.iter().map(|icon| icon.width()).max().unwrap_or(0)
+    }
+
+    /// The height of a row, which fits the text and the tallest icon
+    fn row_height(&self, theme: &Theme) -> usize {
+        let icon_height = self.icons.iter().map(|icon| icon.height()).max().unwrap_or(0);
+        cmp::max(icon_height, theme.line_height()) + PADDING * 2
+    }
+
+    /// Find the left of each column, returning them and the width of the list
+    fn measure(&self, theme: &mut Theme) -> (Vec<isize>, usize) {
+        let mut widths: Vec<usize> = Vec::new();
+        for item in self.items.iter() {
+            for (i, column) in item.columns.iter().enumerate() {
+                let width = theme.measure(column).0;
+                if i < widths.len() {
+                    widths[i] = cmp::max(widths[i], width);
+                } else {
+                    widths.push(width);
+                }
+            }
+        }
+
+        let icon_width = self.icon_width();
+        let mut x = if icon_width > 0 {
+            (icon_width + PADDING * 2) as isize
+        } else {
+            PADDING as isize
+        };
+        let mut columns = Vec::with_capacity(widths.len());
+        for width in widths.iter() {
+            columns.push(x);
+            x += (width + PADDING * 2) as isize;
+        }
+
+        (columns, x as usize)
+    }
+
+    /// The row at a point
+    fn row_at(&self, y: isize) -> Option<usize> {
+        if self.row_height == 0 || y < self.rect.y {
+            return None;
+        }
+        let row = (y - self.rect.y) as usize / self.row_height;
+        if row < self.items.len() {
+            Some(row)
+        } else {
+            None
+        }
+    }
+
+    fn select(&mut self, row: usize) -> Reply {
+        if self.items.is_empty() {
+            return Reply::Ignored;
+        }
+        let row = cmp::min(row, self.items.len() - 1);
+        if self.selected == Some(row) {
+            Reply::Redraw
+        } else {
+            self.selected = Some(row);
+            Reply::Action(Action::Selected(row))
+        }
+    }
+
+    fn key(&mut self, key_event: event::KeyEvent) -> Reply {
+        let last = self.items.len().saturating_sub(1);
+        match key_event.scancode {
+            event::K_UP => {
+                match self.selected {
+                    Some(i) => self.select(i.saturating_sub(1)),
+                    None => self.select(last),
+                }
+            }
+            event::K_DOWN => {
+                match self.selected {
+                    Some(i) => self.select(i + 1),
+                    None => self.select(0),
+                }
+            }
+            event::K_PGUP => {
+                let row = self.selected.unwrap_or(0).saturating_sub(PAGE);
+                self.select(row)
+            }
+            event::K_PGDN => {
+                let row = self.selected.unwrap_or(0) + PAGE;
+                self.select(row)
+            }
+            event::K_HOME => self.select(0),
+            event::K_END => self.select(last),
+            _ => {
+                match key_event.character {
+                    '\0' => Reply::Ignored,
+                    '\n' => {
+                        match self.selected {
+                            Some(i) => Reply::Action(Action::Activated(i)),
+                            None => Reply::Ignored,
+                        }
+                    }
+                    c => {
+                        // Look from the row after the selected one, wrapping around
+                        let start = match self.selected {
+                            Some(i) => i + 1,
+                            None => 0,
+                        };
+                        let count = self.items.len();
+                        for offset in 0..count {
+                            let i = (start + offset) % count;
+                            let found = match self.items[i].columns.get(0) {
+                                Some(text) => text.starts_with(c),
+                                None => false,
+                            };
+                            if found {
+                                return self.select(i);
+                            }
+                        }
+                        Reply::Ignored
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Widget for List {
+    fn rect(&self) -> Rect {
+        self.rect
+    }
+
+    fn set_rect(&mut self, rect: Rect) {
+        self.rect = rect;
+    }
+
+    #[allow(unused_variables)]
+    fn size_hint(&self, theme: &mut Theme, children: &[(usize, usize)]) -> (usize, usize) {
+        let width = self.measure(theme).1;
+        (width, self.row_height(theme) * self.items.len())
+    }
+
+    fn draw(&self, canvas: &mut Canvas, theme: &mut Theme, focused: bool) {
+        let rect = self.rect;
+        canvas.rect(rect.x, rect.y, rect.w, rect.h, theme.base);
+
+        let row_height = self.row_height(theme);
+        let columns = self.measure(theme).0;
+        let icon_width = self.icon_width();
+        let text_offset = (row_height - theme.line_height()) as isize / 2;
+
+        // Only the rows inside of the clip are drawn
+        let (_, clip_y, _, clip_h) = canvas.clip();
+        let first = cmp::max(0, clip_y - rect.y) as usize / row_height;
+        let end = cmp::max(0, clip_y + clip_h as isize - rect.y) as usize / row_height + 1;
+
+        for i in first..cmp::min(end, self.items.len()) {
+            let item = &self.items[i];
+            let y = rect.y + (i * row_height) as isize;
+
+            let color = if self.selected == Some(i) {
+                let (background, color) = if focused {
+                    (theme.highlight, theme.highlight_text)
+                } else {
+                    (theme.selection, theme.text)
+                };
+                canvas.rect(rect.x, y, rect.w, row_height, background);
+                color
+            } else {
+                theme.text
+            };
+
+            if let Some(icon) = item.icon.and_then(|icon| self.icons.get(icon)) {
+                let x = rect.x + (PADDING + (icon_width - icon.width()) / 2) as isize;
+                let icon_y = y + (row_height - icon.height()) as isize / 2;
+                canvas.image_alpha(x, icon_y, icon.width(), icon.height(), icon.as_slice());
+            }
+
+            for (column, text) in columns.iter().zip(item.columns.iter()) {
+                theme.text(canvas, rect.x + column, y + text_offset, text, color);
+            }
+        }
+    }
+
+    fn event(&mut self, input: Input, theme: &mut Theme) -> Reply {
+        self.row_height = self.row_height(theme);
+        match input {
+            Input::Press(_, y) => {
+                match self.row_at(y) {
+                    Some(row) => {
+                        let now = Duration::monotonic();
+                        let (last_row, last_time) = self.last_click;
+                        let double = last_row == row &&
+                                     now - last_time <
+                                     Duration::new(0, 500 * time::NANOS_PER_MILLI);
+                        if double {
+                            self.last_click = (row, Duration::new(0, 0));
+                            self.selected = Some(row);
+                            Reply::Action(Action::Activated(row))
+                        } else {
+                            self.last_click = (row, now);
+                            self.select(row)
+                        }
+                    }
+                    None => Reply::Ignored,
+                }
+            }
+            Input::Key(key_event) => {
+                if key_event.pressed {
+                    self.key(key_event)
+                } else {
+                    Reply::Ignored
+                }
+            }
+            _ => Reply::Ignored,
+        }
+    }
+
+    fn focusable(&self) -> bool {
+        true
+    }
+
+    fn focus_rect(&self) -> Option<Rect> {
+        match self.selected {
+            Some(i) if self.row_height > 0 => {
+                Some(Rect::new(self.rect.x,
+                               self.rect.y + (i * self.row_height) as isize,
+                               self.rect.w,
+                               self.row_height))
+            }
+            _ => None,
+        }
+    }
+
+    fn as_any(&self) -> &Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut Any {
+        self
+    }
+}
@@ -0,0 +1,216 @@
+use core::any::Any;
+use core::cmp;
+
+use string::{String, ToString};
+use vec::Vec;
+
+use event;
+use graphics::canvas::Canvas;
+
+use super::{Action, Input, Rect, Reply, Theme, Widget};
+
+/// The space around the title and each item
+const PADDING: usize = 6;
+
+/// A title that opens a list of items below it when clicked
+///
+/// Choosing an item reports `Action::Activated` with its index. Put menus in a row to make a
+/// menu bar.
+pub struct Menu {
+    rect: Rect,
+    title: String,
+    items: Vec<String>,
+    /// The size of the items when open, found when it was opened
+    popup: Option<Rect>,
+    /// The item under the pointer or chosen with the keyboard
+    hover: Option<usize>,
+    /// The height of an item, found when it was opened
+    item_height: usize,
+}
+
+impl Menu {
+    /// Create a menu
+    pub fn new(title: &str, items: &[&str]) -> Self {
+        Menu {
+            rect: Rect::default(),
+            title: title.to_string(),
+            items: items.iter().map(|item| item.to_string()).collect(),
+            popup: None,
+            hover: None,
+            item_height: 0,
+        }
+    }
+
+    /// The items
+    pub fn items(&self) -> &[String] {
+        &self.items
+    }
+
+    fn open(&mut self, theme: &mut Theme) {
+        self.item_height = theme.line_height() + PADDING;
+        let mut width = 0;
+        for item in self.items.iter() {
+            width = cmp::max(width, theme.measure(item).0);
+        }
+        self.popup = Some(Rect::new(self.rect.x,
+                                    self.rect.bottom(),
+                                    cmp::max(width + PADDING * 4, self.rect.w),
+                                    self.item_height * self.items.len() + 2));
+        self.hover = None;
+    }
+
+    /// The item at a point
+    fn item_at(&self, x: isize, y: isize) -> Option<usize> {
+        match self.popup {
+            Some(popup) if popup.contains(x, y) && self.item_height > 0 => {
+                let i = cmp::max(0, y - popup.y - 1) as usize / self.item_height;
+                if i < self.items.len() {
+                    Some(i)
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        }
+    }
+
+    fn choose(&mut self, item: Option<usize>) -> Reply {
+        self.popup = None;
+        self.hover = None;
+        match item {
+            Some(i) => Reply::Action(Action::Activated(i)),
+            None => Reply::Redraw,
+        }
+    }
+}
+
+impl Widget for Menu {
+    fn rect(&self) -> Rect {
+        self.rect
+    }
+
+    fn set_rect(&mut self, rect: Rect) {
+        self.rect = rect;
+        if let Some(popup) = self.popup {
+            self.popup = Some(Rect::new(rect.x, rect.bottom(), popup.w, popup.h));
+        }
+    }
+
+    #[allow(unused_variables)]
+    fn size_hint(&self, theme: &mut Theme, children: &[(usize, usize)]) -> (usize, usize) {
+        let (width, height) = theme.measure(&self.title);
+        (width + PADDING * 2, height + PADDING)
+    }
+
+    #[allow(unused_variables)]
+    fn draw(&self, canvas: &mut Canvas, theme: &mut Theme, focused: bool) {
+        let rect = self.rect;
+        let color = if self.popup.is_some() {
+            canvas.rect(rect.x, rect.y, rect.w, rect.h, theme.highlight);
+            theme.highlight_text
+        } else {
+            theme.text
+        };
+        let y = rect.y + (rect.h as isize - theme.line_height() as isize) / 2;
+        theme.text(canvas, rect.x + PADDING as isize, y, &self.title, color);
+    }
+
+    fn event(&mut self, input: Input, theme: &mut Theme) -> Reply {
+        match input {
+            Input::Press(x, y) => {
+                if self.rect.contains(x, y) {
+                    if self.popup.is_some() {
+                        self.popup = None;
+                    } else {
+                        self.open(theme);
+                    }
+                    Reply::Redraw
+                } else {
+                    self.hover = self.item_at(x, y);
+                    Reply::Redraw
+                }
+            }
+            Input::Drag(x, y) => {
+                let hover = self.item_at(x, y);
+                if hover != self.hover {
+                    self.hover = hover;
+                    Reply::Redraw
+                } else {
+                    Reply::Ignored
+                }
+            }
+            Input::Release(x, y) => {
+                // Releasing on the title leaves the menu open, so it can be clicked
+                match self.item_at(x, y) {
+                    Some(i) => self.choose(Some(i)),
+                    None if !self.rect.contains(x, y) && self.popup.is_some() => {
+                        self.choose(None)
+                    }
+                    None => Reply::Ignored,
+                }
+            }
+            Input::Key(key_event) if key_event.pressed && self.popup.is_some() => {
+                let last = self.items.len().saturating_sub(1);
+                match key_event.scancode {
+                    event::K_ESC => self.choose(None),
+                    event::K_UP => {
+                        self.hover = match self.hover {
+                            Some(i) if i > 0 => Some(i - 1),
+                            _ => Some(last),
+                        };
+                        Reply::Redraw
+                    }
+                    event::K_DOWN => {
+                        self.hover = match self.hover {
+                            Some(i) if i < last => Some(i + 1),
+                            _ => Some(0),
+                        };
+                        Reply::Redraw
+                    }
+                    _ if key_event.character == '\n' => {
+                        let hover = self.hover;
+                        self.choose(hover)
+                    }
+                    _ => Reply::Redraw,
+                }
+            }
+            _ => Reply::Ignored,
+        }
+    }
+
+    fn popup(&self) -> Option<Rect> {
+        self.popup
+    }
+
+    fn draw_popup(&self, canvas: &mut Canvas, theme: &mut Theme) {
+        if let Some(popup) = self.popup {
+            canvas.rect(popup.x, popup.y, popup.w, popup.h, theme.base);
+            canvas.rounded_rect(popup.x, popup.y, popup.w, popup.h, 0, theme.border);
+
+            for (i, item) in self.items.iter().enumerate() {
+                let y = popup.y + 1 + (i * self.item_height) as isize;
+                let color = if self.hover == Some(i) {
+                    canvas.rect(popup.x + 1, y, popup.w - 2, self.item_height, theme.highlight);
+                    theme.highlight_text
+                } else {
+                    theme.text
+                };
+                let x = popup.x + (PADDING * 2) as isize;
+                theme.text(canvas, x, y + (PADDING / 2) as isize, item, color);
+            }
+        }
+    }
+
+    fn close_popup(&mut self) {
+        self.popup = None;
+        self.hover = None;
+    }
+
+    fn as_any(&self) -> &Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut Any {
+        self
+    }
+}
@@ -0,0 +1,267 @@
+//! Widgets for building the interface of an app
+//!
+//! Widgets are added to a `Ui`, which owns the window and returns an id for each of them.
+//! Containers like `Layout` and `ScrollArea` refer to their children by these ids, and the
+//! `Ui` lays the tree out from its root, draws it, and turns the events of the window into
+//! `Action`s of widgets. An app keeps the ids it needs and gets widgets back with `Ui::get`
+//! and `Ui::get_mut` to read or change them.
+
+use core::any::Any;
+use core::cmp;
+
+use vec::Vec;
+
+use event::KeyEvent;
+use graphics::canvas::Canvas;
+use graphics::color::Color;
+use graphics::font::FontSet;
+
+pub use self::button::Button;
+pub use self::label::Label;
+pub use self::layout::Layout;
+pub use self::list::{List, ListItem};
+pub use self::menu::Menu;
+pub use self::scroll::ScrollArea;
+pub use self::text_input::TextInput;
+pub use self::ui::{Ui, UiEvent};
+
+pub mod button;
+pub mod label;
+pub mod layout;
+pub mod list;
+pub mod menu;
+pub mod scroll;
+pub mod text_input;
+pub mod ui;
+
+/// The index of a widget in its `Ui`
+pub type WidgetId = usize;
+
+/// A rectangle in the coordinates of the window content
+#[derive(Copy, Clone, Default, PartialEq)]
+pub struct Rect {
+    pub x: isize,
+    pub y: isize,
+    pub w: usize,
+    pub h: usize,
+}
+
+impl Rect {
+    /// Create a new rectangle
+    pub fn new(x: isize, y: isize, w: usize, h: usize) -> Self {
+        Rect { x: x, y: y, w: w, h: h }
+    }
+
+    /// The x coordinate past the right edge
+    pub fn right(&self) -> isize {
+        self.x + self.w as isize
+    }
+
+    /// The y coordinate past the bottom edge
+    pub fn bottom(&self) -> isize {
+        self.y + self.h as isize
+    }
+
+    /// Is a point inside of the rectangle?
+    pub fn contains(&self, x: isize, y: isize) -> bool {
+        x >= self.x && y >= self.y && x < self.right() && y < self.bottom()
+    }
+
+    /// The part of the rectangle that is also inside of another one
+    pub fn intersection(&self, other: &Rect) -> Rect {
+        let left = cmp::max(self.x, other.x);
+        let top = cmp::max(self.y, other.y);
+        let right = cmp::min(self.right(), other.right());
+        let bottom = cmp::min(self.bottom(), other.bottom());
+        Rect::new(left,
+                  top,
+                  cmp::max(0, right - left) as usize,
+                  cmp::max(0, bottom - top) as usize)
+    }
+
+    /// Is the rectangle empty?
+    pub fn is_empty(&self) -> bool {
+        self.w == 0 || self.h == 0
+    }
+}
+
+/// Input for a widget, with points in the coordinates of the window content
+#[derive(Copy, Clone)]
+pub enum Input {
+    /// The left button was pressed on the widget, or on its popup
+    Press(isize, isize),
+    /// The pointer moved while the left button was held, after a press on the widget
+    Drag(isize, isize),
+    /// The left button was released, after a press on the widget
+    Release(isize, isize),
+    /// A key, for the focused widget or the widget with a popup
+    Key(KeyEvent),
+    /// The scroll wheel moved over the widget, positive to the right and upwards
+    Scroll(isize, isize),
+}
+
+/// Something a widget did that the app may want to know about
+#[derive(Copy, Clone, PartialEq)]
+pub enum Action {
+    /// A button was clicked
+    Clicked,
+    /// The text of an input was edited
+    Changed,
+    /// Enter was pressed in a single line input
+    Submitted,
+    /// An item of a list was selected
+    Selected(usize),
+    /// An item of a list or a menu was chosen, by a double click or Enter
+    Activated(usize),
+}
+
+/// What a widget did with an input
+#[derive(Copy, Clone, PartialEq)]
+pub enum Reply {
+    /// The input was not used, so it goes to the parent of the widget
+    Ignored,
+    /// The widget changed and the window has to be drawn again
+    Redraw,
+    /// The widget changed and has something for the app
+    Action(Action),
+}
+
+/// The fonts and colors widgets are drawn with
+pub struct Theme {
+    pub fonts: FontSet,
+    /// The size of text, in pixels per em
+    pub size: usize,
+    /// The background of the window
+    pub background: Color,
+    /// The background of inputs and lists
+    pub base: Color,
+    pub text: Color,
+    pub border: Color,
+    pub button: Color,
+    pub button_pressed: Color,
+    /// Selected items and the outline of the focused widget
+    pub highlight: Color,
+    /// Text on `highlight`
+    pub highlight_text: Color,
+    /// Selected items of a widget without the focus
+    pub selection: Color,
+    pub scrollbar: Color,
+    pub scrollbar_thumb: Color,
+}
+
+impl Theme {
+    /// The default theme, with the fonts from `FONT_DIR`
+    pub fn new() -> Self {
+        Theme {
+            fonts: FontSet::load(),
+            size: 16,
+            background: Color::rgb(237, 237, 237),
+            base: Color::WHITE,
+            text: Color::BLACK,
+            border: Color::rgb(160, 160, 160),
+            button: Color::rgb(220, 220, 220),
+            button_pressed: Color::rgb(190, 190, 190),
+            highlight: Color::rgb(66, 133, 244),
+            highlight_text: Color::WHITE,
+            selection: Color::rgb(224, 224, 224),
+            scrollbar: Color::rgb(230, 230, 230),
+            scrollbar_thumb: Color::rgb(180, 180, 180),
+        }
+    }
+
+    /// The distance between lines of text
+    pub fn line_height(&self) -> usize {
+        self.fonts.line_height(self.size)
+    }
+
+    /// The width and height of some text
+    pub fn measure(&mut self, text: &str) -> (usize, usize) {
+        self.fonts.measure(text, self.size)
+    }
+
+    /// Draw text with the top left of the first line at x and y
+    pub fn text(&mut self, canvas: &mut Canvas, x: isize, y: isize, text: &str, color: Color) {
+        canvas.text(x, y, text, self.size, color, &mut self.fonts);
+    }
+
+    /// The pen position of each character of a line of text, and the width of the line
+    pub fn offsets(&mut self, line: &str) -> (Vec<isize>, usize) {
+        let layout = self.fonts.layout(line, self.size, None);
+        let width = match layout.glyphs.last() {
+            Some(glyph) => (glyph.x + glyph.advance as isize) as usize,
+            None => 0,
+        };
+        (layout.glyphs.iter().map(|glyph| glyph.x).collect(), width)
+    }
+}
+
+/// A part of the interface
+///
+/// Widgets are placed by their parent with `set_rect`, and draw themselves inside of that
+/// rectangle, which the `Ui` clips them to.
+pub trait Widget {
+    /// Where the widget is
+    fn rect(&self) -> Rect;
+
+    /// Place the widget
+    fn set_rect(&mut self, rect: Rect);
+
+    /// The smallest width and height that fit the content, given those of the children
+    fn size_hint(&self, theme: &mut Theme, children: &[(usize, usize)]) -> (usize, usize);
+
+    /// The widgets inside of this one, which are drawn over it
+    fn children(&self) -> Vec<WidgetId> {
+        Vec::new()
+    }
+
+    /// Place the children, given the sizes from their `size_hint`
+    #[allow(unused_variables)]
+    fn arrange(&mut self, children: &[(usize, usize)]) -> Vec<Rect> {
+        Vec::new()
+    }
+
+    /// The part of the widget that its children are clipped to
+    fn viewport(&self) -> Rect {
+        self.rect()
+    }
+
+    /// Draw the widget
+    fn draw(&self, canvas: &mut Canvas, theme: &mut Theme, focused: bool);
+
+    /// Handle an input
+    fn event(&mut self, input: Input, theme: &mut Theme) -> Reply;
+
+    /// Can the widget get the keyboard focus?
+    fn focusable(&self) -> bool {
+        false
+    }
+
+    /// The part of the widget to keep in view while it has the focus, like a cursor
+    fn focus_rect(&self) -> Option<Rect> {
+        None
+    }
+
+    /// Scroll so that a rectangle is in view, returning true if it had to scroll
+    #[allow(unused_variables)]
+    fn reveal(&mut self, rect: Rect) -> bool {
+        false
+    }
+
+    /// Where a popup of the widget is open, like the items of a menu
+    fn popup(&self) -> Option<Rect> {
+        None
+    }
+
+    /// Draw the open popup, over every other widget
+    #[allow(unused_variables)]
+    fn draw_popup(&self, canvas: &mut Canvas, theme: &mut Theme) {}
+
+    /// Close the popup, as the pointer was pressed outside of it
+    fn close_popup(&mut self) {}
+
+    /// The widget, to get its type back
+    fn as_any(&self) -> &Any;
+
+    /// The widget, to get its type back
+    fn as_any_mut(&mut self) -> &mut Any;
+}
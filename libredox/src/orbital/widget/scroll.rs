@@ -0,0 +1,313 @@
+use core::any::Any;
+use core::cmp;
+
+use vec::Vec;
+
+use graphics::canvas::Canvas;
+
+use super::{Input, Rect, Reply, Theme, Widget, WidgetId};
+
+/// The thickness of a scrollbar
+const SCROLLBAR: usize = 12;
+
+/// The shortest a scrollbar thumb gets
+const MIN_THUMB: usize = 16;
+
+/// How far a step of the scroll wheel moves
+const WHEEL_STEP: isize = 48;
+
+/// Where a scrollbar thumb is, as its offset along the track and its length
+fn thumb(track: usize, view: usize, content: usize, offset: isize) -> (isize, usize) {
+    if content <= view || track == 0 {
+        return (0, track);
+    }
+    let length = cmp::min(track, cmp::max(MIN_THUMB, track * view / content));
+    let position = offset * (track - length) as isize / (content - view) as isize;
+    (position, length)
+}
+
+/// Shows a part of a child that is larger than itself, with scrollbars to move it
+pub struct ScrollArea {
+    rect: Rect,
+    child: WidgetId,
+    /// How far the child is scrolled, right and down
+    offset: (isize, isize),
+    /// The size hint of the child
+    content: (usize, usize),
+    /// The part of the area the child is shown in, without the scrollbars
+    view: Rect,
+    /// The largest size hint, so that a long child does not ask for a huge window
+    max_size: (usize, usize),
+    /// The thumb being dragged, vertical or not, and where in it it was pressed
+    drag: Option<(bool, isize)>,
+}
+
+impl ScrollArea {
+    /// Create a scroll area for a widget
+    pub fn new(child: WidgetId) -> Self {
+        ScrollArea {
+            rect: Rect::default(),
+            child: child,
+            offset: (0, 0),
+            content: (0, 0),
+            view: Rect::default(),
+            max_size: (640, 480),
+            drag: None,
+        }
+    }
+
+    /// Set the largest size hint
+    pub fn set_max_size(&mut self, width: usize, height: usize) {
+        self.max_size = (width, height);
+    }
+
+    /// How far the child is scrolled, right and down
+    pub fn offset(&self) -> (isize, isize) {
+        self.offset
+    }
+
+    /// Scroll the child, which is kept from going past its edges
+    pub fn scroll_to(&mut self, x: isize, y: isize) {
+        let max_x = cmp::max(0, self.content.0 as isize - self.view.w as isize);
+        let max_y = cmp::max(0, self.content.1 as isize - self.view.h as isize);
+        self.offset = (cmp::max(0, cmp::min(x, max_x)), cmp::max(0, cmp::min(y, max_y)));
+    }
+
+    fn vertical_bar(&self) -> Option<Rect> {
+        if self.content.1 > self.view.h {
+            Some(Rect::new(self.view.right(), self.rect.y, SCROLLBAR, self.view.h))
+        } else {
+            None
+        }
+    }
+
+    fn horizontal_bar(&self) -> Option<Rect> {
+        if self.content.0 > self.view.w {
+            Some(Rect::new(self.rect.x, self.view.bottom(), self.view.w, SCROLLBAR))
+        } else {
+            None
+        }
+    }
+
+    /// Move the thumb so that the point `grab` into it is at `position` along the track
+    fn drag_to(&mut self, vertical: bool, position: isize, grab: isize) {
+        let (track, view, content) = if vertical {
+            (self.view.h, self.view.h, self.content.1)
+        } else {
+            (self.view.w, self.view.w, self.content.0)
+        };
+        let (_, length) = thumb(track, view, content, 0);
+        if track > length {
+            let offset = (position - grab) * (content - view) as isize /
+                         (track - length) as isize;
+            let (x, y) = self.offset;
+            if vertical {
+                self.scroll_to(x, offset);
+            } else {
+                self.scroll_to(offset, y);
+            }
+        }
+    }
+
+    /// Handle a press on a scrollbar, paging on the track or grabbing the thumb
+    fn press(&mut self, x: isize, y: isize) -> Reply {
+        let (offset_x, offset_y) = self.offset;
+
+        if let Some(bar) = self.vertical_bar() {
+            if bar.contains(x, y) {
+                let (position, length) = thumb(bar.h, self.view.h, self.content.1, offset_y);
+                let along = y - bar.y;
+                if along < position {
+                    self.scroll_to(offset_x, offset_y - self.view.h as isize);
+                } else if along >= position + length as isize {
+                    self.scroll_to(offset_x, offset_y + self.view.h as isize);
+                } else {
+                    self.drag = Some((true, along - position));
+                }
+                return Reply::Redraw;
+            }
+        }
+
+        if let Some(bar) = self.horizontal_bar() {
+            if bar.contains(x, y) {
+                let (position, length) = thumb(bar.w, self.view.w, self.content.0, offset_x);
+                let along = x - bar.x;
+                if along < position {
+                    self.scroll_to(offset_x - self.view.w as isize, offset_y);
+                } else if along >= position + length as isize {
+                    self.scroll_to(offset_x + self.view.w as isize, offset_y);
+                } else {
+                    self.drag = Some((false, along - position));
+                }
+                return Reply::Redraw;
+            }
+        }
+
+        Reply::Ignored
+    }
+}
+
+impl Widget for ScrollArea {
+    fn rect(&self) -> Rect {
+        self.rect
+    }
+
+    fn set_rect(&mut self, rect: Rect) {
+        self.rect = rect;
+    }
+
+    #[allow(unused_variables)]
+    fn size_hint(&self, theme: &mut Theme, children: &[(usize, usize)]) -> (usize, usize) {
+        let (width, height) = match children.get(0) {
+            Some(&hint) => hint,
+            None => (0, 0),
+        };
+        let (max_width, max_height) = self.max_size;
+
+        // A scrollbar is needed across whichever way the child does not fit
+        let hint_width = if height > max_height {
+            cmp::min(width, max_width) + SCROLLBAR
+        } else {
+            cmp::min(width, max_width)
+        };
+        let hint_height = if width > max_width {
+            cmp::min(height, max_height) + SCROLLBAR
+        } else {
+            cmp::min(height, max_height)
+        };
+        (hint_width, hint_height)
+    }
+
+    fn children(&self) -> Vec<WidgetId> {
+        vec![self.child]
+    }
+
+    fn arrange(&mut self, children: &[(usize, usize)]) -> Vec<Rect> {
+        self.content = match children.get(0) {
+            Some(&hint) => hint,
+            None => (0, 0),
+        };
+        let (width, height) = self.content;
+
+        // Each scrollbar takes space that may make the other one needed
+        let mut view_w = self.rect.w;
+        let mut view_h = self.rect.h;
+        if height > view_h {
+            view_w = view_w.saturating_sub(SCROLLBAR);
+        }
+        if width > view_w {
+            view_h = view_h.saturating_sub(SCROLLBAR);
+            if height > view_h && view_w == self.rect.w {
+                view_w = view_w.saturating_sub(SCROLLBAR);
+            }
+        }
+        self.view = Rect::new(self.rect.x, self.rect.y, view_w, view_h);
+
+        let (x, y) = self.offset;
+        self.scroll_to(x, y);
+
+        vec![Rect::new(self.view.x - self.offset.0,
+                       self.view.y - self.offset.1,
+                       cmp::max(width, self.view.w),
+                       cmp::max(height, self.view.h))]
+    }
+
+    fn viewport(&self) -> Rect {
+        self.view
+    }
+
+    #[allow(unused_variables)]
+    fn draw(&self, canvas: &mut Canvas, theme: &mut Theme, focused: bool) {
+        if let Some(bar) = self.vertical_bar() {
+            canvas.rect(bar.x, bar.y, bar.w, bar.h, theme.scrollbar);
+            let (position, length) = thumb(bar.h, self.view.h, self.content.1, self.offset.1);
+            canvas.fill_rounded_rect(bar.x + 2,
+                                     bar.y + position,
+                                     bar.w - 4,
+                                     length,
+                                     4,
+                                     theme.scrollbar_thumb);
+        }
+
+        if let Some(bar) = self.horizontal_bar() {
+            canvas.rect(bar.x, bar.y, bar.w, bar.h, theme.scrollbar);
+            let (position, length) = thumb(bar.w, self.view.w, self.content.0, self.offset.0);
+            canvas.fill_rounded_rect(bar.x + position,
+                                     bar.y + 2,
+                                     length,
+                                     bar.h - 4,
+                                     4,
+                                     theme.scrollbar_thumb);
+        }
+
+        if self.vertical_bar().is_some() && self.horizontal_bar().is_some() {
+            canvas.rect(self.view.right(),
+                        self.view.bottom(),
+                        SCROLLBAR,
+                        SCROLLBAR,
+                        theme.scrollbar);
+        }
+    }
+
+    #[allow(unused_variables)]
+    fn event(&mut self, input: Input, theme: &mut Theme) -> Reply {
+        match input {
+            Input::Press(x, y) => self.press(x, y),
+            Input::Drag(x, y) => {
+                match self.drag {
+                    Some((true, grab)) => self.drag_to(true, y - self.rect.y, grab),
+                    Some((false, grab)) => self.drag_to(false, x - self.rect.x, grab),
+                    None => return Reply::Ignored,
+                }
+                Reply::Redraw
+            }
+            Input::Release(_, _) => {
+                self.drag = None;
+                Reply::Ignored
+            }
+            Input::Scroll(dx, dy) => {
+                let old = self.offset;
+                self.scroll_to(old.0 + dx * WHEEL_STEP, old.1 - dy * WHEEL_STEP);
+                if self.offset != old {
+                    Reply::Redraw
+                } else {
+                    Reply::Ignored
+                }
+            }
+            Input::Key(_) => Reply::Ignored,
+        }
+    }
+
+    fn reveal(&mut self, rect: Rect) -> bool {
+        let (mut x, mut y) = self.offset;
+        // Where the rectangle is in the child
+        let left = rect.x - self.view.x + x;
+        let top = rect.y - self.view.y + y;
+
+        // The start is shown when the rectangle does not fit
+        if left + rect.w as isize > x + self.view.w as isize {
+            x = left + rect.w as isize - self.view.w as isize;
+        }
+        if left < x {
+            x = left;
+        }
+        if top + rect.h as isize > y + self.view.h as isize {
+            y = top + rect.h as isize - self.view.h as isize;
+        }
+        if top < y {
+            y = top;
+        }
+
+        let old = self.offset;
+        self.scroll_to(x, y);
+        self.offset != old
+    }
+
+    fn as_any(&self) -> &Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut Any {
+        self
+    }
+}
@@ -0,0 +1,289 @@
+use core::any::Any;
+use core::cmp;
+
+use string::{String, ToString};
+
+use event;
+use graphics::canvas::Canvas;
+
+use super::{Action, Input, Rect, Reply, Theme, Widget};
+
+/// The space between the border and the text
+const PADDING: usize = 4;
+
+/// Editable text, on a single line or on many
+///
+/// A single line input scrolls sideways to show the cursor, and reports Enter as
+/// `Action::Submitted`. Put a multiple line input in a `ScrollArea` to scroll it.
+pub struct TextInput {
+    rect: Rect,
+    text: String,
+    /// The byte offset of the cursor
+    cursor: usize,
+    multi_line: bool,
+    /// The cursor, from the top left of the text, found when it moves
+    caret: Rect,
+    /// How far a single line is scrolled left to show the cursor
+    scroll: isize,
+}
+
+impl TextInput {
+    /// Create an empty single line input
+    pub fn new() -> Self {
+        TextInput {
+            rect: Rect::default(),
+            text: String::new(),
+            cursor: 0,
+            multi_line: false,
+            caret: Rect::default(),
+            scroll: 0,
+        }
+    }
+
+    /// Create an empty input for many lines
+    pub fn multi_line() -> Self {
+        let mut input = TextInput::new();
+        input.multi_line = true;
+        input
+    }
+
+    /// The text
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Replace the text, putting the cursor at the start
+    pub fn set_text(&mut self, text: &str) {
+        self.text = text.to_string();
+        self.cursor = 0;
+        self.caret = Rect::default();
+        self.scroll = 0;
+    }
+
+    fn line_start(&self, pos: usize) -> usize {
+        match self.text[..pos].rfind('\n') {
+            Some(i) => i + 1,
+            None => 0,
+        }
+    }
+
+    fn line_end(&self, pos: usize) -> usize {
+        match self.text[pos..].find('\n') {
+            Some(i) => pos + i,
+            None => self.text.len(),
+        }
+    }
+
+    fn previous(&self, pos: usize) -> usize {
+        match self.text[..pos].char_indices().next_back() {
+            Some((i, _)) => i,
+            None => 0,
+        }
+    }
+
+    fn next(&self, pos: usize) -> usize {
+        match self.text[pos..].char_indices().nth(1) {
+            Some((i, _)) => pos + i,
+            None => self.text.len(),
+        }
+    }
+
+    /// The start of a line, counting from 0
+    fn row_start(&self, row: usize) -> usize {
+        let mut start = 0;
+        for _ in 0..row {
+            match self.text[start..].find('\n') {
+                Some(i) => start += i + 1,
+                None => break,
+            }
+        }
+        start
+    }
+
+    /// The offset in the line starting at `start` that is closest to x
+    fn index_at(&self, theme: &mut Theme, start: usize, x: isize) -> usize {
+        let end = self.line_end(start);
+        let line = &self.text[start..end];
+        let (offsets, width) = theme.offsets(line);
+
+        for ((byte, _), (i, &left)) in line.char_indices().zip(offsets.iter().enumerate()) {
+            let right = match offsets.get(i + 1) {
+                Some(&right) => right,
+                None => width as isize,
+            };
+            if x < (left + right) / 2 {
+                return start + byte;
+            }
+        }
+        end
+    }
+
+    /// The left of the text in the window
+    fn origin(&self) -> (isize, isize) {
+        (self.rect.x + PADDING as isize - self.scroll, self.rect.y + PADDING as isize)
+    }
+
+    /// Find the caret after the cursor moved, scrolling a single line to show it
+    fn update(&mut self, theme: &mut Theme) {
+        let start = self.line_start(self.cursor);
+        let row = self.text[..start].matches('\n').count();
+        let line_height = theme.line_height();
+        let x = theme.offsets(&self.text[start..self.cursor]).1 as isize;
+        self.caret = Rect::new(x, (row * line_height) as isize, 1, line_height);
+
+        if !self.multi_line {
+            let width = self.rect.w.saturating_sub(PADDING * 2) as isize;
+            if x - self.scroll >= width {
+                self.scroll = x - width + 1;
+            }
+            if x < self.scroll {
+                self.scroll = x;
+            }
+        }
+    }
+
+    fn key(&mut self, key_event: event::KeyEvent, theme: &mut Theme) -> Reply {
+        let cursor = self.cursor;
+        let mut reply = Reply::Redraw;
+
+        match key_event.scancode {
+            event::K_LEFT => self.cursor = self.previous(cursor),
+            event::K_RIGHT => self.cursor = self.next(cursor),
+            event::K_HOME => self.cursor = self.line_start(cursor),
+            event::K_END => self.cursor = self.line_end(cursor),
+            event::K_UP if self.multi_line => {
+                let start = self.line_start(cursor);
+                self.cursor = if start > 0 {
+                    let above = self.line_start(start - 1);
+                    self.index_at(theme, above, self.caret.x)
+                } else {
+                    0
+                };
+            }
+            event::K_DOWN if self.multi_line => {
+                let end = self.line_end(cursor);
+                self.cursor = if end < self.text.len() {
+                    self.index_at(theme, end + 1, self.caret.x)
+                } else {
+                    end
+                };
+            }
+            event::K_BKSP => {
+                if cursor > 0 {
+                    self.cursor = self.previous(cursor);
+                    self.text.remove(self.cursor);
+                    reply = Reply::Action(Action::Changed);
+                }
+            }
+            event::K_DEL => {
+                if cursor < self.text.len() {
+                    self.text.remove(cursor);
+                    reply = Reply::Action(Action::Changed);
+                }
+            }
+            _ => {
+                match key_event.character {
+                    '\n' if !self.multi_line => reply = Reply::Action(Action::Submitted),
+                    c if c == '\n' || c >= ' ' => {
+                        self.text.insert(cursor, c);
+                        self.cursor = self.next(cursor);
+                        reply = Reply::Action(Action::Changed);
+                    }
+                    _ => reply = Reply::Ignored,
+                }
+            }
+        }
+
+        self.update(theme);
+        reply
+    }
+}
+
+impl Widget for TextInput {
+    fn rect(&self) -> Rect {
+        self.rect
+    }
+
+    fn set_rect(&mut self, rect: Rect) {
+        self.rect = rect;
+    }
+
+    #[allow(unused_variables)]
+    fn size_hint(&self, theme: &mut Theme, children: &[(usize, usize)]) -> (usize, usize) {
+        let line_height = theme.line_height();
+        if self.multi_line {
+            let (width, height) = theme.measure(&self.text);
+            (cmp::max(240, width + 1) + PADDING * 2,
+             cmp::max(line_height * 3, height) + PADDING * 2)
+        } else {
+            (160 + PADDING * 2, line_height + PADDING * 2)
+        }
+    }
+
+    fn draw(&self, canvas: &mut Canvas, theme: &mut Theme, focused: bool) {
+        let rect = self.rect;
+        let border = if focused {
+            theme.highlight
+        } else {
+            theme.border
+        };
+        canvas.rect(rect.x, rect.y, rect.w, rect.h, theme.base);
+        canvas.rounded_rect(rect.x, rect.y, rect.w, rect.h, 2, border);
+
+        // The text is clipped to the inside of the border
+        let (clip_x, clip_y, clip_w, clip_h) = canvas.clip();
+        let inside = Rect::new(rect.x + 1,
+                               rect.y + 1,
+                               rect.w.saturating_sub(2),
+                               rect.h.saturating_sub(2));
+        let clip = Rect::new(clip_x, clip_y, clip_w, clip_h).intersection(&inside);
+        canvas.set_clip(clip.x, clip.y, clip.w, clip.h);
+
+        let (x, y) = self.origin();
+        let color = theme.text;
+        theme.text(canvas, x, y, &self.text, color);
+        if focused {
+            canvas.rect(x + self.caret.x, y + self.caret.y, 1, theme.line_height(), color);
+        }
+
+        canvas.set_clip(clip_x, clip_y, clip_w, clip_h);
+    }
+
+    fn event(&mut self, input: Input, theme: &mut Theme) -> Reply {
+        match input {
+            Input::Press(x, y) => {
+                let (origin_x, origin_y) = self.origin();
+                let row = cmp::max(0, y - origin_y) as usize / theme.line_height();
+                let start = self.row_start(row);
+                self.cursor = self.index_at(theme, start, x - origin_x);
+                self.update(theme);
+                Reply::Redraw
+            }
+            Input::Key(key_event) => {
+                if key_event.pressed {
+                    self.key(key_event, theme)
+                } else {
+                    Reply::Ignored
+                }
+            }
+            _ => Reply::Ignored,
+        }
+    }
+
+    fn focusable(&self) -> bool {
+        true
+    }
+
+    fn focus_rect(&self) -> Option<Rect> {
+        let (x, y) = self.origin();
+        Some(Rect::new(x + self.caret.x, y + self.caret.y, self.caret.w, self.caret.h))
+    }
+
+    fn as_any(&self) -> &Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut Any {
+        self
+    }
+}
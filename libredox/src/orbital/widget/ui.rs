@@ -0,0 +1,429 @@
+use boxed::Box;
+use vec::Vec;
+
+use event::{self, EventOption, KeyEvent};
+use graphics::canvas::Canvas;
+use orbital::Window;
+
+use super::{Action, Input, Rect, Reply, Theme, Widget, WidgetId};
+
+/// Something for the app to handle
+#[derive(Copy, Clone)]
+pub enum UiEvent {
+    /// A widget did something
+    Action(WidgetId, Action),
+    /// A key that no widget used
+    Key(KeyEvent),
+    /// The window was closed
+    Quit,
+}
+
+/// The event for the app from what a widget did with an input
+fn action(sent: Option<(WidgetId, Reply)>) -> Option<UiEvent> {
+    match sent {
+        Some((id, Reply::Action(action))) => Some(UiEvent::Action(id, action)),
+        _ => None,
+    }
+}
+
+/// The widgets of a window, laid out from a root widget
+pub struct Ui {
+    window: Box<Window>,
+    pub theme: Theme,
+    widgets: Vec<Box<Widget>>,
+    /// The container of each widget, found when laying out
+    parents: Vec<Option<WidgetId>>,
+    /// The size hint of each widget, found when laying out
+    hints: Vec<(usize, usize)>,
+    root: Option<WidgetId>,
+    focus: Option<WidgetId>,
+    /// The widget the left button was pressed on, which gets the pointer until it is released
+    pressed: Option<WidgetId>,
+    left_button: bool,
+    /// Where the pointer was last, as scroll events do not say
+    pointer: (isize, isize),
+    /// The widgets changed, so they have to be laid out and drawn again
+    dirty: bool,
+    /// The focused widget changed, so its focus rect has to be scrolled into view
+    reveal: bool,
+}
+
+impl Ui {
+    /// Create an empty interface for a window
+    pub fn new(window: Box<Window>) -> Self {
+        Ui {
+            window: window,
+            theme: Theme::new(),
+            widgets: Vec::new(),
+            parents: Vec::new(),
+            hints: Vec::new(),
+            root: None,
+            focus: None,
+            pressed: None,
+            left_button: false,
+            pointer: (0, 0),
+            dirty: true,
+            reveal: false,
+        }
+    }
+
+    /// The window
+    pub fn window(&mut self) -> &mut Window {
+        &mut self.window
+    }
+
+    /// Add a widget, which is not shown until it is the root or in a container
+    pub fn add<W: Widget + 'static>(&mut self, widget: W) -> WidgetId {
+        self.widgets.push(box widget);
+        self.parents.push(None);
+        self.hints.push((0, 0));
+        self.dirty = true;
+        self.widgets.len() - 1
+    }
+
+    /// Set the widget that fills the window
+    pub fn set_root(&mut self, id: WidgetId) {
+        self.root = Some(id);
+        self.dirty = true;
+    }
+
+    /// Get a widget by its id, if it has that type
+    pub fn get<W: Widget + 'static>(&self, id: WidgetId) -> Option<&W> {
+        match self.widgets.get(id) {
+            Some(widget) => widget.as_any().downcast_ref::<W>(),
+            None => None,
+        }
+    }
+
+    /// Get a widget by its id to change it, if it has that type
+    pub fn get_mut<W: Widget + 'static>(&mut self, id: WidgetId) -> Option<&mut W> {
+        self.dirty = true;
+        match self.widgets.get_mut(id) {
+            Some(widget) => widget.as_any_mut().downcast_mut::<W>(),
+            None => None,
+        }
+    }
+
+    /// The widget with the keyboard focus
+    pub fn focused(&self) -> Option<WidgetId> {
+        self.focus
+    }
+
+    /// Give a widget the keyboard focus
+    pub fn set_focus(&mut self, id: WidgetId) {
+        if id < self.widgets.len() && self.widgets[id].focusable() {
+            self.focus = Some(id);
+            self.reveal = true;
+            self.dirty = true;
+        }
+    }
+
+    /// Resize the window to the size hint of the root
+    pub fn fit(&mut self) {
+        if let Some(root) = self.root {
+            let (width, height) = self.measure(root);
+            self.window.set_size(width, height);
+            self.dirty = true;
+        }
+    }
+
+    fn measure(&mut self, id: WidgetId) -> (usize, usize) {
+        let children = self.widgets[id].children();
+        let mut hints = Vec::with_capacity(children.len());
+        for &child in children.iter() {
+            self.parents[child] = Some(id);
+            hints.push(self.measure(child));
+        }
+        let hint = self.widgets[id].size_hint(&mut self.theme, &hints);
+        self.hints[id] = hint;
+        hint
+    }
+
+    fn place(&mut self, id: WidgetId, rect: Rect) {
+        self.widgets[id].set_rect(rect);
+
+        let children = self.widgets[id].children();
+        let hints = children.iter().map(|&child| self.hints[child]).collect::<Vec<_>>();
+        let rects = self.widgets[id].arrange(&hints);
+        for (&child, &rect) in children.iter().zip(rects.iter()) {
+            self.place(child, rect);
+        }
+    }
+
+    /// Lay the widgets out to fill the window
+    pub fn layout(&mut self) {
+        if let Some(root) = self.root {
+            for parent in self.parents.iter_mut() {
+                *parent = None;
+            }
+            self.measure(root);
+            let rect = Rect::new(0, 0, self.window.width(), self.window.height());
+            self.place(root, rect);
+        }
+    }
+
+    fn draw_widget(&mut self, id: WidgetId, clip: Rect) {
+        let clip = clip.intersection(&self.widgets[id].rect());
+        if clip.is_empty() {
+            return;
+        }
+
+        let focused = self.focus == Some(id);
+        {
+            let canvas: &mut Canvas = &mut self.window;
+            canvas.set_clip(clip.x, clip.y, clip.w, clip.h);
+            self.widgets[id].draw(canvas, &mut self.theme, focused);
+        }
+
+        let viewport = clip.intersection(&self.widgets[id].viewport());
+        for child in self.widgets[id].children() {
+            self.draw_widget(child, viewport);
+        }
+    }
+
+    /// Draw the widgets and show them
+    pub fn draw(&mut self) {
+        let background = self.theme.background;
+        self.window.reset_clip();
+        self.window.set(background);
+
+        if let Some(root) = self.root {
+            let rect = Rect::new(0, 0, self.window.width(), self.window.height());
+            self.draw_widget(root, rect);
+
+            let canvas: &mut Canvas = &mut self.window;
+            canvas.reset_clip();
+            for widget in self.widgets.iter() {
+                if widget.popup().is_some() {
+                    widget.draw_popup(canvas, &mut self.theme);
+                }
+            }
+        }
+
+        self.window.sync();
+    }
+
+    /// Lay out and draw the widgets, keeping the focused one in view
+    fn refresh(&mut self) {
+        self.layout();
+
+        if self.reveal {
+            self.reveal = false;
+            if let Some(focus) = self.focus {
+                if let Some(rect) = self.widgets[focus].focus_rect() {
+                    let mut scrolled = false;
+                    let mut parent = self.parents[focus];
+                    while let Some(id) = parent {
+                        if self.widgets[id].reveal(rect) {
+                            scrolled = true;
+                        }
+                        parent = self.parents[id];
+                    }
+                    if scrolled {
+                        self.layout();
+                    }
+                }
+            }
+        }
+
+        self.draw();
+        self.dirty = false;
+    }
+
+    /// The deepest widget at a point
+    fn hit(&self, id: WidgetId, x: isize, y: isize) -> Option<WidgetId> {
+        if !self.widgets[id].rect().contains(x, y) {
+            return None;
+        }
+        if self.widgets[id].viewport().contains(x, y) {
+            for &child in self.widgets[id].children().iter().rev() {
+                if let Some(hit) = self.hit(child, x, y) {
+                    return Some(hit);
+                }
+            }
+        }
+        Some(id)
+    }
+
+    /// The widget with an open popup
+    fn popup(&self) -> Option<WidgetId> {
+        self.widgets.iter().position(|widget| widget.popup().is_some())
+    }
+
+    /// Send an input to a widget, and then to its parents until one uses it
+    ///
+    /// Returns the widget that used it and what it did.
+    fn send(&mut self, id: WidgetId, input: Input, bubble: bool) -> Option<(WidgetId, Reply)> {
+        let mut target = Some(id);
+        while let Some(id) = target {
+            let reply = self.widgets[id].event(input, &mut self.theme);
+            if reply != Reply::Ignored {
+                self.dirty = true;
+                return Some((id, reply));
+            }
+
+            target = if bubble {
+                self.parents[id]
+            } else {
+                None
+            };
+        }
+        None
+    }
+
+    /// Move the focus to the next widget that can have it, in the order of the tree
+    fn focus_next(&mut self) {
+        let mut order = Vec::new();
+        let mut stack = Vec::new();
+        if let Some(root) = self.root {
+            stack.push(root);
+        }
+        while let Some(id) = stack.pop() {
+            if self.widgets[id].focusable() {
+                order.push(id);
+            }
+            for &child in self.widgets[id].children().iter().rev() {
+                stack.push(child);
+            }
+        }
+
+        if !order.is_empty() {
+            let current = match self.focus {
+                Some(focus) => order.iter().position(|&id| id == focus),
+                None => None,
+            };
+            let next = match current {
+                Some(i) => order[(i + 1) % order.len()],
+                None => order[0],
+            };
+            self.set_focus(next);
+        }
+    }
+
+    fn mouse(&mut self, x: isize, y: isize, left_button: bool) -> Option<UiEvent> {
+        let was_pressed = self.left_button;
+        self.left_button = left_button;
+
+        if left_button && !was_pressed {
+            let mut target = None;
+            if let Some(popup) = self.popup() {
+                let inside = match self.widgets[popup].popup() {
+                    Some(rect) => rect.contains(x, y) || self.widgets[popup].rect().contains(x, y),
+                    None => false,
+                };
+                if inside {
+                    target = Some(popup);
+                } else {
+                    self.widgets[popup].close_popup();
+                    self.dirty = true;
+                }
+            }
+            if target.is_none() {
+                target = match self.root {
+                    Some(root) => self.hit(root, x, y),
+                    None => None,
+                };
+            }
+
+            // The focus goes to the widget that was clicked, or to the container it is in
+            let mut focus = target;
+            while let Some(id) = focus {
+                if self.widgets[id].focusable() {
+                    if self.focus != Some(id) {
+                        self.focus = Some(id);
+                        self.dirty = true;
+                    }
+                    break;
+                }
+                focus = self.parents[id];
+            }
+
+            self.pressed = target;
+            if let Some(id) = target {
+                self.reveal = self.focus == Some(id);
+                return action(self.send(id, Input::Press(x, y), false));
+            }
+        } else if let Some(id) = self.pressed {
+            if left_button {
+                return action(self.send(id, Input::Drag(x, y), false));
+            } else {
+                self.pressed = None;
+                return action(self.send(id, Input::Release(x, y), false));
+            }
+        }
+
+        None
+    }
+
+    fn key(&mut self, key_event: KeyEvent) -> Option<UiEvent> {
+        if key_event.pressed && key_event.scancode == event::K_TAB && self.popup().is_none() {
+            self.focus_next();
+            return None;
+        }
+
+        let target = match self.popup() {
+            Some(popup) => Some(popup),
+            None => self.focus,
+        };
+        if let Some(id) = target {
+            let sent = self.send(id, Input::Key(key_event), true);
+            if sent.is_some() {
+                self.reveal = true;
+                return action(sent);
+            }
+        }
+
+        Some(UiEvent::Key(key_event))
+    }
+
+    /// Wait for something for the app to handle, drawing the widgets when they change
+    ///
+    /// Returns None when the window is gone.
+    pub fn poll(&mut self) -> Option<UiEvent> {
+        loop {
+            if self.dirty {
+                self.refresh();
+            }
+
+            let event = match self.window.poll() {
+                Some(event) => event,
+                None => return None,
+            };
+
+            let result = match event.to_option() {
+                EventOption::Mouse(mouse_event) => {
+                    self.pointer = (mouse_event.x, mouse_event.y);
+                    self.mouse(mouse_event.x, mouse_event.y, mouse_event.left_button)
+                }
+                EventOption::Key(key_event) => self.key(key_event),
+                EventOption::Scroll(scroll_event) => {
+                    let (x, y) = self.pointer;
+                    let target = match self.root {
+                        Some(root) => self.hit(root, x, y),
+                        None => None,
+                    };
+                    match target {
+                        Some(id) => {
+                            let input = Input::Scroll(scroll_event.x, scroll_event.y);
+                            action(self.send(id, input, true))
+                        }
+                        None => None,
+                    }
+                }
+                EventOption::Resize(_) => {
+                    self.dirty = true;
+                    None
+                }
+                EventOption::Quit(_) => Some(UiEvent::Quit),
+                _ => None,
+            };
+
+            if let Some(result) = result {
+                if self.dirty {
+                    self.refresh();
+                }
+                return Some(result);
+            }
+        }
+    }
+}
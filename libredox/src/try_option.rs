@@ -0,0 +1,13 @@
+//! `try_option!`, which the kernel includes with `#[path]` for the image decoders
+
+/// Unwrap an `Option`, returning `None` from the function if there is no value
+///
+/// Like `try!` for functions returning an `Option`. It is defined here and not in `macros`,
+/// which only builds with `std`.
+#[macro_export]
+macro_rules! try_option {
+    ($expr:expr) => (match $expr {
+        Some(value) => value,
+        None => return None,
+    })
+}
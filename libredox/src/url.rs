@@ -140,8 +140,36 @@ impl Url {
         return password;
     }
 
+    /// Get the host and port of a bracketed IPv6 host, like `[fe80::1]:80`
+    fn bracketed_host_port(&self) -> Option<(String, String)> {
+        if let Some(part) = self.string.split('/').nth(2) {
+            let host_port = match part.rfind('@') {
+                Some(i) => &part[i + 1 ..],
+                None => part,
+            };
+
+            if host_port.starts_with('[') {
+                if let Some(end) = host_port.find(']') {
+                    let host = host_port[1 .. end].to_string();
+                    let port = if host_port[end + 1 ..].starts_with(':') {
+                        host_port[end + 2 ..].to_string()
+                    } else {
+                        String::new()
+                    };
+                    return Some((host, port));
+                }
+            }
+        }
+
+        None
+    }
+
     /// Get the host
     pub fn host(&self) -> String {
+        if let Some((host, _)) = self.bracketed_host_port() {
+            return host;
+        }
+
         let mut username = String::new();
         let mut host = String::new();
 
@@ -184,6 +212,10 @@ impl Url {
 
     /// Get the post of the url
     pub fn port(&self) -> String {
+        if let Some((_, port)) = self.bracketed_host_port() {
+            return port;
+        }
+
         let mut password = String::new();
         let mut port = String::new();
 